@@ -0,0 +1,187 @@
+//! Translations, that are taken from an already translated copy of the game, e.g. a fan translation without translation files.
+//!
+//! Both the original and the copy are read in `allow` duplicate mode, so texts of event commands are at the same positions in both reads, as long as the copy doesn't add or remove commands. Sources of the copy, that are at the same positions in the same sections, are translations of the original's sources. Sections, which numbers of entries differ, were edited in the copy, so positions in them don't correspond, and they're skipped.
+
+use crate::{
+    dedup::split_display_name,
+    translation::{
+        DISPLAY_NAME_COMMENT_PREFIX, Line, TranslationFile,
+        effective_translation,
+    },
+};
+use rvpacker_lib::SEPARATOR;
+use std::collections::{BTreeMap, HashMap};
+
+/// Translations of a single file, taken from the copy.
+#[derive(Default)]
+pub struct Aligned {
+    /// Translations by section and source.
+    entries: HashMap<(Option<u16>, String), String>,
+
+    /// Translations of in-game map names by section.
+    names: HashMap<Option<u16>, String>,
+}
+
+#[derive(Default)]
+pub struct Report {
+    /// Entries, that have a translation in the copy.
+    pub aligned: usize,
+
+    /// Sources of a section, that have different translations at different positions. The first translation is used.
+    pub conflicts: usize,
+
+    /// Sections, that don't align.
+    pub skipped: Vec<Option<u16>>,
+}
+
+/// Sources of each section of a file, in their order.
+type Sections<'a> = BTreeMap<Option<u16>, Vec<&'a str>>;
+
+/// Returns sources of `file` in each section, and in-game map names.
+fn sections(
+    file: &TranslationFile,
+) -> (Sections<'_>, HashMap<Option<u16>, &str>) {
+    let mut sections = Sections::new();
+    let mut names = HashMap::new();
+    let mut section = None;
+
+    for line in &file.lines {
+        match line {
+            Line::Id(id) => {
+                section = Some(*id);
+                sections.entry(section).or_default();
+            }
+            Line::Comment(comment) => {
+                if let Some((name, _)) = split_display_name(comment) {
+                    let name = name
+                        .strip_prefix(DISPLAY_NAME_COMMENT_PREFIX)
+                        .unwrap_or(name)
+                        .trim_end_matches("-->")
+                        .trim();
+                    names.insert(section, name);
+                }
+            }
+            Line::Entry { source, .. } => {
+                sections.entry(section).or_default().push(source);
+            }
+            Line::Raw(_) => {}
+        }
+    }
+
+    (sections, names)
+}
+
+/// Pairs sources of `original` file with sources of `translated` file at the same positions. Both files must be read in `allow` duplicate mode. Sources, that the copy left as they are, aren't translations.
+#[must_use]
+pub fn align(
+    original: &TranslationFile,
+    translated: &TranslationFile,
+) -> (Aligned, Report) {
+    let (original_sections, original_names) = sections(original);
+    let (translated_sections, translated_names) = sections(translated);
+
+    let mut aligned = Aligned::default();
+    let mut report = Report::default();
+
+    for (section, sources) in original_sections {
+        let Some(translations) = translated_sections
+            .get(&section)
+            .filter(|translations| translations.len() == sources.len())
+        else {
+            report.skipped.push(section);
+            continue;
+        };
+
+        for (source, translation) in sources.into_iter().zip(translations) {
+            if source == *translation {
+                continue;
+            }
+
+            match aligned.entries.get(&(section, source.to_string())) {
+                Some(existing) if existing != translation => {
+                    report.conflicts += 1;
+                }
+                Some(_) => {}
+                None => {
+                    aligned.entries.insert(
+                        (section, source.to_string()),
+                        (*translation).to_string(),
+                    );
+                    report.aligned += 1;
+                }
+            }
+        }
+
+        if let (Some(name), Some(translated_name)) =
+            (original_names.get(&section), translated_names.get(&section))
+            && name != translated_name
+        {
+            aligned
+                .names
+                .insert(section, (*translated_name).to_string());
+        }
+    }
+
+    (aligned, report)
+}
+
+/// Writes `aligned` translations to entries of `file`, that have no translation, or to all entries with `overwrite`. Entries, that aren't in the same section, as in the copy, are matched by source anywhere in the file, since the project's duplicate mode may remove them from their sections. Returns the number of filled entries.
+pub fn fill(
+    file: &mut TranslationFile,
+    aligned: &Aligned,
+    overwrite: bool,
+) -> usize {
+    let by_source: HashMap<&str, &str> = aligned
+        .entries
+        .iter()
+        .map(|((_, source), translation)| {
+            (source.as_str(), translation.as_str())
+        })
+        .collect();
+
+    let mut filled = 0;
+    let mut section = None;
+
+    for line in &mut file.lines {
+        match line {
+            Line::Id(id) => section = Some(*id),
+            Line::Comment(comment) => {
+                if let Some((name, translation)) = split_display_name(comment)
+                    && (overwrite || translation.is_empty())
+                    && let Some(aligned_name) = aligned.names.get(&section)
+                    && translation != aligned_name
+                {
+                    *comment = format!("{name}{SEPARATOR}{aligned_name}");
+                    filled += 1;
+                }
+            }
+            Line::Entry {
+                source,
+                translation,
+            } => {
+                let current = effective_translation(translation);
+
+                if !overwrite && !current.is_empty() {
+                    continue;
+                }
+
+                let Some(found) = aligned
+                    .entries
+                    .get(&(section, source.clone()))
+                    .map(String::as_str)
+                    .or_else(|| by_source.get(source.as_str()).copied())
+                else {
+                    continue;
+                };
+
+                if current != found {
+                    *translation = found.to_string();
+                    filled += 1;
+                }
+            }
+            Line::Raw(_) => {}
+        }
+    }
+
+    filled
+}
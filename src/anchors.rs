@@ -0,0 +1,144 @@
+//! Stable anchors of entries.
+//!
+//! Position of an entry in a translation file shifts, when nearby content changes, and its source changes, when the text is edited. Anchors identify entries by their path in game data instead: `Map001/EV003/p0/c12` is the command 12 of the page 0 of the event 3 of the map 1, `Items/5/description` is the description of the item 5. Entries are preceded by `<!-- ANCHOR: path -->` comments, that the library ignores on write, so append, diffs and external tools can track entries across game updates.
+
+use crate::{
+    data::{data_file_path, load_rpgm_file},
+    dialogue,
+    translation::{TranslationFile, normalize},
+};
+use anyhow::Result;
+use rvpacker_lib::types::EngineType;
+use std::{
+    collections::HashMap,
+    fs::{read_to_string, write},
+    path::Path,
+};
+use tracing::info;
+
+pub const ANCHOR_COMMENT_PREFIX: &str = "<!-- ANCHOR: ";
+
+/// Database files, which objects are extracted to sections by ID.
+const DATABASE_FILES: &[&str] = &[
+    "Actors", "Armors", "Classes", "Enemies", "Items", "Skills", "States",
+    "Weapons",
+];
+
+/// Anchors by translation file name, and by section and source.
+type Anchors = HashMap<String, HashMap<(u16, String), String>>;
+
+fn insert(
+    anchors: &mut Anchors,
+    file: &str,
+    id: u16,
+    source: String,
+    anchor: String,
+) {
+    // The library removes duplicates, so the first entry wins.
+    anchors
+        .entry(file.to_string())
+        .or_default()
+        .entry((id, source))
+        .or_insert(anchor);
+}
+
+fn database_anchors(
+    anchors: &mut Anchors,
+    source_path: &Path,
+    engine_type: EngineType,
+) -> Result<()> {
+    for stem in DATABASE_FILES {
+        let path = data_file_path(source_path, stem, engine_type);
+
+        if !path.exists() {
+            continue;
+        }
+
+        let value = load_rpgm_file(&path, engine_type)?;
+        let file = format!("{}.txt", stem.to_lowercase());
+
+        for object in value.as_array().into_iter().flatten() {
+            let Some(object) = object.as_object() else {
+                continue;
+            };
+
+            let Some(id) = object.get("id").and_then(|id| id.as_int()) else {
+                continue;
+            };
+
+            for (key, value) in object.iter() {
+                let Some(text) = value.as_str().filter(|text| !text.is_empty())
+                else {
+                    continue;
+                };
+
+                insert(
+                    anchors,
+                    &file,
+                    id as u16,
+                    normalize(text),
+                    format!("{stem}/{id}/{key}"),
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Precedes entries of translation files in `translation_path` with their anchors. Previous anchors are replaced, so the files can be anchored repeatedly.
+pub fn annotate(
+    source_path: &Path,
+    translation_path: &Path,
+    engine_type: EngineType,
+) -> Result<()> {
+    let mut anchors = Anchors::new();
+
+    for (location, message) in dialogue::messages(source_path, engine_type)? {
+        insert(
+            &mut anchors,
+            location.file,
+            location.id,
+            message.source(),
+            message.anchor,
+        );
+    }
+
+    for (location, choice) in dialogue::choices(source_path, engine_type)? {
+        insert(
+            &mut anchors,
+            location.file,
+            location.id,
+            choice.text,
+            choice.anchor,
+        );
+    }
+
+    database_anchors(&mut anchors, source_path, engine_type)?;
+
+    let mut anchored = 0;
+
+    for (name, file_anchors) in &anchors {
+        let path = translation_path.join(name);
+
+        if !path.exists() {
+            continue;
+        }
+
+        let content = read_to_string(&path)?;
+        let mut file = TranslationFile::parse(&content);
+
+        anchored += file.annotate(ANCHOR_COMMENT_PREFIX, |id, source| {
+            file_anchors.get(&(id?, source.to_string())).cloned()
+        });
+
+        let serialized = file.serialize();
+
+        if serialized != content {
+            write(&path, serialized)?;
+        }
+    }
+
+    info!("Anchored {anchored} entries.");
+    Ok(())
+}
@@ -0,0 +1,692 @@
+use crate::matches_pattern;
+use anyhow::{Result, anyhow, bail};
+use encoding_rs::{Encoding, SHIFT_JIS};
+use std::{
+    borrow::Cow,
+    fs::{File, create_dir_all, read, write},
+    io::Read,
+    num::NonZero,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+use tracing::{info, warn};
+
+const ARCHIVE_HEADER: &[u8; 7] = b"RGSSAD\0";
+const OLDER_KEY: u32 = 0xDEAD_CAFE;
+
+/// Paths longer than this are never produced by RPG Maker, and mean that the archive is corrupted.
+const MAX_PATH_LENGTH: usize = 1024;
+
+/// Upper bound of extraction threads. Past it, threads only contend for the disk.
+const MAX_EXTRACT_THREADS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveVersion {
+    /// `.rgssad` and `.rgss2a` archives of XP and VX.
+    Older,
+
+    /// `.rgss3a` archives of VX Ace.
+    VXAce,
+}
+
+impl ArchiveVersion {
+    /// Guesses the version from archive extension. Used, when the version byte in the header is non-standard.
+    #[must_use]
+    pub fn from_path(path: &Path) -> Self {
+        if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("rgss3a"))
+        {
+            Self::VXAce
+        } else {
+            Self::Older
+        }
+    }
+
+    #[must_use]
+    pub const fn engine_name(self) -> &'static str {
+        match self {
+            Self::Older => "XP/VX",
+            Self::VXAce => "VX Ace",
+        }
+    }
+}
+
+/// Decryption key of an archive.
+#[derive(Debug, Clone, Copy)]
+pub struct ArchiveKey(pub u32);
+
+impl FromStr for ArchiveKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s
+            .strip_prefix("0x")
+            .or_else(|| s.strip_prefix("0X"))
+            .unwrap_or(s);
+
+        u32::from_str_radix(hex, 16)
+            .map(Self)
+            .map_err(|e| format!("Invalid key `{s}`: {e}"))
+    }
+}
+
+/// Location of an entry's data inside the archive.
+#[derive(Debug, Clone)]
+pub struct ArchiveEntry {
+    /// Raw, decrypted path of the entry.
+    pub path: Vec<u8>,
+    pub offset: usize,
+    pub size: usize,
+    key: u32,
+}
+
+/// Parsed RGSSAD archive, that references the archive data.
+///
+/// Parsing validates every offset and size, so corrupted archives produce an error instead of a crash. Entries' data isn't decrypted until it's requested.
+pub struct Archive<'a> {
+    data: &'a [u8],
+    pub version: ArchiveVersion,
+    pub entries: Vec<ArchiveEntry>,
+}
+
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn read_bytes(&mut self, count: usize) -> Result<&'a [u8]> {
+        let Some(bytes) = self
+            .pos
+            .checked_add(count)
+            .and_then(|end| self.data.get(self.pos..end))
+        else {
+            bail!(
+                "Unexpected end of archive at offset {}: expected {count} more bytes, but only {} are left.",
+                self.pos,
+                self.data.len() - self.pos
+            );
+        };
+
+        self.pos += count;
+        Ok(bytes)
+    }
+
+    fn read_u32(&mut self) -> Result<u32> {
+        let bytes = self.read_bytes(size_of::<u32>())?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+}
+
+const fn next_older_key(key: u32) -> u32 {
+    key.wrapping_mul(7).wrapping_add(3)
+}
+
+fn check_path_length(length: usize, pos: usize) -> Result<()> {
+    if length == 0 || length > MAX_PATH_LENGTH {
+        bail!(
+            "Implausible entry path length {length} at offset {pos}. Archive is corrupted, or uses a non-standard key."
+        );
+    }
+
+    Ok(())
+}
+
+impl<'a> Archive<'a> {
+    /// Parses the archive header and entry table.
+    ///
+    /// `key` overrides the standard key. Non-standard headers are tolerated: if the version byte is unknown, `fallback_version` is used.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error, if any entry points outside of the archive.
+    pub fn parse(
+        data: &'a [u8],
+        key: Option<ArchiveKey>,
+        fallback_version: ArchiveVersion,
+    ) -> Result<Self> {
+        let mut cursor = Cursor { data, pos: 0 };
+        let header = cursor.read_bytes(ARCHIVE_HEADER.len())?;
+
+        if header != ARCHIVE_HEADER {
+            warn!(
+                "Non-standard archive header {header:?}. Trying to decrypt anyway."
+            );
+        }
+
+        let version = match cursor.read_bytes(1)?[0] {
+            1 => ArchiveVersion::Older,
+            3 => ArchiveVersion::VXAce,
+            byte => {
+                warn!(
+                    "Non-standard archive version byte {byte}. Assuming {} archive.",
+                    fallback_version.engine_name()
+                );
+                fallback_version
+            }
+        };
+
+        let key = key.map(|key| key.0);
+        let entries = match version {
+            ArchiveVersion::Older => Self::parse_older(&mut cursor, key)?,
+            ArchiveVersion::VXAce => Self::parse_vxace(&mut cursor, key)?,
+        };
+
+        Ok(Self {
+            data,
+            version,
+            entries,
+        })
+    }
+
+    fn parse_older(
+        cursor: &mut Cursor,
+        key: Option<u32>,
+    ) -> Result<Vec<ArchiveEntry>> {
+        let mut key = key.unwrap_or(OLDER_KEY);
+        let mut entries = Vec::new();
+
+        while !cursor.is_at_end() {
+            let path_length = (cursor.read_u32()? ^ key) as usize;
+            key = next_older_key(key);
+            check_path_length(path_length, cursor.pos)?;
+
+            let path = cursor
+                .read_bytes(path_length)?
+                .iter()
+                .map(|byte| {
+                    let decrypted = byte ^ key as u8;
+                    key = next_older_key(key);
+                    decrypted
+                })
+                .collect();
+
+            let size = (cursor.read_u32()? ^ key) as usize;
+            key = next_older_key(key);
+
+            let offset = cursor.pos;
+            cursor.read_bytes(size)?;
+
+            entries.push(ArchiveEntry {
+                path,
+                offset,
+                size,
+                key,
+            });
+        }
+
+        Ok(entries)
+    }
+
+    fn parse_vxace(
+        cursor: &mut Cursor,
+        key: Option<u32>,
+    ) -> Result<Vec<ArchiveEntry>> {
+        let seed = cursor.read_u32()?;
+        let key = key.unwrap_or_else(|| seed.wrapping_mul(9).wrapping_add(3));
+        let key_bytes = key.to_le_bytes();
+        let mut entries = Vec::new();
+
+        loop {
+            let offset = (cursor.read_u32()? ^ key) as usize;
+
+            if offset == 0 {
+                break;
+            }
+
+            let size = (cursor.read_u32()? ^ key) as usize;
+            let entry_key = cursor.read_u32()? ^ key;
+            let path_length = (cursor.read_u32()? ^ key) as usize;
+            check_path_length(path_length, cursor.pos)?;
+
+            let path = cursor
+                .read_bytes(path_length)?
+                .iter()
+                .enumerate()
+                .map(|(i, byte)| byte ^ key_bytes[i % 4])
+                .collect();
+
+            if offset
+                .checked_add(size)
+                .is_none_or(|end| end > cursor.data.len())
+            {
+                bail!(
+                    "Entry at offset {} points to data at {offset}..{}, which is outside of the archive ({} bytes).",
+                    cursor.pos,
+                    offset.saturating_add(size),
+                    cursor.data.len()
+                );
+            }
+
+            entries.push(ArchiveEntry {
+                path,
+                offset,
+                size,
+                key: entry_key,
+            });
+        }
+
+        Ok(entries)
+    }
+
+    /// Decrypts and returns the data of the entry.
+    #[must_use]
+    pub fn read_entry(&self, entry: &ArchiveEntry) -> Vec<u8> {
+        let mut key = entry.key;
+        let mut output = Vec::with_capacity(entry.size);
+
+        for chunk in self.data[entry.offset..entry.offset + entry.size]
+            .chunks(size_of::<u32>())
+        {
+            let key_bytes = key.to_le_bytes();
+            output.extend(chunk.iter().zip(key_bytes).map(|(b, k)| b ^ k));
+            key = next_older_key(key);
+        }
+
+        output
+    }
+}
+
+/// Defines how to decode paths of archive entries.
+///
+/// - [`PathEncoding::Auto`] - Uses UTF-8, and falls back to Shift-JIS (CP932), if path is not valid UTF-8. Japanese games almost always use one of those.
+/// - [`PathEncoding::Explicit`] - Always uses the specified encoding.
+#[derive(Debug, Clone, Copy)]
+pub enum PathEncoding {
+    Auto,
+    Explicit(&'static Encoding),
+}
+
+impl FromStr for PathEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("auto") {
+            return Ok(Self::Auto);
+        }
+
+        Encoding::for_label(s.as_bytes())
+            .map(Self::Explicit)
+            .ok_or_else(|| format!("Unknown encoding `{s}`"))
+    }
+}
+
+/// Wildcard patterns, that restrict which archive entries are extracted.
+///
+/// Patterns are matched against entry paths with `/` separators, case-insensitively, e.g. `Data/*`. Empty filter matches everything.
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveFilter(pub Vec<String>);
+
+impl FromStr for ArchiveFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(
+            s.split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(|s| s.replace('\\', "/").to_lowercase())
+                .collect(),
+        ))
+    }
+}
+
+impl ArchiveFilter {
+    #[must_use]
+    pub fn matches(&self, path: &Path) -> bool {
+        if self.0.is_empty() {
+            return true;
+        }
+
+        let path = path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+            .to_lowercase();
+
+        self.0.iter().any(|pattern| matches_pattern(pattern, &path))
+    }
+}
+
+/// Decodes raw path of an archive entry to a relative path.
+///
+/// Archives store paths with Windows separators, so they're converted to the platform ones. `.` and `..` components are dropped, so entries can't escape the output directory.
+#[must_use]
+pub fn decode_path(path: &[u8], encoding: PathEncoding) -> PathBuf {
+    let decoded: Cow<str> = match encoding {
+        PathEncoding::Auto => match std::str::from_utf8(path) {
+            Ok(path) => Cow::Borrowed(path),
+            Err(_) => SHIFT_JIS.decode_without_bom_handling(path).0,
+        },
+        PathEncoding::Explicit(encoding) => {
+            encoding.decode_without_bom_handling(path).0
+        }
+    };
+
+    decoded
+        .split(['\\', '/'])
+        .filter(|component| !matches!(*component, "" | "." | ".."))
+        .collect()
+}
+
+/// Returns the hash of the archive file, that's recorded in metadata to detect, whether the archive changed since it was extracted.
+pub fn hash(archive_path: &Path) -> Result<String> {
+    let mut file = File::open(archive_path)?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buf = vec![0; 1 << 20];
+
+    loop {
+        let read = file.read(&mut buf)?;
+
+        if read == 0 {
+            break;
+        }
+
+        hasher.update(&buf[..read]);
+    }
+
+    Ok(format!("{:08x}", hasher.finalize()))
+}
+
+/// Decrypts the archive and extracts its entries, that match `filter`, to `output_dir`.
+///
+/// Entries are decrypted and written concurrently, largest first, so a few large entries don't leave the other threads idle at the end.
+pub fn extract(
+    archive_path: &Path,
+    output_dir: &Path,
+    encoding: PathEncoding,
+    key: Option<ArchiveKey>,
+    filter: &ArchiveFilter,
+) -> Result<()> {
+    let archive_data = read(archive_path)?;
+    let archive = Archive::parse(
+        &archive_data,
+        key,
+        ArchiveVersion::from_path(archive_path),
+    )?;
+
+    let mut entries: Vec<(&ArchiveEntry, PathBuf)> = archive
+        .entries
+        .iter()
+        .filter_map(|entry| {
+            let path = decode_path(&entry.path, encoding);
+            filter
+                .matches(&path)
+                .then(|| (entry, output_dir.join(path)))
+        })
+        .collect();
+    entries.sort_by_key(|(entry, _)| std::cmp::Reverse(entry.size));
+
+    let threads = thread::available_parallelism()
+        .map_or(1, NonZero::get)
+        .min(MAX_EXTRACT_THREADS)
+        .min(entries.len())
+        .max(1);
+    let next = AtomicUsize::new(0);
+
+    let extract_next = || -> Result<()> {
+        while let Some((entry, path)) =
+            entries.get(next.fetch_add(1, Ordering::Relaxed))
+        {
+            if let Some(parent) = path.parent() {
+                create_dir_all(parent)?;
+            }
+
+            write(path, archive.read_entry(entry))?;
+        }
+
+        Ok(())
+    };
+
+    thread::scope(|scope| {
+        let workers: Vec<_> =
+            (0..threads).map(|_| scope.spawn(extract_next)).collect();
+
+        workers.into_iter().try_for_each(|worker| {
+            worker
+                .join()
+                .map_err(|_| anyhow!("Archive extraction thread panicked."))?
+        })
+    })
+}
+
+/// Prints entries of the archive with their sizes.
+pub fn list(
+    archive_path: &Path,
+    encoding: PathEncoding,
+    key: Option<ArchiveKey>,
+) -> Result<()> {
+    let archive_data = read(archive_path)?;
+    let archive = Archive::parse(
+        &archive_data,
+        key,
+        ArchiveVersion::from_path(archive_path),
+    )?;
+
+    println!(
+        "{} ({} archive, {} entries)",
+        archive_path.display(),
+        archive.version.engine_name(),
+        archive.entries.len()
+    );
+
+    for entry in &archive.entries {
+        println!(
+            "{:>12}  {}",
+            entry.size,
+            decode_path(&entry.path, encoding).display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Validates the structure of the archive without extracting it.
+///
+/// On top of the checks, that parsing performs, ensures that data of different entries doesn't overlap.
+pub fn check(archive_path: &Path, key: Option<ArchiveKey>) -> Result<()> {
+    let archive_data = read(archive_path)?;
+    let archive = Archive::parse(
+        &archive_data,
+        key,
+        ArchiveVersion::from_path(archive_path),
+    )?;
+
+    let mut ranges: Vec<_> = archive
+        .entries
+        .iter()
+        .map(|entry| (entry.offset, entry.offset + entry.size, &entry.path))
+        .collect();
+    ranges.sort_unstable();
+
+    for pair in ranges.windows(2) {
+        let ((_, end, path), (start, _, next_path)) = (&pair[0], &pair[1]);
+
+        if end > start {
+            bail!(
+                "Data of entries `{}` and `{}` overlaps. Archive is corrupted.",
+                String::from_utf8_lossy(path),
+                String::from_utf8_lossy(next_path)
+            );
+        }
+    }
+
+    let total_size: usize =
+        archive.entries.iter().map(|entry| entry.size).sum();
+
+    info!(
+        "{}: Archive is valid. {} entries, {total_size} bytes of data.",
+        archive_path.display(),
+        archive.entries.len()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILES: [(&str, &[u8]); 2] = [
+        ("Data\\System.rxdata", b"system data, not aligned to 4"),
+        ("Data\\Map001.rxdata", b"map!"),
+    ];
+
+    /// Encrypts entry data the same way, as [`Archive::read_entry`] decrypts it.
+    fn encrypt(data: &[u8], mut key: u32) -> Vec<u8> {
+        let mut output = Vec::with_capacity(data.len());
+
+        for chunk in data.chunks(size_of::<u32>()) {
+            output.extend(
+                chunk.iter().zip(key.to_le_bytes()).map(|(b, k)| b ^ k),
+            );
+            key = next_older_key(key);
+        }
+
+        output
+    }
+
+    fn older_archive() -> Vec<u8> {
+        let mut data = ARCHIVE_HEADER.to_vec();
+        data.push(1);
+        let mut key = OLDER_KEY;
+
+        for (path, content) in FILES {
+            data.extend_from_slice(&(path.len() as u32 ^ key).to_le_bytes());
+            key = next_older_key(key);
+
+            for byte in path.bytes() {
+                data.push(byte ^ key as u8);
+                key = next_older_key(key);
+            }
+
+            data.extend_from_slice(&(content.len() as u32 ^ key).to_le_bytes());
+            key = next_older_key(key);
+            data.extend(encrypt(content, key));
+        }
+
+        data
+    }
+
+    fn vxace_archive() -> Vec<u8> {
+        let seed = 0x1234_5678_u32;
+        let key = seed.wrapping_mul(9).wrapping_add(3);
+        let key_bytes = key.to_le_bytes();
+
+        let mut data = ARCHIVE_HEADER.to_vec();
+        data.push(3);
+        data.extend_from_slice(&seed.to_le_bytes());
+
+        let table_size: usize =
+            FILES.iter().map(|(path, _)| 16 + path.len()).sum::<usize>() + 4;
+        let mut offset = data.len() + table_size;
+        let mut contents = Vec::new();
+
+        for (index, (path, content)) in FILES.iter().enumerate() {
+            let entry_key = 0xABCD_0000 + index as u32;
+
+            for value in [offset, content.len(), entry_key as usize, path.len()]
+            {
+                data.extend_from_slice(&(value as u32 ^ key).to_le_bytes());
+            }
+
+            data.extend(
+                path.bytes()
+                    .enumerate()
+                    .map(|(i, byte)| byte ^ key_bytes[i % 4]),
+            );
+
+            offset += content.len();
+            contents.extend(encrypt(content, entry_key));
+        }
+
+        data.extend_from_slice(&key.to_le_bytes());
+        data.extend(contents);
+        data
+    }
+
+    fn assert_entries(archive: &Archive) {
+        assert_eq!(archive.entries.len(), FILES.len());
+
+        for (entry, (path, content)) in archive.entries.iter().zip(FILES) {
+            assert_eq!(entry.path, path.as_bytes());
+            assert_eq!(archive.read_entry(entry), content);
+        }
+    }
+
+    #[test]
+    fn reads_older_archives() {
+        let data = older_archive();
+        let archive =
+            Archive::parse(&data, None, ArchiveVersion::VXAce).unwrap();
+
+        assert_eq!(archive.version, ArchiveVersion::Older);
+        assert_entries(&archive);
+    }
+
+    #[test]
+    fn reads_vxace_archives() {
+        let data = vxace_archive();
+        let archive =
+            Archive::parse(&data, None, ArchiveVersion::Older).unwrap();
+
+        assert_eq!(archive.version, ArchiveVersion::VXAce);
+        assert_entries(&archive);
+    }
+
+    #[test]
+    fn rejects_truncated_archives() {
+        for data in [older_archive(), vxace_archive()] {
+            for length in [0, 5, 12, data.len() - 1] {
+                assert!(
+                    Archive::parse(
+                        &data[..length],
+                        None,
+                        ArchiveVersion::Older
+                    )
+                    .is_err(),
+                    "{length} bytes"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_entries_outside_of_archive() {
+        let mut data = vxace_archive();
+        let key = 0x1234_5678_u32.wrapping_mul(9).wrapping_add(3);
+        let first_offset = ARCHIVE_HEADER.len() + 1 + 4;
+
+        data[first_offset..first_offset + 4]
+            .copy_from_slice(&(u32::MAX ^ key).to_le_bytes());
+
+        assert!(Archive::parse(&data, None, ArchiveVersion::Older).is_err());
+    }
+
+    #[test]
+    fn rejects_implausible_path_lengths() {
+        let mut data = older_archive();
+        let path_length = ARCHIVE_HEADER.len() + 1;
+
+        data[path_length..path_length + 4].copy_from_slice(
+            &((MAX_PATH_LENGTH as u32 + 1) ^ OLDER_KEY).to_le_bytes(),
+        );
+
+        assert!(Archive::parse(&data, None, ArchiveVersion::Older).is_err());
+    }
+
+    #[test]
+    fn parses_keys() {
+        assert_eq!("0xDEADCAFE".parse::<ArchiveKey>().unwrap().0, OLDER_KEY);
+        assert_eq!("deadcafe".parse::<ArchiveKey>().unwrap().0, OLDER_KEY);
+        assert!("0xNOPE".parse::<ArchiveKey>().is_err());
+    }
+}
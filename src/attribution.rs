@@ -0,0 +1,245 @@
+//! Record of who translated each entry and when.
+//!
+//! Translation files have no room for per-entry metadata, that the library wouldn't mistake for translation, so attribution is kept in `.rvpacker-attribution` file next to them. Entries are keyed by file, section and source, like imported rows are matched.
+
+use crate::translation::{
+    Line, TranslationFile, effective_translation, translation_files,
+};
+use anyhow::Result;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{read_to_string, write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::info;
+
+pub const ATTRIBUTION_FILE: &str = ".rvpacker-attribution";
+
+/// Entry of a translation file, that was changed: file name, section and source.
+pub type Changed = (String, Option<u16>, String);
+
+#[derive(Clone, Deserialize, Serialize)]
+pub struct Attribution {
+    pub translator: String,
+
+    /// Date of the change in `YYYY-MM-DD` form.
+    pub date: String,
+}
+
+/// Attributions by file name, and by `section:source` key.
+#[derive(Default, Deserialize, Serialize)]
+struct Attributions(BTreeMap<String, BTreeMap<String, Attribution>>);
+
+fn key(section: Option<u16>, source: &str) -> String {
+    match section {
+        Some(section) => format!("{section}:{source}"),
+        None => format!(":{source}"),
+    }
+}
+
+/// Returns today's date in UTC in `YYYY-MM-DD` form.
+fn today() -> String {
+    date(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs()),
+    )
+}
+
+/// Returns the date in UTC of `timestamp`, that's in seconds since the epoch, in `YYYY-MM-DD` form.
+pub fn date(timestamp: u64) -> String {
+    let days = (timestamp / 86_400) as i64;
+
+    // Converts days since the epoch to the civil date, see http://howardhinnant.github.io/date_algorithms.html#civil_from_days.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+fn load(translation_path: &Path) -> Result<Attributions> {
+    let path = translation_path.join(ATTRIBUTION_FILE);
+
+    if !path.exists() {
+        return Ok(Attributions::default());
+    }
+
+    Ok(serde_json::from_str(&read_to_string(path)?)?)
+}
+
+/// Attributes `changed` entries in `translation_path` to `translator`, as of today.
+pub fn record(
+    translation_path: &Path,
+    translator: &str,
+    changed: &[Changed],
+) -> Result<()> {
+    if changed.is_empty() {
+        return Ok(());
+    }
+
+    let mut attributions = load(translation_path)?;
+    let attribution = Attribution {
+        translator: translator.to_string(),
+        date: today(),
+    };
+
+    for (file, section, source) in changed {
+        attributions
+            .0
+            .entry(file.clone())
+            .or_default()
+            .insert(key(*section, source), attribution.clone());
+    }
+
+    write(
+        translation_path.join(ATTRIBUTION_FILE),
+        serde_json::to_string_pretty(&attributions)?,
+    )?;
+
+    info!(
+        "{ATTRIBUTION_FILE}: Attributed {} entries to {translator}.",
+        changed.len()
+    );
+    Ok(())
+}
+
+/// Attributed entry of a translation file.
+pub struct Attributed {
+    pub file: String,
+    pub source: String,
+    pub translation: String,
+    pub attribution: Attribution,
+}
+
+/// Returns up to `limit` attributed entries of translation files in `translation_path`, that were changed most recently, newest first. Entries, that no longer exist or have no translation, are skipped.
+pub fn recent(
+    translation_path: &Path,
+    limit: usize,
+) -> Result<Vec<Attributed>> {
+    let attributions = load(translation_path)?;
+    let mut entries = Vec::new();
+
+    for name in translation_files(translation_path)? {
+        let Some(file_attributions) = attributions.0.get(&name) else {
+            continue;
+        };
+
+        let file = TranslationFile::parse(&read_to_string(
+            translation_path.join(&name),
+        )?);
+
+        for (section, source, translation) in file.entries() {
+            if translation.is_empty() {
+                continue;
+            }
+
+            if let Some(attribution) =
+                file_attributions.get(&key(section, source))
+            {
+                entries.push(Attributed {
+                    file: name.clone(),
+                    source: source.to_string(),
+                    translation: translation.to_string(),
+                    attribution: attribution.clone(),
+                });
+            }
+        }
+    }
+
+    // Dates are in `YYYY-MM-DD` form, so they sort as strings. Stable sort keeps entries of a day in file order.
+    entries.sort_by(|a, b| b.attribution.date.cmp(&a.attribution.date));
+    entries.truncate(limit);
+    Ok(entries)
+}
+
+/// Filters of [`show`].
+pub struct Filter<'a> {
+    /// Only entries, which source or translation match the pattern.
+    pub pattern: Option<&'a Regex>,
+
+    /// Only entries of this translator.
+    pub translator: Option<&'a str>,
+
+    /// Names or stems of translation files. All files if empty.
+    pub files: &'a [String],
+}
+
+/// Prints attributed entries of translation files in `translation_path`, that pass `filter`.
+pub fn show(translation_path: &Path, filter: &Filter) -> Result<()> {
+    let attributions = load(translation_path)?;
+    let mut shown = 0;
+
+    for name in translation_files(translation_path)? {
+        if !filter.files.is_empty()
+            && !filter.files.iter().any(|file| {
+                *file == name || name.strip_suffix(".txt") == Some(file)
+            })
+        {
+            continue;
+        }
+
+        let Some(file_attributions) = attributions.0.get(&name) else {
+            continue;
+        };
+
+        let file = TranslationFile::parse(&read_to_string(
+            translation_path.join(&name),
+        )?);
+
+        let mut section = None;
+
+        for (line_index, line) in file.lines.iter().enumerate() {
+            let (source, translation) = match line {
+                Line::Id(id) => {
+                    section = Some(*id);
+                    continue;
+                }
+                Line::Entry {
+                    source,
+                    translation,
+                } => (source, effective_translation(translation)),
+                _ => continue,
+            };
+
+            let Some(attribution) =
+                file_attributions.get(&key(section, source))
+            else {
+                continue;
+            };
+
+            if filter
+                .translator
+                .is_some_and(|translator| attribution.translator != translator)
+                || filter.pattern.is_some_and(|pattern| {
+                    !pattern.is_match(source) && !pattern.is_match(translation)
+                })
+            {
+                continue;
+            }
+
+            println!(
+                "{name}:{}: {} on {}\n  {source}\n  {translation}",
+                line_index + 1,
+                attribution.translator,
+                attribution.date
+            );
+            shown += 1;
+        }
+    }
+
+    if shown == 0 {
+        info!("No attributed entries match.");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,209 @@
+//! Append-only log of destructive operations, so teams can reconstruct, what happened, when translations unexpectedly disappear.
+//!
+//! Force reads, purges, writes and undoing of patches append a line to `.rvpacker-audit.log` in the translation directory. Each line is a JSON object with the time, the user, the command line, the outcome, files, that the operation wrote, purged or restored, and entry counts of translation files, that changed, before and after the operation.
+
+use crate::{attribution, log_file, report};
+use anyhow::{Context as _, Result};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{self, Write as _},
+    fs::OpenOptions,
+    io::Write as _,
+    path::Path,
+    sync::{
+        LazyLock, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::{Event, Level, Subscriber, field::Field};
+use tracing_subscriber::{field::Visit, layer::Context, layer::Layer};
+
+pub const AUDIT_FILE: &str = ".rvpacker-audit.log";
+
+/// Endings of messages, that the library and patches log for each file, that they changed.
+const CHANGED_SUFFIXES: &[&str] = &[
+    "Successfully read.",
+    "Successfully written.",
+    "Successfully purged.",
+    "Restored.",
+    "Removed.",
+];
+
+static RECORDING: AtomicBool = AtomicBool::new(false);
+
+static CHANGED: LazyLock<Mutex<BTreeSet<String>>> =
+    LazyLock::new(Mutex::default);
+
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.0, "{value:?}");
+        }
+    }
+}
+
+/// [`Layer`], that collects files, that the audited operation changed.
+pub struct Recorder;
+
+impl<S: Subscriber> Layer<S> for Recorder {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if !RECORDING.load(Ordering::Relaxed)
+            || *event.metadata().level() != Level::INFO
+        {
+            return;
+        }
+
+        let mut visitor = MessageVisitor(String::new());
+        event.record(&mut visitor);
+
+        if let Some((name, message)) = visitor.0.split_once(": ")
+            && CHANGED_SUFFIXES.contains(&message)
+            && let Ok(mut changed) = CHANGED.lock()
+        {
+            changed.insert(name.to_string());
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EntryChange {
+    file: String,
+    entries_before: usize,
+    translated_before: usize,
+    entries_after: usize,
+    translated_after: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Record<'a> {
+    time: String,
+    user: String,
+    command: &'a str,
+    arguments: Vec<String>,
+    status: &'static str,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    files: BTreeSet<String>,
+    entries: Vec<EntryChange>,
+}
+
+/// Destructive operation, that's being audited.
+pub struct Audit {
+    command: &'static str,
+    before: BTreeMap<String, (usize, usize)>,
+}
+
+/// Returns entry counts of translation files in `translation_path`, or none, if it doesn't exist yet.
+fn entry_counts(
+    translation_path: &Path,
+) -> Result<BTreeMap<String, (usize, usize)>> {
+    if translation_path.exists() {
+        report::entry_counts(translation_path)
+    } else {
+        Ok(BTreeMap::new())
+    }
+}
+
+/// Returns the current time in UTC in `YYYY-MM-DDTHH:MM:SSZ` form.
+fn now() -> String {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+    let seconds = timestamp % 86_400;
+
+    format!(
+        "{}T{:02}:{:02}:{:02}Z",
+        attribution::date(timestamp),
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+impl Audit {
+    /// Starts auditing `command`, recording entry counts of translation files in `translation_path`.
+    pub fn start(
+        command: &'static str,
+        translation_path: &Path,
+    ) -> Result<Self> {
+        let before = entry_counts(translation_path)?;
+        RECORDING.store(true, Ordering::Relaxed);
+
+        Ok(Self { command, before })
+    }
+
+    /// Appends the record of the operation, that finished with `error`, to the audit log in `translation_path`.
+    pub fn finish(
+        self,
+        translation_path: &Path,
+        error: Option<&anyhow::Error>,
+    ) -> Result<()> {
+        RECORDING.store(false, Ordering::Relaxed);
+
+        let files = CHANGED
+            .lock()
+            .map(|mut changed| std::mem::take(&mut *changed))
+            .unwrap_or_default();
+
+        // Failed force read may leave no translation directory to log into.
+        if !translation_path.exists() {
+            return Ok(());
+        }
+
+        let after = entry_counts(translation_path)?;
+        let entries = self
+            .before
+            .keys()
+            .chain(after.keys())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .filter_map(|file| {
+                let before = self.before.get(file).copied().unwrap_or_default();
+                let after = after.get(file).copied().unwrap_or_default();
+
+                (before != after).then(|| EntryChange {
+                    file: file.clone(),
+                    entries_before: before.0,
+                    translated_before: before.1,
+                    entries_after: after.0,
+                    translated_after: after.1,
+                })
+            })
+            .collect();
+
+        let record = Record {
+            time: now(),
+            user: std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_default(),
+            command: self.command,
+            arguments: log_file::redacted_arguments(),
+            status: if error.is_some() {
+                "failure"
+            } else {
+                "success"
+            },
+            error: error.map(|err| format!("{err:#}")),
+            files,
+            entries,
+        };
+
+        let path = translation_path.join(AUDIT_FILE);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Opening {}", path.display()))?;
+
+        writeln!(file, "{}", serde_json::to_string(&record)?)
+            .with_context(|| format!("Writing {}", path.display()))?;
+        Ok(())
+    }
+}
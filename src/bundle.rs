@@ -0,0 +1,338 @@
+//! Single-file bundles of translation projects.
+//!
+//! A bundle is a ZIP archive with the whole `translation` directory, including metadata, ignore and attribution files, so a project can be shared as one file over chats or attached to releases. It also holds `bundle.json` manifest with the version of the tool, that created it, and the engine and title of the game, which are checked on import:
+//!
+//! ```json
+//! { "version": "11.2.0", "engineType": 0, "gameTitle": "My Game" }
+//! ```
+//!
+//! Bundles of other major versions are rejected, since their translation files may differ.
+
+use crate::error::{ErrorKind, WithKind};
+use anyhow::{Context, Result, anyhow, bail};
+use rvpacker_lib::types::EngineType;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{File, create_dir_all, read, read_dir, remove_dir_all, write},
+    io::{BufReader, Read, Write},
+    path::{Component, Path},
+};
+use tracing::warn;
+use zip::{
+    CompressionMethod, DateTime, ZipArchive, ZipWriter,
+    write::SimpleFileOptions,
+};
+
+const MANIFEST_FILE: &str = "bundle.json";
+const TRANSLATION_DIR: &str = "translation/";
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub version: String,
+    pub engine_type: EngineType,
+    pub game_title: String,
+}
+
+/// Returns the major part of `version`.
+fn major(version: &str) -> &str {
+    version.split('.').next().unwrap_or_default()
+}
+
+/// Deflated files with a fixed timestamp, that keeps bundles reproducible.
+fn file_options() -> SimpleFileOptions {
+    SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .last_modified_time(DateTime::DEFAULT)
+}
+
+/// Adds files of `dir` to `writer` recursively, with names relative to `root`. Returns the number of added files.
+fn add_dir(
+    writer: &mut ZipWriter<File>,
+    root: &Path,
+    dir: &Path,
+) -> Result<usize> {
+    let mut entries: Vec<_> = read_dir(dir)?.flatten().collect();
+    entries.sort_by_key(std::fs::DirEntry::file_name);
+
+    let mut added = 0;
+
+    for entry in entries {
+        let path = entry.path();
+
+        if path.is_dir() {
+            added += add_dir(writer, root, &path)?;
+            continue;
+        }
+
+        let relative = path
+            .strip_prefix(root)?
+            .to_string_lossy()
+            .replace('\\', "/");
+        writer.start_file(
+            format!("{TRANSLATION_DIR}{relative}"),
+            file_options(),
+        )?;
+        writer.write_all(&read(&path)?)?;
+        added += 1;
+    }
+
+    Ok(added)
+}
+
+/// Packs `translation_path` with `manifest` into the bundle at `bundle_path`. Returns the number of packed files.
+pub fn export(
+    translation_path: &Path,
+    bundle_path: &Path,
+    manifest: &Manifest,
+) -> Result<usize> {
+    let mut writer = ZipWriter::new(
+        File::create(bundle_path)
+            .with_context(|| format!("Writing {}", bundle_path.display()))?,
+    );
+    writer.start_file(MANIFEST_FILE, file_options())?;
+    writer.write_all(&serde_json::to_vec_pretty(manifest)?)?;
+
+    let packed = add_dir(&mut writer, translation_path, translation_path)?;
+
+    writer.finish()?;
+    Ok(packed)
+}
+
+/// Reads all files of the bundle at `bundle_path`, and returns their `(name, content)` in the order of the archive.
+fn read_files(bundle_path: &Path) -> Result<Vec<(String, Vec<u8>)>> {
+    let mut archive = ZipArchive::new(BufReader::new(
+        File::open(bundle_path)
+            .with_context(|| format!("Reading {}", bundle_path.display()))?,
+    ))
+    .with_context(|| format!("{} is not a bundle.", bundle_path.display()))?;
+    let mut files = Vec::with_capacity(archive.len());
+
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+
+        if file.is_dir() {
+            continue;
+        }
+
+        let mut content = Vec::new();
+        file.read_to_end(&mut content)?;
+        files.push((file.name().to_string(), content));
+    }
+
+    Ok(files)
+}
+
+/// Unpacks the bundle at `bundle_path` to `translation_path`, after checking it against `expected` manifest of the current game. Existing translation is replaced only if `force` is set. Returns the number of unpacked files.
+pub fn import(
+    bundle_path: &Path,
+    translation_path: &Path,
+    expected: &Manifest,
+    force: bool,
+) -> Result<usize> {
+    let files = read_files(bundle_path)?;
+
+    let Some((_, manifest)) =
+        files.iter().find(|(name, _)| name == MANIFEST_FILE)
+    else {
+        bail!(
+            "{} is not a bundle: {MANIFEST_FILE} is missing.",
+            bundle_path.display()
+        );
+    };
+
+    let manifest: Manifest = serde_json::from_slice(manifest)
+        .with_context(|| format!("{MANIFEST_FILE} is malformed."))?;
+
+    if major(&manifest.version) != major(&expected.version) {
+        return Err(anyhow!(
+            "Bundle was created by version {} of the tool, which is incompatible with version {}.",
+            manifest.version,
+            expected.version
+        ))
+        .kind(ErrorKind::ValidationFailed);
+    }
+
+    if manifest.engine_type as u8 != expected.engine_type as u8 {
+        return Err(anyhow!(
+            "Bundle was created for a game on another engine."
+        ))
+        .kind(ErrorKind::ValidationFailed);
+    }
+
+    if manifest.game_title != expected.game_title {
+        warn!(
+            "Bundle was created for \"{}\", but the game is \"{}\".",
+            manifest.game_title, expected.game_title
+        );
+    }
+
+    let mut entries = Vec::new();
+
+    for (name, content) in &files {
+        let Some(relative) = name.strip_prefix(TRANSLATION_DIR) else {
+            continue;
+        };
+
+        let relative = Path::new(relative);
+
+        if !relative
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            bail!(
+                "{name}: Path in bundle points outside of the translation directory."
+            );
+        }
+
+        entries.push((translation_path.join(relative), content));
+    }
+
+    if translation_path.exists() {
+        if !force {
+            return Err(anyhow!(
+                "`translation` directory already exists. Pass `--force` to replace it."
+            ))
+            .kind(ErrorKind::Aborted);
+        }
+
+        remove_dir_all(translation_path)?;
+    }
+
+    for (path, content) in &entries {
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
+        }
+
+        write(path, content)?;
+    }
+
+    Ok(entries.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("rvpacker-{}-{name}", std::process::id()));
+        let _ = remove_dir_all(&dir);
+        create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn manifest(version: &str, engine_type: EngineType) -> Manifest {
+        Manifest {
+            version: version.into(),
+            engine_type,
+            game_title: "Game".into(),
+        }
+    }
+
+    #[test]
+    fn round_trips_translation() {
+        let dir = temp_dir("bundle");
+        let source = dir.join("source");
+        create_dir_all(source.join("maps")).unwrap();
+        write(source.join("system.txt"), "a<#>b\n").unwrap();
+        write(source.join("maps/map1.txt"), "c<#>d\n").unwrap();
+
+        let bundle_path = dir.join("bundle.zip");
+        let manifest = manifest("1.2.0", EngineType::VXAce);
+        assert_eq!(export(&source, &bundle_path, &manifest).unwrap(), 2);
+
+        let target = dir.join("target");
+        let imported = import(
+            &bundle_path,
+            &target,
+            &self::manifest("1.5.0", EngineType::VXAce),
+            false,
+        );
+        let system = read(target.join("system.txt"));
+        let map = read(target.join("maps/map1.txt"));
+        remove_dir_all(&dir).unwrap();
+
+        assert_eq!(imported.unwrap(), 2);
+        assert_eq!(system.unwrap(), b"a<#>b\n");
+        assert_eq!(map.unwrap(), b"c<#>d\n");
+    }
+
+    #[test]
+    fn rejects_incompatible_bundles() {
+        let dir = temp_dir("incompatible");
+        let source = dir.join("source");
+        create_dir_all(&source).unwrap();
+        write(source.join("system.txt"), "a<#>b\n").unwrap();
+
+        let bundle_path = dir.join("bundle.zip");
+        export(&source, &bundle_path, &manifest("1.0.0", EngineType::XP))
+            .unwrap();
+
+        let target = dir.join("target");
+        let version = import(
+            &bundle_path,
+            &target,
+            &manifest("2.0.0", EngineType::XP),
+            false,
+        );
+        let engine = import(
+            &bundle_path,
+            &target,
+            &manifest("1.0.0", EngineType::New),
+            false,
+        );
+        remove_dir_all(&dir).unwrap();
+
+        assert!(version.is_err());
+        assert!(engine.is_err());
+        assert!(!target.exists());
+    }
+
+    #[test]
+    fn rejects_paths_outside_of_translation() {
+        let dir = temp_dir("traversal");
+        let bundle_path = dir.join("bundle.zip");
+        let manifest = manifest("1.0.0", EngineType::VX);
+
+        let mut writer = ZipWriter::new(File::create(&bundle_path).unwrap());
+        writer.start_file(MANIFEST_FILE, file_options()).unwrap();
+        writer
+            .write_all(&serde_json::to_vec(&manifest).unwrap())
+            .unwrap();
+        writer
+            .start_file(
+                format!("{TRANSLATION_DIR}../escaped.txt"),
+                file_options(),
+            )
+            .unwrap();
+        writer.write_all(b"x").unwrap();
+        writer.finish().unwrap();
+
+        let imported =
+            import(&bundle_path, &dir.join("target"), &manifest, false);
+        let escaped = dir.join("escaped.txt").exists();
+        remove_dir_all(&dir).unwrap();
+
+        assert!(imported.is_err());
+        assert!(!escaped);
+    }
+
+    #[test]
+    fn rejects_files_without_manifest() {
+        let dir = temp_dir("plain");
+        let bundle_path = dir.join("bundle.zip");
+        write(&bundle_path, b"not a zip").unwrap();
+
+        let imported = import(
+            &bundle_path,
+            &dir.join("target"),
+            &manifest("1.0.0", EngineType::New),
+            false,
+        );
+        remove_dir_all(&dir).unwrap();
+
+        assert!(imported.is_err());
+    }
+}
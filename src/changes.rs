@@ -0,0 +1,231 @@
+//! Changelog of translation-relevant changes between versions of the game.
+//!
+//! Existing translation files serve as the snapshot of the previous version, like in upgrade, and are compared with fresh translation files of the new version. Sources, that only moved to another position or section, aren't changes, since their translations still apply. Removed and added sources of the same section, that are similar enough, are listed as modified, so translators see, what was edited.
+
+use crate::{fuzzy::similarity, remap, translation::TranslationFile};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+/// Sections, that are at least this similar, are considered renumbered.
+const SECTION_THRESHOLD: f64 = 0.5;
+
+/// Source, that was edited in the new version.
+pub struct Modified {
+    pub section: Option<u16>,
+    pub old_source: String,
+    pub new_source: String,
+}
+
+#[derive(Default)]
+pub struct FileChanges {
+    /// Sources of the new version, that the previous one didn't have, by section.
+    pub added: Vec<(Option<u16>, String)>,
+
+    /// Sources of the previous version, that the new one doesn't have, by section.
+    pub removed: Vec<(Option<u16>, String)>,
+
+    pub modified: Vec<Modified>,
+}
+
+impl FileChanges {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.modified.is_empty()
+    }
+}
+
+/// Returns sources of `file`, that aren't in `other`, with their first section. Each source is returned once.
+fn missing_in<'a>(
+    file: &'a TranslationFile,
+    other: &TranslationFile,
+) -> Vec<(Option<u16>, &'a str)> {
+    let other_sources: HashSet<&str> =
+        other.entries().map(|(_, source, _)| source).collect();
+    let mut seen = HashSet::new();
+
+    file.entries()
+        .filter(|(_, source, _)| {
+            !other_sources.contains(source) && seen.insert(*source)
+        })
+        .map(|(section, source, _)| (section, source))
+        .collect()
+}
+
+/// Compares sources of `old` file of the previous version with `new` file of the new version.
+///
+/// Removed and added sources of the same section are paired as modified, if they're at least `threshold` similar, most similar first.
+pub fn diff(
+    old: &TranslationFile,
+    new: &TranslationFile,
+    threshold: f64,
+) -> FileChanges {
+    // New section ID to old one.
+    let renumbered: HashMap<u16, u16> =
+        remap::derive(old, new, SECTION_THRESHOLD)
+            .into_iter()
+            .map(|(old_id, new_id, _)| (new_id, old_id))
+            .collect();
+
+    let removed = missing_in(old, new);
+    let added = missing_in(new, old);
+
+    let mut used = vec![false; removed.len()];
+    let mut changes = FileChanges::default();
+
+    for (section, new_source) in added {
+        let old_section =
+            section.map(|id| renumbered.get(&id).copied().unwrap_or(id));
+
+        let found = removed
+            .iter()
+            .enumerate()
+            .filter(|(index, (id, _))| *id == old_section && !used[*index])
+            .map(|(index, (_, old_source))| {
+                (similarity(old_source, new_source, threshold), index)
+            })
+            .filter(|(score, _)| *score >= threshold)
+            .max_by(|a, b| a.0.total_cmp(&b.0));
+
+        match found {
+            Some((_, index)) => {
+                used[index] = true;
+                changes.modified.push(Modified {
+                    section,
+                    old_source: removed[index].1.to_string(),
+                    new_source: new_source.to_string(),
+                });
+            }
+            None => changes.added.push((section, new_source.to_string())),
+        }
+    }
+
+    changes.removed = removed
+        .into_iter()
+        .zip(used)
+        .filter(|(_, used)| !used)
+        .map(|((section, source), _)| (section, source.to_string()))
+        .collect();
+
+    changes
+}
+
+/// Returns `text` as Markdown code span, that may contain backticks itself.
+fn code(text: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+
+    for char in text.chars() {
+        if char == '`' {
+            run += 1;
+            longest = longest.max(run);
+        } else {
+            run = 0;
+        }
+    }
+
+    let fence = "`".repeat(longest + 1);
+    let padding = if text.starts_with('`') || text.ends_with('`') {
+        " "
+    } else {
+        ""
+    };
+
+    format!("{fence}{padding}{text}{padding}{fence}")
+}
+
+fn location(section: Option<u16>) -> String {
+    section.map_or_else(String::new, |id| format!("Section {id}: "))
+}
+
+/// Formats changes of all files of game `title` to a Markdown changelog. `unchanged_data` tells, whether hashes of game files match the ones, that were recorded in the last read.
+#[must_use]
+pub fn format_changelog(
+    title: &str,
+    files: &[(String, FileChanges)],
+    unchanged_data: bool,
+) -> String {
+    let mut output = format!("# {title}: Changes of the game\n");
+
+    if unchanged_data {
+        output.push_str(
+            "\nGame files haven't changed since the last read of translation files.\n",
+        );
+    }
+
+    let changed: Vec<_> = files
+        .iter()
+        .filter(|(_, changes)| !changes.is_empty())
+        .collect();
+
+    if changed.is_empty() {
+        output.push_str("\nNo source lines were added, removed or modified.\n");
+        return output;
+    }
+
+    let (added, removed, modified) = changed.iter().fold(
+        (0, 0, 0),
+        |(added, removed, modified), (_, changes)| {
+            (
+                added + changes.added.len(),
+                removed + changes.removed.len(),
+                modified + changes.modified.len(),
+            )
+        },
+    );
+
+    let _ = writeln!(
+        output,
+        "\n{added} source lines were added, {removed} removed and {modified} modified in {} files.",
+        changed.len()
+    );
+
+    for (name, changes) in changed {
+        let _ = writeln!(output, "\n## {name}");
+
+        if !changes.added.is_empty() {
+            output.push_str("\n### Added\n\n");
+
+            for (section, source) in &changes.added {
+                let _ = writeln!(
+                    output,
+                    "- {}{}",
+                    location(*section),
+                    code(source)
+                );
+            }
+        }
+
+        if !changes.removed.is_empty() {
+            output.push_str("\n### Removed\n\n");
+
+            for (section, source) in &changes.removed {
+                let _ = writeln!(
+                    output,
+                    "- {}{}",
+                    location(*section),
+                    code(source)
+                );
+            }
+        }
+
+        if !changes.modified.is_empty() {
+            output.push_str("\n### Modified\n\n");
+
+            for modified in &changes.modified {
+                let _ = writeln!(
+                    output,
+                    "- {}{} → {}",
+                    location(modified.section),
+                    code(&modified.old_source),
+                    code(&modified.new_source)
+                );
+            }
+        }
+    }
+
+    output
+}
@@ -0,0 +1,131 @@
+//! Annotation of control codes with their values from the database.
+//!
+//! Messages refer to actors and variables by ID: `\N[3]` is displayed as the name of actor 3, and `\V[5]` as the value of variable 5. Translators can't tell, who or what that is, without opening the editor, so entries, that use these codes, are preceded by a comment with actor names and variable names, e.g. `<!-- CODES: \N[3] = Harold, \V[5] = Gold count -->`. The library ignores such comments on write.
+
+use crate::{
+    data::{data_file_path, load_rpgm_file, named_entries},
+    translation::{TranslationFile, translation_files},
+};
+use anyhow::Result;
+use regex::Regex;
+use rvpacker_lib::types::EngineType;
+use std::{
+    collections::HashMap,
+    fs::{read_to_string, write},
+    path::Path,
+    sync::LazyLock,
+};
+use tracing::info;
+
+pub const CODES_COMMENT_PREFIX: &str = "<!-- CODES: ";
+
+static CODE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\\([nv])\[(\d+)\]").unwrap());
+
+/// Names of actors and variables by their IDs.
+#[derive(Default)]
+struct Names {
+    actors: HashMap<u16, String>,
+    variables: HashMap<u16, String>,
+}
+
+impl Names {
+    fn load(source_path: &Path, engine_type: EngineType) -> Result<Self> {
+        let mut names = Self::default();
+
+        let actors_path = data_file_path(source_path, "Actors", engine_type);
+
+        if actors_path.exists() {
+            let value = load_rpgm_file(&actors_path, engine_type)?;
+
+            names.actors = named_entries(&value)
+                .into_iter()
+                .filter(|(_, name)| !name.trim().is_empty())
+                .map(|(id, name)| (id, name.to_string()))
+                .collect();
+        }
+
+        let system_path = data_file_path(source_path, "System", engine_type);
+
+        if system_path.exists() {
+            let value = load_rpgm_file(&system_path, engine_type)?;
+
+            names.variables = value
+                .as_object()
+                .and_then(|object| object.get("variables"))
+                .and_then(|variables| variables.as_array())
+                .into_iter()
+                .flatten()
+                .enumerate()
+                .filter_map(|(id, name)| {
+                    let name = name.as_str()?;
+                    (!name.trim().is_empty())
+                        .then(|| (id as u16, name.to_string()))
+                })
+                .collect();
+        }
+
+        Ok(names)
+    }
+
+    /// Returns `code = value` pairs of codes in `source`, that can be resolved, in order of their first occurrence.
+    fn resolve(&self, source: &str) -> Vec<String> {
+        let mut resolved: Vec<String> = Vec::new();
+
+        for captures in CODE_RE.captures_iter(source) {
+            let Ok(id) = captures[2].parse::<u16>() else {
+                continue;
+            };
+
+            let code = captures[1].to_ascii_uppercase();
+            let names = if code == "N" {
+                &self.actors
+            } else {
+                &self.variables
+            };
+
+            let Some(name) = names.get(&id) else {
+                continue;
+            };
+
+            // Codes are case-insensitive, so `\n[1]` and `\N[1]` are shown once.
+            let pair = format!("\\{code}[{id}] = {name}");
+
+            if !resolved.contains(&pair) {
+                resolved.push(pair);
+            }
+        }
+
+        resolved
+    }
+}
+
+/// Annotates entries of all translation files in `translation_path` with values of their control codes. Previous annotations are replaced, so the files can be annotated repeatedly.
+pub fn annotate(
+    source_path: &Path,
+    translation_path: &Path,
+    engine_type: EngineType,
+) -> Result<()> {
+    let names = Names::load(source_path, engine_type)?;
+    let mut annotated = 0;
+
+    for name in translation_files(translation_path)? {
+        let path = translation_path.join(&name);
+        let content = read_to_string(&path)?;
+        let mut file = TranslationFile::parse(&content);
+
+        annotated += file.annotate(CODES_COMMENT_PREFIX, |_, source| {
+            let resolved = names.resolve(source);
+            (!resolved.is_empty()).then(|| resolved.join(", "))
+        });
+
+        let serialized = file.serialize();
+
+        if serialized != content {
+            write(&path, serialized)?;
+        }
+    }
+
+    info!("Annotated control codes of {annotated} entries.");
+    Ok(())
+}
@@ -0,0 +1,244 @@
+//! Transparent compression of translation files.
+//!
+//! Library only works with plain `.txt` files, so compressed files are unpacked next to their archives before the command runs, and packed back after it finishes.
+
+use anyhow::{Result, bail};
+use clap::ValueEnum;
+use flate2::{Compression as Level, read::GzDecoder, write::GzEncoder};
+use std::{
+    fs::{self, File, read_dir, remove_file},
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+use tracing::debug;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Compression {
+    /// `.txt.gz` files.
+    Gzip,
+
+    /// `.txt.zst` files.
+    Zstd,
+}
+
+impl Compression {
+    const fn extension(self) -> &'static str {
+        match self {
+            Self::Gzip => "gz",
+            Self::Zstd => "zst",
+        }
+    }
+
+    /// Decompresses `compressed` into `plain`.
+    fn unpack_file(self, compressed: &Path, plain: &Path) -> Result<()> {
+        let input = BufReader::new(File::open(compressed)?);
+        let mut output = BufWriter::new(File::create(plain)?);
+
+        match self {
+            Self::Gzip => io::copy(&mut GzDecoder::new(input), &mut output)?,
+            Self::Zstd => {
+                io::copy(&mut zstd::Decoder::new(input)?, &mut output)?
+            }
+        };
+
+        output.flush()?;
+        Ok(())
+    }
+
+    /// Compresses `plain` into `compressed`.
+    fn pack_file(self, plain: &Path, compressed: &Path) -> Result<()> {
+        let mut input = BufReader::new(File::open(plain)?);
+        let output = BufWriter::new(File::create(compressed)?);
+
+        match self {
+            Self::Gzip => {
+                let mut encoder = GzEncoder::new(output, Level::default());
+                io::copy(&mut input, &mut encoder)?;
+                encoder.finish()?.flush()?;
+            }
+            Self::Zstd => {
+                let mut encoder = zstd::Encoder::new(output, 0)?;
+                io::copy(&mut input, &mut encoder)?;
+                encoder.finish()?.flush()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns `(compressed, plain)` paths of translation files, that are compressed with `compression`.
+fn compressed_files(
+    translation_path: &Path,
+    compression: Compression,
+) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let suffix = format!(".txt.{}", compression.extension());
+
+    let mut files: Vec<_> = read_dir(translation_path)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter_map(|path| {
+            let name = path.file_name()?.to_str()?;
+            let plain = name.strip_suffix(&suffix)?;
+            let plain = path.with_file_name(format!("{plain}.txt"));
+            Some((path, plain))
+        })
+        .collect();
+
+    files.sort();
+    Ok(files)
+}
+
+fn is_newer(path: &Path, than: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified());
+
+    matches!(
+        (modified(path), modified(than)),
+        (Ok(path), Ok(than)) if path > than
+    )
+}
+
+/// Unpacks compressed translation files in `translation_path`, and returns the compression, that files should be packed with after the command. `requested` compression is used, if files aren't compressed yet.
+///
+/// If a plain file is newer than its compressed counterpart, which happens when the previous run was interrupted, the plain file is kept.
+pub fn unpack(
+    translation_path: &Path,
+    requested: Option<Compression>,
+) -> Result<Option<Compression>> {
+    if !translation_path.exists() {
+        return Ok(requested);
+    }
+
+    let mut found = None;
+
+    // Both checks come before any file is unpacked, so failures don't leave plain files, that nothing packs back.
+    for compression in [Compression::Gzip, Compression::Zstd] {
+        let files = compressed_files(translation_path, compression)?;
+
+        if files.is_empty() {
+            continue;
+        }
+
+        if found.is_some() {
+            bail!(
+                "Translation files are compressed with different formats. Use only one of them."
+            );
+        }
+
+        found = Some((compression, files));
+    }
+
+    let Some((found, files)) = found else {
+        return Ok(requested);
+    };
+
+    if let Some(requested) = requested
+        && found != requested
+    {
+        bail!(
+            "Translation files are already compressed with {found:?}, but {requested:?} was requested."
+        );
+    }
+
+    for (compressed, plain) in files {
+        if is_newer(&plain, &compressed) {
+            continue;
+        }
+
+        found.unpack_file(&compressed, &plain)?;
+
+        debug!("{}: Unpacked.", compressed.display());
+    }
+
+    Ok(Some(found))
+}
+
+/// Packs all plain translation files in `translation_path` with `compression`, and removes them.
+pub fn pack(translation_path: &Path, compression: Compression) -> Result<()> {
+    if !translation_path.exists() {
+        return Ok(());
+    }
+
+    for entry in read_dir(translation_path)?.flatten() {
+        let plain = entry.path();
+
+        if plain.extension().is_none_or(|ext| ext != "txt") {
+            continue;
+        }
+
+        let mut compressed = plain.clone().into_os_string();
+        compressed.push(".");
+        compressed.push(compression.extension());
+
+        compression.pack_file(&plain, Path::new(&compressed))?;
+
+        remove_file(&plain)?;
+        debug!("{}: Packed.", plain.display());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, read_to_string, remove_dir_all, write};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("rvpacker-{}-{name}", std::process::id()));
+        let _ = remove_dir_all(&dir);
+        create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn plain_files(dir: &Path) -> usize {
+        read_dir(dir)
+            .unwrap()
+            .flatten()
+            .filter(|entry| {
+                entry.path().extension().is_some_and(|ext| ext == "txt")
+            })
+            .count()
+    }
+
+    #[test]
+    fn round_trips_files() {
+        for compression in [Compression::Gzip, Compression::Zstd] {
+            let dir = temp_dir("compression");
+            write(dir.join("map.txt"), "a<#>b").unwrap();
+
+            pack(&dir, compression).unwrap();
+            let packed = plain_files(&dir);
+            let found = unpack(&dir, None);
+            let content = read_to_string(dir.join("map.txt"));
+            remove_dir_all(&dir).unwrap();
+
+            assert_eq!(packed, 0);
+            assert_eq!(found.unwrap(), Some(compression));
+            assert_eq!(content.unwrap(), "a<#>b");
+        }
+    }
+
+    #[test]
+    fn rejects_mixed_and_mismatched_formats_before_unpacking() {
+        let dir = temp_dir("compression-mixed");
+        write(dir.join("map.txt"), "a<#>b").unwrap();
+        pack(&dir, Compression::Gzip).unwrap();
+
+        let mismatched = unpack(&dir, Some(Compression::Zstd));
+        let mismatched_plain = plain_files(&dir);
+
+        write(dir.join("system.txt"), "c<#>d").unwrap();
+        pack(&dir, Compression::Zstd).unwrap();
+
+        let mixed = unpack(&dir, None);
+        let mixed_plain = plain_files(&dir);
+        remove_dir_all(&dir).unwrap();
+
+        assert!(mismatched.is_err());
+        assert_eq!(mismatched_plain, 0);
+        assert!(mixed.is_err());
+        assert_eq!(mixed_plain, 0);
+    }
+}
@@ -0,0 +1,104 @@
+//! Annotation of messages with the way they're shown.
+//!
+//! Tone and speaker are often ambiguous from the text alone, so messages are preceded by a comment with the face graphic, window settings and conditions of the map event page, that shows them, e.g. `<!-- CONTEXT: face Actor1 #2; window top, dim; page: switch 5 ON -->`. Default window settings, bottom position and normal background, aren't mentioned. The library ignores such comments on write.
+
+use crate::{
+    dialogue::{self, Message},
+    translation::TranslationFile,
+};
+use anyhow::Result;
+use rvpacker_lib::types::EngineType;
+use std::{
+    collections::HashMap,
+    fs::{read_to_string, write},
+    path::Path,
+};
+use tracing::info;
+
+pub const CONTEXT_COMMENT_PREFIX: &str = "<!-- CONTEXT: ";
+
+/// Returns the description of the way `message` is shown, or `None` if there's nothing to describe.
+fn describe(message: &Message) -> Option<String> {
+    let mut parts = Vec::new();
+
+    if !message.face_name.is_empty() {
+        parts.push(format!(
+            "face {} #{}",
+            message.face_name, message.face_index
+        ));
+    }
+
+    let mut window = Vec::new();
+
+    match message.position {
+        0 => window.push("top"),
+        1 => window.push("middle"),
+        _ => {}
+    }
+
+    match message.background {
+        1 => window.push("dim"),
+        2 => window.push("transparent"),
+        _ => {}
+    }
+
+    if !window.is_empty() {
+        parts.push(format!("window {}", window.join(", ")));
+    }
+
+    if !message.conditions.is_empty() {
+        parts.push(format!("page: {}", message.conditions.join(", ")));
+    }
+
+    (!parts.is_empty()).then(|| parts.join("; "))
+}
+
+/// Annotates messages in translation files of maps, common events and troops in `translation_path` with the way they're shown. Previous annotations are replaced, so the files can be annotated repeatedly.
+pub fn annotate(
+    source_path: &Path,
+    translation_path: &Path,
+    engine_type: EngineType,
+) -> Result<()> {
+    let mut contexts: HashMap<&str, HashMap<(u16, String), String>> =
+        HashMap::new();
+
+    for (location, message) in dialogue::messages(source_path, engine_type)? {
+        let Some(description) = describe(&message) else {
+            continue;
+        };
+
+        // The library removes duplicates, so the first message wins.
+        contexts
+            .entry(location.file)
+            .or_default()
+            .entry((location.id, message.source()))
+            .or_insert(description);
+    }
+
+    let mut annotated = 0;
+
+    for name in ["maps.txt", "commonevents.txt", "troops.txt"] {
+        let path = translation_path.join(name);
+
+        if !path.exists() {
+            continue;
+        }
+
+        let content = read_to_string(&path)?;
+        let mut file = TranslationFile::parse(&content);
+        let file_contexts = contexts.get(name);
+
+        annotated += file.annotate(CONTEXT_COMMENT_PREFIX, |id, source| {
+            file_contexts?.get(&(id?, source.to_string())).cloned()
+        });
+
+        let serialized = file.serialize();
+
+        if serialized != content {
+            write(&path, serialized)?;
+        }
+    }
+
+    info!("Annotated context of {annotated} messages.");
+    Ok(())
+}
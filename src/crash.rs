@@ -0,0 +1,232 @@
+//! Crash reports, that non-technical users can attach to bug reports, so issues can be reproduced.
+//!
+//! On panics, and on errors without an [`ErrorKind`], that happen while a file is processed, a report is written next to the log file, if it's enabled, or to the temporary directory. It's never written to the game's directory, that the output directory defaults to. It holds the version of the tool, the command line, the engine of the game, the file, that was processed, the project's metadata, recent log messages and the backtrace.
+
+use crate::{
+    error::{ErrorKind, GENERIC_EXIT_CODE},
+    log_file,
+};
+use std::{
+    backtrace::{Backtrace, BacktraceStatus},
+    collections::VecDeque,
+    fmt::{self, Write as _},
+    fs::{read_to_string, write},
+    panic,
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::{Event, Subscriber, field::Field};
+use tracing_subscriber::{field::Visit, layer::Context, layer::Layer};
+
+/// Number of recent log messages, that are kept for the report.
+const RECENT_MESSAGES: usize = 50;
+
+/// Library logs `name: Started ...` before processing each file, and `name: Successfully ...` after.
+const STARTED_PREFIX: &str = "Started ";
+const FINISHED_PREFIX: &str = "Successfully ";
+
+#[derive(Default)]
+struct State {
+    engine: Option<String>,
+    input_dir: Option<PathBuf>,
+    log_dir: Option<PathBuf>,
+    metadata_file_path: Option<PathBuf>,
+    current_file: Option<String>,
+    recent: VecDeque<String>,
+}
+
+static STATE: LazyLock<Mutex<State>> = LazyLock::new(Mutex::default);
+
+/// Records the game, that the command processes.
+pub fn set_project(
+    engine: String,
+    input_dir: &Path,
+    metadata_file_path: &Path,
+) {
+    if let Ok(mut state) = STATE.lock() {
+        state.engine = Some(engine);
+        state.input_dir = Some(input_dir.to_path_buf());
+        state.metadata_file_path = Some(metadata_file_path.to_path_buf());
+    }
+}
+
+/// Records the log file at `path`, so reports are written next to it.
+pub fn set_log_file(path: &Path) {
+    if let Ok(mut state) = STATE.lock() {
+        state.log_dir = path.parent().map(Path::to_path_buf);
+    }
+}
+
+/// Records the file, that's processed, until dropped.
+#[must_use]
+pub struct Processing;
+
+impl Drop for Processing {
+    fn drop(&mut self) {
+        if let Ok(mut state) = STATE.lock() {
+            state.current_file = None;
+        }
+    }
+}
+
+/// Records `path` as the file, that's processed, for the library-independent code, that doesn't log it.
+pub fn processing(path: &Path) -> Processing {
+    if let Ok(mut state) = STATE.lock() {
+        state.current_file = Some(path.display().to_string());
+    }
+
+    Processing
+}
+
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.0, "{value:?}");
+        }
+    }
+}
+
+/// [`Layer`], that keeps recent log messages and the file, that's being processed.
+pub struct Recorder;
+
+impl<S: Subscriber> Layer<S> for Recorder {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = MessageVisitor(String::new());
+        event.record(&mut visitor);
+
+        let Ok(mut state) = STATE.lock() else {
+            return;
+        };
+
+        if let Some((name, message)) = visitor.0.split_once(": ") {
+            if message.starts_with(STARTED_PREFIX) {
+                state.current_file = Some(name.to_string());
+            } else if message.starts_with(FINISHED_PREFIX) {
+                state.current_file = None;
+            }
+        }
+
+        if state.recent.len() == RECENT_MESSAGES {
+            state.recent.pop_front();
+        }
+
+        state.recent.push_back(format!(
+            "{:>5} {}",
+            event.metadata().level(),
+            visitor.0
+        ));
+    }
+}
+
+/// Writes the report of the failure with `description` and `backtrace`, and returns its path.
+fn write_report(description: &str, backtrace: &str) -> Option<PathBuf> {
+    // The report is written even if a thread panicked while holding the state.
+    let state = STATE
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+    let mut report = String::from("rvpacker-txt-rs crash report\n\n");
+    let _ = writeln!(report, "Version: {}", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(
+        report,
+        "Platform: {} {}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    let _ = writeln!(
+        report,
+        "Command line: {}",
+        log_file::redacted_arguments().join(" ")
+    );
+    let _ = writeln!(
+        report,
+        "Engine: {}",
+        state.engine.as_deref().unwrap_or("unknown")
+    );
+
+    if let Some(input_dir) = &state.input_dir {
+        let _ = writeln!(report, "Input directory: {}", input_dir.display());
+    }
+
+    let _ = writeln!(
+        report,
+        "File: {}",
+        state.current_file.as_deref().unwrap_or("none")
+    );
+    let _ = writeln!(report, "\nFailure:\n{description}");
+
+    if let Some(metadata) = state
+        .metadata_file_path
+        .as_ref()
+        .and_then(|path| read_to_string(path).ok())
+    {
+        let _ = writeln!(report, "\nMetadata:\n{metadata}");
+    }
+
+    report.push_str("\nRecent log:\n");
+
+    for message in &state.recent {
+        let _ = writeln!(report, "{message}");
+    }
+
+    let _ = writeln!(report, "\nBacktrace:\n{backtrace}");
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let name = format!("rvpacker-crash-{timestamp}.txt");
+
+    [state.log_dir.clone(), Some(std::env::temp_dir())]
+        .into_iter()
+        .flatten()
+        .map(|dir| dir.join(&name))
+        .find(|path| write(path, &report).is_ok())
+}
+
+fn announce(path: Option<PathBuf>) {
+    match path {
+        Some(path) => eprintln!(
+            "Crash report was written to {}. Attach it to the bug report, so the issue can be reproduced.",
+            path.display()
+        ),
+        None => eprintln!("Crash report couldn't be written."),
+    }
+}
+
+/// Installs the panic hook, that writes crash reports.
+pub fn install() {
+    let default_hook = panic::take_hook();
+
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+
+        let backtrace = Backtrace::force_capture();
+        announce(write_report(
+            &format!("Panic: {info}"),
+            &backtrace.to_string(),
+        ));
+    }));
+}
+
+/// Writes the crash report of `err`, if it's unexpected, i.e. doesn't have an [`ErrorKind`], and happened while a file was processed. Other errors are about the input, and their messages say what to fix.
+pub fn report_error(err: &anyhow::Error) {
+    let processing =
+        STATE.lock().is_ok_and(|state| state.current_file.is_some());
+
+    if !processing || ErrorKind::exit_code_of(err) != GENERIC_EXIT_CODE {
+        return;
+    }
+
+    let backtrace = match err.backtrace().status() {
+        BacktraceStatus::Captured => err.backtrace().to_string(),
+        _ => String::from(
+            "Not captured. Set `RUST_BACKTRACE=1` environment variable to capture it.",
+        ),
+    };
+
+    announce(write_report(&format!("Error: {err:#}"), &backtrace));
+}
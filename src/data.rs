@@ -0,0 +1,297 @@
+use crate::crash;
+use anyhow::{Context, Result};
+use marshal_rs::{Value, dump, load_utf8};
+use rvpacker_lib::{get_engine_extension, types::EngineType};
+use std::{
+    collections::HashMap,
+    fs::{read, read_dir, write},
+    path::{Path, PathBuf},
+};
+
+/// MZ includes Byte Order Mark in files.
+const BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+
+/// Largest map ID, that the library can process. It reads at most four digits of map file names.
+pub const MAX_MAP_ID: u16 = 9999;
+
+/// Returns path to the data file with `stem` in `dir`, e.g. `data/CommonEvents.json`.
+#[must_use]
+pub fn data_file_path(
+    dir: &Path,
+    stem: &str,
+    engine_type: EngineType,
+) -> PathBuf {
+    dir.join(format!("{stem}.{}", get_engine_extension(engine_type)))
+}
+
+/// Returns `(id, path)` of all map files in `source_path`, sorted by ID.
+pub fn map_files(
+    source_path: &Path,
+    engine_type: EngineType,
+) -> Result<Vec<(u16, PathBuf)>> {
+    let extension = get_engine_extension(engine_type);
+    let mut maps: Vec<_> = read_dir(source_path)
+        .with_context(|| format!("Reading {}", source_path.display()))?
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let id = path
+                .file_name()?
+                .to_str()?
+                .strip_prefix("Map")?
+                .strip_suffix(extension)?
+                .strip_suffix('.')?
+                .parse()
+                .ok()?;
+
+            Some((id, path))
+        })
+        .collect();
+
+    maps.sort_unstable_by_key(|(id, _)| *id);
+    Ok(maps)
+}
+
+/// Returns names of files in `source_path`, that the library would take for maps, but can't process correctly, with the reason.
+///
+/// The library processes every file, which name starts with `Map` and a digit, and takes the leading digits for the ID. So copies like `Map001 - Copy.json` would overwrite the translation of the map, and IDs above [`MAX_MAP_ID`] crash it.
+pub fn unsupported_map_files(
+    source_path: &Path,
+    engine_type: EngineType,
+) -> Result<Vec<(String, String)>> {
+    let extension = get_engine_extension(engine_type);
+    let mut unsupported = Vec::new();
+    let mut by_id: HashMap<u16, Vec<String>> = HashMap::new();
+
+    for entry in read_dir(source_path)
+        .with_context(|| format!("Reading {}", source_path.display()))?
+        .flatten()
+    {
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+
+        let Some(stem) = name
+            .strip_prefix("Map")
+            .and_then(|rest| rest.strip_suffix(extension))
+            .and_then(|rest| rest.strip_suffix('.'))
+            .filter(|stem| stem.starts_with(|c: char| c.is_ascii_digit()))
+        else {
+            continue;
+        };
+
+        if !stem.bytes().all(|b| b.is_ascii_digit()) {
+            unsupported.push((
+                name.clone(),
+                String::from("it isn't a map, but its name starts like one"),
+            ));
+            continue;
+        }
+
+        match stem.parse::<u16>() {
+            Ok(id) if id <= MAX_MAP_ID && stem.len() <= 4 => {
+                by_id.entry(id).or_default().push(name);
+            }
+            _ => unsupported.push((
+                name.clone(),
+                format!("map IDs above {MAX_MAP_ID} aren't supported"),
+            )),
+        }
+    }
+
+    // Of names with the same ID, like `Map01` and `Map001`, the one in the editor's format is kept.
+    for (id, mut names) in by_id {
+        if names.len() < 2 {
+            continue;
+        }
+
+        let canonical = format!("Map{id:03}.{extension}");
+        names.sort_by_key(|name| *name != canonical);
+
+        let kept = names.remove(0);
+
+        for name in names {
+            unsupported.push((name, format!("its ID {id} duplicates {kept}")));
+        }
+    }
+
+    unsupported.sort_unstable();
+    Ok(unsupported)
+}
+
+/// Loads RPG Maker data file into a [`Value`], regardless of the engine.
+///
+/// JSON files of newer engines are converted to the same [`Value`] representation that Marshal files of older engines use, so callers can walk both uniformly.
+pub fn load_rpgm_file(path: &Path, engine_type: EngineType) -> Result<Value> {
+    let _processing = crash::processing(path);
+    let content =
+        read(path).with_context(|| format!("Reading {}", path.display()))?;
+
+    let value = if engine_type.is_new() {
+        let content = content.strip_prefix(BOM).unwrap_or(&content);
+        let parsed: serde_json::Value = serde_json::from_slice(content)
+            .with_context(|| format!("Parsing {}", path.display()))?;
+
+        Value::from(parsed)
+    } else {
+        load_utf8(&content, Some(""))
+            .with_context(|| format!("Loading {}", path.display()))?
+    };
+
+    Ok(value)
+}
+
+/// Returns `(id, name)` pairs of all objects in a data file.
+///
+/// Handles both arrays of objects (`CommonEvents`, `Troops`, MV/MZ `MapInfos`) and hashes of objects keyed by ID (older engines' `MapInfos`). Null entries are skipped.
+pub fn named_entries(value: &Value) -> Vec<(u16, &str)> {
+    fn name_of(object: &Value) -> Option<&str> {
+        object.as_object()?.get("name")?.as_str()
+    }
+
+    if let Some(array) = value.as_array() {
+        array
+            .iter()
+            .filter_map(|object| {
+                let id = object.as_object()?.get("id")?.as_int()?;
+                Some((id as u16, name_of(object)?))
+            })
+            .collect()
+    } else if let Some(hashmap) = value.as_hashmap() {
+        hashmap
+            .0
+            .iter()
+            .filter_map(|(id, object)| {
+                Some((id.as_int()? as u16, name_of(object)?))
+            })
+            .collect()
+    } else {
+        Vec::new()
+    }
+}
+
+/// Removes events of a map, which names satisfy `matches`, and returns their count.
+///
+/// MV/MZ maps hold events in an array, where removed events are replaced with nulls, so the rest keep their IDs. Older engines' maps hold them in a hash keyed by ID.
+pub fn remove_named_events(
+    map: &mut Value,
+    matches: impl Fn(&str) -> bool,
+) -> usize {
+    let is_matched = |event: &Value| {
+        event
+            .as_object()
+            .and_then(|object| object.get("name")?.as_str())
+            .is_some_and(&matches)
+    };
+
+    let Some(events) = map
+        .as_object_mut()
+        .and_then(|object| object.get_mut("events"))
+    else {
+        return 0;
+    };
+
+    let mut removed = 0;
+
+    if let Some(array) = events.as_array_mut() {
+        for event in array.iter_mut().filter(|event| is_matched(event)) {
+            *event = Value::null();
+            removed += 1;
+        }
+    } else if let Some(hashmap) = events.as_hashmap_mut() {
+        let before = hashmap.0.len();
+        hashmap.0.retain(|_, event| !is_matched(event));
+        removed = before - hashmap.0.len();
+    }
+
+    removed
+}
+
+/// Serializes [`Value`] back to RPG Maker data file.
+pub fn save_rpgm_file(
+    path: &Path,
+    value: Value,
+    engine_type: EngineType,
+) -> Result<()> {
+    let content = if engine_type.is_new() {
+        serde_json::to_vec(&serde_json::Value::from(value))?
+    } else {
+        dump(value, Some(""))
+    };
+
+    write(path, content)
+        .with_context(|| format!("Writing {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matches_pattern;
+    use marshal_rs::{HashMap, Object};
+
+    fn event(name: &str) -> Value {
+        Value::object(Object::from([("name".to_string(), Value::string(name))]))
+    }
+
+    fn remove(map: &mut Value, patterns: &[&str]) -> usize {
+        remove_named_events(map, |name| {
+            patterns
+                .iter()
+                .any(|pattern| matches_pattern(pattern, name))
+        })
+    }
+
+    #[test]
+    fn removes_named_events_of_mv_maps() {
+        let mut map = Value::from(serde_json::json!({
+            "events": [null, {"name": "EV001"}, {"name": "Debug room"}, {"name": "DEBUG"}, {"name": "Shop"}]
+        }));
+
+        assert_eq!(remove(&mut map, &["Debug*", "DEBUG"]), 2);
+
+        let names: Vec<Option<&str>> = map.as_object().unwrap()["events"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|event| {
+                event.as_object().and_then(|object| object["name"].as_str())
+            })
+            .collect();
+
+        assert_eq!(names, [None, Some("EV001"), None, None, Some("Shop")]);
+    }
+
+    #[test]
+    fn removes_named_events_of_older_maps() {
+        let events = HashMap::from([
+            (Value::int(1), event("EV001")),
+            (Value::int(2), event("Test?")),
+            (Value::int(5), event("Tester")),
+        ]);
+        let mut map = Value::object(Object::from([(
+            "events".to_string(),
+            Value::hash(events),
+        )]));
+
+        assert_eq!(remove(&mut map, &["Test??"]), 1);
+        assert_eq!(remove(&mut map, &["Nothing*"]), 0);
+
+        let ids: Vec<i32> = map.as_object().unwrap()["events"]
+            .as_hashmap()
+            .unwrap()
+            .0
+            .keys()
+            .filter_map(|id| id.as_int())
+            .collect();
+
+        assert_eq!(ids, [1, 2]);
+    }
+
+    #[test]
+    fn skips_maps_without_events() {
+        let mut map = Value::from(serde_json::json!({ "displayName": "Town" }));
+
+        assert_eq!(remove(&mut map, &["*"]), 0);
+    }
+}
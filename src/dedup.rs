@@ -0,0 +1,148 @@
+//! Re-application of a duplicate mode to existing translation files.
+//!
+//! Translation files are read from scratch in the wanted mode, and translations are carried over from existing files: `remove` mode merges duplicates of a source into its first entry, `allow` mode restores duplicates in every section, that contains the source. Duplicates, that were translated differently, are conflicts, and one of the translations has to be chosen. Translations of in-game map names, that are kept in comments, are carried over by section, so converting a project between modes loses no work.
+
+use crate::translation::{
+    DISPLAY_NAME_COMMENT_PREFIX, Line, TranslationFile, effective_translation,
+};
+use anyhow::Result;
+use rvpacker_lib::SEPARATOR;
+use std::collections::HashMap;
+
+#[derive(Default)]
+pub struct FileReport {
+    /// Entries, which translation was carried over.
+    pub carried: usize,
+
+    /// Entries, which duplicates were translated differently.
+    pub conflicts: usize,
+}
+
+/// Splits `<!-- IN-GAME DISPLAYED NAME: name --><#>translation` comment to its name and translation parts.
+pub fn split_display_name(comment: &str) -> Option<(&str, &str)> {
+    if !comment.starts_with(DISPLAY_NAME_COMMENT_PREFIX) {
+        return None;
+    }
+
+    comment.rsplit_once(SEPARATOR)
+}
+
+/// Returns translations of in-game map names by section and name comment.
+pub fn display_names(
+    file: &TranslationFile,
+) -> HashMap<(Option<u16>, &str), &str> {
+    let mut names = HashMap::new();
+    let mut section = None;
+
+    for line in &file.lines {
+        match line {
+            Line::Id(id) => section = Some(*id),
+            Line::Comment(comment) => {
+                if let Some((name, translation)) = split_display_name(comment)
+                    && !translation.is_empty()
+                {
+                    names.insert((section, name), translation);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    names
+}
+
+/// Carries translations of `old` file over to `new` file, that was read in the wanted duplicate mode.
+///
+/// In `allow` mode, translation of the same section takes precedence. Otherwise, distinct translations of the source in all sections are candidates, and `choose(source, candidates)` returns the index of the one to use, if there's more than one. Candidates are sorted from the most used to the least used.
+pub fn reconcile(
+    old: &TranslationFile,
+    new: &mut TranslationFile,
+    allow: bool,
+    mut choose: impl FnMut(&str, &[&str]) -> Result<usize>,
+) -> Result<FileReport> {
+    let mut report = FileReport::default();
+    let mut by_section: HashMap<(Option<u16>, &str), &str> = HashMap::new();
+
+    // Translations of each source with the count of their uses.
+    let mut by_source: HashMap<&str, Vec<(&str, usize)>> = HashMap::new();
+
+    for (id, source, translation) in old.entries() {
+        if translation.is_empty() {
+            continue;
+        }
+
+        by_section.entry((id, source)).or_insert(translation);
+
+        let candidates = by_source.entry(source).or_default();
+
+        if let Some((_, count)) = candidates
+            .iter_mut()
+            .find(|(candidate, _)| *candidate == translation)
+        {
+            *count += 1;
+        } else {
+            candidates.push((translation, 1));
+        }
+    }
+
+    let by_source: HashMap<&str, Vec<&str>> = by_source
+        .into_iter()
+        .map(|(source, mut candidates)| {
+            candidates.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+            (source, candidates.into_iter().map(|(c, _)| c).collect())
+        })
+        .collect();
+
+    let display_names = display_names(old);
+    let mut section = None;
+
+    for line in &mut new.lines {
+        let (source, translation) = match line {
+            Line::Id(id) => {
+                section = Some(*id);
+                continue;
+            }
+            Line::Comment(comment) => {
+                if let Some((name, translation)) = split_display_name(comment)
+                    && translation.is_empty()
+                    && let Some(old_translation) =
+                        display_names.get(&(section, name))
+                {
+                    *comment = format!("{name}{SEPARATOR}{old_translation}");
+                    report.carried += 1;
+                }
+
+                continue;
+            }
+            Line::Entry {
+                source,
+                translation,
+            } => (source, translation),
+            Line::Raw(_) => continue,
+        };
+
+        if !effective_translation(translation).is_empty() {
+            continue;
+        }
+
+        let same_section = by_section.get(&(section, source.as_str()));
+
+        let found = if let Some(found) = same_section.filter(|_| allow) {
+            *found
+        } else {
+            match by_source.get(source.as_str()).map(Vec::as_slice) {
+                None | Some([]) => continue,
+                Some([found]) => *found,
+                Some(candidates) => {
+                    report.conflicts += 1;
+                    candidates[choose(source, candidates)?]
+                }
+            }
+        };
+
+        *translation = found.to_string();
+        report.carried += 1;
+    }
+
+    Ok(report)
+}
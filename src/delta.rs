@@ -0,0 +1,267 @@
+//! Binary deltas of big files in patches.
+//!
+//! A delta describes the new file as copies of ranges of the old file, and literal bytes, that don't appear in it:
+//!
+//! ```text
+//! "RVPD" <new size: u32>
+//! 0x00 <offset: u32> <length: u32>   - copies the range of the old file
+//! 0x01 <length: u32> <bytes>         - inserts the bytes
+//! ```
+//!
+//! Numbers are little-endian. Translated data files keep most of their structure, so their deltas are a fraction of their size.
+//!
+//! Patches, that mustn't redistribute any data of the game, use standard VCDIFF deltas instead, which `xdelta3` creates and applies, so players may apply them with any VCDIFF tool.
+
+use crate::process::run;
+use anyhow::{Result, bail};
+use std::{
+    collections::HashMap,
+    fs::{remove_file, write},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+const MAGIC: &[u8; 4] = b"RVPD";
+
+const COPY: u8 = 0;
+const INSERT: u8 = 1;
+
+/// Length of windows, that matches are searched by. Shorter matches are inserted as is.
+const WINDOW: usize = 16;
+
+/// Distance between indexed windows of the old file.
+const STEP: usize = 4;
+
+fn push_u32(data: &mut Vec<u8>, value: usize) -> Result<()> {
+    let Ok(value) = u32::try_from(value) else {
+        bail!("File is too large for a delta.");
+    };
+
+    data.extend_from_slice(&value.to_le_bytes());
+    Ok(())
+}
+
+fn push_insert(delta: &mut Vec<u8>, bytes: &[u8]) -> Result<()> {
+    if bytes.is_empty() {
+        return Ok(());
+    }
+
+    delta.push(INSERT);
+    push_u32(delta, bytes.len())?;
+    delta.extend_from_slice(bytes);
+    Ok(())
+}
+
+/// Returns the delta, that turns `old` file into `new` one.
+pub fn encode(old: &[u8], new: &[u8]) -> Result<Vec<u8>> {
+    let mut index: HashMap<&[u8], usize> = HashMap::new();
+
+    for offset in (0..old.len().saturating_sub(WINDOW - 1)).step_by(STEP) {
+        index.entry(&old[offset..offset + WINDOW]).or_insert(offset);
+    }
+
+    let mut delta = MAGIC.to_vec();
+    push_u32(&mut delta, new.len())?;
+
+    let mut literal_start = 0;
+    let mut position = 0;
+
+    while position + WINDOW <= new.len() {
+        let Some(&found) = index.get(&new[position..position + WINDOW]) else {
+            position += 1;
+            continue;
+        };
+
+        let mut start = position;
+        let mut old_start = found;
+
+        while start > literal_start
+            && old_start > 0
+            && new[start - 1] == old[old_start - 1]
+        {
+            start -= 1;
+            old_start -= 1;
+        }
+
+        let length = new[start..]
+            .iter()
+            .zip(&old[old_start..])
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        push_insert(&mut delta, &new[literal_start..start])?;
+        delta.push(COPY);
+        push_u32(&mut delta, old_start)?;
+        push_u32(&mut delta, length)?;
+
+        position = start + length;
+        literal_start = position;
+    }
+
+    push_insert(&mut delta, &new[literal_start..])?;
+    Ok(delta)
+}
+
+/// Reads little-endian `u32` at `position` of `delta` and advances it.
+fn read_u32(delta: &[u8], position: &mut usize) -> Result<usize> {
+    let Some(bytes) = delta.get(*position..*position + 4) else {
+        bail!("Delta is truncated.");
+    };
+
+    *position += 4;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+}
+
+/// Applies `delta` to `old` file and returns the new one.
+pub fn decode(old: &[u8], delta: &[u8]) -> Result<Vec<u8>> {
+    if !delta.starts_with(MAGIC) {
+        bail!("Not a delta.");
+    }
+
+    let mut position = MAGIC.len();
+    // The size isn't trusted for allocation, since malformed deltas may declare any.
+    let size = read_u32(delta, &mut position)?;
+    let mut new = Vec::new();
+
+    while position < delta.len() {
+        let tag = delta[position];
+        position += 1;
+
+        let bytes = match tag {
+            COPY => {
+                let offset = read_u32(delta, &mut position)?;
+                let length = read_u32(delta, &mut position)?;
+                old.get(offset..offset + length)
+            }
+            INSERT => {
+                let length = read_u32(delta, &mut position)?;
+                position += length;
+                delta.get(position - length..position)
+            }
+            _ => bail!("Delta is malformed."),
+        };
+
+        let Some(bytes) = bytes else {
+            bail!("Delta doesn't match the file.");
+        };
+
+        if new.len() + bytes.len() > size {
+            bail!("Delta is malformed.");
+        }
+
+        new.extend_from_slice(bytes);
+    }
+
+    if new.len() != size {
+        bail!("Delta is truncated.");
+    }
+
+    Ok(new)
+}
+
+/// Runs `xdelta3` in `mode`, `-e` or `-d`, with `old` file as the source, passing `input` on stdin. Returns stdout.
+fn xdelta3(mode: &str, old: &[u8], input: &[u8]) -> Result<Vec<u8>> {
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+    // `xdelta3` reads the source only from a file.
+    let old_path = std::env::temp_dir().join(format!(
+        "rvpacker-xdelta-{}-{}",
+        std::process::id(),
+        NEXT_ID.fetch_add(1, Ordering::Relaxed)
+    ));
+    write(&old_path, old)?;
+
+    // Secondary compression is an extension of `xdelta3`, that other VCDIFF decoders don't support.
+    let output = run(
+        "xdelta3",
+        &[mode, "-c", "-S", "none", "-s", &old_path.to_string_lossy()],
+        input,
+    );
+    let _ = remove_file(&old_path);
+    output
+}
+
+/// Returns VCDIFF delta of `new` file against `old` file. Requires `xdelta3`.
+pub fn encode_vcdiff(old: &[u8], new: &[u8]) -> Result<Vec<u8>> {
+    xdelta3("-e", old, new)
+}
+
+/// Applies VCDIFF `delta` to `old` file, and returns the new file. Requires `xdelta3`.
+pub fn decode_vcdiff(old: &[u8], delta: &[u8]) -> Result<Vec<u8>> {
+    xdelta3("-d", old, delta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game_file(replacement: &str) -> Vec<u8> {
+        (0..200)
+            .map(|index| {
+                if index == 100 {
+                    format!("{{\"id\":{index},\"text\":\"{replacement}\"}}\n")
+                } else {
+                    format!("{{\"id\":{index},\"text\":\"Line {index}\"}}\n")
+                }
+            })
+            .collect::<String>()
+            .into_bytes()
+    }
+
+    #[test]
+    fn round_trips() {
+        let old = game_file("こんにちは");
+        let new = game_file("Hello there");
+        let delta = encode(&old, &new).unwrap();
+
+        assert_eq!(decode(&old, &delta).unwrap(), new);
+        assert!(delta.len() < new.len() / 10);
+    }
+
+    #[test]
+    fn round_trips_unrelated_and_empty_files() {
+        for (old, new) in [
+            (&b""[..], &b"new"[..]),
+            (b"old", b""),
+            (b"short", b"completely different content of the file"),
+        ] {
+            assert_eq!(decode(old, &encode(old, new).unwrap()).unwrap(), new);
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_deltas() {
+        let old = game_file("a");
+        let delta = encode(&old, &game_file("b")).unwrap();
+
+        assert!(decode(&old, b"XXXX\0\0\0\0").is_err());
+        assert!(decode(&old, &delta[..delta.len() - 1]).is_err());
+        assert!(decode(&old[..old.len() / 2], &delta).is_err());
+    }
+
+    #[test]
+    fn rejects_copies_outside_of_old_file() {
+        let mut delta = MAGIC.to_vec();
+        delta.extend_from_slice(&4u32.to_le_bytes());
+        delta.push(COPY);
+        delta.extend_from_slice(&u32::MAX.to_le_bytes());
+        delta.extend_from_slice(&4u32.to_le_bytes());
+
+        assert!(decode(b"data", &delta).is_err());
+    }
+
+    #[test]
+    fn rejects_sizes_that_differ_from_content() {
+        let mut delta = MAGIC.to_vec();
+        delta.extend_from_slice(&u32::MAX.to_le_bytes());
+        delta.push(INSERT);
+        delta.extend_from_slice(&4u32.to_le_bytes());
+        delta.extend_from_slice(b"data");
+
+        assert!(decode(b"", &delta).is_err());
+
+        delta[MAGIC.len()..MAGIC.len() + 4]
+            .copy_from_slice(&2u32.to_le_bytes());
+
+        assert!(decode(b"", &delta).is_err());
+    }
+}
@@ -0,0 +1,432 @@
+//! Extraction of dialogue messages from event commands, along with their face and speaker name.
+
+use crate::{
+    data::{data_file_path, load_rpgm_file, map_files},
+    translation::normalize,
+};
+use anyhow::Result;
+use marshal_rs::Value;
+use rvpacker_lib::types::EngineType;
+use std::path::Path;
+
+/// `Show Text` command. In XP, it also holds the first line of the message.
+const SHOW_TEXT: i32 = 101;
+
+/// Line of the message, that follows `Show Text` command.
+const TEXT_LINE: i32 = 401;
+
+/// `Show Choices` command.
+const SHOW_CHOICES: i32 = 102;
+
+/// Translation file and its section, where the message is extracted to.
+#[derive(Debug, Clone, Copy)]
+pub struct Location {
+    pub file: &'static str,
+    pub id: u16,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Message {
+    pub face_name: String,
+    pub face_index: i32,
+
+    /// Name box of MZ `Show Text` command.
+    pub speaker_name: String,
+
+    /// Window background: 0 - window, 1 - dim, 2 - transparent.
+    pub background: i32,
+
+    /// Window position: 0 - top, 1 - middle, 2 - bottom.
+    pub position: i32,
+
+    /// Path of `Show Text` command in game data, e.g. `Map001/EV003/p0/c12`, that identifies the message regardless of its text.
+    pub anchor: String,
+
+    /// Name of the map event, common event or troop, that shows the message.
+    pub event_name: String,
+
+    /// Conditions of the map event page, that shows the message. Empty for common events and troops.
+    pub conditions: Vec<String>,
+    pub lines: Vec<String>,
+}
+
+impl Message {
+    /// Returns the message in the form, that library extracts it to translation files.
+    #[must_use]
+    pub fn source(&self) -> String {
+        normalize(&self.lines.join("\n"))
+    }
+}
+
+fn field<'a>(object: &'a Value, key: &str) -> Option<&'a Value> {
+    object.as_object()?.get(key)
+}
+
+fn list_of(object: &Value) -> Option<&[Value]> {
+    field(object, "list")?.as_array().map(Vec::as_slice)
+}
+
+/// Event, that a command list belongs to.
+struct Origin<'a> {
+    /// Map event, common event or troop.
+    event: &'a Value,
+
+    /// Map event page. `None` for common events and troops.
+    page: Option<&'a Value>,
+
+    /// Path of the command list in game data, e.g. `Map001/EV003/p0`.
+    path: String,
+}
+
+impl Origin<'_> {
+    fn event_name(&self) -> String {
+        field(self.event, "name")
+            .and_then(|name| name.as_str())
+            .unwrap_or_default()
+            .to_string()
+    }
+}
+
+/// Returns `(origin, command list)` of all event pages of the map with `map_id`.
+fn map_command_lists(map: &Value, map_id: u16) -> Vec<(Origin<'_>, &[Value])> {
+    let Some(events) = field(map, "events") else {
+        return Vec::new();
+    };
+
+    let events: Vec<&Value> = if let Some(array) = events.as_array() {
+        array.iter().collect()
+    } else if let Some(hashmap) = events.as_hashmap() {
+        hashmap.0.values().collect()
+    } else {
+        Vec::new()
+    };
+
+    events
+        .into_iter()
+        .filter_map(|event| Some((event, field(event, "pages")?.as_array()?)))
+        .flat_map(|(event, pages)| {
+            let event_id = field(event, "id")
+                .and_then(|id| id.as_int())
+                .unwrap_or_default();
+
+            pages
+                .iter()
+                .enumerate()
+                .filter_map(move |(page_index, page)| {
+                    Some((
+                        Origin {
+                            event,
+                            page: Some(page),
+                            path: format!(
+                                "Map{map_id:03}/EV{event_id:03}/p{page_index}"
+                            ),
+                        },
+                        list_of(page)?,
+                    ))
+                })
+        })
+        .collect()
+}
+
+/// Returns field of MV/MZ page conditions, or of older engines' ones, which are named in snake case.
+fn condition_field<'a>(
+    conditions: &'a Value,
+    camel: &str,
+    snake: &str,
+) -> Option<&'a Value> {
+    field(conditions, camel).or_else(|| field(conditions, snake))
+}
+
+fn condition_int(conditions: &Value, camel: &str, snake: &str) -> i64 {
+    condition_field(conditions, camel, snake)
+        .and_then(|value| value.as_int())
+        .map_or(0, i64::from)
+}
+
+fn condition_valid(conditions: &Value, camel: &str, snake: &str) -> bool {
+    condition_field(conditions, camel, snake)
+        .and_then(|value| value.as_bool())
+        .unwrap_or_default()
+}
+
+/// Describes conditions of the map event page, e.g. `switch 5 ON`.
+fn page_conditions(page: &Value) -> Vec<String> {
+    let Some(conditions) =
+        field(page, "conditions").or_else(|| field(page, "condition"))
+    else {
+        return Vec::new();
+    };
+
+    let mut described = Vec::new();
+
+    for (number, camel_valid, snake_valid) in [
+        (1, "switch1Valid", "switch1_valid"),
+        (2, "switch2Valid", "switch2_valid"),
+    ] {
+        if condition_valid(conditions, camel_valid, snake_valid) {
+            let id = condition_int(
+                conditions,
+                &format!("switch{number}Id"),
+                &format!("switch{number}_id"),
+            );
+            described.push(format!("switch {id} ON"));
+        }
+    }
+
+    if condition_valid(conditions, "variableValid", "variable_valid") {
+        described.push(format!(
+            "variable {} >= {}",
+            condition_int(conditions, "variableId", "variable_id"),
+            condition_int(conditions, "variableValue", "variable_value")
+        ));
+    }
+
+    if condition_valid(conditions, "selfSwitchValid", "self_switch_valid")
+        && let Some(ch) =
+            condition_field(conditions, "selfSwitchCh", "self_switch_ch")
+                .and_then(|ch| ch.as_str())
+    {
+        described.push(format!("self switch {ch} ON"));
+    }
+
+    if condition_valid(conditions, "itemValid", "item_valid") {
+        described.push(format!(
+            "item {} in inventory",
+            condition_int(conditions, "itemId", "item_id")
+        ));
+    }
+
+    if condition_valid(conditions, "actorValid", "actor_valid") {
+        described.push(format!(
+            "actor {} in party",
+            condition_int(conditions, "actorId", "actor_id")
+        ));
+    }
+
+    described
+}
+
+/// Returns `(id, origin, command list)` of each common event or each troop page of `stem` data file.
+fn indexed_command_lists<'a>(
+    value: &'a Value,
+    stem: &str,
+    pages: bool,
+) -> Vec<(u16, Origin<'a>, &'a [Value])> {
+    let Some(array) = value.as_array() else {
+        return Vec::new();
+    };
+
+    array
+        .iter()
+        .filter_map(|object| {
+            let id = field(object, "id")?.as_int()? as u16;
+            Some((object, id))
+        })
+        .flat_map(|(object, id)| {
+            let lists: Vec<(String, &[Value])> = if pages {
+                field(object, "pages")
+                    .and_then(|pages| pages.as_array())
+                    .into_iter()
+                    .flatten()
+                    .enumerate()
+                    .filter_map(|(page_index, page)| {
+                        Some((
+                            format!("{stem}/{id}/p{page_index}"),
+                            list_of(page)?,
+                        ))
+                    })
+                    .collect()
+            } else {
+                list_of(object)
+                    .into_iter()
+                    .map(|list| (format!("{stem}/{id}"), list))
+                    .collect()
+            };
+
+            lists.into_iter().map(move |(path, list)| {
+                let origin = Origin {
+                    event: object,
+                    page: None,
+                    path,
+                };
+                (id, origin, list)
+            })
+        })
+        .collect()
+}
+
+fn command_parts(command: &Value) -> Option<(i32, &[Value])> {
+    let code = field(command, "code")?.as_int()?;
+    let parameters = field(command, "parameters")?.as_array()?;
+    Some((code, parameters))
+}
+
+fn string_parameter(parameters: &[Value], index: usize) -> String {
+    parameters
+        .get(index)
+        .and_then(|parameter| parameter.as_str())
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn int_parameter(parameters: &[Value], index: usize) -> i32 {
+    parameters
+        .get(index)
+        .and_then(|parameter| parameter.as_int())
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone)]
+pub struct Choice {
+    /// Path of the choice in game data, e.g. `Map001/EV003/p0/c12/1`.
+    pub anchor: String,
+    pub text: String,
+}
+
+/// Collects messages from a command list of `origin`.
+fn list_messages(
+    list: &[Value],
+    origin: &Origin,
+    engine_type: EngineType,
+) -> Vec<Message> {
+    let mut messages = Vec::new();
+    let mut current: Option<Message> = None;
+
+    for (index, (code, parameters)) in list
+        .iter()
+        .enumerate()
+        .filter_map(|(index, command)| Some((index, command_parts(command)?)))
+    {
+        match code {
+            SHOW_TEXT => {
+                messages.extend(current.take());
+
+                let anchor = format!("{}/c{index}", origin.path);
+
+                current = Some(if engine_type.is_xp() {
+                    Message {
+                        anchor,
+                        lines: vec![string_parameter(parameters, 0)],
+                        ..Default::default()
+                    }
+                } else {
+                    Message {
+                        anchor,
+                        face_name: string_parameter(parameters, 0),
+                        face_index: int_parameter(parameters, 1),
+                        background: int_parameter(parameters, 2),
+                        position: int_parameter(parameters, 3),
+                        speaker_name: string_parameter(parameters, 4),
+                        ..Default::default()
+                    }
+                });
+            }
+            TEXT_LINE => {
+                if let Some(message) = &mut current {
+                    message.lines.push(string_parameter(parameters, 0));
+                }
+            }
+            _ => messages.extend(current.take()),
+        }
+    }
+
+    messages.extend(current);
+    messages.retain(|message| !message.lines.is_empty());
+    messages
+}
+
+/// Collects results of `f` for each command list of maps, common events and troops in `source_path`, in the order the library extracts them. `f` also receives the event, that the list belongs to.
+fn collect<T>(
+    source_path: &Path,
+    engine_type: EngineType,
+    f: impl Fn(&[Value], &Origin) -> Vec<T>,
+) -> Result<Vec<(Location, T)>> {
+    let mut items = Vec::new();
+
+    for (id, path) in map_files(source_path, engine_type)? {
+        let map = load_rpgm_file(&path, engine_type)?;
+        let location = Location {
+            file: "maps.txt",
+            id,
+        };
+
+        for (origin, list) in map_command_lists(&map, id) {
+            items.extend(
+                f(list, &origin).into_iter().map(|item| (location, item)),
+            );
+        }
+    }
+
+    for (stem, file, pages) in [
+        ("CommonEvents", "commonevents.txt", false),
+        ("Troops", "troops.txt", true),
+    ] {
+        let path = data_file_path(source_path, stem, engine_type);
+
+        if !path.exists() {
+            continue;
+        }
+
+        let value = load_rpgm_file(&path, engine_type)?;
+
+        for (id, origin, list) in indexed_command_lists(&value, stem, pages) {
+            let location = Location { file, id };
+            items.extend(
+                f(list, &origin).into_iter().map(|item| (location, item)),
+            );
+        }
+    }
+
+    Ok(items)
+}
+
+/// Collects all messages of maps, common events and troops in `source_path`, in the order the library extracts them.
+pub fn messages(
+    source_path: &Path,
+    engine_type: EngineType,
+) -> Result<Vec<(Location, Message)>> {
+    collect(source_path, engine_type, |list, origin| {
+        let mut messages = list_messages(list, origin, engine_type);
+        let event_name = origin.event_name();
+        let conditions = origin.page.map(page_conditions).unwrap_or_default();
+
+        for message in &mut messages {
+            message.event_name.clone_from(&event_name);
+            message.conditions.clone_from(&conditions);
+        }
+
+        messages
+    })
+}
+
+/// Collects all choices of `Show Choices` commands of maps, common events and troops in `source_path`.
+pub fn choices(
+    source_path: &Path,
+    engine_type: EngineType,
+) -> Result<Vec<(Location, Choice)>> {
+    collect(source_path, engine_type, |list, origin| {
+        list.iter()
+            .enumerate()
+            .filter_map(|(index, command)| {
+                Some((index, command_parts(command)?))
+            })
+            .filter(|(_, (code, _))| *code == SHOW_CHOICES)
+            .filter_map(|(index, (_, parameters))| {
+                Some((index, parameters.first()?.as_array()?))
+            })
+            .flat_map(|(index, choices)| {
+                choices.iter().enumerate().filter_map(
+                    move |(choice_index, choice)| {
+                        Some(Choice {
+                            anchor: format!(
+                                "{}/c{index}/{choice_index}",
+                                origin.path
+                            ),
+                            text: normalize(choice.as_str()?),
+                        })
+                    },
+                )
+            })
+            .collect()
+    })
+}
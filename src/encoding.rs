@@ -0,0 +1,178 @@
+//! Detection of text in translation files, that isn't UTF-8.
+//!
+//! Editors sometimes save translation files, or pasted fragments of them, in the system's legacy encoding. Such bytes would either fail the whole operation, or end up as mojibake in game files, so they're reported with their location upfront.
+
+use crate::translation::translation_files;
+use anyhow::Result;
+use encoding_rs::{SHIFT_JIS, WINDOWS_1252};
+use rvpacker_lib::RVPACKER_IGNORE_FILE;
+use std::{fs::read, path::Path};
+use tracing::warn;
+
+/// Problems reported per file. The rest of them are only counted.
+const MAX_REPORTED: usize = 10;
+
+/// Returns the length of the run of non-UTF-8 bytes at the start of `bytes`.
+fn foreign_run(bytes: &[u8]) -> usize {
+    let mut length = 0;
+
+    while let Some(&byte) = bytes.get(length) {
+        let rest = &bytes[length..];
+
+        if byte.is_ascii()
+            || std::str::from_utf8(rest)
+                .map_or_else(|err| err.valid_up_to() != 0, |_| true)
+        {
+            break;
+        }
+
+        // Shift-JIS lead byte, whose trail byte may be ASCII.
+        length += if matches!(byte, 0x81..=0x9F | 0xE0..=0xFC) {
+            2.min(rest.len())
+        } else {
+            1
+        };
+    }
+
+    length
+}
+
+/// Splits `line` into UTF-8 text and runs of non-UTF-8 bytes.
+fn split_foreign(line: &[u8]) -> Vec<Result<&str, &[u8]>> {
+    let mut parts = Vec::new();
+    let mut rest = line;
+
+    while !rest.is_empty() {
+        match std::str::from_utf8(rest) {
+            Ok(text) => {
+                parts.push(Ok(text));
+                break;
+            }
+            Err(err) => {
+                let (valid, invalid) = rest.split_at(err.valid_up_to());
+
+                if !valid.is_empty() {
+                    // Bytes are valid up to this point.
+                    parts.push(Ok(std::str::from_utf8(valid).unwrap()));
+                }
+
+                let length = foreign_run(invalid).max(1);
+                parts.push(Err(&invalid[..length]));
+                rest = &invalid[length..];
+            }
+        }
+    }
+
+    parts
+}
+
+/// Describes the problem of a single line, if it has any.
+fn diagnose(line: &[u8]) -> Option<(usize, String)> {
+    match std::str::from_utf8(line) {
+        Err(err) => {
+            let parts = split_foreign(line);
+
+            // Japanese games are the most common source of non-UTF-8 text, and Shift-JIS rarely decodes without errors by accident.
+            let encoding = if parts
+                .iter()
+                .filter_map(|part| part.err())
+                .any(|bytes| SHIFT_JIS.decode_without_bom_handling(bytes).1)
+            {
+                WINDOWS_1252
+            } else {
+                SHIFT_JIS
+            };
+
+            let decoded: String = parts
+                .into_iter()
+                .map(|part| match part {
+                    Ok(text) => text.to_string(),
+                    Err(bytes) => encoding
+                        .decode_without_bom_handling(bytes)
+                        .0
+                        .into_owned(),
+                })
+                .collect();
+
+            Some((
+                err.valid_up_to(),
+                format!(
+                    "Invalid UTF-8. It looks like {} text: {}",
+                    if encoding == SHIFT_JIS {
+                        "Shift-JIS"
+                    } else {
+                        "Latin-1"
+                    },
+                    decoded.trim_end()
+                ),
+            ))
+        }
+        Ok(text) => {
+            if text.is_ascii() {
+                return None;
+            }
+
+            // UTF-8 text, that was decoded as Latin-1 and saved as UTF-8 again, turns back to valid UTF-8, when reversed.
+            let (encoded, _, unmappable) = WINDOWS_1252.encode(text);
+
+            if unmappable {
+                return None;
+            }
+
+            let original = std::str::from_utf8(&encoded).ok()?;
+            Some((
+                0,
+                format!(
+                    "UTF-8 text was decoded as Latin-1. It probably is: {}",
+                    original.trim_end()
+                ),
+            ))
+        }
+    }
+}
+
+/// Checks translation files and the ignore file in `translation_path`, and warns about text, that isn't UTF-8. Returns the number of problematic lines.
+pub fn check(translation_path: &Path) -> Result<usize> {
+    let mut names = translation_files(translation_path)?;
+
+    if translation_path.join(RVPACKER_IGNORE_FILE).exists() {
+        names.push(RVPACKER_IGNORE_FILE.to_string());
+    }
+
+    let mut total = 0;
+
+    for name in names {
+        let content = read(translation_path.join(&name))?;
+        let mut problems = 0;
+        let mut line_offset = 0;
+
+        for (line_index, line) in
+            content.split_inclusive(|b| *b == b'\n').enumerate()
+        {
+            if let Some((offset, description)) = diagnose(line) {
+                problems += 1;
+
+                if problems <= MAX_REPORTED {
+                    warn!(
+                        "{name}:{}: Byte offset {}. {description}",
+                        line_index + 1,
+                        line_offset + offset
+                    );
+                }
+            }
+
+            line_offset += line.len();
+        }
+
+        if problems > MAX_REPORTED {
+            warn!(
+                "{name}: {} more lines aren't UTF-8.",
+                problems - MAX_REPORTED
+            );
+        }
+
+        total += problems;
+    }
+
+    Ok(total)
+}
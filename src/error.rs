@@ -0,0 +1,103 @@
+//! Kinds of failures, that have their own exit code, so wrapper scripts and GUIs can branch on them without parsing stderr.
+//!
+//! Attach a kind to an error with [`WithKind::kind`], which keeps the message of the error as it is, so it's still the first line, that's printed. Errors without a kind exit with [`GENERIC_EXIT_CODE`].
+
+use std::{error::Error, fmt};
+
+/// Exit code of errors without an [`ErrorKind`]. Code `2` is reserved for invalid arguments, which `clap` reports itself.
+pub const GENERIC_EXIT_CODE: u8 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// Game engine couldn't be determined from the input directory.
+    EngineNotDetected,
+
+    /// `translation` directory, or a file inside it, that's required for the operation, doesn't exist.
+    TranslationMissing,
+
+    /// Validated data is malformed.
+    ValidationFailed,
+
+    /// Operation was partially completed, but some of its steps failed.
+    PartialFailure,
+
+    /// User declined a confirmation, or confirmation couldn't be requested.
+    Aborted,
+}
+
+impl ErrorKind {
+    #[must_use]
+    pub const fn exit_code(self) -> u8 {
+        match self {
+            Self::EngineNotDetected => 3,
+            Self::TranslationMissing => 4,
+            Self::ValidationFailed => 5,
+            Self::PartialFailure => 6,
+            Self::Aborted => 7,
+        }
+    }
+
+    /// Returns the exit code of the outermost kind, that's attached to `error`.
+    #[must_use]
+    pub fn exit_code_of(error: &anyhow::Error) -> u8 {
+        error
+            .chain()
+            .find_map(|cause| {
+                cause
+                    .downcast_ref::<Kinded>()
+                    .map(|kinded| kinded.kind)
+                    .or_else(|| cause.downcast_ref::<Self>().copied())
+            })
+            .map_or(GENERIC_EXIT_CODE, Self::exit_code)
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::EngineNotDetected => "Game engine not detected.",
+            Self::TranslationMissing => "Translation is missing.",
+            Self::ValidationFailed => "Validation failed.",
+            Self::PartialFailure => "Operation partially failed.",
+            Self::Aborted => "Aborted.",
+        })
+    }
+}
+
+impl Error for ErrorKind {}
+
+/// Error with an attached [`ErrorKind`], that displays as the error itself.
+#[derive(Debug)]
+struct Kinded {
+    kind: ErrorKind,
+    error: anyhow::Error,
+}
+
+impl fmt::Display for Kinded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Causes are printed from the source, so only the message itself is.
+        write!(f, "{}", self.error)
+    }
+}
+
+impl Error for Kinded {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        self.error.source()
+    }
+}
+
+pub trait WithKind<T> {
+    /// Attaches `kind` to the error, keeping its message.
+    fn kind(self, kind: ErrorKind) -> anyhow::Result<T>;
+}
+
+impl<T, E: Into<anyhow::Error>> WithKind<T> for Result<T, E> {
+    fn kind(self, kind: ErrorKind) -> anyhow::Result<T> {
+        self.map_err(|error| {
+            anyhow::Error::new(Kinded {
+                kind,
+                error: error.into(),
+            })
+        })
+    }
+}
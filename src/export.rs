@@ -0,0 +1,667 @@
+//! Export of translation files to other formats, and import of translations back from them.
+//!
+//! Table-like formats share [`Row`] representation of entries. Import never adds or removes entries, it only updates translations of entries, that already exist in translation files.
+
+mod csv;
+mod json;
+mod mtool;
+mod omegat;
+mod po;
+mod po_domains;
+mod review;
+mod rpgmt;
+mod speakers;
+mod sqlite;
+mod trans;
+mod verify;
+mod weblate;
+mod xliff;
+mod xlsx;
+mod yaml;
+
+pub use speakers::detect_speakers;
+pub use verify::verify;
+
+use crate::attribution::Changed;
+use crate::memory::PRETRANSLATION_COMMENT_PREFIX;
+use crate::translate::MACHINE_TRANSLATION_COMMENT_PREFIX;
+use crate::translation::{
+    Line, TranslationFile, effective_translation, remove_annotations,
+    translation_files,
+};
+use anyhow::Result;
+use clap::ValueEnum;
+use rvpacker_lib::types::EngineType;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Write,
+    fs::{read_to_string, write},
+    mem::take,
+    path::Path,
+};
+use tracing::{info, warn};
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    /// CSV file per translation file, for spreadsheets, with columns for ID, context, source, translation and status
+    Csv,
+
+    /// `translation.json` with an array of all entries, each with ID, file, context, source and translation, for scripts and machine translation pipelines
+    Json,
+
+    /// `MTool` `ManualTransFile.json`, a flat object of original texts and translations, for players, who inject translations with `MTool`
+    Mtool,
+
+    /// `OmegaT` project with PO source files, and existing translations in its translation memory. Requires `--target-language`
+    Omegat,
+
+    /// Gettext PO file per translation file, for Poedit, Weblate and other gettext tools. Sections and nearby comments are `msgctxt`
+    Po,
+
+    /// Gettext PO file per map, common event and troop, e.g. `map042.po`, and per other translation file, with `index.json` catalog of domains, so each game file is a separate component, that reviewers claim
+    PoDomains,
+
+    /// Bilingual text file per translation file, with original lines directly above translations, for proofreading printouts
+    Review,
+
+    /// RPG Maker Trans v3 patch directory, for players, who apply patches with that tool
+    RpgmakerTrans,
+
+    /// Groups messages by detected speaker into per-character files, for character-voice consistency passes
+    Speakers,
+
+    /// `SQLite` database `translation.db` of all entries, with contexts, stable hashes and statuses, for SQL queries and database tools
+    #[value(alias = "sql")]
+    Sqlite,
+
+    /// `i18n/<language>/<file>.json` layout for Weblate and Crowdin, with the source language as monolingual base, and keys, that stay the same across exports. Requires `--target-language`
+    Weblate,
+
+    /// XLIFF 2.0 file per translation file, for CAT tools. Unit IDs are hashes of entries, that stay the same across exports
+    Xliff,
+
+    /// Excel workbook with one sheet per translation file, and columns for section, context, source, translation and status. Only translations are editable
+    Xlsx,
+
+    /// YAML file per translation file, with a mapping per section, and original texts as comments above keys, for reviewers
+    Yaml,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ImportFormat {
+    /// CSV files in the same layout, as exported ones. Rows, which source no longer matches the entry with their ID, are rejected
+    Csv,
+
+    /// `translation.json` in the same form, as exported one. Import path may also be the file itself. Entries are matched by file and ID, and ones with a changed source are rejected. Empty translations don't change translations
+    Json,
+
+    /// `MTool` `ManualTransFile.json`. Import path may also be the file itself. Translations are matched to entries by original text, and unmatched ones are reported
+    Mtool,
+
+    /// Translated PO files in `target` directory of the exported `OmegaT` project. Untranslated segments don't change translations
+    Omegat,
+
+    /// Gettext PO files in the same form, as exported ones. Fuzzy entries aren't imported
+    Po,
+
+    /// PO domains in the same form, as exported ones, located by `index.json` catalog. Domains without a file are skipped, so reviewers may return only the domains they claimed
+    PoDomains,
+
+    /// Edited review files in the same form, as exported ones. Entries are matched by section and original lines, so they must stay as they are
+    Review,
+
+    /// RPG Maker Trans v3 patch directory, with `RPGMKTRANSPATCH` file and `patch` directory. Only translated strings are imported
+    RpgmakerTrans,
+
+    /// `SQLite` database `translation.db` with the exported schema. Import path may also be the database itself. Empty translations don't change translations
+    #[value(alias = "sql")]
+    Sqlite,
+
+    /// Translator++ `.trans` projects. Import path may also be a single project. Rows are matched to entries by original text, and unmatched rows are reported
+    #[value(name = "translator++")]
+    TranslatorPlusPlus,
+
+    /// JSON files of the target language in `i18n` layout, as exported. Requires `--target-language`. Empty strings don't change translations
+    Weblate,
+
+    /// XLIFF 2.0 files in the same form, as exported ones. Units are matched to entries by their IDs, and only targets are imported
+    Xliff,
+
+    /// Excel workbook in the same layout, as exported one. Only `Translation` column is imported
+    Xlsx,
+
+    /// YAML files in the same form, as exported ones. Values are matched to entries by their keys, and empty values don't change translations
+    Yaml,
+}
+
+/// Paths and settings of the project, that's being exported.
+pub struct Project<'a> {
+    pub source_path: &'a Path,
+    pub translation_path: &'a Path,
+    pub engine_type: EngineType,
+
+    /// Exports only entries, that are untranslated or marked fuzzy by import, as a work package for a translator.
+    pub untranslated_only: bool,
+}
+
+impl Project<'_> {
+    /// Collects entries to export from translation files.
+    pub fn rows(&self) -> Result<Vec<Row>> {
+        collect_rows(self.translation_path, self.untranslated_only)
+    }
+}
+
+/// Languages of the project, for formats, that record them.
+pub struct Languages<'a> {
+    pub source: &'a str,
+    pub target: Option<&'a str>,
+}
+
+/// Entry of a translation file, along with its location.
+#[derive(Debug, Clone, Default)]
+pub struct Row {
+    pub file: String,
+    pub section: Option<u16>,
+
+    /// Last comment, that precedes the entry in its section, e.g. map name or event position.
+    pub context: String,
+    pub source: String,
+    pub translation: String,
+}
+
+impl Row {
+    #[must_use]
+    pub const fn status(&self) -> &'static str {
+        if self.translation.is_empty() {
+            "untranslated"
+        } else {
+            "translated"
+        }
+    }
+}
+
+/// Collects entries of all translation files in `translation_path`.
+pub fn rows(translation_path: &Path) -> Result<Vec<Row>> {
+    collect_rows(translation_path, false)
+}
+
+/// Collects entries of translation files in `translation_path`, or only untranslated and fuzzy ones with `untranslated_only`. Fuzzy and machine translation comments aren't contexts of entries.
+fn collect_rows(
+    translation_path: &Path,
+    untranslated_only: bool,
+) -> Result<Vec<Row>> {
+    let mut rows = Vec::new();
+
+    for name in translation_files(translation_path)? {
+        let file = TranslationFile::parse(&read_to_string(
+            translation_path.join(&name),
+        )?);
+        let mut section = None;
+        let mut context = String::new();
+        let mut fuzzy = false;
+
+        for line in file.lines {
+            match line {
+                Line::Id(id) => {
+                    section = Some(id);
+                    context.clear();
+                    fuzzy = false;
+                }
+                Line::Comment(comment)
+                    if comment.starts_with(FUZZY_COMMENT_PREFIX) =>
+                {
+                    fuzzy = true;
+                }
+                Line::Comment(comment)
+                    if comment
+                        .starts_with(MACHINE_TRANSLATION_COMMENT_PREFIX)
+                        || comment
+                            .starts_with(PRETRANSLATION_COMMENT_PREFIX) => {}
+                Line::Comment(comment) => context = comment,
+                Line::Entry {
+                    source,
+                    translation,
+                } => {
+                    let translation = effective_translation(&translation);
+
+                    if !untranslated_only || translation.is_empty() || fuzzy {
+                        rows.push(Row {
+                            file: name.clone(),
+                            section,
+                            context: context.clone(),
+                            translation: translation.to_string(),
+                            source,
+                        });
+                    }
+
+                    fuzzy = false;
+                }
+                Line::Raw(_) => {}
+            }
+        }
+    }
+
+    Ok(rows)
+}
+
+/// Returns 64-bit FNV-1a hash of `bytes`. It's stable across versions and platforms, unlike the standard hasher.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Assigns IDs to `rows` of a single file, in their order. IDs are hashes of sections and sources, so they stay the same across exports. Rows with the same section and source share the ID, and colliding hashes of different rows get a suffix.
+pub(super) fn stable_ids<'a>(rows: &[&'a Row]) -> Vec<(String, &'a Row)> {
+    let mut ids: HashMap<String, (Option<u16>, &str)> = HashMap::new();
+    let mut units = Vec::with_capacity(rows.len());
+
+    for row in rows {
+        let key = (row.section, row.source.as_str());
+        let section = row.section.map_or(String::new(), |s| s.to_string());
+        let base = format!(
+            "u{:016x}",
+            fnv1a(format!("{section}\0{}", row.source).as_bytes())
+        );
+        let mut id = base.clone();
+        let mut suffix = 1;
+
+        loop {
+            match ids.get(&id) {
+                None => {
+                    ids.insert(id.clone(), key);
+                    units.push((id, *row));
+                    break;
+                }
+                Some(existing) if *existing == key => break,
+                Some(_) => {
+                    suffix += 1;
+                    id = format!("{base}-{suffix}");
+                }
+            }
+        }
+    }
+
+    units
+}
+
+/// Groups `rows` by their translation files.
+pub(super) fn by_file(rows: &[Row]) -> BTreeMap<&str, Vec<&Row>> {
+    let mut files: BTreeMap<&str, Vec<&Row>> = BTreeMap::new();
+
+    for row in rows {
+        files.entry(&row.file).or_default().push(row);
+    }
+
+    files
+}
+
+/// Comment, that precedes entries, which imported translation conflicted with the existing one, and holds the imported translation. The library ignores such comments on write.
+pub const FUZZY_COMMENT_PREFIX: &str = "<!-- FUZZY: ";
+
+/// What import does with an entry, that already has a different translation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ConflictPolicy {
+    /// Keeps existing translations
+    KeepExisting,
+
+    /// Replaces existing translations with imported ones
+    #[default]
+    Overwrite,
+
+    /// Asks, whether to keep, replace or mark each conflicting translation
+    Prompt,
+
+    /// Keeps existing translations, and precedes entries with `<!-- FUZZY: ... -->` comments, that hold imported translations for review
+    MarkFuzzy,
+}
+
+/// Entry, which existing translation differs from the imported one.
+pub struct Conflict<'a> {
+    pub file: &'a str,
+    pub section: Option<u16>,
+    pub source: &'a str,
+    pub existing: &'a str,
+    pub imported: &'a str,
+}
+
+/// Resolution of a [`Conflict`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    Keep,
+    Overwrite,
+    MarkFuzzy,
+}
+
+/// Translations of rows of a single file.
+#[derive(Default)]
+struct FileRows {
+    by_section: HashMap<(u16, String), String>,
+
+    /// Translations of rows without a section, which match the source anywhere in the file.
+    by_source: HashMap<String, String>,
+
+    /// Sources of `by_source`, that matched an entry.
+    matched_sources: HashSet<String>,
+}
+
+impl FileRows {
+    /// Returns the translation of the entry with `source` in `section`.
+    fn find(&mut self, section: Option<u16>, source: &str) -> Option<String> {
+        if let Some(new) = section.and_then(|section| {
+            self.by_section.remove(&(section, source.to_string()))
+        }) {
+            return Some(new);
+        }
+
+        let new = self.by_source.get(source)?;
+        self.matched_sources.insert(source.to_string());
+        Some(new.clone())
+    }
+
+    /// Returns the number of rows, that didn't match any entry.
+    fn unmatched(&self) -> usize {
+        self.by_section.len()
+            + self
+                .by_source
+                .keys()
+                .filter(|source| !self.matched_sources.contains(*source))
+                .count()
+    }
+}
+
+/// Writes translations of `rows` to the matching entries of translation files in `translation_path`.
+///
+/// Entries are matched by file, section and source. Rows without a section match the source in any section. Rows, that don't match any entry, are reported and skipped. Entries, that already have a different translation, are resolved with `resolve`. Returns entries, which translation changed.
+pub fn apply_rows(
+    translation_path: &Path,
+    rows: Vec<Row>,
+    mut resolve: impl FnMut(&Conflict) -> Result<Resolution>,
+) -> Result<Vec<Changed>> {
+    let mut changed = Vec::new();
+    let mut by_file: BTreeMap<String, FileRows> = BTreeMap::new();
+
+    for row in rows {
+        let file = by_file.entry(row.file).or_default();
+
+        match row.section {
+            Some(section) => {
+                file.by_section
+                    .insert((section, row.source), row.translation);
+            }
+            None => {
+                file.by_source.insert(row.source, row.translation);
+            }
+        }
+    }
+
+    for (name, mut rows) in by_file {
+        let path = translation_path.join(&name);
+
+        if !path.exists() {
+            warn!("{name}: Translation file doesn't exist. Skipping it.");
+            continue;
+        }
+
+        let mut file = TranslationFile::parse(&read_to_string(&path)?);
+        let mut section = None;
+        let mut updated = 0;
+        let mut kept = 0;
+        let mut marked = 0;
+        let mut lines = Vec::with_capacity(file.lines.len());
+
+        for mut line in take(&mut file.lines) {
+            if let Line::Id(id) = line {
+                section = Some(id);
+            }
+
+            if let Line::Entry {
+                source,
+                translation,
+            } = &mut line
+                && let Some(new) = rows.find(section, source)
+                && effective_translation(translation) != new
+            {
+                let existing = effective_translation(translation);
+                let resolution = if existing.is_empty() {
+                    Resolution::Overwrite
+                } else {
+                    resolve(&Conflict {
+                        file: &name,
+                        section,
+                        source,
+                        existing,
+                        imported: &new,
+                    })?
+                };
+
+                // Import resolves the previous mark of the entry, unless the existing translation is kept.
+                if !matches!(resolution, Resolution::Keep) {
+                    remove_annotations(&mut lines, |comment| {
+                        comment.starts_with(FUZZY_COMMENT_PREFIX)
+                    });
+                }
+
+                // Overwritten translation is no longer machine-translated or pretranslated.
+                if matches!(resolution, Resolution::Overwrite) {
+                    remove_annotations(&mut lines, |comment| {
+                        comment.starts_with(MACHINE_TRANSLATION_COMMENT_PREFIX)
+                            || comment
+                                .starts_with(PRETRANSLATION_COMMENT_PREFIX)
+                    });
+                }
+
+                match resolution {
+                    Resolution::Keep => kept += 1,
+                    Resolution::Overwrite => {
+                        *translation = new;
+                        updated += 1;
+                        changed.push((name.clone(), section, source.clone()));
+                    }
+                    Resolution::MarkFuzzy => {
+                        lines.push(Line::Comment(format!(
+                            "{FUZZY_COMMENT_PREFIX}{new} -->"
+                        )));
+                        marked += 1;
+                    }
+                }
+            }
+
+            lines.push(line);
+        }
+
+        file.lines = lines;
+
+        let unmatched = rows.unmatched();
+
+        if unmatched != 0 {
+            warn!(
+                "{name}: {unmatched} imported entries don't match any entry in the file, and were skipped."
+            );
+        }
+
+        if kept != 0 {
+            info!("{name}: Kept {kept} existing translations.");
+        }
+
+        if marked != 0 {
+            info!(
+                "{name}: Marked {marked} entries as fuzzy. Their imported translations are in `<!-- FUZZY: ... -->` comments above them."
+            );
+        }
+
+        if updated != 0 || marked != 0 {
+            write(&path, file.serialize())?;
+        }
+
+        info!("{name}: Successfully imported. Updated {updated} entries.");
+    }
+
+    Ok(changed)
+}
+
+/// Number of unmatched sources, that [`rows_of_translations`] lists in the warning.
+const LISTED_UNMATCHED: usize = 20;
+
+/// Returns rows of all entries with sources of `translations` in translation files in `translation_path`, for formats, that don't locate entries. Sources, that don't match any entry, are reported and skipped.
+pub fn rows_of_translations(
+    translation_path: &Path,
+    translations: &HashMap<String, String>,
+) -> Result<Vec<Row>> {
+    let mut matched = HashSet::new();
+    let rows: Vec<Row> = rows(translation_path)?
+        .into_iter()
+        .filter_map(|row| {
+            let translation = translations.get(&row.source)?;
+            matched.insert(row.source.clone());
+
+            Some(Row {
+                translation: translation.clone(),
+                ..row
+            })
+        })
+        .collect();
+
+    let mut unmatched: Vec<&String> = translations
+        .keys()
+        .filter(|original| !matched.contains(*original))
+        .collect();
+
+    if !unmatched.is_empty() {
+        unmatched.sort_unstable();
+
+        let mut listed = unmatched
+            .iter()
+            .take(LISTED_UNMATCHED)
+            .map(|original| original.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        if unmatched.len() > LISTED_UNMATCHED {
+            let _ = write!(
+                listed,
+                "\n... and {} more.",
+                unmatched.len() - LISTED_UNMATCHED
+            );
+        }
+
+        warn!(
+            "{} imported entries don't match any entry in translation files, and were skipped:\n{listed}",
+            unmatched.len()
+        );
+    }
+
+    Ok(rows)
+}
+
+pub fn export(
+    format: ExportFormat,
+    project: &Project,
+    languages: &Languages,
+    export_path: &Path,
+) -> Result<()> {
+    match format {
+        ExportFormat::Csv => csv::export(project, export_path),
+        ExportFormat::Json => json::export(project, export_path),
+        ExportFormat::Mtool => mtool::export(project, export_path),
+        ExportFormat::Omegat => omegat::export(project, languages, export_path),
+        ExportFormat::Po => po::export(project, languages, export_path),
+        ExportFormat::PoDomains => {
+            po_domains::export(project, languages, export_path)
+        }
+        ExportFormat::Review => review::export(project, export_path),
+        ExportFormat::RpgmakerTrans => rpgmt::export(project, export_path),
+        ExportFormat::Speakers => speakers::export(project, export_path),
+        ExportFormat::Sqlite => sqlite::export(project, export_path),
+        ExportFormat::Weblate => {
+            weblate::export(project, languages, export_path)
+        }
+        ExportFormat::Xliff => xliff::export(project, languages, export_path),
+        ExportFormat::Xlsx => xlsx::export(project, export_path),
+        ExportFormat::Yaml => yaml::export(project, export_path),
+    }
+}
+
+/// Imports translations from `import_path` in `format`. Conflicts with existing translations are resolved with `resolve`. Returns entries, which translation changed.
+pub fn import(
+    format: ImportFormat,
+    translation_path: &Path,
+    import_path: &Path,
+    target_language: Option<&str>,
+    resolve: impl FnMut(&Conflict) -> Result<Resolution>,
+) -> Result<Vec<Changed>> {
+    let rows = match format {
+        ImportFormat::Csv => csv::import(translation_path, import_path),
+        ImportFormat::Json => json::import(translation_path, import_path),
+        ImportFormat::Mtool => mtool::import(translation_path, import_path),
+        ImportFormat::Omegat => omegat::import(import_path),
+        ImportFormat::Po => po::import(import_path),
+        ImportFormat::PoDomains => po_domains::import(import_path),
+        ImportFormat::Review => review::import(import_path),
+        ImportFormat::RpgmakerTrans => rpgmt::import(import_path),
+        ImportFormat::Sqlite => sqlite::import(import_path),
+        ImportFormat::TranslatorPlusPlus => {
+            trans::import(translation_path, import_path)
+        }
+        ImportFormat::Weblate => {
+            weblate::import(translation_path, import_path, target_language)
+        }
+        ImportFormat::Xliff => xliff::import(translation_path, import_path),
+        ImportFormat::Xlsx => xlsx::import(import_path),
+        ImportFormat::Yaml => yaml::import(translation_path, import_path),
+    }?;
+
+    apply_rows(translation_path, rows, resolve)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, remove_dir_all};
+
+    fn row(source: &str, translation: &str) -> Row {
+        Row {
+            file: "system.txt".into(),
+            section: None,
+            context: String::new(),
+            source: source.into(),
+            translation: translation.into(),
+        }
+    }
+
+    #[test]
+    fn removes_marks_of_overwritten_entries_behind_annotations() {
+        let dir = std::env::temp_dir()
+            .join(format!("rvpacker-{}-apply-rows", std::process::id()));
+        let _ = remove_dir_all(&dir);
+        create_dir_all(&dir).unwrap();
+
+        let path = dir.join("system.txt");
+        write(
+            &path,
+            "<!-- MACHINE TRANSLATION: deepl -->\n<!-- CODES: \\C[1] -->\nMachine<#>Машина\n<!-- FUZZY: Нечто -->\n<!-- CONTEXT: face Actor1 #2 -->\nFuzzy<#>Старое\n<!-- PRETRANSLATED: memory.tmx -->\n<!-- ANCHOR: intro -->\nKept<#>Прежнее",
+        )
+        .unwrap();
+
+        let rows = vec![
+            row("Machine", "Человек"),
+            row("Fuzzy", "Новое"),
+            row("Kept", "Другое"),
+        ];
+        let changed = apply_rows(&dir, rows, |conflict| {
+            Ok(if conflict.source == "Kept" {
+                Resolution::Keep
+            } else {
+                Resolution::Overwrite
+            })
+        });
+        let imported = read_to_string(&path);
+        remove_dir_all(&dir).unwrap();
+
+        assert_eq!(changed.unwrap().len(), 2);
+        assert_eq!(
+            imported.unwrap(),
+            "<!-- CODES: \\C[1] -->\nMachine<#>Человек\n<!-- CONTEXT: face Actor1 #2 -->\nFuzzy<#>Новое\n<!-- PRETRANSLATED: memory.tmx -->\n<!-- ANCHOR: intro -->\nKept<#>Прежнее"
+        );
+    }
+}
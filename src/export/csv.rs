@@ -0,0 +1,263 @@
+//! CSV files, one per translation file, for translators, that work in spreadsheets.
+//!
+//! Columns are `id`, `context`, `source`, `translation` and `status`. ID is the section and the position of the entry in it, e.g. `3:12`, so import finds the entry, even if the rows were sorted or filtered, and rejects rows, whose source no longer matches the entry, e.g. after the game was updated. Line breaks, that spreadsheets insert into cells, become `\#` markers on import. Files start with a byte order mark, so Excel detects UTF-8.
+
+use super::{Project, Row, by_file, rows};
+use crate::translation::normalize;
+use anyhow::{Context, Result, bail};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{create_dir_all, read_dir, read_to_string, write},
+    path::Path,
+};
+use tracing::{info, warn};
+
+const EXTENSION: &str = "csv";
+
+const BOM: char = '\u{feff}';
+
+const HEADERS: [&str; 5] = ["id", "context", "source", "translation", "status"];
+
+/// Assigns IDs to `rows` of a single file, in their order.
+fn entry_ids<'a>(rows: &[&'a Row]) -> Vec<(String, &'a Row)> {
+    let mut positions: HashMap<Option<u16>, usize> = HashMap::new();
+
+    rows.iter()
+        .map(|row| {
+            let position = positions.entry(row.section).or_default();
+            *position += 1;
+
+            let id = match row.section {
+                Some(section) => format!("{section}:{position}"),
+                None => position.to_string(),
+            };
+
+            (id, *row)
+        })
+        .collect()
+}
+
+/// Returns `field` quoted, if it has to be.
+fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+pub fn export(project: &Project, export_path: &Path) -> Result<()> {
+    // IDs are positions among all entries, so subsets are matched on import too.
+    let rows = rows(project.translation_path)?;
+    let exported = project.rows()?;
+    let included: HashSet<(&str, Option<u16>, &str)> = exported
+        .iter()
+        .map(|row| (row.file.as_str(), row.section, row.source.as_str()))
+        .collect();
+
+    create_dir_all(export_path)?;
+
+    for (name, rows) in by_file(&rows) {
+        let stem = name.strip_suffix(".txt").unwrap_or(name);
+        let entries: Vec<_> = entry_ids(&rows)
+            .into_iter()
+            .filter(|(_, row)| {
+                included.contains(&(name, row.section, row.source.as_str()))
+            })
+            .collect();
+
+        if entries.is_empty() {
+            continue;
+        }
+
+        let mut output = String::from(BOM);
+        output.push_str(&HEADERS.join(","));
+        output.push_str("\r\n");
+
+        for (id, row) in &entries {
+            let fields = [
+                id.as_str(),
+                &row.context,
+                &row.source,
+                &row.translation,
+                row.status(),
+            ];
+
+            output.push_str(
+                &fields
+                    .iter()
+                    .copied()
+                    .map(quote)
+                    .collect::<Vec<_>>()
+                    .join(","),
+            );
+            output.push_str("\r\n");
+        }
+
+        let file_name = format!("{stem}.{EXTENSION}");
+        write(export_path.join(&file_name), output)?;
+        info!(
+            "{file_name}: Successfully exported. {} entries.",
+            entries.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Parses records of CSV `content`. Quoted fields may contain commas, quotes and line breaks.
+fn parse(content: &str) -> Result<Vec<Vec<String>>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = content.strip_prefix(BOM).unwrap_or(content).chars();
+
+    while let Some(char) = chars.next() {
+        if quoted {
+            match char {
+                '"' if chars.as_str().starts_with('"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = false,
+                _ => field.push(char),
+            }
+
+            continue;
+        }
+
+        match char {
+            '"' if field.is_empty() => quoted = true,
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' if chars.as_str().starts_with('\n') => {}
+            '\n' | '\r' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(char),
+        }
+    }
+
+    if quoted {
+        bail!("Quoted field is not terminated.");
+    }
+
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+pub fn import(translation_path: &Path, import_path: &Path) -> Result<Vec<Row>> {
+    let current = rows(translation_path)?;
+    let entries: HashMap<(&str, String), &Row> = by_file(&current)
+        .into_iter()
+        .flat_map(|(name, rows)| {
+            entry_ids(&rows)
+                .into_iter()
+                .map(move |(id, row)| ((name, id), row))
+        })
+        .collect();
+
+    let mut paths: Vec<_> = read_dir(import_path)
+        .with_context(|| format!("Reading {}", import_path.display()))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == EXTENSION))
+        .collect();
+    paths.sort();
+
+    if paths.is_empty() {
+        bail!("{}: No CSV files to import.", import_path.display());
+    }
+
+    let mut rows = Vec::new();
+
+    for path in paths {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let stem = file_name.strip_suffix(".csv").unwrap_or(&file_name);
+        let name = format!("{stem}.txt");
+
+        let mut records = parse(&read_to_string(&path)?)
+            .with_context(|| format!("Parsing {file_name}"))?
+            .into_iter();
+
+        let Some(header) = records.next() else {
+            continue;
+        };
+
+        let column = |title: &str| {
+            header
+                .iter()
+                .position(|cell| cell.trim().eq_ignore_ascii_case(title))
+        };
+
+        let (Some(id), Some(source), Some(translation)) =
+            (column("id"), column("source"), column("translation"))
+        else {
+            warn!(
+                "{file_name}: File doesn't have id, source and translation columns. Skipping it."
+            );
+            continue;
+        };
+
+        let mut read = 0;
+        let mut rejected = 0;
+
+        for (index, record) in records.enumerate() {
+            let field = |index: usize| {
+                record.get(index).map(String::as_str).unwrap_or_default()
+            };
+
+            let (row_id, row_source) =
+                (field(id).trim(), normalize(field(source)));
+
+            if row_id.is_empty() && row_source.is_empty() {
+                continue;
+            }
+
+            let number = index + 2;
+
+            let Some(entry) = entries.get(&(name.as_str(), row_id.to_string()))
+            else {
+                warn!(
+                    "{file_name}: Row {number}: Entry `{row_id}` doesn't exist. Skipping the row."
+                );
+                rejected += 1;
+                continue;
+            };
+
+            if entry.source != row_source {
+                warn!(
+                    "{file_name}: Row {number}: Source of entry `{row_id}` no longer matches. Skipping the row.\nExpected: {}\nFound: {row_source}",
+                    entry.source
+                );
+                rejected += 1;
+                continue;
+            }
+
+            rows.push(Row {
+                file: name.clone(),
+                section: entry.section,
+                context: String::new(),
+                source: entry.source.clone(),
+                translation: normalize(field(translation)),
+            });
+            read += 1;
+        }
+
+        if rejected != 0 {
+            warn!("{file_name}: Rejected {rejected} rows.");
+        }
+
+        info!("{file_name}: Read {read} entries.");
+    }
+
+    Ok(rows)
+}
@@ -0,0 +1,131 @@
+//! Single JSON file with an array of all entries, for scripts and custom machine translation pipelines.
+//!
+//! Each entry is an object with `id`, `file`, `context`, `source` and `translation`, where untranslated entries have an empty translation. Line breaks replace `\#` markers. IDs are stable hashes of entries within their files, so import matches objects to entries by file and ID, and rejects objects, which source no longer matches the entry.
+
+use super::{Project, Row, by_file, rows, stable_ids};
+use crate::translation::{denormalize, normalize};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{create_dir_all, read_to_string, write},
+    path::{Path, PathBuf},
+};
+use tracing::{info, warn};
+
+const TRANSLATION_FILE: &str = "translation.json";
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    id: String,
+    file: String,
+    #[serde(default)]
+    context: String,
+    #[serde(default)]
+    source: String,
+    #[serde(default)]
+    translation: String,
+}
+
+pub fn export(project: &Project, export_path: &Path) -> Result<()> {
+    let rows = project.rows()?;
+    let mut entries = Vec::with_capacity(rows.len());
+
+    for (name, rows) in by_file(&rows) {
+        for (id, row) in stable_ids(&rows) {
+            entries.push(Entry {
+                id,
+                file: name.to_string(),
+                context: row.context.clone(),
+                source: denormalize(&row.source),
+                translation: denormalize(&row.translation),
+            });
+        }
+    }
+
+    create_dir_all(export_path)?;
+    write(
+        export_path.join(TRANSLATION_FILE),
+        serde_json::to_string_pretty(&entries)?,
+    )?;
+
+    info!(
+        "{TRANSLATION_FILE}: Successfully exported. {} entries.",
+        entries.len()
+    );
+    Ok(())
+}
+
+/// Returns the translation file at `import_path`, which is either the file or a directory with it.
+fn translation_file(import_path: &Path) -> PathBuf {
+    if import_path.is_file() {
+        import_path.to_path_buf()
+    } else {
+        import_path.join(TRANSLATION_FILE)
+    }
+}
+
+pub fn import(translation_path: &Path, import_path: &Path) -> Result<Vec<Row>> {
+    let path = translation_file(import_path);
+    let entries: Vec<Entry> = serde_json::from_str(
+        &read_to_string(&path)
+            .with_context(|| format!("Reading {}", path.display()))?,
+    )
+    .with_context(|| format!("Parsing {}", path.display()))?;
+
+    let current = rows(translation_path)?;
+    let ids: HashMap<(&str, String), &Row> = by_file(&current)
+        .into_iter()
+        .flat_map(|(name, rows)| {
+            stable_ids(&rows)
+                .into_iter()
+                .map(move |(id, row)| ((name, id), row))
+        })
+        .collect();
+
+    let mut rows = Vec::new();
+    let mut unknown = 0;
+    let mut changed_sources = 0;
+
+    for entry in entries {
+        // Untranslated entries are left empty, which must not clear translations.
+        if entry.translation.is_empty() {
+            continue;
+        }
+
+        let Some(row) = ids.get(&(entry.file.as_str(), entry.id)) else {
+            unknown += 1;
+            continue;
+        };
+
+        if !entry.source.is_empty() && normalize(&entry.source) != row.source {
+            changed_sources += 1;
+            continue;
+        }
+
+        rows.push(Row {
+            file: row.file.clone(),
+            section: row.section,
+            context: String::new(),
+            source: row.source.clone(),
+            translation: normalize(&entry.translation),
+        });
+    }
+
+    let file_name = path.file_name().unwrap_or_default().display();
+
+    if unknown != 0 {
+        warn!(
+            "{file_name}: {unknown} entries don't match any entry in translation files by file and ID, and were skipped."
+        );
+    }
+
+    if changed_sources != 0 {
+        warn!(
+            "{file_name}: {changed_sources} entries have a source, that differs from the entry with their ID, and were rejected."
+        );
+    }
+
+    info!("{file_name}: Read {} entries.", rows.len());
+    Ok(rows)
+}
@@ -0,0 +1,87 @@
+//! `MTool` translation file, a flat JSON object of original texts and their translations.
+//!
+//! `MTool` injects translations by original text, so the file has no locations. Export writes all entries, untranslated ones with their source as translation, as `MTool` itself does in `ManualTransFile.json`, and import skips translations, that equal their original.
+
+use super::{Project, Row, rows_of_translations};
+use crate::translation::{denormalize, normalize};
+use anyhow::{Context, Result, bail};
+use serde_json::{Map, Value};
+use std::{
+    collections::HashMap,
+    fs::{create_dir_all, read_to_string, write},
+    path::{Path, PathBuf},
+};
+use tracing::info;
+
+const TRANSLATION_FILE: &str = "ManualTransFile.json";
+
+pub fn export(project: &Project, export_path: &Path) -> Result<()> {
+    let mut translations = Map::new();
+
+    for row in project.rows()? {
+        let original = denormalize(&row.source);
+
+        // Entries with the same source are translated the same by MTool anyway.
+        if translations.contains_key(&original) {
+            continue;
+        }
+
+        let translation = if row.translation.is_empty() {
+            original.clone()
+        } else {
+            denormalize(&row.translation)
+        };
+
+        translations.insert(original, Value::String(translation));
+    }
+
+    create_dir_all(export_path)?;
+    write(
+        export_path.join(TRANSLATION_FILE),
+        serde_json::to_string_pretty(&translations)?,
+    )?;
+
+    info!(
+        "{TRANSLATION_FILE}: Successfully exported. {} entries.",
+        translations.len()
+    );
+    Ok(())
+}
+
+/// Returns the translation file at `import_path`, which is either the file or a directory with it.
+fn translation_file(import_path: &Path) -> PathBuf {
+    if import_path.is_file() {
+        import_path.to_path_buf()
+    } else {
+        import_path.join(TRANSLATION_FILE)
+    }
+}
+
+pub fn import(translation_path: &Path, import_path: &Path) -> Result<Vec<Row>> {
+    let path = translation_file(import_path);
+    let content = read_to_string(&path)
+        .with_context(|| format!("Reading {}", path.display()))?;
+
+    let Value::Object(object) = serde_json::from_str(&content)
+        .with_context(|| format!("Parsing {}", path.display()))?
+    else {
+        bail!("{}: File is not a JSON object.", path.display());
+    };
+
+    let translations: HashMap<String, String> = object
+        .into_iter()
+        .filter_map(|(original, translation)| {
+            let translation = translation.as_str()?;
+
+            (!translation.is_empty() && translation != original)
+                .then(|| (normalize(&original), normalize(translation)))
+        })
+        .collect();
+
+    info!(
+        "{}: Read {} entries.",
+        path.file_name().unwrap_or_default().display(),
+        translations.len()
+    );
+    rows_of_translations(translation_path, &translations)
+}
@@ -0,0 +1,184 @@
+//! `OmegaT` project, that's ready to open.
+//!
+//! Source files are PO files, that `OmegaT` supports natively, and existing translations go to a TMX file in `tm/auto`, which `OmegaT` inserts into matching segments when the project is opened. Sentence segmentation is disabled, since each entry is a message or a name, and existing translations only match whole entries. Project's segmentation rules keep escape codes and closing brackets with the sentence they follow, for translators, who enable sentence segmentation. Import reads translated PO files from `target` directory, after they're created with `Project > Create Translated Documents`.
+
+use super::{Languages, Project, Row, po, rows};
+use crate::translation::denormalize;
+use crate::xml::escape;
+use anyhow::{Result, bail};
+use std::{
+    fmt::Write,
+    fs::{create_dir_all, write},
+    path::Path,
+};
+use tracing::info;
+
+const SOURCE_DIR: &str = "source";
+const TARGET_DIR: &str = "target";
+
+/// Directories of the project, that must exist for `OmegaT` to open it.
+const DIRS: &[&str] = &["dictionary", "glossary", "omegat", "tm/auto"];
+
+const PROJECT_FILE: &str = "omegat.project";
+const SEGMENTATION_FILE: &str = "omegat/segmentation.srx";
+const MEMORY_FILE: &str = "tm/auto/rvpacker.tmx";
+
+const SEGMENTATION: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<srx xmlns="http://www.lisa.org/srx20" version="2.0">
+  <header segmentsubflows="yes" cascade="yes">
+    <formathandle type="start" include="no"/>
+    <formathandle type="end" include="yes"/>
+    <formathandle type="isolated" include="yes"/>
+  </header>
+  <body>
+    <languagerules>
+      <languagerule languagerulename="RPG Maker">
+        <rule break="no">
+          <beforebreak>[.!?。！？…]+</beforebreak>
+          <afterbreak>\\[A-Za-z{}.|!&lt;&gt;^$]</afterbreak>
+        </rule>
+        <rule break="no">
+          <beforebreak>[.!?。！？…]+</beforebreak>
+          <afterbreak>[」』）)"”]</afterbreak>
+        </rule>
+        <rule break="yes">
+          <beforebreak>[。！？]+</beforebreak>
+          <afterbreak></afterbreak>
+        </rule>
+        <rule break="yes">
+          <beforebreak>[.!?]+</beforebreak>
+          <afterbreak>\s</afterbreak>
+        </rule>
+      </languagerule>
+    </languagerules>
+    <maprules>
+      <languagemap languagepattern=".*" languagerulename="RPG Maker"/>
+    </maprules>
+  </body>
+</srx>
+"#;
+
+fn project_file(source: &str, target: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<omegat>
+  <project version="1.0">
+    <source_dir>__DEFAULT__</source_dir>
+    <source_dir_excludes>
+      <mask>**/.svn/**</mask>
+      <mask>**/.git/**</mask>
+    </source_dir_excludes>
+    <target_dir>__DEFAULT__</target_dir>
+    <tm_dir>__DEFAULT__</tm_dir>
+    <glossary_dir>__DEFAULT__</glossary_dir>
+    <glossary_file>__DEFAULT__</glossary_file>
+    <dictionary_dir>__DEFAULT__</dictionary_dir>
+    <source_lang>{}</source_lang>
+    <target_lang>{}</target_lang>
+    <sentence_seg>false</sentence_seg>
+    <support_default_translations>true</support_default_translations>
+    <remove_tags>false</remove_tags>
+  </project>
+</omegat>
+"#,
+        escape(source),
+        escape(target)
+    )
+}
+
+/// Writes translated entries of `project` as a translation memory. Returns the number of written entries.
+fn write_memory(
+    project: &Project,
+    source: &str,
+    target: &str,
+    path: &Path,
+) -> Result<usize> {
+    let mut output = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<tmx version="1.4">
+  <header creationtool="rvpacker-txt-rs" creationtoolversion="{}" segtype="paragraph" o-tmf="rvpacker-txt-rs" adminlang="en" srclang="{}" datatype="plaintext"/>
+  <body>
+"#,
+        env!("CARGO_PKG_VERSION"),
+        escape(source)
+    );
+    let mut written = 0;
+
+    for row in rows(project.translation_path)? {
+        if row.translation.is_empty() {
+            continue;
+        }
+
+        let _ = write!(
+            output,
+            "    <tu>\n      <tuv xml:lang=\"{}\"><seg>{}</seg></tuv>\n      <tuv xml:lang=\"{}\"><seg>{}</seg></tuv>\n    </tu>\n",
+            escape(source),
+            escape(&denormalize(&row.source)),
+            escape(target),
+            escape(&denormalize(&row.translation))
+        );
+        written += 1;
+    }
+
+    output.push_str("  </body>\n</tmx>\n");
+    write(path, output)?;
+    Ok(written)
+}
+
+pub fn export(
+    project: &Project,
+    languages: &Languages,
+    export_path: &Path,
+) -> Result<()> {
+    let Some(target) = languages.target else {
+        bail!(
+            "OmegaT project needs a target language. Set it with `--target-language`."
+        );
+    };
+
+    for dir in DIRS {
+        create_dir_all(export_path.join(dir))?;
+    }
+
+    po::export(project, languages, &export_path.join(SOURCE_DIR))?;
+    create_dir_all(export_path.join(TARGET_DIR))?;
+
+    write(
+        export_path.join(PROJECT_FILE),
+        project_file(languages.source, target),
+    )?;
+    write(export_path.join(SEGMENTATION_FILE), SEGMENTATION)?;
+
+    let remembered = write_memory(
+        project,
+        languages.source,
+        target,
+        &export_path.join(MEMORY_FILE),
+    )?;
+
+    info!(
+        "{PROJECT_FILE}: Successfully exported. {remembered} existing translations are in the translation memory."
+    );
+    Ok(())
+}
+
+pub fn import(import_path: &Path) -> Result<Vec<Row>> {
+    let target_path = import_path.join(TARGET_DIR);
+
+    if !target_path.exists() {
+        bail!(
+            "{}: Directory doesn't exist. Create translated documents in OmegaT first.",
+            target_path.display()
+        );
+    }
+
+    // OmegaT leaves untranslated segments empty or with the source, which must not clear or replace translations.
+    let rows = po::read(&target_path)?
+        .into_iter()
+        .filter(|row| {
+            !row.translation.is_empty() && row.translation != row.source
+        })
+        .collect();
+
+    Ok(rows)
+}
@@ -0,0 +1,342 @@
+//! Gettext PO files, one per translation file, for Poedit, Weblate and other gettext tooling.
+//!
+//! Sources are `msgid`s and translations are `msgstr`s, with line breaks instead of `\#` markers. `msgctxt` holds the section and the nearest comment, e.g. `1: NAME: Town`, so identical sources of different maps and events stay distinct, and import matches entries by the section before the first colon. Fuzzy and obsolete entries aren't imported, like `msgfmt` doesn't compile them.
+
+use super::{Languages, Project, Row, by_file};
+use crate::translation::{COMMENT_PREFIX, denormalize, normalize};
+use anyhow::{Context, Result, bail};
+use rvpacker_lib::SEPARATOR;
+use std::{
+    collections::HashSet,
+    fmt::Write,
+    fs::{create_dir_all, read_dir, read_to_string, write},
+    path::Path,
+};
+use tracing::info;
+
+pub(super) const EXTENSION: &str = "po";
+
+const HEADER: &str = r#"msgid ""
+msgstr ""
+"Content-Type: text/plain; charset=UTF-8\n"
+"Content-Transfer-Encoding: 8bit\n"
+"X-Generator: rvpacker-txt-rs\n"
+"#;
+
+/// Returns readable form of `comment`, e.g. `NAME: Town` of `<!-- NAME --><#>Town`.
+pub(super) fn describe(comment: &str) -> String {
+    let comment = comment.strip_prefix(COMMENT_PREFIX).unwrap_or(comment);
+    let (label, value) = comment.split_once(SEPARATOR).unwrap_or((comment, ""));
+    let label = label.trim_end_matches("-->").trim();
+
+    if value.is_empty() {
+        label.to_string()
+    } else {
+        format!("{label}: {value}")
+    }
+}
+
+/// Writes `keyword` with quoted `text`, splitting it after line breaks, like gettext tools do.
+fn write_string(output: &mut String, keyword: &str, text: &str) {
+    let escaped = |line: &str| {
+        line.replace('\\', r"\\")
+            .replace('"', "\\\"")
+            .replace('\t', r"\t")
+            .replace('\r', r"\r")
+            .replace('\n', r"\n")
+    };
+
+    if !text.contains('\n') || text.trim_end_matches('\n').is_empty() {
+        let _ = writeln!(output, "{keyword} \"{}\"", escaped(text));
+        return;
+    }
+
+    let _ = writeln!(output, "{keyword} \"\"");
+
+    for line in text.split_inclusive('\n') {
+        let _ = writeln!(output, "\"{}\"", escaped(line));
+    }
+}
+
+fn context(row: &Row) -> Option<String> {
+    let section = row.section?;
+
+    Some(if row.context.is_empty() {
+        section.to_string()
+    } else {
+        format!("{section}: {}", describe(&row.context))
+    })
+}
+
+/// Returns PO file of `rows` of translation file `name`, and the number of its entries.
+pub(super) fn catalog(
+    name: &str,
+    rows: &[&Row],
+    languages: &Languages,
+) -> (String, usize) {
+    let mut output = String::from(HEADER);
+
+    if let Some(target) = languages.target {
+        let _ = writeln!(output, "\"Language: {target}\\n\"");
+    }
+
+    let mut seen = HashSet::new();
+    let mut exported = 0;
+
+    for row in rows {
+        let context = context(row);
+
+        // Gettext doesn't allow duplicate entries, and import would match them to the same entries anyway.
+        if !seen.insert((context.clone(), &row.source)) {
+            continue;
+        }
+
+        output.push('\n');
+
+        match row.section {
+            Some(section) => {
+                let _ = writeln!(output, "#: {name}:{section}");
+            }
+            None => {
+                let _ = writeln!(output, "#: {name}");
+            }
+        }
+
+        if let Some(context) = &context {
+            write_string(&mut output, "msgctxt", context);
+        }
+
+        write_string(&mut output, "msgid", &denormalize(&row.source));
+        write_string(&mut output, "msgstr", &denormalize(&row.translation));
+        exported += 1;
+    }
+
+    (output, exported)
+}
+
+pub fn export(
+    project: &Project,
+    languages: &Languages,
+    export_path: &Path,
+) -> Result<()> {
+    let rows = project.rows()?;
+    create_dir_all(export_path)?;
+
+    for (name, rows) in by_file(&rows) {
+        let stem = name.strip_suffix(".txt").unwrap_or(name);
+        let (output, exported) = catalog(name, &rows, languages);
+
+        let file_name = format!("{stem}.{EXTENSION}");
+        write(export_path.join(&file_name), output)?;
+        info!("{file_name}: Successfully exported. {exported} entries.");
+    }
+
+    Ok(())
+}
+
+/// Returns the text of quoted PO string `quoted`.
+fn unquote(quoted: &str) -> Option<String> {
+    let inner = quoted.trim().strip_prefix('"')?.strip_suffix('"')?;
+    let mut text = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            text.push(char);
+            continue;
+        }
+
+        match chars.next()? {
+            'n' => text.push('\n'),
+            't' => text.push('\t'),
+            'r' => text.push('\r'),
+            other => text.push(other),
+        }
+    }
+
+    Some(text)
+}
+
+/// Entry of a PO file.
+#[derive(Default)]
+struct Message {
+    context: Option<String>,
+    id: Option<String>,
+    string: Option<String>,
+    fuzzy: bool,
+}
+
+/// Field of [`Message`], that continuation strings are appended to.
+#[derive(Clone, Copy)]
+enum Field {
+    Context,
+    Id,
+    String,
+
+    /// Plural forms, that the export doesn't produce.
+    Other,
+}
+
+impl Field {
+    const fn of(self, message: &mut Message) -> Option<&mut Option<String>> {
+        match self {
+            Self::Context => Some(&mut message.context),
+            Self::Id => Some(&mut message.id),
+            Self::String => Some(&mut message.string),
+            Self::Other => None,
+        }
+    }
+}
+
+/// Parses messages of PO file `content`. Obsolete entries are comments, and are skipped along with other comments.
+fn parse(content: &str) -> Result<Vec<Message>> {
+    let mut messages = Vec::new();
+    let mut message = Message::default();
+    let mut field = None;
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        if line.starts_with('"') {
+            let Some(text) = unquote(line) else {
+                bail!("Line {}: String is malformed.", index + 1);
+            };
+
+            let Some(field) = field else {
+                bail!(
+                    "Line {}: String doesn't belong to any field.",
+                    index + 1
+                );
+            };
+
+            if let Some(value) = Field::of(field, &mut message) {
+                value.get_or_insert_default().push_str(&text);
+            }
+
+            continue;
+        }
+
+        // Message ends with its translation, so anything, but plural forms, starts the next one.
+        if message.string.is_some() && !line.starts_with("msgstr[") {
+            messages.push(std::mem::take(&mut message));
+        }
+
+        if line.starts_with('#') {
+            if line.starts_with("#,") && line.contains("fuzzy") {
+                message.fuzzy = true;
+            }
+
+            field = None;
+            continue;
+        }
+
+        let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let selected = match keyword {
+            "msgctxt" => Field::Context,
+            "msgid" => Field::Id,
+            "msgstr" | "msgstr[0]" => Field::String,
+            "msgid_plural" => Field::Other,
+            _ if keyword.starts_with("msgstr[") => Field::Other,
+            _ => bail!("Line {}: Unknown keyword `{keyword}`.", index + 1),
+        };
+
+        let Some(text) = unquote(rest) else {
+            bail!("Line {}: String is malformed.", index + 1);
+        };
+
+        if let Some(value) = selected.of(&mut message) {
+            *value = Some(text);
+        }
+
+        field = Some(selected);
+    }
+
+    messages.push(message);
+    Ok(messages)
+}
+
+/// Reads entries of PO file at `path` into `rows`, as entries of translation file `name`. Returns the number of read entries.
+pub(super) fn read_file(
+    path: &Path,
+    name: &str,
+    rows: &mut Vec<Row>,
+) -> Result<usize> {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let messages = parse(&read_to_string(path)?)
+        .with_context(|| format!("Parsing {file_name}"))?;
+    let mut read = 0;
+
+    for message in messages {
+        let (Some(id), Some(string)) = (message.id, message.string) else {
+            continue;
+        };
+
+        if id.is_empty() || message.fuzzy {
+            continue;
+        }
+
+        let section = match message.context {
+            Some(context) => {
+                let section = context.split(':').next().unwrap_or_default();
+
+                let Ok(section) = section.trim().parse() else {
+                    bail!(
+                        "{file_name}: Context `{context}` doesn't start with a section ID."
+                    );
+                };
+
+                Some(section)
+            }
+            None => None,
+        };
+
+        rows.push(Row {
+            file: name.to_string(),
+            section,
+            context: String::new(),
+            source: normalize(&id),
+            translation: normalize(&string),
+        });
+        read += 1;
+    }
+
+    info!("{file_name}: Read {read} entries.");
+    Ok(read)
+}
+
+/// Reads entries of PO files in `import_path`.
+pub(super) fn read(import_path: &Path) -> Result<Vec<Row>> {
+    let mut rows = Vec::new();
+    let mut paths: Vec<_> = read_dir(import_path)
+        .with_context(|| format!("Reading {}", import_path.display()))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == EXTENSION))
+        .collect();
+    paths.sort();
+
+    if paths.is_empty() {
+        bail!("{}: No PO files to import.", import_path.display());
+    }
+
+    for path in paths {
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        read_file(&path, &format!("{stem}.txt"), &mut rows)?;
+    }
+
+    Ok(rows)
+}
+
+pub fn import(import_path: &Path) -> Result<Vec<Row>> {
+    read(import_path)
+}
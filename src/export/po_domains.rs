@@ -0,0 +1,164 @@
+//! Gettext PO files, split into a domain per map, common event and troop, so each domain maps to a single game file, e.g. a Weblate component, that a reviewer claims.
+//!
+//! Sections of `maps.txt`, `commonevents.txt` and `troops.txt` are domains like `map042.po`, and other translation files are domains with their own names, e.g. `actors.po`. Domain files have the same form, as PO export. `index.json` catalog lists domains with their translation files, sections, names and progress, and import reads it to locate domains, so domain files may be imported partially.
+
+use super::{
+    Languages, Project, Row, by_file,
+    po::{EXTENSION, catalog, describe, read_file},
+};
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs::{create_dir_all, read_to_string, write},
+    path::Path,
+};
+use tracing::{info, warn};
+
+const INDEX_FILE: &str = "index.json";
+
+/// Translation files, which sections are separate domains, and prefixes of domain names.
+const SPLIT_FILES: &[(&str, &str)] = &[
+    ("maps.txt", "map"),
+    ("commonevents.txt", "commonevent"),
+    ("troops.txt", "troop"),
+];
+
+/// Domain in the index catalog.
+#[derive(Serialize, Deserialize)]
+struct IndexEntry {
+    domain: String,
+    file: String,
+    #[serde(default)]
+    section: Option<u16>,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    entries: usize,
+    #[serde(default)]
+    translated: usize,
+}
+
+/// Returns domain of `section` of translation file `name`, and the section, if the domain is a single section.
+fn domain(name: &str, section: Option<u16>) -> (String, Option<u16>) {
+    match (SPLIT_FILES.iter().find(|(file, _)| *file == name), section) {
+        (Some((_, prefix)), Some(section)) => {
+            (format!("{prefix}{section:03}"), Some(section))
+        }
+        _ => (name.strip_suffix(".txt").unwrap_or(name).to_string(), None),
+    }
+}
+
+pub fn export(
+    project: &Project,
+    languages: &Languages,
+    export_path: &Path,
+) -> Result<()> {
+    let rows = project.rows()?;
+    create_dir_all(export_path)?;
+
+    let mut index = Vec::new();
+
+    for (name, rows) in by_file(&rows) {
+        let mut domains: BTreeMap<String, (Option<u16>, Vec<&Row>)> =
+            BTreeMap::new();
+
+        for row in rows {
+            let (domain, section) = domain(name, row.section);
+
+            domains
+                .entry(domain)
+                .or_insert_with(|| (section, Vec::new()))
+                .1
+                .push(row);
+        }
+
+        for (domain, (section, rows)) in domains {
+            let (output, exported) = catalog(name, &rows, languages);
+            let file_name = format!("{domain}.{EXTENSION}");
+            write(export_path.join(&file_name), output)?;
+
+            // The first comment of a section is the map's display name or event's name.
+            let title = section
+                .and_then(|_| rows.iter().find(|row| !row.context.is_empty()))
+                .map(|row| describe(&row.context))
+                .unwrap_or_default();
+
+            index.push(IndexEntry {
+                domain,
+                file: name.to_string(),
+                section,
+                name: title,
+                entries: exported,
+                translated: rows
+                    .iter()
+                    .filter(|row| !row.translation.is_empty())
+                    .count(),
+            });
+        }
+    }
+
+    write(
+        export_path.join(INDEX_FILE),
+        serde_json::to_string_pretty(&index)?,
+    )?;
+
+    info!(
+        "{INDEX_FILE}: Successfully exported. {} domains.",
+        index.len()
+    );
+    Ok(())
+}
+
+pub fn import(import_path: &Path) -> Result<Vec<Row>> {
+    let index_path = import_path.join(INDEX_FILE);
+
+    if !index_path.exists() {
+        bail!(
+            "{}: Index catalog doesn't exist. Import domains from the directory they were exported to.",
+            index_path.display()
+        );
+    }
+
+    let index: Vec<IndexEntry> =
+        serde_json::from_str(&read_to_string(&index_path)?)
+            .with_context(|| format!("Parsing {INDEX_FILE}"))?;
+
+    let mut rows = Vec::new();
+    let mut missing = 0;
+
+    for domain in index {
+        let path = import_path.join(format!("{}.{EXTENSION}", domain.domain));
+
+        // Reviewers may hand back only the domains they claimed.
+        if !path.exists() {
+            missing += 1;
+            continue;
+        }
+
+        let start = rows.len();
+        read_file(&path, &domain.file, &mut rows)?;
+
+        if let Some(section) = domain.section {
+            let foreign = rows[start..]
+                .iter()
+                .filter(|row| row.section != Some(section))
+                .count();
+
+            if foreign != 0 {
+                warn!(
+                    "{}.{EXTENSION}: {foreign} entries have a context of another section, than the domain's section {section}.",
+                    domain.domain
+                );
+            }
+        }
+    }
+
+    if missing != 0 {
+        info!(
+            "{INDEX_FILE}: {missing} domains of the index have no file, and were skipped."
+        );
+    }
+
+    Ok(rows)
+}
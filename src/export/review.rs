@@ -0,0 +1,200 @@
+//! Bilingual review file per translation file, for proofreading on paper or in a plain text editor.
+//!
+//! Each entry is a block of the original lines, indented by two spaces, followed by translation lines, that start with `>`, and blocks are separated by empty lines. Sections start with a `[<section>]` line, and comments of translation files, e.g. event names, are `#` lines. Import reads edited translation lines back, and matches blocks to entries by section and original text, so original lines must stay as they are.
+
+use super::{Project, Row, by_file, po::describe};
+use crate::translation::{denormalize, normalize};
+use anyhow::{Context, Result, bail};
+use std::{
+    fmt::Write,
+    fs::{create_dir_all, read_dir, read_to_string, write},
+    path::Path,
+};
+use tracing::info;
+
+const EXTENSION: &str = "review.txt";
+
+const SOURCE_PREFIX: &str = "  ";
+const TRANSLATION_PREFIX: char = '>';
+
+pub fn export(project: &Project, export_path: &Path) -> Result<()> {
+    create_dir_all(export_path)?;
+    let rows = project.rows()?;
+
+    for (name, rows) in by_file(&rows) {
+        let stem = name.strip_suffix(".txt").unwrap_or(name);
+        let mut output = format!(
+            "# {name}: Original lines are indented, and translations start with `{TRANSLATION_PREFIX}`. Edit only translations.\n"
+        );
+        let mut section = None;
+        let mut context = None;
+
+        for row in &rows {
+            if row.section != section {
+                section = row.section;
+                context = None;
+
+                if let Some(section) = section {
+                    let _ = write!(output, "\n[{section}]\n");
+                }
+            }
+
+            if context != Some(&row.context) {
+                context = Some(&row.context);
+
+                if !row.context.is_empty() {
+                    let _ = write!(output, "\n# {}\n", describe(&row.context));
+                }
+            }
+
+            output.push('\n');
+
+            for line in denormalize(&row.source).split('\n') {
+                let _ = writeln!(output, "{SOURCE_PREFIX}{line}");
+            }
+
+            for line in denormalize(&row.translation).split('\n') {
+                if line.is_empty() {
+                    output.push(TRANSLATION_PREFIX);
+                    output.push('\n');
+                } else {
+                    let _ = writeln!(output, "{TRANSLATION_PREFIX} {line}");
+                }
+            }
+        }
+
+        let file_name = format!("{stem}.{EXTENSION}");
+        write(export_path.join(&file_name), output)?;
+        info!(
+            "{file_name}: Successfully exported. {} entries.",
+            rows.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Parses blocks of review file `content` of translation file `name` into `rows`. Returns the number of read entries.
+fn parse(content: &str, name: &str, rows: &mut Vec<Row>) -> Result<usize> {
+    let mut section = None;
+    let mut read = 0;
+    let mut source: Vec<&str> = Vec::new();
+    let mut translation: Vec<&str> = Vec::new();
+    let mut start = 0;
+
+    // A trailing empty line ends the last block.
+    for (index, line) in content.lines().chain([""]).enumerate() {
+        // Empty original lines of multiline entries are indented, unlike separators.
+        let in_source = !source.is_empty() && translation.is_empty();
+
+        if line.is_empty() || (line.trim().is_empty() && !in_source) {
+            if !source.is_empty() {
+                if translation.is_empty() {
+                    bail!(
+                        "Line {}: Entry has no translation lines, that start with `{TRANSLATION_PREFIX}`.",
+                        start + 1
+                    );
+                }
+
+                let text = translation.join("\n");
+
+                // Untranslated entries have an empty translation line, which must not clear translations.
+                if !text.is_empty() {
+                    rows.push(Row {
+                        file: name.to_string(),
+                        section,
+                        context: String::new(),
+                        source: normalize(&source.join("\n")),
+                        translation: normalize(&text),
+                    });
+                    read += 1;
+                }
+            }
+
+            source.clear();
+            translation.clear();
+            continue;
+        }
+
+        if let Some(text) = line.strip_prefix(TRANSLATION_PREFIX) {
+            if source.is_empty() {
+                bail!(
+                    "Line {}: Translation doesn't follow original lines.",
+                    index + 1
+                );
+            }
+
+            translation.push(text.strip_prefix(' ').unwrap_or(text));
+            continue;
+        }
+
+        if !translation.is_empty() {
+            bail!(
+                "Line {}: Original line follows translation lines. Separate entries with an empty line.",
+                index + 1
+            );
+        }
+
+        if source.is_empty() {
+            if line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(id) = line
+                .strip_prefix('[')
+                .and_then(|line| line.strip_suffix(']'))
+            {
+                let Ok(id) = id.trim().parse() else {
+                    bail!("Line {}: Section `{line}` is malformed.", index + 1);
+                };
+
+                section = Some(id);
+                continue;
+            }
+
+            start = index;
+        }
+
+        source.push(line.strip_prefix(SOURCE_PREFIX).unwrap_or(line));
+    }
+
+    Ok(read)
+}
+
+pub fn import(import_path: &Path) -> Result<Vec<Row>> {
+    let mut paths: Vec<_> = read_dir(import_path)
+        .with_context(|| format!("Reading {}", import_path.display()))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name().is_some_and(|name| {
+                name.to_string_lossy().ends_with(&format!(".{EXTENSION}"))
+            })
+        })
+        .collect();
+    paths.sort();
+
+    if paths.is_empty() {
+        bail!("{}: No review files to import.", import_path.display());
+    }
+
+    let mut rows = Vec::new();
+
+    for path in paths {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let stem = file_name
+            .strip_suffix(&format!(".{EXTENSION}"))
+            .unwrap_or(&file_name);
+
+        let read =
+            parse(&read_to_string(&path)?, &format!("{stem}.txt"), &mut rows)
+                .with_context(|| format!("Parsing {file_name}"))?;
+
+        info!("{file_name}: Read {read} entries.");
+    }
+
+    Ok(rows)
+}
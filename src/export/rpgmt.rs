@@ -0,0 +1,299 @@
+//! RPG Maker Trans v3 patches.
+//!
+//! A patch is a directory with `RPGMKTRANSPATCH` marker file and `patch` directory, that holds a file per game data file. Each file consists of string blocks:
+//!
+//! ```text
+//! > BEGIN STRING
+//! Source
+//! > CONTEXT: Items/1/name/ < UNTRANSLATED
+//! Translation
+//! > END STRING
+//! ```
+//!
+//! Contexts locate the string in game data. Only the data file and the ID of the object are taken from them, since translation files don't store exact locations. For the same reason, exported contexts consist of the data file, the ID and the position of the entry in the section only.
+
+use super::{Project, Row};
+use crate::translation::{denormalize, normalize};
+use anyhow::{Result, bail};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    fs::{create_dir_all, read_dir, read_to_string, write},
+    path::{Path, PathBuf},
+};
+use tracing::{info, warn};
+
+const PATCH_DIR: &str = "patch";
+const MARKER_FILE: &str = "RPGMKTRANSPATCH";
+const MARKER: &str = "> RPGMAKER TRANS PATCH V3\n";
+const FILE_HEADER: &str = "> RPGMAKER TRANS PATCH FILE VERSION 3.2\n";
+
+const BEGIN_STRING: &str = "> BEGIN STRING";
+const END_STRING: &str = "> END STRING";
+const CONTEXT_PREFIX: &str = "> CONTEXT:";
+const UNTRANSLATED_SUFFIX: &str = "< UNTRANSLATED";
+
+/// Patch file stems and translation files, which they correspond to. Maps are handled separately.
+const FILES: &[(&str, &str)] = &[
+    ("Actors", "actors.txt"),
+    ("Armors", "armors.txt"),
+    ("Classes", "classes.txt"),
+    ("CommonEvents", "commonevents.txt"),
+    ("Enemies", "enemies.txt"),
+    ("Items", "items.txt"),
+    ("Skills", "skills.txt"),
+    ("States", "states.txt"),
+    ("Troops", "troops.txt"),
+    ("Weapons", "weapons.txt"),
+    ("System", "system.txt"),
+    ("Scripts", "scripts.txt"),
+];
+
+/// Names of common events and troops are extracted to their own translation files.
+const NAME_FILES: &[(&str, &str)] = &[
+    ("CommonEvents", "commonevents-names.txt"),
+    ("Troops", "troops-names.txt"),
+];
+
+/// String block of a patch file.
+#[derive(Default)]
+struct Block {
+    source: Vec<String>,
+    contexts: Vec<String>,
+    translation: Vec<String>,
+    untranslated: bool,
+}
+
+/// Parses string blocks of a patch file.
+fn parse(text: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut block: Option<Block> = None;
+
+    for line in text.trim_start_matches('\u{FEFF}').lines() {
+        if line == BEGIN_STRING {
+            block = Some(Block::default());
+            continue;
+        }
+
+        let Some(current) = &mut block else {
+            continue;
+        };
+
+        if line == END_STRING {
+            blocks.extend(block.take());
+        } else if let Some(context) = line.strip_prefix(CONTEXT_PREFIX) {
+            let context = context.trim();
+
+            match context.strip_suffix(UNTRANSLATED_SUFFIX) {
+                Some(context) => {
+                    current.untranslated = true;
+                    current.contexts.push(context.trim_end().to_string());
+                }
+                None => current.contexts.push(context.to_string()),
+            }
+        } else if current.contexts.is_empty() {
+            current.source.push(line.to_string());
+        } else {
+            current.translation.push(line.to_string());
+        }
+    }
+
+    blocks
+}
+
+/// Returns the translation file and the section, that the string with `context` in `stem` patch file belongs to.
+fn locate(stem: &str, context: &str) -> Option<(&'static str, Option<u16>)> {
+    if let Some(id) = stem.strip_prefix("Map").and_then(|id| id.parse().ok()) {
+        return Some(("maps.txt", Some(id)));
+    }
+
+    let mut parts = context.split('/').filter(|part| !part.is_empty());
+    let section = parts.nth(1).and_then(|part| part.parse().ok());
+    let is_name = parts.next() == Some("name") && parts.next().is_none();
+
+    if is_name
+        && let Some((_, file)) = NAME_FILES.iter().find(|(s, _)| *s == stem)
+    {
+        return Some((file, section));
+    }
+
+    FILES
+        .iter()
+        .find(|(s, _)| *s == stem)
+        .map(|(_, file)| (*file, section))
+}
+
+/// Returns the directory with patch files.
+fn patch_dir(import_path: &Path) -> Result<PathBuf> {
+    let patch_path = import_path.join(PATCH_DIR);
+
+    if patch_path.is_dir() {
+        return Ok(patch_path);
+    }
+
+    if import_path.is_dir() {
+        return Ok(import_path.to_path_buf());
+    }
+
+    bail!(
+        "{} is not RPG Maker Trans patch directory.",
+        import_path.display()
+    );
+}
+
+pub fn import(import_path: &Path) -> Result<Vec<Row>> {
+    let patch_path = patch_dir(import_path)?;
+    let mut rows = Vec::new();
+
+    let mut names: Vec<String> = read_dir(&patch_path)?
+        .flatten()
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| {
+            Path::new(name)
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("txt"))
+        })
+        .collect();
+    names.sort_unstable();
+
+    for name in names {
+        let stem = name.split_once('.').map_or(name.as_str(), |(stem, _)| stem);
+        let content = read_to_string(patch_path.join(&name))?;
+        let mut imported = 0;
+
+        for block in parse(&content) {
+            let translation = normalize(&block.translation.join("\n"));
+
+            if block.untranslated || translation.is_empty() {
+                continue;
+            }
+
+            let source = normalize(&block.source.join("\n"));
+            let mut located = false;
+
+            for context in &block.contexts {
+                let Some((file, section)) = locate(stem, context) else {
+                    continue;
+                };
+
+                located = true;
+                rows.push(Row {
+                    file: file.to_string(),
+                    section,
+                    source: source.clone(),
+                    translation: translation.clone(),
+                    ..Default::default()
+                });
+            }
+
+            if located {
+                imported += 1;
+            }
+        }
+
+        if imported == 0 && locate(stem, "").is_none() {
+            warn!(
+                "{name}: Patch file doesn't correspond to any translation file. Skipping it."
+            );
+            continue;
+        }
+
+        info!("{name}: Read {imported} translated strings.");
+    }
+
+    Ok(rows)
+}
+
+/// Returns the patch file stem and the context prefix of entries in `section` of `file` translation file.
+fn patch_location(
+    file: &str,
+    section: Option<u16>,
+) -> Option<(String, String)> {
+    if file == "maps.txt" {
+        let stem = format!("Map{:03}", section?);
+        return Some((stem.clone(), format!("{stem}/")));
+    }
+
+    let (stem, suffix) = if let Some((stem, _)) =
+        NAME_FILES.iter().find(|(_, name)| *name == file)
+    {
+        (stem, "name/")
+    } else {
+        (&FILES.iter().find(|(_, name)| *name == file)?.0, "")
+    };
+
+    let prefix = match section {
+        Some(section) => format!("{stem}/{section}"),
+        None => (*stem).to_string(),
+    };
+
+    Some(((*stem).to_string(), format!("{prefix}/{suffix}")))
+}
+
+pub fn export(project: &Project, export_path: &Path) -> Result<()> {
+    let mut files: BTreeMap<String, String> = BTreeMap::new();
+    let mut ordinals: HashMap<(String, Option<u16>), usize> = HashMap::new();
+    let mut unsupported = Vec::new();
+
+    for row in project.rows()? {
+        let Some((stem, prefix)) = patch_location(&row.file, row.section)
+        else {
+            if !unsupported.contains(&row.file) {
+                unsupported.push(row.file);
+            }
+            continue;
+        };
+
+        let ordinal = ordinals.entry((row.file, row.section)).or_default();
+        *ordinal += 1;
+
+        // Names are the only entry of their object, so they don't need the position.
+        let context = if prefix.ends_with("name/") {
+            prefix
+        } else {
+            format!("{prefix}{ordinal}/")
+        };
+
+        let output =
+            files.entry(stem).or_insert_with(|| FILE_HEADER.to_string());
+
+        let _ =
+            writeln!(output, "{BEGIN_STRING}\n{}", denormalize(&row.source));
+
+        if row.translation.is_empty() {
+            let _ = writeln!(
+                output,
+                "{CONTEXT_PREFIX} {context} {UNTRANSLATED_SUFFIX}"
+            );
+        } else {
+            let _ = writeln!(
+                output,
+                "{CONTEXT_PREFIX} {context}\n{}",
+                denormalize(&row.translation)
+            );
+        }
+
+        let _ = writeln!(output, "{END_STRING}");
+    }
+
+    for file in unsupported {
+        warn!(
+            "{file}: RPG Maker Trans doesn't support this file. Skipping it."
+        );
+    }
+
+    let patch_path = export_path.join(PATCH_DIR);
+    create_dir_all(&patch_path)?;
+    write(export_path.join(MARKER_FILE), MARKER)?;
+
+    for (stem, content) in &files {
+        write(patch_path.join(format!("{stem}.txt")), content)?;
+    }
+
+    info!(
+        "{MARKER_FILE}: Successfully exported. {} patch files.",
+        files.len()
+    );
+
+    Ok(())
+}
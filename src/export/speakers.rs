@@ -0,0 +1,245 @@
+//! Groups messages by their speaker.
+//!
+//! Speaker is detected from, in order of priority: MZ name box, name box escape codes of message plugins (`\n<Name>`), first line, that consists only of a name (`\N[1]`, `【Name】`), and an actor, whose face is shown with the message.
+
+use super::Project;
+use crate::{
+    data::{data_file_path, load_rpgm_file, named_entries},
+    dialogue::{self, Location, Message},
+    translation::{Line, TranslationFile, TranslationIndex},
+};
+use anyhow::Result;
+use regex::Regex;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::{create_dir_all, write},
+    path::Path,
+    sync::LazyLock,
+};
+use tracing::info;
+
+/// Speaker of messages without a face and a name.
+const NARRATION: &str = "Narration";
+
+static NAME_BOX_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\\n[cr]?<([^>]+)>").unwrap());
+static ACTOR_NAME_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\\n\[(\d+)\]").unwrap());
+static NAME_LINE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*(?:(\\[Nn]\[\d+\])|【([^】]+)】)\s*:?\s*$").unwrap()
+});
+
+#[derive(Default)]
+struct Actors {
+    names: HashMap<u16, String>,
+    faces: HashMap<(String, i32), String>,
+}
+
+impl Actors {
+    fn load(project: &Project) -> Result<Self> {
+        let mut actors = Self::default();
+        let path =
+            data_file_path(project.source_path, "Actors", project.engine_type);
+
+        if !path.exists() {
+            return Ok(actors);
+        }
+
+        let value = load_rpgm_file(&path, project.engine_type)?;
+
+        for (id, name) in named_entries(&value) {
+            actors.names.insert(id, name.to_string());
+        }
+
+        for actor in value.as_array().into_iter().flatten() {
+            let Some(object) = actor.as_object() else {
+                continue;
+            };
+
+            let face_name = object
+                .get("faceName")
+                .or_else(|| object.get("face_name"))
+                .and_then(|face| face.as_str());
+            let face_index = object
+                .get("faceIndex")
+                .or_else(|| object.get("face_index"))
+                .and_then(|index| index.as_int());
+            let name = object.get("name").and_then(|name| name.as_str());
+
+            if let (Some(face_name), Some(face_index), Some(name)) =
+                (face_name, face_index, name)
+                && !face_name.is_empty()
+            {
+                actors
+                    .faces
+                    .entry((face_name.to_string(), face_index))
+                    .or_insert_with(|| name.to_string());
+            }
+        }
+
+        Ok(actors)
+    }
+
+    /// Replaces `\N[n]` codes with actor names.
+    fn resolve(&self, text: &str) -> String {
+        ACTOR_NAME_RE
+            .replace_all(text, |captures: &regex::Captures| {
+                captures[1]
+                    .parse()
+                    .ok()
+                    .and_then(|id: u16| self.names.get(&id))
+                    .cloned()
+                    .unwrap_or_else(|| captures[0].to_string())
+            })
+            .trim()
+            .to_string()
+    }
+}
+
+fn detect_speaker(message: &Message, actors: &Actors) -> String {
+    if !message.speaker_name.trim().is_empty() {
+        return actors.resolve(&message.speaker_name);
+    }
+
+    let first_line = message.lines.first().map_or("", String::as_str);
+
+    if let Some(captures) = NAME_BOX_RE.captures(first_line) {
+        return actors.resolve(&captures[1]);
+    }
+
+    if message.lines.len() > 1
+        && let Some(captures) = NAME_LINE_RE.captures(first_line)
+    {
+        let name = captures.get(1).or_else(|| captures.get(2)).unwrap();
+        return actors.resolve(name.as_str());
+    }
+
+    if !message.face_name.is_empty() {
+        return actors
+            .faces
+            .get(&(message.face_name.clone(), message.face_index))
+            .cloned()
+            .unwrap_or_else(|| {
+                format!("{} {}", message.face_name, message.face_index)
+            });
+    }
+
+    NARRATION.to_string()
+}
+
+/// Returns speakers of `messages`, in the same order.
+pub fn detect_speakers(
+    project: &Project,
+    messages: &[(Location, Message)],
+) -> Result<Vec<String>> {
+    let actors = Actors::load(project)?;
+
+    Ok(messages
+        .iter()
+        .map(|(_, message)| detect_speaker(message, &actors))
+        .collect())
+}
+
+/// Makes the name usable as a file name on all platforms.
+fn sanitize_file_name(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| {
+            if c.is_control() || r#"<>:"/\|?*"#.contains(c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+
+    let sanitized = sanitized.trim().trim_end_matches('.');
+
+    if sanitized.is_empty() {
+        String::from("_")
+    } else {
+        sanitized.to_string()
+    }
+}
+
+/// Writes a file per speaker to `export_path/speakers`.
+///
+/// Each file contains speaker's messages with their translations, grouped in sections by translation file and section ID, in the same format as translation files.
+pub fn export(project: &Project, export_path: &Path) -> Result<()> {
+    let actors = Actors::load(project)?;
+    let translations = TranslationIndex::load(
+        project.translation_path,
+        &["maps.txt", "commonevents.txt", "troops.txt"],
+    )?;
+
+    // Only work packages need entries, that are left to translate.
+    let rows = if project.untranslated_only {
+        project.rows()?
+    } else {
+        Vec::new()
+    };
+    let included: Option<HashSet<(&str, Option<u16>, &str)>> =
+        project.untranslated_only.then(|| {
+            rows.iter()
+                .map(|row| {
+                    (row.file.as_str(), row.section, row.source.as_str())
+                })
+                .collect()
+        });
+
+    let mut speakers: BTreeMap<String, TranslationFile> = BTreeMap::new();
+    let mut last_locations: HashMap<String, (&'static str, u16)> =
+        HashMap::new();
+
+    for (location, message) in
+        dialogue::messages(project.source_path, project.engine_type)?
+    {
+        let source = message.source();
+
+        if let Some(included) = &included
+            && !included.contains(&(location.file, Some(location.id), &source))
+        {
+            continue;
+        }
+
+        let speaker = detect_speaker(&message, &actors);
+        let file = speakers.entry(speaker.clone()).or_default();
+
+        let last_location = last_locations.get(&speaker).copied();
+
+        if last_location.is_none_or(|(last_file, _)| last_file != location.file)
+        {
+            file.lines.push(Line::Comment(format!(
+                "<!-- FILE --><#>{}",
+                location.file
+            )));
+        }
+
+        if last_location != Some((location.file, location.id)) {
+            file.lines.push(Line::Id(location.id));
+        }
+
+        last_locations.insert(speaker, (location.file, location.id));
+
+        let translation = translations
+            .get(location.file, location.id, &source)
+            .unwrap_or_default()
+            .to_string();
+
+        file.lines.push(Line::Entry {
+            source,
+            translation,
+        });
+    }
+
+    let output_path = export_path.join("speakers");
+    create_dir_all(&output_path)?;
+
+    for (speaker, file) in &speakers {
+        let file_name = format!("{}.txt", sanitize_file_name(speaker));
+        write(output_path.join(&file_name), file.serialize())?;
+        info!("{file_name}: Successfully exported.");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,141 @@
+//! `SQLite` database of all entries, for projects, which translation files are too large to search and filter as text.
+//!
+//! The database is created with a bundled `SQLite`, so no tools need to be installed. `entries` table holds contexts, statuses and stable hashes of entries, that are the same as XLIFF unit IDs, so databases of different exports can be joined by them. Import reads `file`, `section`, `source` and `translation` columns back, so translations edited in the database, or with any `SQLite` tool, are synced to translation files. Rows, which translation is empty, don't change translations.
+
+use super::{Project, Row, by_file, stable_ids};
+use crate::translation::normalize;
+use anyhow::{Context, Result, bail};
+use rusqlite::{Connection, params};
+use std::{
+    collections::HashMap,
+    fs::{create_dir_all, remove_file},
+    path::{Path, PathBuf},
+};
+use tracing::info;
+
+const DATABASE_FILE: &str = "translation.db";
+
+const SCHEMA: &str = "CREATE TABLE entries (
+    id INTEGER PRIMARY KEY,
+    file TEXT NOT NULL,
+    section INTEGER,
+    context TEXT NOT NULL,
+    source TEXT NOT NULL,
+    translation TEXT NOT NULL,
+    status TEXT NOT NULL,
+    hash TEXT NOT NULL
+);
+CREATE INDEX entries_location ON entries (file, section);
+CREATE INDEX entries_hash ON entries (file, hash);
+";
+
+const QUERY: &str = "SELECT file, section, context, source, translation FROM entries WHERE translation != '' ORDER BY id";
+
+/// Creates the database of `rows` at `database_path`, replacing the previous one.
+pub(super) fn create(database_path: &Path, rows: &[Row]) -> Result<()> {
+    let hashes: HashMap<(&str, Option<u16>, &str), String> = by_file(rows)
+        .into_iter()
+        .flat_map(|(name, rows)| {
+            stable_ids(&rows).into_iter().map(move |(hash, row)| {
+                ((name, row.section, row.source.as_str()), hash)
+            })
+        })
+        .collect();
+
+    if database_path.exists() {
+        remove_file(database_path)?;
+    }
+
+    let mut connection = Connection::open(database_path)?;
+    let transaction = connection.transaction()?;
+    transaction.execute_batch(SCHEMA)?;
+
+    {
+        let mut insert = transaction.prepare(
+            "INSERT INTO entries VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        )?;
+
+        for (id, row) in (1i64..).zip(rows) {
+            let hash = hashes
+                .get(&(row.file.as_str(), row.section, row.source.as_str()))
+                .map_or("", String::as_str);
+
+            insert.execute(params![
+                id,
+                row.file,
+                row.section,
+                row.context,
+                row.source,
+                row.translation,
+                row.status(),
+                hash,
+            ])?;
+        }
+    }
+
+    transaction.commit()?;
+    Ok(())
+}
+
+/// Returns rows of the database at `database_path`, which translation isn't empty.
+pub(super) fn read(database_path: &Path) -> Result<Vec<Row>> {
+    let connection = Connection::open(database_path)?;
+    let mut query = connection.prepare(QUERY).with_context(|| {
+        format!(
+            "Querying {}. Does `entries` table have the exported schema?",
+            database_path.display()
+        )
+    })?;
+
+    let rows = query
+        .query_map([], |row| {
+            Ok(Row {
+                file: row.get(0)?,
+                section: row.get(1)?,
+                context: row.get(2)?,
+                source: normalize(&row.get::<_, String>(3)?),
+                translation: normalize(&row.get::<_, String>(4)?),
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<Row>>>()
+        .with_context(|| format!("Reading {}", database_path.display()))?;
+
+    Ok(rows)
+}
+
+pub fn export(project: &Project, export_path: &Path) -> Result<()> {
+    let rows = project.rows()?;
+    let database_path = export_path.join(DATABASE_FILE);
+
+    create_dir_all(export_path)?;
+    create(&database_path, &rows)
+        .with_context(|| format!("Creating {}", database_path.display()))?;
+
+    info!(
+        "{DATABASE_FILE}: Successfully exported. {} entries.",
+        rows.len()
+    );
+    Ok(())
+}
+
+/// Returns the database at `import_path`, which is either the database or a directory with it.
+fn database_file(import_path: &Path) -> PathBuf {
+    if import_path.is_file() {
+        import_path.to_path_buf()
+    } else {
+        import_path.join(DATABASE_FILE)
+    }
+}
+
+pub fn import(import_path: &Path) -> Result<Vec<Row>> {
+    let database_path = database_file(import_path);
+
+    if !database_path.exists() {
+        bail!("{}: Database doesn't exist.", database_path.display());
+    }
+
+    let rows = read(&database_path)?;
+
+    info!("{DATABASE_FILE}: Read {} entries.", rows.len());
+    Ok(rows)
+}
@@ -0,0 +1,115 @@
+//! Translator++ `.trans` projects.
+//!
+//! A project is a JSON object, that holds a table of rows per game data file, e.g. `data/Map001.json`. The first cell of a row is the original text, and the rest are translation columns, where the rightmost non-empty one is the final translation, like Translator++ itself picks it. Paths and contexts of Translator++ don't correspond to sections of translation files, so rows are matched to entries of all translation files by original text only.
+
+use super::{Row, rows_of_translations};
+use crate::translation::normalize;
+use anyhow::{Context, Result, bail};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    fs::{read_dir, read_to_string},
+    path::{Path, PathBuf},
+};
+use tracing::{info, warn};
+
+const EXTENSION: &str = "trans";
+
+/// Returns `.trans` projects at `import_path`, which is either a project or a directory with projects.
+fn projects(import_path: &Path) -> Result<Vec<PathBuf>> {
+    if import_path.is_file() {
+        return Ok(vec![import_path.to_path_buf()]);
+    }
+
+    let mut paths: Vec<_> = read_dir(import_path)
+        .with_context(|| format!("Reading {}", import_path.display()))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == EXTENSION))
+        .collect();
+    paths.sort();
+
+    if paths.is_empty() {
+        bail!(
+            "{}: No Translator++ projects to import.",
+            import_path.display()
+        );
+    }
+
+    Ok(paths)
+}
+
+/// Collects original texts and final translations of project `json`. Returns the number of originals, that have different translations in different rows.
+fn collect_translations(
+    json: &Value,
+    translations: &mut HashMap<String, String>,
+) -> Result<usize> {
+    let Some(files) = json
+        .pointer("/project/files")
+        .or_else(|| json.get("files"))
+        .and_then(Value::as_object)
+    else {
+        bail!("Project doesn't have `files` object.");
+    };
+
+    let mut conflicts = 0;
+
+    for file in files.values() {
+        let Some(data) = file.get("data").and_then(Value::as_array) else {
+            continue;
+        };
+
+        for row in data.iter().filter_map(Value::as_array) {
+            let mut cells = row.iter().map(|cell| cell.as_str().unwrap_or(""));
+
+            let Some(original) = cells.next().filter(|cell| !cell.is_empty())
+            else {
+                continue;
+            };
+
+            let Some(translation) = cells.rfind(|cell| !cell.is_empty()) else {
+                continue;
+            };
+
+            let (original, translation) =
+                (normalize(original), normalize(translation));
+
+            match translations.get(&original) {
+                Some(existing) if *existing != translation => conflicts += 1,
+                Some(_) => {}
+                None => {
+                    translations.insert(original, translation);
+                }
+            }
+        }
+    }
+
+    Ok(conflicts)
+}
+
+pub fn import(translation_path: &Path, import_path: &Path) -> Result<Vec<Row>> {
+    let mut translations = HashMap::new();
+
+    for path in projects(import_path)? {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let json: Value = serde_json::from_str(&read_to_string(&path)?)
+            .with_context(|| format!("Parsing {file_name}"))?;
+
+        let before = translations.len();
+        let conflicts = collect_translations(&json, &mut translations)
+            .with_context(|| format!("Reading {file_name}"))?;
+
+        if conflicts != 0 {
+            warn!(
+                "{file_name}: {conflicts} rows translate already read original text differently. The first translation is used."
+            );
+        }
+
+        info!("{file_name}: Read {} entries.", translations.len() - before);
+    }
+
+    rows_of_translations(translation_path, &translations)
+}
@@ -0,0 +1,132 @@
+//! Round-trip check of exported files, that proves, that escaping and encoding of a format don't lose translations.
+//!
+//! Exported files are imported into a copy of translation files without translations in a temporary directory, and translations of the copy are compared with the originals entry by entry.
+
+use super::{ExportFormat, ImportFormat, Resolution, import};
+use crate::translation::{Line, TranslationFile, translation_files};
+use anyhow::{Result, bail};
+use std::{
+    fs::{create_dir_all, read_to_string, remove_dir_all, write},
+    path::{Path, PathBuf},
+};
+use tracing::warn;
+
+/// Number of mismatching entries, that are listed in warnings.
+const LISTED_MISMATCHES: usize = 20;
+
+/// Returns the format, that reads files exported in `format` back, and the path to import them from.
+fn reader(
+    format: ExportFormat,
+    export_path: &Path,
+) -> Result<(ImportFormat, PathBuf)> {
+    let format = match format {
+        ExportFormat::Csv => ImportFormat::Csv,
+        ExportFormat::Json => ImportFormat::Json,
+        ExportFormat::Mtool => ImportFormat::Mtool,
+        // Translated documents don't exist until `OmegaT` creates them, but source files hold existing translations.
+        ExportFormat::Omegat => {
+            return Ok((ImportFormat::Po, export_path.join("source")));
+        }
+        ExportFormat::Po => ImportFormat::Po,
+        ExportFormat::PoDomains => ImportFormat::PoDomains,
+        ExportFormat::Review => ImportFormat::Review,
+        ExportFormat::RpgmakerTrans => ImportFormat::RpgmakerTrans,
+        ExportFormat::Speakers => {
+            bail!("Speaker files can't be imported, so they can't be verified.")
+        }
+        ExportFormat::Sqlite => ImportFormat::Sqlite,
+        ExportFormat::Weblate => ImportFormat::Weblate,
+        ExportFormat::Xliff => ImportFormat::Xliff,
+        ExportFormat::Xlsx => ImportFormat::Xlsx,
+        ExportFormat::Yaml => ImportFormat::Yaml,
+    };
+
+    Ok((format, export_path.to_path_buf()))
+}
+
+/// Imports files, that were exported in `format` to `export_path`, into a copy of translation files in `translation_path` without translations, and compares translations of the copy with the originals. Entries, which translation was lost or changed, are reported. Returns the number of verified translations, and the number of mismatching entries.
+pub fn verify(
+    format: ExportFormat,
+    translation_path: &Path,
+    export_path: &Path,
+    target_language: Option<&str>,
+) -> Result<(usize, usize)> {
+    let (import_format, import_path) = reader(format, export_path)?;
+    let names = translation_files(translation_path)?;
+
+    let temp_path = std::env::temp_dir()
+        .join(format!("rvpacker-verify-{}", std::process::id()));
+    create_dir_all(&temp_path)?;
+
+    let result = (|| {
+        let mut originals = Vec::with_capacity(names.len());
+
+        for name in &names {
+            let original = TranslationFile::parse(&read_to_string(
+                translation_path.join(name),
+            )?);
+            let mut cleared = original.clone();
+
+            for line in &mut cleared.lines {
+                if let Line::Entry { translation, .. } = line {
+                    translation.clear();
+                }
+            }
+
+            write(temp_path.join(name), cleared.serialize())?;
+            originals.push(original);
+        }
+
+        import(
+            import_format,
+            &temp_path,
+            &import_path,
+            target_language,
+            |_| Ok(Resolution::Overwrite),
+        )?;
+
+        let mut verified = 0;
+        let mut mismatches = 0;
+
+        for (name, original) in names.iter().zip(&originals) {
+            let imported =
+                TranslationFile::parse(&read_to_string(temp_path.join(name))?);
+
+            for ((section, source, original), (_, _, imported)) in
+                original.entries().zip(imported.entries())
+            {
+                if original == imported {
+                    if !original.is_empty() {
+                        verified += 1;
+                    }
+
+                    continue;
+                }
+
+                mismatches += 1;
+
+                if mismatches <= LISTED_MISMATCHES {
+                    let location = section
+                        .map_or_else(String::new, |section| {
+                            format!(" (section {section})")
+                        });
+                    warn!(
+                        "{name}{location}: `{source}` doesn't survive the round trip.\n  Original: {original}\n  Imported: {imported}"
+                    );
+                }
+            }
+        }
+
+        if mismatches > LISTED_MISMATCHES {
+            warn!(
+                "... and {} more mismatching entries.",
+                mismatches - LISTED_MISMATCHES
+            );
+        }
+
+        Ok((verified, mismatches))
+    })();
+
+    let _ = remove_dir_all(&temp_path);
+    result
+}
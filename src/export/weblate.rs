@@ -0,0 +1,151 @@
+//! Directory layout for translation platforms, like Weblate and Crowdin: `i18n/<language>/<file>.json`.
+//!
+//! Each file is a flat JSON object of stable entry keys and strings, in monolingual form: the directory of the source language is the base, that holds sources, and the directory of the target language holds translations, with empty strings for untranslated entries. Line breaks replace `\#` markers. Keys are stable hashes of entries, so platforms keep the history of strings across exports, and import matches strings to entries by their keys.
+
+use super::{Languages, Project, Row, by_file, rows, stable_ids};
+use crate::translation::{denormalize, normalize};
+use anyhow::{Context, Result, bail};
+use serde_json::{Map, Value};
+use std::{
+    collections::HashMap,
+    fs::{create_dir_all, read_dir, read_to_string, write},
+    path::Path,
+};
+use tracing::{info, warn};
+
+const LAYOUT_DIR: &str = "i18n";
+const EXTENSION: &str = "json";
+
+pub fn export(
+    project: &Project,
+    languages: &Languages,
+    export_path: &Path,
+) -> Result<()> {
+    let Some(target) = languages.target else {
+        bail!(
+            "Platform layout needs a target language. Set it with `--target-language`."
+        );
+    };
+
+    let source_path = export_path.join(LAYOUT_DIR).join(languages.source);
+    let target_path = export_path.join(LAYOUT_DIR).join(target);
+    create_dir_all(&source_path)?;
+    create_dir_all(&target_path)?;
+
+    let rows = project.rows()?;
+
+    for (name, rows) in by_file(&rows) {
+        let stem = name.strip_suffix(".txt").unwrap_or(name);
+        let mut sources = Map::new();
+        let mut translations = Map::new();
+
+        for (key, row) in stable_ids(&rows) {
+            sources
+                .insert(key.clone(), Value::String(denormalize(&row.source)));
+            translations
+                .insert(key, Value::String(denormalize(&row.translation)));
+        }
+
+        let file_name = format!("{stem}.{EXTENSION}");
+        write(
+            source_path.join(&file_name),
+            serde_json::to_string_pretty(&sources)?,
+        )?;
+        write(
+            target_path.join(&file_name),
+            serde_json::to_string_pretty(&translations)?,
+        )?;
+
+        info!(
+            "{file_name}: Successfully exported. {} entries.",
+            sources.len()
+        );
+    }
+
+    Ok(())
+}
+
+pub fn import(
+    translation_path: &Path,
+    import_path: &Path,
+    target: Option<&str>,
+) -> Result<Vec<Row>> {
+    let Some(target) = target else {
+        bail!(
+            "Platform layout needs a target language to import. Set it with `--target-language`."
+        );
+    };
+
+    let current = rows(translation_path)?;
+    let keys: HashMap<(&str, String), &Row> = by_file(&current)
+        .into_iter()
+        .flat_map(|(name, rows)| {
+            stable_ids(&rows)
+                .into_iter()
+                .map(move |(key, row)| ((name, key), row))
+        })
+        .collect();
+
+    let target_path = import_path.join(LAYOUT_DIR).join(target);
+    let mut paths: Vec<_> = read_dir(&target_path)
+        .with_context(|| format!("Reading {}", target_path.display()))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == EXTENSION))
+        .collect();
+    paths.sort();
+
+    let mut rows = Vec::new();
+
+    for path in paths {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let stem = file_name.strip_suffix(".json").unwrap_or(&file_name);
+        let name = format!("{stem}.txt");
+
+        let Value::Object(strings) =
+            serde_json::from_str(&read_to_string(&path)?)
+                .with_context(|| format!("Parsing {file_name}"))?
+        else {
+            bail!("{file_name}: File is not a JSON object.");
+        };
+
+        let mut read = 0;
+        let mut unknown = 0;
+
+        for (key, translation) in strings {
+            // Platforms leave untranslated strings empty, which must not clear translations.
+            let Some(translation) =
+                translation.as_str().filter(|string| !string.is_empty())
+            else {
+                continue;
+            };
+
+            let Some(row) = keys.get(&(name.as_str(), key)) else {
+                unknown += 1;
+                continue;
+            };
+
+            rows.push(Row {
+                file: name.clone(),
+                section: row.section,
+                context: String::new(),
+                source: row.source.clone(),
+                translation: normalize(translation),
+            });
+            read += 1;
+        }
+
+        if unknown != 0 {
+            warn!(
+                "{file_name}: {unknown} keys don't match any entry in translation files, and were skipped."
+            );
+        }
+
+        info!("{file_name}: Read {read} entries.");
+    }
+
+    Ok(rows)
+}
@@ -0,0 +1,339 @@
+//! XLIFF 2.0 files, one per translation file, for CAT tools, that only accept XLIFF.
+//!
+//! Each entry is a unit with a single segment. Unit IDs are stable hashes of entries, and import matches units to entries by their IDs, rather than by sources, that CAT tools may normalize. Line breaks replace `\#` markers, and the nearest comment is a note of the unit. Units forbid re-segmentation, and import joins targets of all segments of units, that CAT tools split anyway.
+
+use super::{Languages, Project, Row, by_file, po::describe, rows, stable_ids};
+use crate::{
+    translation::{denormalize, normalize},
+    xml::{escape, unescape},
+};
+use anyhow::{Context, Result, bail};
+use quick_xml::{
+    Reader,
+    events::{BytesStart, Event},
+};
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    fs::{create_dir_all, read_dir, read_to_string, write},
+    path::Path,
+};
+use tracing::{info, warn};
+
+const EXTENSION: &str = "xlf";
+
+pub fn export(
+    project: &Project,
+    languages: &Languages,
+    export_path: &Path,
+) -> Result<()> {
+    let rows = project.rows()?;
+    create_dir_all(export_path)?;
+
+    for (name, rows) in by_file(&rows) {
+        let stem = name.strip_suffix(".txt").unwrap_or(name);
+        let units = stable_ids(&rows);
+
+        let mut output = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<xliff xmlns=\"urn:oasis:names:tc:xliff:document:2.0\" version=\"2.0\" srcLang=\"{}\"",
+            escape(languages.source)
+        );
+
+        if let Some(target) = languages.target {
+            let _ = write!(output, " trgLang=\"{}\"", escape(target));
+        }
+
+        let _ = writeln!(
+            output,
+            ">\n  <file id=\"{}\" original=\"{}\" canResegment=\"no\">",
+            escape(stem),
+            escape(name)
+        );
+
+        for (id, row) in &units {
+            let _ = writeln!(output, "    <unit id=\"{id}\">");
+
+            if row.section.is_some() || !row.context.is_empty() {
+                output.push_str("      <notes>\n");
+
+                if let Some(section) = row.section {
+                    let _ = writeln!(
+                        output,
+                        "        <note category=\"location\">{name}:{section}</note>"
+                    );
+                }
+
+                if !row.context.is_empty() {
+                    let _ = writeln!(
+                        output,
+                        "        <note category=\"context\">{}</note>",
+                        escape(&describe(&row.context))
+                    );
+                }
+
+                output.push_str("      </notes>\n");
+            }
+
+            let _ = writeln!(
+                output,
+                "      <segment state=\"{}\">\n        <source xml:space=\"preserve\">{}</source>",
+                if row.translation.is_empty() {
+                    "initial"
+                } else {
+                    "translated"
+                },
+                escape(&denormalize(&row.source))
+            );
+
+            if !row.translation.is_empty() {
+                let _ = writeln!(
+                    output,
+                    "        <target xml:space=\"preserve\">{}</target>",
+                    escape(&denormalize(&row.translation))
+                );
+            }
+
+            output.push_str("      </segment>\n    </unit>\n");
+        }
+
+        output.push_str("  </file>\n</xliff>\n");
+
+        let file_name = format!("{stem}.{EXTENSION}");
+        write(export_path.join(&file_name), output)?;
+        info!(
+            "{file_name}: Successfully exported. {} entries.",
+            units.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Unit of an XLIFF file, that has a target.
+struct Unit {
+    file: String,
+    id: String,
+    target: String,
+}
+
+/// Returns unescaped value of `name` attribute of `element`.
+fn attribute(element: &BytesStart, name: &str) -> Result<Option<String>> {
+    Ok(element
+        .try_get_attribute(name)?
+        .map(|attribute| unescape(&String::from_utf8_lossy(&attribute.value))))
+}
+
+/// Returns units of `xml`, that have a target, in their order.
+///
+/// CAT tools may split a unit into several segments, so targets of all segments and ignorables of a unit are joined. Inline elements are dropped, but their text is kept. Files without `original` attribute are named after their `id`, or after `stem` of the XLIFF file.
+fn read_units(xml: &str, stem: &str) -> Result<Vec<Unit>> {
+    let mut reader = Reader::from_str(xml);
+    let mut units = Vec::new();
+    let mut file = String::new();
+    let mut id = None;
+    let mut target: Option<String> = None;
+    let mut in_target = false;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) => match element.local_name().as_ref() {
+                b"file" => {
+                    file = match attribute(&element, "original")? {
+                        Some(original) => original,
+                        None => format!(
+                            "{}.txt",
+                            attribute(&element, "id")?
+                                .unwrap_or_else(|| stem.to_string())
+                        ),
+                    };
+                }
+                b"unit" => {
+                    id = attribute(&element, "id")?;
+                    target = None;
+                }
+                b"target" => {
+                    target.get_or_insert_default();
+                    in_target = true;
+                }
+                _ => {}
+            },
+            Event::Empty(element) => match element.local_name().as_ref() {
+                b"target" => {
+                    target.get_or_insert_default();
+                }
+                // Code points, that XML doesn't allow as is.
+                b"cp" if in_target => {
+                    if let Some(target) = target.as_mut()
+                        && let Some(char) = attribute(&element, "hex")?
+                            .and_then(|hex| u32::from_str_radix(&hex, 16).ok())
+                            .and_then(char::from_u32)
+                    {
+                        target.push(char);
+                    }
+                }
+                _ => {}
+            },
+            Event::End(element) => match element.local_name().as_ref() {
+                b"target" => in_target = false,
+                b"unit" => {
+                    if let (Some(id), Some(target)) = (id.take(), target.take())
+                    {
+                        units.push(Unit {
+                            file: file.clone(),
+                            id,
+                            target,
+                        });
+                    }
+                }
+                _ => {}
+            },
+            Event::Text(text) => {
+                if in_target && let Some(target) = target.as_mut() {
+                    target.push_str(&text.xml10_content()?);
+                }
+            }
+            Event::CData(data) => {
+                if in_target && let Some(target) = target.as_mut() {
+                    target.push_str(&data.decode()?);
+                }
+            }
+            Event::GeneralRef(reference) => {
+                if in_target && let Some(target) = target.as_mut() {
+                    target.push_str(&unescape(&format!(
+                        "&{};",
+                        reference.decode()?
+                    )));
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(units)
+}
+
+pub fn import(translation_path: &Path, import_path: &Path) -> Result<Vec<Row>> {
+    let current = rows(translation_path)?;
+    let ids: HashMap<(&str, String), &Row> = by_file(&current)
+        .into_iter()
+        .flat_map(|(name, rows)| {
+            stable_ids(&rows)
+                .into_iter()
+                .map(move |(id, row)| ((name, id), row))
+        })
+        .collect();
+
+    let mut paths: Vec<_> = read_dir(import_path)
+        .with_context(|| format!("Reading {}", import_path.display()))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == EXTENSION))
+        .collect();
+    paths.sort();
+
+    if paths.is_empty() {
+        bail!("{}: No XLIFF files to import.", import_path.display());
+    }
+
+    let mut rows = Vec::new();
+
+    for path in paths {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let xml = read_to_string(&path)?;
+        let units = read_units(
+            &xml,
+            file_name.strip_suffix(".xlf").unwrap_or(&file_name),
+        )
+        .with_context(|| format!("Parsing {file_name}"))?;
+        let mut read = 0;
+        let mut unknown = 0;
+
+        for unit in units {
+            let Some(row) = ids.get(&(unit.file.as_str(), unit.id)) else {
+                unknown += 1;
+                continue;
+            };
+
+            rows.push(Row {
+                file: row.file.clone(),
+                section: row.section,
+                context: String::new(),
+                source: row.source.clone(),
+                translation: normalize(&unit.target),
+            });
+            read += 1;
+        }
+
+        if unknown != 0 {
+            warn!(
+                "{file_name}: {unknown} units don't match any entry in translation files, and were skipped."
+            );
+        }
+
+        info!("{file_name}: Read {read} entries.");
+    }
+
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joins_targets_of_segments() {
+        let xml = r#"<?xml version="1.0"?>
+<xliff xmlns="urn:oasis:names:tc:xliff:document:2.0" version="2.0" srcLang="ja">
+  <file id="map001" original="maps.txt">
+    <unit id="a">
+      <segment><source>一。</source><target>One.</target></segment>
+      <ignorable><source> </source><target> </target></ignorable>
+      <segment><source>二。</source><target>Two &amp; <pc id="1">three</pc><cp hex="0001"/>.</target></segment>
+    </unit>
+    <unit id="b">
+      <segment><source>三</source><target><![CDATA[<b>&amp;</b>]]>&#x41;
+B</target></segment>
+    </unit>
+    <unit id="c">
+      <segment><source>四</source></segment>
+    </unit>
+    <unit id="d">
+      <segment><source>五</source><target/></segment>
+    </unit>
+  </file>
+  <file id="system">
+    <unit id="e"><segment><source>六</source><target>Six</target></segment></unit>
+  </file>
+</xliff>"#;
+        let units: Vec<_> = read_units(xml, "map001")
+            .unwrap()
+            .into_iter()
+            .map(|unit| (unit.file, unit.id, unit.target))
+            .collect();
+
+        assert_eq!(
+            units,
+            [
+                (
+                    "maps.txt".into(),
+                    "a".into(),
+                    "One. Two & three\u{1}.".into()
+                ),
+                ("maps.txt".into(), "b".into(), "<b>&amp;</b>A\nB".into()),
+                ("maps.txt".into(), "d".into(), String::new()),
+                ("system.txt".into(), "e".into(), "Six".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_xml() {
+        assert!(
+            read_units("<xliff><file><unit id=\"a\"></file>", "a").is_err()
+        );
+    }
+}
@@ -0,0 +1,184 @@
+//! Excel workbook with one sheet per translation file.
+//!
+//! Cells are written as strings, so Excel never interprets sources and translations as formulas or numbers. Header rows are frozen, and sheets are protected, except for the `Translation` column.
+
+use super::{Project, Row};
+use anyhow::{Context, Result, bail};
+use calamine::{Data, Reader, Xlsx, open_workbook};
+use rust_xlsxwriter::{Format, ProtectionOptions, Workbook};
+use std::{collections::BTreeMap, fs::create_dir_all, path::Path};
+use tracing::{info, warn};
+
+const WORKBOOK_FILE: &str = "translation.xlsx";
+
+/// Excel doesn't allow longer sheet names.
+const MAX_SHEET_NAME_LENGTH: usize = 31;
+
+const HEADERS: [&str; 5] =
+    ["Section", "Context", "Source", "Translation", "Status"];
+
+const COLUMN_WIDTHS: [f64; 5] = [10.0, 30.0, 60.0, 60.0, 14.0];
+
+/// Index of the only column, that's editable in protected sheets.
+const TRANSLATION_COLUMN: u16 = 3;
+
+pub fn export(project: &Project, export_path: &Path) -> Result<()> {
+    let rows = project.rows()?;
+    let mut sheets: BTreeMap<&str, Vec<&Row>> = BTreeMap::new();
+
+    for row in &rows {
+        sheets.entry(&row.file).or_default().push(row);
+    }
+
+    let mut workbook = Workbook::new();
+    let header_format = Format::new().set_bold();
+    let translation_format = Format::new().set_unlocked();
+
+    // Sheets are protected without a password, so only translations can be edited by accident, while sources stay intact for matching on import. Resizing, sorting and filtering still work.
+    let protection = ProtectionOptions {
+        format_columns: true,
+        format_rows: true,
+        sort: true,
+        use_autofilter: true,
+        ..ProtectionOptions::new()
+    };
+    let mut sheet_count = 0;
+
+    for (file, rows) in &sheets {
+        let name = file.strip_suffix(".txt").unwrap_or(file);
+
+        if name.chars().count() > MAX_SHEET_NAME_LENGTH {
+            warn!(
+                "{file}: File name is too long for a sheet name. Skipping it."
+            );
+            continue;
+        }
+
+        sheet_count += 1;
+
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name(name)?;
+        worksheet.set_freeze_panes(1, 0)?;
+        worksheet.set_column_format(TRANSLATION_COLUMN, &translation_format)?;
+        worksheet.protect_with_options(&protection);
+
+        for (column, (header, width)) in
+            (0..).zip(HEADERS.iter().zip(COLUMN_WIDTHS))
+        {
+            worksheet.set_column_width(column, width)?;
+            worksheet.write_string_with_format(
+                0,
+                column,
+                *header,
+                &header_format,
+            )?;
+        }
+
+        for (index, row) in (1..).zip(rows) {
+            let section =
+                row.section.map(|id| id.to_string()).unwrap_or_default();
+            let cells = [
+                section.as_str(),
+                &row.context,
+                &row.source,
+                &row.translation,
+                row.status(),
+            ];
+
+            for (column, cell) in (0..).zip(cells) {
+                if cell.is_empty() {
+                    continue;
+                }
+
+                if column == TRANSLATION_COLUMN {
+                    worksheet.write_string_with_format(
+                        index,
+                        column,
+                        cell,
+                        &translation_format,
+                    )?;
+                } else {
+                    worksheet.write_string(index, column, cell)?;
+                }
+            }
+        }
+    }
+
+    if sheet_count == 0 {
+        bail!("There are no entries to export.");
+    }
+
+    create_dir_all(export_path)?;
+    workbook.save(export_path.join(WORKBOOK_FILE))?;
+    info!(
+        "{WORKBOOK_FILE}: Successfully exported. {} sheets, {} entries.",
+        sheet_count,
+        rows.len()
+    );
+
+    Ok(())
+}
+
+pub fn import(import_path: &Path) -> Result<Vec<Row>> {
+    let workbook_path = import_path.join(WORKBOOK_FILE);
+    let mut workbook: Xlsx<_> = open_workbook(&workbook_path)
+        .with_context(|| format!("Reading {}", workbook_path.display()))?;
+    let mut rows = Vec::new();
+
+    for name in workbook.sheet_names() {
+        let range = workbook.worksheet_range(&name).with_context(|| {
+            format!("{WORKBOOK_FILE}: Reading sheet {name}")
+        })?;
+
+        let mut cells = range.rows();
+        let Some(header) = cells.next() else {
+            continue;
+        };
+
+        let column = |title: &str| {
+            header.iter().position(|cell| {
+                cell.to_string().trim().eq_ignore_ascii_case(title)
+            })
+        };
+
+        let (Some(section), Some(source), Some(translation)) =
+            (column("Section"), column("Source"), column("Translation"))
+        else {
+            warn!(
+                "{name}: Sheet doesn't have Section, Source and Translation columns. Skipping it."
+            );
+            continue;
+        };
+
+        let context = column("Context");
+        let file = format!("{name}.txt");
+
+        for cells in cells {
+            let cell = |index: usize| {
+                cells.get(index).map(Data::to_string).unwrap_or_default()
+            };
+
+            let row_source = cell(source);
+
+            if row_source.is_empty() {
+                continue;
+            }
+
+            rows.push(Row {
+                file: file.clone(),
+                section: cell(section).trim().parse::<f64>().ok().and_then(
+                    |id| {
+                        (id.fract() == 0.0 && (0.0..=65535.0).contains(&id))
+                            .then_some(id as u16)
+                    },
+                ),
+                context: context.map(cell).unwrap_or_default(),
+                source: row_source,
+                translation: cell(translation),
+            });
+        }
+    }
+
+    info!("{WORKBOOK_FILE}: Read {} entries.", rows.len());
+    Ok(rows)
+}
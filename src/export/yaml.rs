@@ -0,0 +1,338 @@
+//! YAML file per translation file, for reviewers, who read translations next to original texts.
+//!
+//! Files mirror translation files: each section is a mapping under its number, and each entry is a key with its translation as the value, where untranslated entries have an empty string. Original text is a comment above its key, and the nearest comment of the translation file, e.g. `NAME: Town`, is a `##` comment above the entries it precedes. Line breaks replace `\#` markers. Keys are stable hashes of entries, so import matches values to entries by their keys, and reads plain, quoted and literal block scalars, that reviewers may write by hand.
+
+use super::{Project, Row, by_file, po::describe, rows, stable_ids};
+use crate::translation::{denormalize, normalize};
+use anyhow::{Context, Result, bail};
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    fs::{create_dir_all, read_dir, read_to_string, write},
+    path::Path,
+};
+use tracing::{info, warn};
+
+const EXTENSION: &str = "yaml";
+
+/// Indentation of entries in sections.
+const INDENT: &str = "  ";
+
+/// Returns `string` as double-quoted YAML scalar.
+fn quote(string: &str) -> String {
+    let mut quoted = String::with_capacity(string.len() + 2);
+    quoted.push('"');
+
+    for char in string.chars() {
+        match char {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str(r"\\"),
+            '\n' => quoted.push_str(r"\n"),
+            '\r' => quoted.push_str(r"\r"),
+            '\t' => quoted.push_str(r"\t"),
+            char if char.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", u32::from(char));
+            }
+            char => quoted.push(char),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+pub fn export(project: &Project, export_path: &Path) -> Result<()> {
+    create_dir_all(export_path)?;
+    let rows = project.rows()?;
+
+    for (name, rows) in by_file(&rows) {
+        let stem = name.strip_suffix(".txt").unwrap_or(name);
+        let mut output = format!(
+            "# {name}: Translate the values. Comments above keys are original texts.\n"
+        );
+        let mut section = None;
+        let mut context = None;
+        let mut exported = 0;
+
+        for (key, row) in stable_ids(&rows) {
+            if row.section != section {
+                section = row.section;
+                context = None;
+
+                if let Some(section) = section {
+                    let _ = write!(output, "\n{section}:\n");
+                }
+            }
+
+            let indent = if section.is_some() { INDENT } else { "" };
+
+            if context != Some(&row.context) {
+                context = Some(&row.context);
+
+                if !row.context.is_empty() {
+                    let _ = writeln!(
+                        output,
+                        "{indent}## {}",
+                        describe(&row.context)
+                    );
+                }
+            }
+
+            for line in denormalize(&row.source).split('\n') {
+                let _ = writeln!(output, "{indent}# {line}");
+            }
+
+            let _ = writeln!(
+                output,
+                "{indent}{key}: {}",
+                quote(&denormalize(&row.translation))
+            );
+            exported += 1;
+        }
+
+        let file_name = format!("{stem}.{EXTENSION}");
+        write(export_path.join(&file_name), output)?;
+        info!("{file_name}: Successfully exported. {exported} entries.");
+    }
+
+    Ok(())
+}
+
+/// Returns the text of double-quoted scalar `quoted`, and the rest of the line after it.
+fn unquote_double(quoted: &str) -> Option<(String, &str)> {
+    let mut text = String::new();
+    let mut chars = quoted.strip_prefix('"')?.char_indices();
+
+    while let Some((index, char)) = chars.next() {
+        match char {
+            '"' => return Some((text, &quoted[index + 2..])),
+            '\\' => {}
+            char => {
+                text.push(char);
+                continue;
+            }
+        }
+
+        let (_, escaped) = chars.next()?;
+        let digits = match escaped {
+            'n' => {
+                text.push('\n');
+                continue;
+            }
+            't' => {
+                text.push('\t');
+                continue;
+            }
+            'r' => {
+                text.push('\r');
+                continue;
+            }
+            '0' => {
+                text.push('\0');
+                continue;
+            }
+            'x' => 2,
+            'u' => 4,
+            'U' => 8,
+            other => {
+                text.push(other);
+                continue;
+            }
+        };
+
+        let code: String =
+            chars.by_ref().take(digits).map(|(_, char)| char).collect();
+        text.push(char::from_u32(u32::from_str_radix(&code, 16).ok()?)?);
+    }
+
+    None
+}
+
+/// Returns the text of single-quoted scalar `quoted`, and the rest of the line after it.
+fn unquote_single(quoted: &str) -> Option<(String, &str)> {
+    let inner = quoted.strip_prefix('\'')?;
+    let mut text = String::new();
+    let mut chars = inner.char_indices().peekable();
+
+    while let Some((index, char)) = chars.next() {
+        if char != '\'' {
+            text.push(char);
+            continue;
+        }
+
+        if chars.next_if(|(_, next)| *next == '\'').is_some() {
+            text.push('\'');
+        } else {
+            return Some((text, &inner[index + 1..]));
+        }
+    }
+
+    None
+}
+
+/// Returns whether `rest` of the line after a scalar is empty or a comment.
+fn is_line_end(rest: &str) -> bool {
+    let rest = rest.trim_start();
+    rest.is_empty() || rest.starts_with('#')
+}
+
+/// Parses keys and scalar values of YAML file `content`, that has the same structure, as exported files. Keys of sections, that have no value, are skipped.
+fn parse(content: &str) -> Result<Vec<(String, String)>> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut values = Vec::new();
+    let mut index = 0;
+
+    while index < lines.len() {
+        let line = lines[index];
+        let number = index + 1;
+        index += 1;
+
+        let trimmed = line.trim_start();
+
+        if trimmed.is_empty() || trimmed.starts_with('#') || line == "---" {
+            continue;
+        }
+
+        let indent = line.len() - trimmed.len();
+
+        let Some((key, value)) = trimmed
+            .split_once(": ")
+            .or_else(|| trimmed.strip_suffix(':').map(|key| (key, "")))
+        else {
+            bail!("Line {number}: Line isn't a key with a value.");
+        };
+
+        let key = key.trim().trim_matches(['"', '\'']).to_string();
+        let value = value.trim();
+
+        let text = if value.is_empty() || value.starts_with('#') {
+            continue;
+        } else if value.starts_with('"') {
+            match unquote_double(value) {
+                Some((text, rest)) if is_line_end(rest) => text,
+                _ => bail!("Line {number}: Double-quoted string is malformed."),
+            }
+        } else if value.starts_with('\'') {
+            match unquote_single(value) {
+                Some((text, rest)) if is_line_end(rest) => text,
+                _ => bail!("Line {number}: Single-quoted string is malformed."),
+            }
+        } else if let Some(chomping) = value.strip_prefix('|') {
+            let chomping = chomping.split('#').next().unwrap_or("").trim();
+            let mut block = Vec::new();
+            let mut block_indent = None;
+
+            while index < lines.len() {
+                let line = lines[index];
+                let trimmed = line.trim_start();
+                let line_indent = line.len() - trimmed.len();
+
+                if !trimmed.is_empty() && line_indent <= indent {
+                    break;
+                }
+
+                let block_indent = *block_indent.get_or_insert(line_indent);
+                block.push(line.get(block_indent..).unwrap_or(""));
+                index += 1;
+            }
+
+            let text = block.join("\n");
+
+            match chomping {
+                "-" => text.trim_end_matches('\n').to_string(),
+                "+" => text + "\n",
+                _ => text.trim_end_matches('\n').to_string() + "\n",
+            }
+        } else if value.starts_with('>') {
+            bail!(
+                "Line {number}: Folded scalars aren't supported. Use a literal block scalar `|` or a quoted string."
+            );
+        } else {
+            let plain = value.split(" #").next().unwrap_or(value).trim_end();
+
+            if plain == "~" || plain == "null" {
+                continue;
+            }
+
+            plain.to_string()
+        };
+
+        values.push((key, text));
+    }
+
+    Ok(values)
+}
+
+pub fn import(translation_path: &Path, import_path: &Path) -> Result<Vec<Row>> {
+    let current = rows(translation_path)?;
+    let keys: HashMap<(&str, String), &Row> = by_file(&current)
+        .into_iter()
+        .flat_map(|(name, rows)| {
+            stable_ids(&rows)
+                .into_iter()
+                .map(move |(key, row)| ((name, key), row))
+        })
+        .collect();
+
+    let mut paths: Vec<_> = read_dir(import_path)
+        .with_context(|| format!("Reading {}", import_path.display()))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext == EXTENSION || ext == "yml")
+        })
+        .collect();
+    paths.sort();
+
+    let mut rows = Vec::new();
+
+    for path in paths {
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let stem = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let name = format!("{stem}.txt");
+
+        let values = parse(&read_to_string(&path)?)
+            .with_context(|| format!("Parsing {file_name}"))?;
+
+        let mut read = 0;
+        let mut unknown = 0;
+
+        for (key, translation) in values {
+            // Untranslated entries are empty, which must not clear translations.
+            if translation.is_empty() {
+                continue;
+            }
+
+            let Some(row) = keys.get(&(name.as_str(), key)) else {
+                unknown += 1;
+                continue;
+            };
+
+            rows.push(Row {
+                file: name.clone(),
+                section: row.section,
+                context: String::new(),
+                source: row.source.clone(),
+                translation: normalize(&translation),
+            });
+            read += 1;
+        }
+
+        if unknown != 0 {
+            warn!(
+                "{file_name}: {unknown} keys don't match any entry in translation files, and were skipped."
+            );
+        }
+
+        info!("{file_name}: Read {read} entries.");
+    }
+
+    Ok(rows)
+}
@@ -0,0 +1,535 @@
+//! Extraction of translatable fields, which the library doesn't process.
+//!
+//! Each field is extracted to its own translation file, that follows the same format as library's translation files, and is written back to the game files after the library finishes writing.
+
+use crate::{
+    data::{self, load_rpgm_file, map_files, named_entries, save_rpgm_file},
+    translation::{
+        Line, TranslationFile, denormalize, effective_translation, normalize,
+    },
+};
+use anyhow::{Context, Result};
+use marshal_rs::{Value, ValueType};
+use regex::{Captures, Regex};
+use rvpacker_lib::types::{EngineType, ReadMode};
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{create_dir_all, read_to_string, write},
+    path::{Path, PathBuf},
+};
+use tracing::{debug, info};
+
+#[derive(Debug, Clone, Copy)]
+pub enum ExtraKind {
+    CommonEventNames,
+    TroopNames,
+
+    /// Notes of tilesets, that some plugins read display text from.
+    TilesetNotes,
+
+    /// Note of `System`, if the game has one.
+    SystemNotes,
+
+    /// Name boxes of MZ `Show Text` commands. Each name is extracted once, like actor names, so it's translated the same everywhere.
+    SpeakerNames,
+}
+
+/// `Show Text` command, the fifth parameter of which is the name box in MZ.
+const SHOW_TEXT: i32 = 101;
+const SPEAKER_NAME_PARAMETER: usize = 4;
+
+/// Data files besides maps, that have event commands.
+const EVENT_FILES: &[&str] = &["CommonEvents", "Troops"];
+
+/// Collects speaker names of all `Show Text` commands in `value`.
+fn speaker_names(value: &mut Value) -> Vec<&mut Value> {
+    let mut names = Vec::new();
+    collect_speaker_names(value, &mut names);
+    names
+}
+
+fn collect_speaker_names<'a>(
+    value: &'a mut Value,
+    names: &mut Vec<&'a mut Value>,
+) {
+    match &mut **value {
+        ValueType::Array(array) => {
+            for value in array {
+                collect_speaker_names(value, names);
+            }
+        }
+        ValueType::HashMap(hashmap) => {
+            for value in hashmap.values_mut() {
+                collect_speaker_names(value, names);
+            }
+        }
+        ValueType::Object(object) => {
+            if object.get("code").and_then(|code| code.as_int())
+                == Some(SHOW_TEXT)
+            {
+                if let Some(name) = object
+                    .get_mut("parameters")
+                    .and_then(|parameters| parameters.as_array_mut())
+                    .and_then(|parameters| {
+                        parameters.get_mut(SPEAKER_NAME_PARAMETER)
+                    })
+                    .filter(|name| name.as_str().is_some())
+                {
+                    names.push(name);
+                }
+
+                return;
+            }
+
+            for value in object.values_mut() {
+                collect_speaker_names(value, names);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns regular expressions, that match `<Tag: value>` and `<Tag>value</Tag>` forms of each tag in `tags`. The value is the first capture group, that matched.
+fn tag_patterns(tags: &[String]) -> Vec<Regex> {
+    tags.iter()
+        .filter_map(|tag| {
+            let tag = regex::escape(tag);
+            Regex::new(&format!(
+                r"(?is)<{tag}\s*:\s*([^>]*)>|<{tag}>(.*?)</{tag}>"
+            ))
+            .ok()
+        })
+        .collect()
+}
+
+fn tag_value<'a>(captures: &Captures<'a>) -> Option<regex::Match<'a>> {
+    captures.get(1).or_else(|| captures.get(2))
+}
+
+/// Returns translatable parts of `note`: values of tags, that `patterns` match, or the whole note, if there are no patterns.
+fn note_texts<'a>(note: &'a str, patterns: &[Regex]) -> Vec<&'a str> {
+    if patterns.is_empty() {
+        return vec![note];
+    }
+
+    patterns
+        .iter()
+        .flat_map(|pattern| pattern.captures_iter(note))
+        .filter_map(|captures| Some(tag_value(&captures)?.as_str()))
+        .collect()
+}
+
+/// Replaces translatable parts of `note` with `translate(part)`, if it returns a translation.
+fn translate_note(
+    note: &str,
+    patterns: &[Regex],
+    translate: impl Fn(&str) -> Option<String>,
+) -> Option<String> {
+    if patterns.is_empty() {
+        return translate(note);
+    }
+
+    let mut translated = note.to_string();
+    let mut changed = false;
+
+    for pattern in patterns {
+        translated = pattern
+            .replace_all(&translated, |captures: &Captures| {
+                let whole = captures.get(0).unwrap();
+
+                let Some(value) = tag_value(captures) else {
+                    return whole.as_str().to_string();
+                };
+
+                let Some(translation) = translate(value.as_str()) else {
+                    return whole.as_str().to_string();
+                };
+
+                changed = true;
+                let start = value.start() - whole.start();
+                let end = value.end() - whole.start();
+                format!(
+                    "{}{translation}{}",
+                    &whole.as_str()[..start],
+                    &whole.as_str()[end..]
+                )
+            })
+            .into_owned();
+    }
+
+    changed.then_some(translated)
+}
+
+/// Returns `(id, object)` of objects with notes in the data file. `System` is a single object, and gets ID 0.
+fn noted_objects(kind: ExtraKind, value: &mut Value) -> Vec<(u16, &mut Value)> {
+    if matches!(kind, ExtraKind::SystemNotes) {
+        return if value.as_object().is_some() {
+            vec![(0, value)]
+        } else {
+            Vec::new()
+        };
+    }
+
+    value
+        .as_array_mut()
+        .into_iter()
+        .flatten()
+        .filter_map(|object| {
+            let id = object.as_object()?.get("id")?.as_int()?;
+            Some((id as u16, object))
+        })
+        .collect()
+}
+
+impl ExtraKind {
+    /// Returns paths of existing data files in `dir`, that contain the field.
+    fn data_files(
+        self,
+        dir: &Path,
+        engine_type: EngineType,
+    ) -> Result<Vec<PathBuf>> {
+        let stem = match self {
+            Self::CommonEventNames => "CommonEvents",
+            Self::TroopNames => "Troops",
+            Self::TilesetNotes => "Tilesets",
+            Self::SystemNotes => "System",
+            Self::SpeakerNames => {
+                let mut paths: Vec<PathBuf> = map_files(dir, engine_type)?
+                    .into_iter()
+                    .map(|(_, path)| path)
+                    .collect();
+
+                paths.extend(
+                    EVENT_FILES
+                        .iter()
+                        .map(|stem| {
+                            data::data_file_path(dir, stem, engine_type)
+                        })
+                        .filter(|path| path.exists()),
+                );
+                return Ok(paths);
+            }
+        };
+
+        let path = data::data_file_path(dir, stem, engine_type);
+        Ok(if path.exists() {
+            vec![path]
+        } else {
+            Vec::new()
+        })
+    }
+
+    pub const fn translation_file(self) -> &'static str {
+        match self {
+            Self::CommonEventNames => "commonevents-names.txt",
+            Self::TroopNames => "troops-names.txt",
+            Self::TilesetNotes => "tilesets-notes.txt",
+            Self::SystemNotes => "system-notes.txt",
+            Self::SpeakerNames => "speakers.txt",
+        }
+    }
+
+    /// Collects `(id, text)` pairs of the field from the data file. Only values of `note_tags` are collected from notes, if any are given.
+    fn collect(
+        self,
+        value: &Value,
+        note_tags: &[String],
+    ) -> Vec<(u16, String)> {
+        match self {
+            Self::CommonEventNames | Self::TroopNames => named_entries(value)
+                .into_iter()
+                .filter(|(_, name)| !name.trim().is_empty())
+                .map(|(id, name)| (id, normalize(name)))
+                .collect(),
+            Self::TilesetNotes | Self::SystemNotes => {
+                let patterns = tag_patterns(note_tags);
+                let mut value = value.clone();
+
+                noted_objects(self, &mut value)
+                    .into_iter()
+                    .filter_map(|(id, object)| {
+                        let note = object.as_object()?.get("note")?.as_str()?;
+
+                        Some(
+                            note_texts(note, &patterns)
+                                .into_iter()
+                                .filter(|text| !text.trim().is_empty())
+                                .map(|text| (id, normalize(text)))
+                                .collect::<Vec<_>>(),
+                        )
+                    })
+                    .flatten()
+                    .collect()
+            }
+            Self::SpeakerNames => speaker_names(&mut value.clone())
+                .into_iter()
+                .filter_map(|name| name.as_str().map(normalize))
+                .filter(|name| !name.trim().is_empty())
+                .map(|name| (0, name))
+                .collect(),
+        }
+    }
+
+    /// Replaces the field's text with translations. Returns the count of replaced entries.
+    fn apply(
+        self,
+        value: &mut Value,
+        translations: &Translations,
+        note_tags: &[String],
+    ) -> usize {
+        let mut count = 0;
+
+        match self {
+            Self::SpeakerNames => {
+                for name in speaker_names(value) {
+                    let Some(translation) = name.as_str().and_then(|source| {
+                        translations.get(&(0, normalize(source)))
+                    }) else {
+                        continue;
+                    };
+
+                    *name = Value::string(denormalize(translation));
+                    count += 1;
+                }
+            }
+            Self::TilesetNotes | Self::SystemNotes => {
+                let patterns = tag_patterns(note_tags);
+
+                for (id, object) in noted_objects(self, value) {
+                    let Some(note) = object
+                        .as_object_mut()
+                        .and_then(|object| object.get_mut("note"))
+                    else {
+                        continue;
+                    };
+
+                    let Some(translated) = note.as_str().and_then(|source| {
+                        translate_note(source, &patterns, |text| {
+                            translations
+                                .get(&(id, normalize(text)))
+                                .map(|translation| denormalize(translation))
+                        })
+                    }) else {
+                        continue;
+                    };
+
+                    *note = Value::string(translated);
+                    count += 1;
+                }
+            }
+            Self::CommonEventNames | Self::TroopNames => {
+                let Some(array) = value.as_array_mut() else {
+                    return 0;
+                };
+
+                for object in array.iter_mut().filter_map(|o| o.as_object_mut())
+                {
+                    let Some(id) = object.get("id").and_then(|id| id.as_int())
+                    else {
+                        continue;
+                    };
+
+                    let Some(name) = object.get_mut("name") else {
+                        continue;
+                    };
+
+                    let Some(source) = name.as_str().map(normalize) else {
+                        continue;
+                    };
+
+                    if let Some(translation) =
+                        translations.get(&(id as u16, source))
+                    {
+                        *name = Value::string(denormalize(translation));
+                        count += 1;
+                    }
+                }
+            }
+        }
+
+        count
+    }
+}
+
+type Translations = HashMap<(u16, String), String>;
+
+/// Extracts the field to its translation file.
+///
+/// Follows the library's read modes: default mode doesn't overwrite existing file, append mode preserves existing translations, force modes rewrite the file.
+pub fn read(
+    kind: ExtraKind,
+    source_path: &Path,
+    translation_path: &Path,
+    engine_type: EngineType,
+    read_mode: ReadMode,
+    note_tags: &[String],
+) -> Result<()> {
+    let data_paths = kind.data_files(source_path, engine_type)?;
+
+    if data_paths.is_empty() {
+        return Ok(());
+    }
+
+    let translation_file_path = translation_path.join(kind.translation_file());
+
+    if read_mode.is_default_default() && translation_file_path.exists() {
+        info!(
+            "{}: File already exists. Use append mode to append text or force mode to overwrite.",
+            translation_file_path.display()
+        );
+        return Ok(());
+    }
+
+    let mut existing: Translations = HashMap::new();
+
+    if read_mode.is_append() && translation_file_path.exists() {
+        let content = read_to_string(&translation_file_path)?;
+
+        for (id, source, translation) in
+            TranslationFile::parse(&content).entries()
+        {
+            if let Some(id) = id {
+                existing.insert((id, source.to_string()), translation.into());
+            }
+        }
+    }
+
+    debug!("{}: Started reading.", kind.translation_file());
+
+    let mut file = TranslationFile::default();
+    let mut last_id = None;
+    let mut seen = HashSet::new();
+
+    for data_path in data_paths {
+        let value = load_rpgm_file(&data_path, engine_type)?;
+
+        for (id, source) in kind.collect(&value, note_tags) {
+            if !seen.insert((id, source.clone())) {
+                continue;
+            }
+
+            let translation = existing
+                .get(&(id, source.clone()))
+                .or_else(|| {
+                    existing
+                        .iter()
+                        .find(|((_, s), t)| *s == source && !t.is_empty())
+                        .map(|(_, t)| t)
+                })
+                .cloned()
+                .unwrap_or_default();
+
+            if last_id != Some(id) {
+                file.lines.push(Line::Id(id));
+                last_id = Some(id);
+            }
+
+            file.lines.push(Line::Entry {
+                source,
+                translation,
+            });
+        }
+    }
+
+    write(&translation_file_path, file.serialize())?;
+    info!("{}: Successfully read.", kind.translation_file());
+
+    Ok(())
+}
+
+/// Writes translations of the field to the data files in `output_data_path`.
+///
+/// If the library has already written a data file, it's patched in place. Otherwise, source data file is used as a base.
+pub fn write_back(
+    kind: ExtraKind,
+    source_path: &Path,
+    translation_path: &Path,
+    output_data_path: &Path,
+    engine_type: EngineType,
+    note_tags: &[String],
+) -> Result<()> {
+    debug!("{}: Started writing.", kind.translation_file());
+
+    let translation_file_path = translation_path.join(kind.translation_file());
+    let content =
+        read_to_string(&translation_file_path).with_context(|| {
+            format!("Reading {}", translation_file_path.display())
+        })?;
+
+    let translations: Translations = TranslationFile::parse(&content)
+        .entries()
+        .filter(|(id, _, translation)| id.is_some() && !translation.is_empty())
+        .map(|(id, source, translation)| {
+            ((id.unwrap(), source.to_string()), translation.to_string())
+        })
+        .collect();
+
+    if translations.is_empty() {
+        return Ok(());
+    }
+
+    let mut written = false;
+
+    for source_file_path in kind.data_files(source_path, engine_type)? {
+        let Some(file_name) = source_file_path.file_name() else {
+            continue;
+        };
+
+        let output_file_path = output_data_path.join(file_name);
+        let base_path = if output_file_path.exists() {
+            &output_file_path
+        } else {
+            &source_file_path
+        };
+
+        let mut value = load_rpgm_file(base_path, engine_type)?;
+
+        if kind.apply(&mut value, &translations, note_tags) != 0 {
+            create_dir_all(output_data_path)?;
+            save_rpgm_file(&output_file_path, value, engine_type)?;
+            written = true;
+        }
+    }
+
+    if written {
+        info!("{}: Successfully written.", kind.translation_file());
+    }
+
+    Ok(())
+}
+
+/// Removes entries without translation from the field's translation file.
+pub fn purge(kind: ExtraKind, translation_path: &Path) -> Result<()> {
+    let translation_file_path = translation_path.join(kind.translation_file());
+
+    if !translation_file_path.exists() {
+        return Ok(());
+    }
+
+    debug!("{}: Started purging.", kind.translation_file());
+
+    let file = TranslationFile::parse(&read_to_string(&translation_file_path)?);
+    let mut purged = TranslationFile::default();
+    let mut pending_id = None;
+
+    for line in file.lines {
+        match line {
+            Line::Id(id) => pending_id = Some(id),
+            Line::Entry {
+                ref translation, ..
+            } if effective_translation(translation).is_empty() => {}
+            line => {
+                if let Some(id) = pending_id.take() {
+                    purged.lines.push(Line::Id(id));
+                }
+
+                purged.lines.push(line);
+            }
+        }
+    }
+
+    write(&translation_file_path, purged.serialize())?;
+    info!("{}: Successfully purged.", kind.translation_file());
+
+    Ok(())
+}
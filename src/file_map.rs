@@ -0,0 +1,262 @@
+//! Mapping of standard data file names to the ones, that the game uses.
+//!
+//! Some games obfuscate names of their data files, or move them to subdirectories. The mapping file is a JSON object of standard names to paths of the game's files, relative to the data directory. `%d` and `%0Nd` stand for the number of the map:
+//!
+//! ```json
+//! {
+//!     "Map%03d.json": "maps/m_%d.bin",
+//!     "System.json": "../config/core.dat"
+//! }
+//! ```
+//!
+//! Mapped files are copied to a temporary directory under their standard names, along with the rest of the data, so all commands operate on it, and `write` gives output files the game's names back.
+
+use crate::layers::{copy_plugins, file_names};
+use anyhow::{Context, Result, bail};
+use serde_json::{Map, Value};
+use std::{
+    collections::HashSet,
+    fs::{copy, create_dir_all, read_to_string, remove_dir_all, rename},
+    path::{Path, PathBuf},
+};
+use tracing::debug;
+
+/// File name with optional map number in it.
+struct Pattern {
+    prefix: String,
+
+    /// Minimal number of digits and the rest of the name, if the name has a number.
+    number: Option<(usize, String)>,
+}
+
+impl Pattern {
+    fn parse(pattern: &str) -> Result<Self> {
+        let Some((prefix, rest)) = pattern.split_once('%') else {
+            return Ok(Self {
+                prefix: pattern.to_string(),
+                number: None,
+            });
+        };
+
+        let Some((width, suffix)) = rest.split_once('d') else {
+            bail!("`{pattern}` must use `%d` or `%0Nd` for map numbers.");
+        };
+
+        let width = if width.is_empty() {
+            1
+        } else if let Some(width) = width.strip_prefix('0')
+            && let Ok(width) = width.parse()
+        {
+            width
+        } else {
+            bail!("`{pattern}` must use `%d` or `%0Nd` for map numbers.");
+        };
+
+        if suffix.contains('%') {
+            bail!("`{pattern}` can only have one map number.");
+        }
+
+        Ok(Self {
+            prefix: prefix.to_string(),
+            number: Some((width, suffix.to_string())),
+        })
+    }
+
+    fn format(&self, number: u32) -> String {
+        match &self.number {
+            Some((width, suffix)) => {
+                format!("{}{number:0width$}{suffix}", self.prefix)
+            }
+            None => self.prefix.clone(),
+        }
+    }
+
+    /// Returns the map number in `name`, or `0` for names without a number, if `name` matches.
+    fn matches(&self, name: &str) -> Option<u32> {
+        let rest = name.strip_prefix(&self.prefix)?;
+
+        let Some((width, suffix)) = &self.number else {
+            return rest.is_empty().then_some(0);
+        };
+
+        let digits = rest.strip_suffix(suffix.as_str())?;
+
+        if digits.len() < *width || !digits.bytes().all(|b| b.is_ascii_digit())
+        {
+            return None;
+        }
+
+        digits.parse().ok()
+    }
+}
+
+/// Rule, that maps the standard name to the game's file.
+struct Rule {
+    standard: Pattern,
+
+    /// Directory of the game's file, relative to the data directory.
+    dir: PathBuf,
+    game: Pattern,
+}
+
+/// Rules of the mapping file.
+pub struct FileMap {
+    rules: Vec<Rule>,
+}
+
+impl FileMap {
+    /// Loads the mapping file at `path`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = read_to_string(path)
+            .with_context(|| format!("Reading {}", path.display()))?;
+        let object: Map<String, Value> = serde_json::from_str(&content)
+            .with_context(|| format!("Parsing {}", path.display()))?;
+
+        let mut rules = Vec::with_capacity(object.len());
+
+        for (standard, game) in object {
+            let Some(game) = game.as_str() else {
+                bail!("Mapping of `{standard}` must be a path.");
+            };
+
+            let game = game.replace('\\', "/");
+            let (dir, name) = game.rsplit_once('/').unwrap_or(("", &game));
+
+            if dir.contains('%') {
+                bail!("Map number in `{game}` can only be in the file name.");
+            }
+
+            let rule = Rule {
+                standard: Pattern::parse(&standard)?,
+                dir: PathBuf::from(dir),
+                game: Pattern::parse(name)?,
+            };
+
+            if rule.standard.number.is_some() != rule.game.number.is_some() {
+                bail!(
+                    "`{standard}` and `{game}` must both have a map number, or both have none."
+                );
+            }
+
+            rules.push(rule);
+        }
+
+        Ok(Self { rules })
+    }
+
+    /// Returns the path of the mapped directory, that belongs to this process.
+    #[must_use]
+    pub fn temp_path() -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("rvpacker-mapped-{}", std::process::id()))
+    }
+
+    /// Copies files of `data_path` directory to `mapped_path`, giving mapped files their standard names.
+    pub fn apply(
+        &self,
+        data_path: &Path,
+        mapped_path: PathBuf,
+    ) -> Result<Mapped> {
+        if !data_path.exists() {
+            bail!(
+                "Data directory {} does not exist. File mapping can only apply to extracted game data.",
+                data_path.display()
+            );
+        }
+
+        if mapped_path.exists() {
+            remove_dir_all(&mapped_path)?;
+        }
+
+        let data_name = data_path.file_name().unwrap_or_default();
+        let target_path = mapped_path.join(data_name);
+        create_dir_all(&target_path)?;
+
+        // Construct it first, so the mapped directory is removed on failure.
+        let mut mapped = Mapped {
+            mapped_path,
+            names: Vec::new(),
+        };
+
+        let mut sources = HashSet::new();
+
+        for rule in &self.rules {
+            let dir = data_path.join(&rule.dir);
+
+            if !dir.is_dir() {
+                continue;
+            }
+
+            for name in file_names(&dir)? {
+                let Some(number) = rule.game.matches(&name) else {
+                    continue;
+                };
+
+                let standard = rule.standard.format(number);
+                let game = rule.dir.join(&name);
+
+                copy(dir.join(&name), target_path.join(&standard))?;
+                debug!("{}: Mapped to {standard}.", game.display());
+
+                sources.insert(game.clone());
+                mapped.names.push((standard, game));
+            }
+        }
+
+        for name in file_names(data_path)? {
+            if !sources.contains(Path::new(&name))
+                && !target_path.join(&name).exists()
+            {
+                copy(data_path.join(&name), target_path.join(&name))?;
+            }
+        }
+
+        copy_plugins(data_path, &mapped.mapped_path)?;
+        Ok(mapped)
+    }
+}
+
+/// Data directory, where mapped files have standard names.
+///
+/// The mapped directory is removed, when the value is dropped.
+pub struct Mapped {
+    mapped_path: PathBuf,
+
+    /// Standard names of mapped files, and paths of the game's files, relative to the data directory.
+    names: Vec<(String, PathBuf)>,
+}
+
+impl Mapped {
+    /// Returns the path of the mapped data directory, that corresponds to `base` data directory.
+    #[must_use]
+    pub fn data_path(&self, base: &Path) -> PathBuf {
+        self.mapped_path.join(base.file_name().unwrap_or_default())
+    }
+
+    /// Gives files in `output_data_path`, that were mapped, the game's names back.
+    pub fn restore(&self, output_data_path: &Path) -> Result<()> {
+        for (standard, game) in &self.names {
+            let output_file_path = output_data_path.join(standard);
+
+            if !output_file_path.exists() {
+                continue;
+            }
+
+            let game_path = output_data_path.join(game);
+
+            if let Some(parent) = game_path.parent() {
+                create_dir_all(parent)?;
+            }
+
+            rename(&output_file_path, &game_path)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Mapped {
+    fn drop(&mut self) {
+        let _ = remove_dir_all(&self.mapped_path);
+    }
+}
@@ -0,0 +1,70 @@
+//! Similarity of texts, for matching edited sources.
+
+/// Returns the similarity of `a` and `b` from 0 to 1, based on Levenshtein distance of their characters. Texts, that can't be at least `threshold` similar, get 0, without computing the distance.
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn similarity(a: &str, b: &str, threshold: f64) -> f64 {
+    if a == b {
+        return 1.0;
+    }
+
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let longest = a.len().max(b.len());
+
+    if longest == 0 {
+        return 1.0;
+    }
+
+    // Distance is at least the difference of lengths, and it's much cheaper to check.
+    let bound = 1.0 - a.len().abs_diff(b.len()) as f64 / longest as f64;
+
+    if bound < threshold {
+        return 0.0;
+    }
+
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, a_char) in a.iter().enumerate() {
+        current[0] = i + 1;
+
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            current[j + 1] =
+                substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    1.0 - previous[b.len()] as f64 / longest as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measures_similarity_by_characters() {
+        assert!(
+            (similarity("kitten", "sitting", 0.0) - 4.0 / 7.0).abs() < 1e-9
+        );
+        assert!(
+            (similarity("こんにちは", "こんばんは", 0.0) - 0.6).abs() < 1e-9
+        );
+        assert!((similarity("same", "same", 1.0) - 1.0).abs() < f64::EPSILON);
+        assert!((similarity("", "", 1.0) - 1.0).abs() < f64::EPSILON);
+        assert!(similarity("", "text", 0.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn skips_texts_only_below_threshold() {
+        // Lengths alone bound the similarity at 0.5.
+        assert!(similarity("ab", "abcd", 0.6).abs() < f64::EPSILON);
+        assert!((similarity("ab", "abcd", 0.5) - 0.5).abs() < 1e-9);
+
+        // Equal lengths don't bound it, so the distance decides.
+        assert!((similarity("abcd", "abxy", 0.9) - 0.5).abs() < 1e-9);
+    }
+}
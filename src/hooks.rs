@@ -0,0 +1,143 @@
+//! Shell commands, that run before and after `read`, `write` and `purge`.
+//!
+//! Hooks integrate the tool into a team's pipeline: formatting translation files, building the game, or notifying a chat. They receive paths of the run in environment variables. Post-hooks also receive the outcome in environment variables, and the run summary as JSON on stdin:
+//!
+//! ```json
+//! {
+//!     "command": "write",
+//!     "status": "success",
+//!     "exitCode": 0,
+//!     "error": null,
+//!     "elapsed": 0.42,
+//!     "warnings": 1,
+//!     "total": { "entries": 10, "translated": 8, "ignored": 0 },
+//!     "files": [{ "name": "maps.txt: Map001", "entries": 10, "translated": 8, "ignored": 0, "warnings": 0 }]
+//! }
+//! ```
+
+use crate::{
+    error::{ErrorKind, WithKind},
+    report,
+};
+use anyhow::{Result, anyhow};
+use std::{
+    io::Write,
+    path::Path,
+    process::{Command, Stdio},
+};
+use tracing::{debug, warn};
+
+/// Paths and the name of the command, that hooks run around.
+pub struct Context<'a> {
+    pub command: &'static str,
+    pub input_dir: &'a Path,
+    pub translation_dir: &'a Path,
+    pub output_dir: &'a Path,
+}
+
+/// Outcome of the command, that's passed to post-hooks.
+pub struct Outcome<'a> {
+    pub error: Option<&'a anyhow::Error>,
+    pub elapsed: f64,
+}
+
+fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.args(["/C", command]);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.args(["-c", command]);
+        shell
+    }
+}
+
+fn prepare(command: &str, context: &Context) -> Command {
+    let mut shell = shell(command);
+    shell
+        .env("RVPACKER_COMMAND", context.command)
+        .env("RVPACKER_INPUT_DIR", context.input_dir)
+        .env("RVPACKER_TRANSLATION_DIR", context.translation_dir)
+        .env("RVPACKER_OUTPUT_DIR", context.output_dir);
+    shell
+}
+
+/// Runs pre-hooks in order. Fails on the first hook, that exits unsuccessfully, so the command doesn't run.
+pub fn run_pre(commands: &[String], context: &Context) -> Result<()> {
+    for command in commands {
+        debug!("Running pre-hook `{command}`.");
+        let status = prepare(command, context).status()?;
+
+        if !status.success() {
+            return Err(anyhow!(
+                "Pre-hook `{command}` failed with {status}. Command isn't run."
+            ))
+            .kind(ErrorKind::Aborted);
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs post-hooks in order, whether the command succeeded or not. Fails, if any hook exits unsuccessfully.
+pub fn run_post(
+    commands: &[String],
+    context: &Context,
+    outcome: &Outcome,
+) -> Result<()> {
+    if commands.is_empty() {
+        return Ok(());
+    }
+
+    let exit_code = outcome.error.map_or(0, ErrorKind::exit_code_of);
+    let status = if outcome.error.is_some() {
+        "failure"
+    } else {
+        "success"
+    };
+    let error = outcome.error.map(|err| format!("{err:#}"));
+
+    let summary = report::run_summary(
+        context.command,
+        outcome.error,
+        outcome.elapsed,
+        context.translation_dir,
+    )?
+    .to_string();
+
+    let mut failed = 0;
+
+    for command in commands {
+        debug!("Running post-hook `{command}`.");
+
+        let mut shell = prepare(command, context);
+        shell
+            .env("RVPACKER_STATUS", status)
+            .env("RVPACKER_EXIT_CODE", exit_code.to_string())
+            .env("RVPACKER_ELAPSED", format!("{:.2}", outcome.elapsed))
+            .env("RVPACKER_ERROR", error.as_deref().unwrap_or_default())
+            .stdin(Stdio::piped());
+
+        let mut child = shell.spawn()?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            // Hooks don't have to read the summary.
+            let _ = stdin.write_all(summary.as_bytes());
+        }
+
+        let exit_status = child.wait()?;
+
+        if !exit_status.success() {
+            warn!("Post-hook `{command}` failed with {exit_status}.");
+            failed += 1;
+        }
+    }
+
+    if failed != 0 {
+        return Err(anyhow!("{failed} post-hooks failed."))
+            .kind(ErrorKind::PartialFailure);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,59 @@
+//! HTTP requests, that `curl` sends, to APIs of Google, translation providers and language models.
+
+use crate::process::{run, write_private};
+use anyhow::{Result, bail};
+use serde_json::Value;
+use std::{
+    fs::remove_file,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Sends a request with `curl`, and returns the JSON response. `arguments` precede the URL.
+pub fn request(arguments: &[&str], url: &str, body: &[u8]) -> Result<Value> {
+    let mut all = vec!["-sS", "-w", "\n%{http_code}"];
+    all.extend_from_slice(arguments);
+    all.push(url);
+
+    let output = String::from_utf8(run("curl", &all, body)?)?;
+    let (response, status) = output.rsplit_once('\n').unwrap_or((&output, ""));
+
+    let value: Value = serde_json::from_str(response).unwrap_or(Value::Null);
+
+    if !status.starts_with('2') {
+        let message = value
+            .pointer("/error/message")
+            .or_else(|| value.get("error_description"))
+            .or_else(|| value.get("message"))
+            .or_else(|| value.get("error"))
+            .and_then(Value::as_str)
+            .unwrap_or(response);
+        bail!("Request failed with status {status}: {message}");
+    }
+
+    Ok(value)
+}
+
+/// Sends a request like [`request`], with `authorization` header, e.g. `Authorization: Bearer <token>`. The header is passed to `curl` in a file, that only the current user can read, since command lines of processes are visible to all users.
+pub fn authorized_request(
+    authorization: &str,
+    arguments: &[&str],
+    url: &str,
+    body: &[u8],
+) -> Result<Value> {
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+    let header_path = std::env::temp_dir().join(format!(
+        "rvpacker-header-{}-{}",
+        std::process::id(),
+        NEXT_ID.fetch_add(1, Ordering::Relaxed)
+    ));
+    write_private(&header_path, authorization)?;
+
+    let header_argument = format!("@{}", header_path.display());
+    let mut all = vec!["-H", header_argument.as_str()];
+    all.extend_from_slice(arguments);
+
+    let result = request(&all, url, body);
+    let _ = remove_file(&header_path);
+    result
+}
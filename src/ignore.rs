@@ -0,0 +1,115 @@
+//! Ignore file, that lists entries, which library skips when reading with `--ignore`.
+//!
+//! Each entry of the file is a `<!-- Ignore Entry --><#>Type: id` comment, followed by sources of ignored lines.
+
+use crate::translation::COMMENT_PREFIX;
+use anyhow::Result;
+use rvpacker_lib::{RVPACKER_IGNORE_FILE, SEPARATOR, types::RPGMFileType};
+use std::{
+    collections::HashMap,
+    fs::{read_to_string, write},
+    path::Path,
+};
+
+const IGNORE_ENTRY_COMMENT: &str = "<!-- Ignore Entry -->";
+
+/// Ignore file, represented as `(entry comment, lines)` pairs in file order.
+#[derive(Debug, Default)]
+pub struct IgnoreFile {
+    entries: Vec<(String, Vec<String>)>,
+}
+
+impl IgnoreFile {
+    pub fn load(translation_path: &Path) -> Self {
+        let mut ignore_file = Self::default();
+
+        let Ok(content) =
+            read_to_string(translation_path.join(RVPACKER_IGNORE_FILE))
+        else {
+            return ignore_file;
+        };
+
+        for line in content.lines().filter(|line| !line.is_empty()) {
+            if line.starts_with(COMMENT_PREFIX) {
+                ignore_file.entries.push((line.to_string(), Vec::new()));
+            } else if let Some((_, lines)) = ignore_file.entries.last_mut() {
+                lines.push(line.to_string());
+            }
+        }
+
+        ignore_file
+    }
+
+    pub fn insert(&mut self, file_type: RPGMFileType, id: u16, source: String) {
+        let comment =
+            format!("{IGNORE_ENTRY_COMMENT}{SEPARATOR}{file_type}: {id}");
+
+        let lines = if let Some(index) =
+            self.entries.iter().position(|(entry, _)| *entry == comment)
+        {
+            &mut self.entries[index].1
+        } else {
+            self.entries.push((comment, Vec::new()));
+            &mut self.entries.last_mut().unwrap().1
+        };
+
+        if !lines.contains(&source) {
+            lines.push(source);
+        }
+    }
+
+    /// Changes IDs of `file_type` entries according to `mapping` of old IDs to new ones.
+    ///
+    /// Entries with the same resulting ID are merged.
+    pub fn remap(
+        &mut self,
+        file_type: RPGMFileType,
+        mapping: &HashMap<u16, u16>,
+    ) {
+        let prefix = format!("{IGNORE_ENTRY_COMMENT}{SEPARATOR}{file_type}: ");
+        let mut remapped: Vec<(String, Vec<String>)> =
+            Vec::with_capacity(self.entries.len());
+
+        for (mut comment, lines) in self.entries.drain(..) {
+            if let Some(id) = comment
+                .strip_prefix(&prefix)
+                .and_then(|id| id.trim().parse::<u16>().ok())
+                && let Some(new_id) = mapping.get(&id)
+            {
+                comment = format!("{prefix}{new_id}");
+            }
+
+            if let Some((_, existing)) =
+                remapped.iter_mut().find(|(entry, _)| *entry == comment)
+            {
+                for line in lines {
+                    if !existing.contains(&line) {
+                        existing.push(line);
+                    }
+                }
+            } else {
+                remapped.push((comment, lines));
+            }
+        }
+
+        self.entries = remapped;
+    }
+
+    /// Serializes the file the same way, as library does.
+    pub fn save(&self, translation_path: &Path) -> Result<()> {
+        let mut output = String::new();
+
+        for (comment, lines) in &self.entries {
+            output.push_str(comment);
+            output.push('\n');
+
+            for line in lines {
+                output.push_str(line);
+                output.push('\n');
+            }
+        }
+
+        write(translation_path.join(RVPACKER_IGNORE_FILE), output)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+//! Overlaying data directories of patches and DLC over the game's own data.
+//!
+//! Data files of all layers are merged into a temporary directory, where files of later layers replace files of earlier ones, just like the game loads them. All commands operate on the merged directory, and `write` moves output files back to the layer they came from.
+
+use anyhow::{Context, Result, bail};
+use std::{
+    collections::HashMap,
+    fs::{copy, create_dir_all, read_dir, remove_dir_all, rename},
+    path::{Path, PathBuf},
+};
+use tracing::{debug, info};
+
+const PLUGINS_FILE: &str = "js/plugins.js";
+
+/// Merged view of the base data directory and extra source directories.
+///
+/// The merged directory is removed, when the value is dropped.
+pub struct Layers {
+    merged_path: PathBuf,
+
+    /// Names of output subdirectories of extra layers, in the order of layers.
+    names: Vec<String>,
+
+    /// File name to the index of the extra layer, that it comes from. Files of the base layer aren't included.
+    origins: HashMap<String, usize>,
+}
+
+/// Returns names of regular files in `dir`.
+pub fn file_names(dir: &Path) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+
+    for entry in read_dir(dir)
+        .with_context(|| format!("Reading {}", dir.display()))?
+        .flatten()
+    {
+        if entry.file_type()?.is_file()
+            && let Some(name) = entry.file_name().to_str()
+        {
+            names.push(name.to_string());
+        }
+    }
+
+    Ok(names)
+}
+
+/// Copies MV/MZ `plugins.js`, that's looked up next to the data directory, from next to `base` data directory to `merged_path`.
+pub fn copy_plugins(base: &Path, merged_path: &Path) -> Result<()> {
+    if let Some(game_path) = base.parent()
+        && game_path.join(PLUGINS_FILE).exists()
+    {
+        let js_path = merged_path.join("js");
+        create_dir_all(&js_path)?;
+        copy(game_path.join(PLUGINS_FILE), js_path.join("plugins.js"))?;
+    }
+
+    Ok(())
+}
+
+impl Layers {
+    /// Returns the path of the merged directory, that belongs to this process.
+    #[must_use]
+    pub fn temp_path() -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("rvpacker-merged-{}", std::process::id()))
+    }
+
+    /// Merges `base` data directory with `extra` directories to `merged_path`.
+    pub fn merge(
+        base: &Path,
+        extra: &[PathBuf],
+        merged_path: PathBuf,
+    ) -> Result<Self> {
+        if !base.exists() {
+            bail!(
+                "Data directory {} does not exist. Extra sources can only overlay extracted game data.",
+                base.display()
+            );
+        }
+
+        if merged_path.exists() {
+            remove_dir_all(&merged_path)?;
+        }
+
+        let base_name = base.file_name().unwrap_or_default();
+        let data_path = merged_path.join(base_name);
+        create_dir_all(&data_path)?;
+
+        // Construct it first, so the merged directory is removed on failure.
+        let mut layers = Self {
+            merged_path,
+            names: Vec::with_capacity(extra.len()),
+            origins: HashMap::new(),
+        };
+
+        for name in file_names(base)? {
+            copy(base.join(&name), data_path.join(&name))?;
+        }
+
+        copy_plugins(base, &layers.merged_path)?;
+
+        for (index, dir) in extra.iter().enumerate() {
+            if !dir.is_dir() {
+                bail!("Extra source {} does not exist.", dir.display());
+            }
+
+            let mut name = dir
+                .canonicalize()?
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+
+            if name.is_empty()
+                || *name == *base_name
+                || layers.names.contains(&name)
+            {
+                name = format!("{name}-{}", index + 1);
+            }
+
+            let files = file_names(dir)?;
+
+            for file in &files {
+                copy(dir.join(file), data_path.join(file))?;
+                layers.origins.insert(file.clone(), index);
+            }
+
+            debug!(
+                "{}: Overlaid {} files over the game's data.",
+                dir.display(),
+                files.len()
+            );
+            layers.names.push(name);
+        }
+
+        Ok(layers)
+    }
+
+    /// Returns the path of the merged data directory, that corresponds to `base` data directory.
+    #[must_use]
+    pub fn data_path(&self, base: &Path) -> PathBuf {
+        self.merged_path.join(base.file_name().unwrap_or_default())
+    }
+
+    /// Moves files in `output_data_path`, that come from extra layers, to subdirectories of `output_path`, named after the layers.
+    pub fn split_output(
+        &self,
+        output_data_path: &Path,
+        output_path: &Path,
+    ) -> Result<()> {
+        if !output_data_path.exists() {
+            return Ok(());
+        }
+
+        for file in file_names(output_data_path)? {
+            let Some(&index) = self.origins.get(&file) else {
+                continue;
+            };
+
+            let layer_path = output_path.join(&self.names[index]);
+            create_dir_all(&layer_path)?;
+            rename(output_data_path.join(&file), layer_path.join(&file))?;
+        }
+
+        for name in &self.names {
+            if output_path.join(name).exists() {
+                info!(
+                    "{name}: Successfully wrote patched files to {}.",
+                    output_path.join(name).display()
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Layers {
+    fn drop(&mut self) {
+        let _ = remove_dir_all(&self.merged_path);
+    }
+}
@@ -0,0 +1,293 @@
+//! Layouts of `write` output.
+//!
+//! By default, written files are placed in `output/data` (`output/Data` for older engines) and `output/js`. In `game` layout, output mirrors the game's own structure, so it can be dropped onto a game install as is: data of deployed MV games, that keep it in `www`, goes to `output/www`, and `Game.ini` or `package.json` is copied next to it with the translated title, that the game window shows.
+//!
+//! Full output also places every game file, that the translation doesn't modify, in the output, so it's a complete playable build. Assets are hard-linked, when possible, to save space and time. Files, that `write` may produce, i.e. data files, `js` directory and files in the game root, are always copied, so writing to the output later never changes the game's own files through a link. `.rgss` archive is left out, since the game would read data from it instead of the translated files.
+
+use crate::data::{data_file_path, load_rpgm_file};
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use rvpacker_lib::types::EngineType;
+use std::{
+    fs::{
+        copy, create_dir_all, hard_link, read, read_dir, read_to_string, write,
+    },
+    path::{Path, PathBuf},
+};
+use tracing::info;
+
+const WWW_DIR: &str = "www";
+const PACKAGE_FILE: &str = "package.json";
+const INI_FILE: &str = "Game.ini";
+
+/// Directories of the game, that `write` writes files to.
+const WRITTEN_DIRS: &[&str] = &["data", "Data", "js"];
+
+/// Prefix of `Game.rgssad`, `Game.rgss2a` and `Game.rgss3a` archives.
+const ARCHIVE_PREFIX: &str = "Game.rgss";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Layout {
+    /// `data` and `js` directories directly in `output`.
+    #[default]
+    Flat,
+
+    /// The game's own structure, with `Game.ini` or `package.json`.
+    Game,
+}
+
+/// Returns the root directory of a deployed MV game, if `input_dir` is its `www` directory.
+fn www_root(input_dir: &Path) -> Option<PathBuf> {
+    let input_dir = input_dir.canonicalize().ok()?;
+
+    if input_dir.file_name()? != WWW_DIR {
+        return None;
+    }
+
+    let root = input_dir.parent()?;
+    root.join(PACKAGE_FILE).exists().then(|| root.to_path_buf())
+}
+
+/// Returns the root directory of the game in `input_dir`, which is the parent of `www` for deployed MV games.
+pub fn game_root(input_dir: &Path) -> Result<PathBuf> {
+    match www_root(input_dir) {
+        Some(root) => Ok(root),
+        None => Ok(input_dir.canonicalize()?),
+    }
+}
+
+/// Returns the path of `relative` path in `output` directory, relative to the root of the game in `input_dir`, in either layout.
+#[must_use]
+pub fn game_relative(relative: &Path, input_dir: &Path) -> PathBuf {
+    let in_input_dir = relative.components().next().is_some_and(|component| {
+        WRITTEN_DIRS.iter().any(|dir| component.as_os_str() == *dir)
+    });
+
+    if in_input_dir && www_root(input_dir).is_some() {
+        Path::new(WWW_DIR).join(relative)
+    } else {
+        relative.to_path_buf()
+    }
+}
+
+impl Layout {
+    /// Returns the directory in `output_root`, that the library writes `data` and `js` directories to.
+    #[must_use]
+    pub fn library_path(self, output_root: &Path, input_dir: &Path) -> PathBuf {
+        if self == Self::Game && www_root(input_dir).is_some() {
+            output_root.join(WWW_DIR)
+        } else {
+            output_root.to_path_buf()
+        }
+    }
+}
+
+/// Returns the title from `System` file, that was written to `output_data_path`.
+fn written_title(
+    output_data_path: &Path,
+    engine_type: EngineType,
+) -> Result<Option<String>> {
+    let path = data_file_path(output_data_path, "System", engine_type);
+
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let value = load_rpgm_file(&path, engine_type)?;
+
+    Ok(value.as_object().and_then(|object| {
+        ["gameTitle", "game_title"]
+            .into_iter()
+            .find_map(|key| object.get(key)?.as_str().map(str::to_string))
+    }))
+}
+
+/// Copies `Game.ini` of older engines or `package.json` of newer ones from the game in `input_dir` to `output_root`, with the title, that was written to `output_data_path`.
+pub fn copy_game_files(
+    output_root: &Path,
+    output_data_path: &Path,
+    input_dir: &Path,
+    engine_type: EngineType,
+) -> Result<()> {
+    let title = written_title(output_data_path, engine_type)?;
+
+    if engine_type.is_new() {
+        let package_path = www_root(input_dir)
+            .as_deref()
+            .unwrap_or(input_dir)
+            .join(PACKAGE_FILE);
+
+        if !package_path.exists() {
+            return Ok(());
+        }
+
+        let mut package: serde_json::Value =
+            serde_json::from_str(&read_to_string(&package_path)?)
+                .with_context(|| {
+                    format!("Parsing {}", package_path.display())
+                })?;
+
+        if let Some(title) = title
+            && let Some(window) = package
+                .get_mut("window")
+                .and_then(|window| window.as_object_mut())
+        {
+            window.insert("title".into(), title.into());
+        }
+
+        write(
+            output_root.join(PACKAGE_FILE),
+            serde_json::to_string_pretty(&package)?,
+        )?;
+        info!("{PACKAGE_FILE}: Successfully written.");
+    } else {
+        let ini_path = input_dir.join(INI_FILE);
+
+        if !ini_path.exists() {
+            return Ok(());
+        }
+
+        // Game.ini may be in a legacy encoding, so only the title line is replaced, and other lines are kept as is.
+        let content = read(&ini_path)?;
+        let mut output = Vec::with_capacity(content.len());
+
+        for line in content.split_inclusive(|&byte| byte == b'\n') {
+            match &title {
+                Some(title) if line.starts_with(b"Title=") => {
+                    let ending: &[u8] = if line.ends_with(b"\r\n") {
+                        b"\r\n"
+                    } else if line.ends_with(b"\n") {
+                        b"\n"
+                    } else {
+                        b""
+                    };
+
+                    output.extend_from_slice(b"Title=");
+                    output.extend_from_slice(title.as_bytes());
+                    output.extend_from_slice(ending);
+                }
+                _ => output.extend_from_slice(line),
+            }
+        }
+
+        write(output_root.join(INI_FILE), output)?;
+        info!("{INI_FILE}: Successfully written.");
+    }
+
+    Ok(())
+}
+
+/// Places `from` file at `to`: hard-links it if `link` is set and the file system allows it, and copies it otherwise.
+fn place(from: &Path, to: &Path, link: bool) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        create_dir_all(parent)?;
+    }
+
+    if !(link && hard_link(from, to).is_ok()) {
+        copy(from, to)
+            .with_context(|| format!("Copying {}", from.display()))?;
+    }
+
+    Ok(())
+}
+
+/// Places files of `from` directory, that don't exist in `to` directory yet, recursively. Paths in `skipped` are left out. Returns the number of placed files.
+fn fill_dir(
+    from: &Path,
+    to: &Path,
+    skipped: &[PathBuf],
+    link: bool,
+) -> Result<usize> {
+    let mut placed = 0;
+
+    for entry in read_dir(from)?.flatten() {
+        let path = entry.path();
+
+        if skipped.contains(&path) {
+            continue;
+        }
+
+        let target = to.join(entry.file_name());
+
+        if path.is_dir() {
+            placed += fill_dir(&path, &target, skipped, link)?;
+        } else if !target.exists() {
+            place(&path, &target, link)?;
+            placed += 1;
+        }
+    }
+
+    Ok(placed)
+}
+
+/// Places files of the game in `game_root`, that weren't written, to `output_root`. Files in `WRITTEN_DIRS` and the root are copied, others are hard-linked.
+fn fill_game(
+    game_root: &Path,
+    output_root: &Path,
+    skipped: &[PathBuf],
+) -> Result<usize> {
+    let mut placed = 0;
+
+    for entry in read_dir(game_root)?.flatten() {
+        let path = entry.path();
+
+        if skipped.contains(&path) {
+            continue;
+        }
+
+        let name = entry.file_name();
+        let target = output_root.join(&name);
+
+        if !path.is_dir() {
+            if !target.exists() {
+                place(&path, &target, false)?;
+                placed += 1;
+            }
+        } else if name == WWW_DIR {
+            placed += fill_game(&path, &target, skipped)?;
+        } else {
+            let link = !WRITTEN_DIRS.iter().any(|dir| name == *dir);
+            placed += fill_dir(&path, &target, skipped, link)?;
+        }
+    }
+
+    Ok(placed)
+}
+
+/// Completes the output in `output_root` with unchanged files of the game in `input_dir`, and with unchanged data files from `source_path`, which may be outside of the game, e.g. when the archive was extracted to a work directory. Paths in `skipped`, like translation and output directories, are left out. Returns the number of placed files.
+pub fn fill_output(
+    output_root: &Path,
+    output_data_path: &Path,
+    input_dir: &Path,
+    source_path: &Path,
+    skipped: &[&Path],
+) -> Result<usize> {
+    let game_root = game_root(input_dir)?;
+
+    let mut skipped: Vec<PathBuf> = skipped
+        .iter()
+        .filter_map(|path| path.canonicalize().ok())
+        .collect();
+
+    for entry in read_dir(&game_root)?.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+
+        if name.starts_with(ARCHIVE_PREFIX) {
+            info!(
+                "{name}: Left out of the output, so the game reads translated files."
+            );
+            skipped.push(entry.path());
+        }
+    }
+
+    let placed = fill_game(&game_root, output_root, &skipped)?
+        + fill_dir(
+            &source_path.canonicalize()?,
+            output_data_path,
+            &skipped,
+            false,
+        )?;
+
+    Ok(placed)
+}
@@ -0,0 +1,382 @@
+//! Hygiene checks of translations: trailing whitespace, double spaces, tabs, mismatched brackets and quotes, and suspicious leading punctuation.
+//!
+//! Issues, that the source has itself, are deliberate, and aren't flagged. Whitespace issues can be fixed automatically, while brackets, quotes and punctuation are only reported, since fixing them needs a translator.
+//!
+//! The consistency check compares translations of identical sources across all files instead, and reports sources, that are translated differently, with places of each translation.
+
+use crate::{
+    error::{ErrorKind, WithKind},
+    translation::{
+        Line, TranslationFile, effective_translation,
+        map_effective_translation, strip_placeholders, translation_files,
+    },
+};
+use anyhow::{Result, anyhow, bail};
+use rvpacker_lib::NEW_LINE;
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    fs::{read_to_string, write},
+    path::Path,
+};
+use tracing::{info, warn};
+
+/// Pairs of brackets and quotes, that must match.
+const PAIRS: &[(char, char)] = &[
+    ('(', ')'),
+    ('[', ']'),
+    ('{', '}'),
+    ('（', '）'),
+    ('「', '」'),
+    ('『', '』'),
+    ('“', '”'),
+    ('«', '»'),
+];
+
+/// Quotes, that open and close with the same character.
+const QUOTES: &[char] = &['"'];
+
+/// Punctuation, that text doesn't start with, unless it's misplaced, e.g. after a line break, that went to the wrong place.
+const LEADING_PUNCTUATION: &[char] = &[
+    ',', '.', ';', ':', '!', '?', ')', ']', '}', '、', '。', '，', '！', '？',
+    '；', '：', '）', '」', '』',
+];
+
+/// Options of a single `lint` run.
+pub struct Options<'a> {
+    /// Names or stems of translation files to check. All files are checked if empty.
+    pub files: &'a [String],
+
+    /// Fixes whitespace issues, instead of reporting them.
+    pub fix: bool,
+}
+
+impl Options<'_> {
+    fn includes(&self, name: &str) -> bool {
+        self.files.is_empty()
+            || self.files.iter().any(|file| {
+                file == name || name.strip_suffix(".txt") == Some(file)
+            })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Check {
+    Tab,
+    DoubleSpace,
+    TrailingWhitespace,
+    Unbalanced,
+    LeadingPunctuation,
+}
+
+impl Check {
+    /// Tabs are fixed first, since they become spaces, that other fixes handle.
+    const ALL: [Self; 5] = [
+        Self::Tab,
+        Self::DoubleSpace,
+        Self::TrailingWhitespace,
+        Self::Unbalanced,
+        Self::LeadingPunctuation,
+    ];
+
+    const fn fixable(self) -> bool {
+        matches!(
+            self,
+            Self::TrailingWhitespace | Self::DoubleSpace | Self::Tab
+        )
+    }
+
+    /// Returns the description of the issue in `text`, if it has one.
+    fn find(self, text: &str) -> Option<String> {
+        let mut lines = text.split(NEW_LINE);
+
+        match self {
+            Self::TrailingWhitespace => lines
+                .position(|line| line.ends_with([' ', '\t']))
+                .map(|index| {
+                    format!("Line {} has trailing whitespace", index + 1)
+                }),
+            Self::DoubleSpace => lines
+                .position(|line| line.trim_start().trim_end().contains("  "))
+                .map(|index| format!("Line {} has double spaces", index + 1)),
+            Self::Tab => lines
+                .position(|line| line.contains('\t'))
+                .map(|index| format!("Line {} has tabs", index + 1)),
+            Self::Unbalanced => unbalanced(&strip_placeholders(text)),
+            Self::LeadingPunctuation => lines
+                .position(|line| starts_with_punctuation(line.trim_start()))
+                .map(|index| {
+                    format!(
+                        "Line {} starts with suspicious punctuation",
+                        index + 1
+                    )
+                }),
+        }
+    }
+
+    /// Fixes the issue in `text`.
+    fn fix(self, text: &str) -> String {
+        match self {
+            Self::TrailingWhitespace => text
+                .split(NEW_LINE)
+                .map(str::trim_end)
+                .collect::<Vec<_>>()
+                .join(NEW_LINE),
+            Self::DoubleSpace => text
+                .split(NEW_LINE)
+                .map(collapse_spaces)
+                .collect::<Vec<_>>()
+                .join(NEW_LINE),
+            Self::Tab => text.replace('\t', " "),
+            Self::Unbalanced | Self::LeadingPunctuation => text.to_string(),
+        }
+    }
+}
+
+/// Collapses runs of spaces inside `line` to a single space. Leading and trailing spaces are kept.
+fn collapse_spaces(line: &str) -> String {
+    let content = line.trim_start_matches(' ');
+    let leading = &line[..line.len() - content.len()];
+    let inner = content.trim_end_matches(' ');
+    let trailing = &content[inner.len()..];
+
+    let mut collapsed = String::with_capacity(line.len());
+    collapsed.push_str(leading);
+
+    for (index, word) in
+        inner.split(' ').filter(|word| !word.is_empty()).enumerate()
+    {
+        if index != 0 {
+            collapsed.push(' ');
+        }
+
+        collapsed.push_str(word);
+    }
+
+    collapsed.push_str(trailing);
+    collapsed
+}
+
+/// Returns `true` if `text` starts with punctuation. Ellipsis, written with dots, is a common start of a line, and isn't suspicious.
+fn starts_with_punctuation(text: &str) -> bool {
+    let mut chars = text.chars();
+
+    match chars.next() {
+        Some('.') => chars.next() != Some('.'),
+        Some(char) => LEADING_PUNCTUATION.contains(&char),
+        None => false,
+    }
+}
+
+/// Returns the description of the first bracket or quote in `text`, that has no pair.
+fn unbalanced(text: &str) -> Option<String> {
+    for &(open, close) in PAIRS {
+        let mut depth = 0usize;
+
+        for char in text.chars() {
+            if char == open {
+                depth += 1;
+            } else if char == close {
+                if depth == 0 {
+                    return Some(format!("`{close}` has no opening `{open}`"));
+                }
+
+                depth -= 1;
+            }
+        }
+
+        if depth != 0 {
+            return Some(format!("`{open}` has no closing `{close}`"));
+        }
+    }
+
+    QUOTES
+        .iter()
+        .find(|&&quote| text.matches(quote).count() % 2 != 0)
+        .map(|quote| format!("`{quote}` has no pair"))
+}
+
+/// Returns names of translation files in `translation_path`, that `options` include.
+fn included_files(
+    translation_path: &Path,
+    options: &Options,
+) -> Result<Vec<String>> {
+    let names: Vec<String> = translation_files(translation_path)?
+        .into_iter()
+        .filter(|name| options.includes(name))
+        .collect();
+
+    if names.is_empty() {
+        bail!("No translation files match the given `--files`.");
+    }
+
+    Ok(names)
+}
+
+/// Checks translations in `translation_path`, and warns about issues. Fixes whitespace issues with `fix` option. Fails, if there are issues left.
+pub fn lint(translation_path: &Path, options: &Options) -> Result<()> {
+    let names = included_files(translation_path, options)?;
+    let mut total_issues = 0;
+    let mut total_fixed = 0;
+
+    for name in names {
+        let path = translation_path.join(&name);
+        let mut file = TranslationFile::parse(&read_to_string(&path)?);
+        let mut fixed = 0;
+
+        for (line_index, line) in file.lines.iter_mut().enumerate() {
+            let Line::Entry {
+                source,
+                translation,
+            } = line
+            else {
+                continue;
+            };
+
+            let mut text = effective_translation(translation).to_string();
+
+            if text.is_empty() {
+                continue;
+            }
+
+            let line_number = line_index + 1;
+            let mut changed = false;
+
+            for check in Check::ALL {
+                if check.find(source).is_some() {
+                    continue;
+                }
+
+                let Some(issue) = check.find(&text) else {
+                    continue;
+                };
+
+                if options.fix && check.fixable() {
+                    text = check.fix(&text);
+                    changed = true;
+                    fixed += 1;
+                    continue;
+                }
+
+                warn!("{name}:{line_number}: {issue}.\nTranslation: {text}");
+                total_issues += 1;
+            }
+
+            if changed {
+                *translation = map_effective_translation(translation, |_| text);
+            }
+        }
+
+        if fixed != 0 {
+            write(&path, file.serialize())?;
+            info!("{name}: Fixed {fixed} issues.");
+            total_fixed += fixed;
+        }
+    }
+
+    if total_issues != 0 {
+        return Err(anyhow!(
+            "{total_issues} issues in translations need fixing.{}",
+            if options.fix {
+                ""
+            } else {
+                " Whitespace issues can be fixed with `--fix`."
+            }
+        ))
+        .kind(ErrorKind::ValidationFailed);
+    }
+
+    if total_fixed == 0 {
+        info!("No issues found in translations.");
+    }
+
+    Ok(())
+}
+
+/// Translation of a source, and places, that have it, as `file:line`.
+struct Variant {
+    translation: String,
+    places: Vec<String>,
+}
+
+/// Warns about identical sources in `translation_path`, that have different translations, with files and lines of each translation. Fails, if there are any.
+pub fn consistency(translation_path: &Path, options: &Options) -> Result<()> {
+    // Translations of each source, in the order of their first appearance.
+    let mut sources: Vec<(String, Vec<Variant>)> = Vec::new();
+    let mut indices: HashMap<String, usize> = HashMap::new();
+
+    for name in included_files(translation_path, options)? {
+        let file = TranslationFile::parse(&read_to_string(
+            translation_path.join(&name),
+        )?);
+
+        for (line_index, line) in file.lines.iter().enumerate() {
+            let Line::Entry {
+                source,
+                translation,
+            } = line
+            else {
+                continue;
+            };
+
+            let text = effective_translation(translation);
+
+            if text.is_empty() {
+                continue;
+            }
+
+            let index = *indices.entry(source.clone()).or_insert_with(|| {
+                sources.push((source.clone(), Vec::new()));
+                sources.len() - 1
+            });
+            let translations = &mut sources[index].1;
+            let place = format!("{name}:{}", line_index + 1);
+
+            match translations
+                .iter_mut()
+                .find(|variant| variant.translation == text)
+            {
+                Some(variant) => variant.places.push(place),
+                None => translations.push(Variant {
+                    translation: text.to_string(),
+                    places: vec![place],
+                }),
+            }
+        }
+    }
+
+    let mut inconsistent = 0;
+
+    for (source, translations) in &sources {
+        if translations.len() < 2 {
+            continue;
+        }
+
+        let mut message = format!(
+            "Source has {} different translations: {source}",
+            translations.len()
+        );
+
+        for variant in translations {
+            let _ = write!(
+                message,
+                "\n{}: {}",
+                variant.places.join(", "),
+                variant.translation
+            );
+        }
+
+        warn!("{message}");
+        inconsistent += 1;
+    }
+
+    if inconsistent != 0 {
+        return Err(anyhow!(
+            "{inconsistent} sources are translated inconsistently."
+        ))
+        .kind(ErrorKind::ValidationFailed);
+    }
+
+    info!("Identical sources are translated consistently.");
+    Ok(())
+}
@@ -0,0 +1,71 @@
+//! Mirroring of the log to a file, so complete logs can be attached to bug reports.
+//!
+//! The file gets all events at its own level, independent of `-v` and `-q`, with timestamps and without ANSI codes. It starts with the version and the command line, where secrets, e.g. API keys, are redacted, and ends with the error, if the command failed.
+
+use anyhow::{Context, Result};
+use std::{fs::File, path::Path, sync::Mutex};
+use tracing::{Subscriber, error, info, level_filters::LevelFilter};
+use tracing_subscriber::{Layer, registry::LookupSpan};
+
+/// Target of events, that only go to the log file.
+pub const TARGET: &str = "log_file";
+
+/// Options, which values are secrets, that must not end up in logs and reports.
+const SECRET_OPTIONS: &[&str] = &["--api-key"];
+
+/// Returns arguments of the run, without the program, where values of secret options are redacted.
+#[must_use]
+pub fn redacted_arguments() -> Vec<String> {
+    let mut redact_next = false;
+
+    std::env::args_os()
+        .skip(1)
+        .map(|arg| {
+            let arg = arg.to_string_lossy().into_owned();
+
+            if std::mem::take(&mut redact_next) {
+                return "<redacted>".to_string();
+            }
+
+            if SECRET_OPTIONS.contains(&arg.as_str()) {
+                redact_next = true;
+            } else if let Some((option, _)) = arg.split_once('=')
+                && SECRET_OPTIONS.contains(&option)
+            {
+                return format!("{option}=<redacted>");
+            }
+
+            arg
+        })
+        .collect()
+}
+
+/// Returns the layer, that writes events at `level` to the file at `path`. Existing file is overwritten.
+pub fn layer<S>(path: &Path, level: LevelFilter) -> Result<impl Layer<S>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let file = File::create(path)
+        .with_context(|| format!("Creating log file {}", path.display()))?;
+
+    Ok(tracing_subscriber::fmt::layer()
+        .with_writer(Mutex::new(file))
+        .with_ansi(false)
+        .with_target(false)
+        .with_filter(level))
+}
+
+/// Records the version and the command line of the run.
+pub fn record_start() {
+    info!(
+        target: TARGET,
+        "rvpacker-txt-rs {}: {}",
+        env!("CARGO_PKG_VERSION"),
+        redacted_arguments().join(" ")
+    );
+}
+
+/// Records `err`, that the command failed with.
+pub fn record_error(err: &anyhow::Error) {
+    error!(target: TARGET, "{err:?}");
+}
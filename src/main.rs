@@ -5,34 +5,113 @@
 #![allow(clippy::cast_sign_loss)]
 #![allow(clippy::deref_addrof)]
 
-use anyhow::{Context, Result, bail};
+mod align;
+mod anchors;
+mod archive;
+mod attribution;
+mod audit;
+mod bundle;
+mod changes;
+mod codes;
+mod compression;
+mod context;
+mod crash;
+mod data;
+mod dedup;
+mod delta;
+mod dialogue;
+mod encoding;
+mod error;
+mod export;
+mod extra;
+mod file_map;
+mod fuzzy;
+mod hooks;
+mod http;
+mod ignore;
+mod layers;
+mod layout;
+mod lint;
+mod log_file;
+mod memory;
+mod opaque;
+mod overflow;
+mod patch;
+mod plural;
+mod process;
+mod progress;
+mod purge;
+mod remap;
+mod replace;
+mod report;
+mod reromanize;
+mod resources;
+mod romanize;
+mod rules;
+mod salvage;
+mod sheets;
+mod sidecar;
+mod structure;
+mod terms;
+mod translate;
+mod translation;
+mod trim;
+mod upgrade;
+mod wizard;
+mod xml;
+
+use anyhow::{Context, Result, anyhow, bail};
+use archive::{ArchiveFilter, ArchiveKey, PathEncoding};
 use clap::{
     ArgAction, Args, Parser, Subcommand, ValueEnum,
     builder::{PossibleValuesParser, TypedValueParser},
     crate_version, value_parser,
 };
 use clap_verbosity_flag::{InfoLevel, Verbosity};
-use rpgmad_lib::Decrypter;
+use compression::Compression;
+use error::{ErrorKind, WithKind};
+use export::{
+    Conflict, ConflictPolicy, ExportFormat, ImportFormat, Resolution,
+};
+use extra::ExtraKind;
+use file_map::{FileMap, Mapped};
+use ignore::IgnoreFile;
+use layers::Layers;
+use opaque::Opaque;
+use overflow::Limits;
+use regex::Regex;
+use remap::Mapping;
 use rvpacker_lib::{
     BaseFlags, Mode, ProcessedData, PurgerBuilder, RPGMFileType,
     RVPACKER_IGNORE_FILE, RVPACKER_METADATA_FILE, ReaderBuilder, WriterBuilder,
     core::parse_ignore,
-    generic, get_ini_title, get_system_title, json,
+    generic, get_engine_extension, get_ini_title, get_system_title, json,
     types::{DuplicateMode, EngineType, FileFlags, GameType, ReadMode},
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{from_str, to_string};
 use std::{
-    fs::{create_dir_all, read, read_dir, read_to_string, write},
+    collections::{HashMap, HashSet},
+    ffi::OsString,
+    fs::{
+        copy, create_dir_all, read, read_dir, read_to_string, remove_dir_all,
+        remove_file, write,
+    },
     io::stdin,
     mem::take,
     path::{Path, PathBuf},
-    process::exit,
+    process::ExitCode,
     str::FromStr,
     time::Instant,
 };
+use structure::ParseMode;
 use strum::VariantNames;
-use strum_macros::EnumIs;
+use strum_macros::{EnumIs, IntoStaticStr};
+use tracing::{debug, info, level_filters::LevelFilter, warn};
+use tracing_subscriber::{
+    Layer, filter::filter_fn, layer::SubscriberExt, util::SubscriberInitExt,
+};
+use translation::TranslationFile;
 
 #[derive(Debug, Clone)]
 pub struct SkipMaps(pub Vec<u16>);
@@ -45,12 +124,17 @@ impl FromStr for SkipMaps {
 
         for part in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
             if let Some((a, b)) = part.split_once('-') {
-                let start = a.parse::<u16>().map_err(|e| {
+                let start = parse_map_id(a).map_err(|e| {
                     format!("Invalid start of range `{a}`: {e}")
                 })?;
-                let end = b
-                    .parse::<u16>()
-                    .map_err(|e| format!("Invalid end of range `{b}`: {e}"))?;
+                // Open ranges extend to the last map, that can be processed.
+                let end = if b.trim().is_empty() {
+                    data::MAX_MAP_ID
+                } else {
+                    parse_map_id(b).map_err(|e| {
+                        format!("Invalid end of range `{b}`: {e}")
+                    })?
+                };
 
                 if start > end {
                     return Err(format!(
@@ -62,8 +146,7 @@ impl FromStr for SkipMaps {
                     indices.push(v);
                 }
             } else {
-                let v = part
-                    .parse::<u16>()
+                let v = parse_map_id(part)
                     .map_err(|e| format!("Invalid integer `{part}`: {e}"))?;
                 indices.push(v);
             }
@@ -73,6 +156,28 @@ impl FromStr for SkipMaps {
     }
 }
 
+fn parse_map_id(s: &str) -> Result<u16, String> {
+    let id = s.trim().parse::<u64>().map_err(|e| e.to_string())?;
+
+    u16::try_from(id)
+        .ok()
+        .filter(|id| *id <= data::MAX_MAP_ID)
+        .ok_or_else(|| format!("map IDs can't exceed {}", data::MAX_MAP_ID))
+}
+
+/// Parses similarity thresholds, that must be greater than 0, and at most 1.
+fn parse_similarity(s: &str) -> Result<f64, String> {
+    let similarity = s.trim().parse::<f64>().map_err(|e| e.to_string())?;
+
+    if similarity > 0.0 && similarity <= 1.0 {
+        Ok(similarity)
+    } else {
+        Err(String::from(
+            "similarity must be greater than 0, and at most 1",
+        ))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct SkipEvents(pub Vec<(RPGMFileType, Vec<u16>)>);
 
@@ -122,6 +227,50 @@ impl FromStr for SkipEvents {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct SkipEventNames(pub Vec<String>);
+
+impl FromStr for SkipEventNames {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(SkipEventNames(
+            s.split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(String::from)
+                .collect(),
+        ))
+    }
+}
+
+/// Matches `name` against a wildcard `pattern`, where `*` matches any sequence of characters and `?` matches a single character.
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = backtrack {
+            p = star_p + 1;
+            n = star_n + 1;
+            backtrack = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
 #[derive(Debug, Clone, Copy, ValueEnum)]
 pub enum GenericType {
     JSON,
@@ -148,14 +297,34 @@ impl FromStr for FFlags {
 
 #[derive(Default, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
+#[allow(clippy::struct_excessive_bools)]
 struct Metadata {
     romanize: bool,
     disable_custom_processing: bool,
     trim: bool,
     duplicate_mode: DuplicateMode,
     hashes: Option<Vec<u128>>,
+    #[serde(default)]
+    common_event_names: bool,
+    #[serde(default)]
+    troop_names: bool,
+    #[serde(default)]
+    romanize_table_hash: Option<String>,
+    #[serde(default)]
+    notes: bool,
+    #[serde(default)]
+    note_tags: Vec<String>,
+    #[serde(default)]
+    speaker_names: bool,
+    #[serde(default)]
+    anchors: bool,
+    #[serde(default)]
+    archive_hash: Option<String>,
 }
 
+/// Parsed translation files of a snapshot by name.
+type SnapshotFiles = Vec<(String, TranslationFile)>;
+
 #[derive(Debug, Args)]
 #[allow(clippy::struct_excessive_bools)]
 struct SharedArgs {
@@ -183,6 +352,26 @@ struct SharedArgs {
     #[arg(short = 'R', long, action = ArgAction::SetTrue, display_order = 5)]
     romanize: bool,
 
+    /// JSON file, that maps characters or sequences to their replacements, e.g. `{"「": "'", "♥": "<3"}`. Replaces the built-in table of `--romanize`.
+    /// Its hash is recorded in metadata, and `write` and `purge` require the same table
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with = "romanize",
+        display_order = 5
+    )]
+    romanize_table: Option<PathBuf>,
+
+    /// TSV file with names of characters and locations, one per line, that `--romanize-table` doesn't change. Its hash is recorded in metadata with the table's one.
+    /// A chosen spelling in the second column is only used by `translate --protected-terms`
+    #[arg(
+        long,
+        value_name = "TSV_PATH",
+        requires = "romanize_table",
+        display_order = 5
+    )]
+    protected_terms: Option<PathBuf>,
+
     /// Disables built-in custom processing, implemented for some games.
     /// Right now, implemented for the following titles: LISA: The Painful and its derivatives, Fear & Hunger 2: Termina.
     /// Will be automatically set if it was used in read.
@@ -201,7 +390,7 @@ struct SharedArgs {
     )]
     skip_files: FFlags,
 
-    /// Skips processing specified maps, separated by comma.
+    /// Skips processing specified maps, separated by comma. Ranges, like `10-20`, and open ranges, like `1000-`, are allowed.
     #[arg(
         long,
         alias = "sm",
@@ -211,6 +400,10 @@ struct SharedArgs {
     )]
     skip_maps: SkipMaps,
 
+    /// Processes only specified maps, separated by comma, and skips the rest. Takes the same syntax as `--skip-maps`.
+    #[arg(long, alias = "om", value_name = "MAP_INDICES", value_parser = value_parser!(SkipMaps))]
+    only_maps: Option<SkipMaps>,
+
     /// Skips processing specified events. Has no effect on maps.
     /// Follows the following syntax: `file:0,1,..;file:0,1,..`
     #[arg(
@@ -222,22 +415,197 @@ struct SharedArgs {
     )]
     skip_events: SkipEvents,
 
+    /// Skips processing events, which names match specified patterns, separated by comma. `*` matches any sequence of characters, `?` matches a single character.
+    /// Patterns are matched against the names of common events, troops and map events. Map events are left out of read translation, so on write, their text stays untranslated, unless the same text is translated elsewhere in the map.
+    #[arg(
+        long,
+        alias = "sen",
+        value_name = "PATTERNS",
+        value_parser = value_parser!(SkipEventNames),
+        default_value = ""
+    )]
+    skip_event_names: SkipEventNames,
+
     #[arg(short, long, alias = "me", action = ArgAction::SetTrue)]
     map_events: bool,
 
-    /// Controls how to handle duplicates in text
+    /// Extracts common event names as translatable entries to `commonevents-names.txt`, independently of common events' contents.
+    /// Will be automatically set if it was used in read.
+    #[arg(long, alias = "cen", action = ArgAction::SetTrue)]
+    common_event_names: bool,
+
+    /// Extracts troop names as translatable entries to `troops-names.txt`, independently of troops' contents.
+    /// Will be automatically set if it was used in read.
+    #[arg(long, alias = "tn", action = ArgAction::SetTrue)]
+    troop_names: bool,
+
+    /// Extracts notes of tilesets and `System` to `tilesets-notes.txt` and `system-notes.txt`, since some plugins read display text from them.
+    /// Will be automatically set if it was used in read.
+    #[arg(long, action = ArgAction::SetTrue)]
+    notes: bool,
+
+    /// Extracts only values of these note tags, separated by comma, instead of whole notes. Both `<Tag: value>` and `<Tag>value</Tag>` forms are supported.
+    /// Will be automatically set if it was used in read.
+    #[arg(
+        long,
+        value_name = "TAGS",
+        value_delimiter = ',',
+        requires = "notes"
+    )]
+    note_tags: Vec<String>,
+
+    /// Extracts name boxes of MZ `Show Text` commands to `speakers.txt`. Each name is extracted once, so it's translated the same in every message.
+    /// Will be automatically set if it was used in read.
+    #[arg(long, alias = "sn", action = ArgAction::SetTrue)]
+    speaker_names: bool,
+
+    /// Controls how to handle duplicates in text. Defaults to `remove`
     #[arg(
         short,
         long,
         alias = "dup-mode",
-        default_value = "remove",
         display_order = 93,
         value_parser = PossibleValuesParser::new(DuplicateMode::VARIANTS).map(|s| DuplicateMode::from_str(&s).unwrap())
     )]
-    duplicate_mode: DuplicateMode,
+    duplicate_mode: Option<DuplicateMode>,
+
+    /// Fails on any structure of game data, that can't be fully processed, instead of skipping it
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with = "lenient_parse", display_order = 95)]
+    strict_parse: bool,
+
+    /// Recovers from minor corruption of game data, like data after the end of JSON or event commands without a code, instead of failing. Damaged parts are dropped
+    #[arg(long, action = ArgAction::SetTrue, display_order = 95)]
+    lenient_parse: bool,
+
+    /// Salvages JSON data files, that fail to parse, by extracting events and entries, that parse correctly. Skipped byte ranges are reported. Implies `--lenient-parse`
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with = "strict_parse", display_order = 95)]
+    salvage: bool,
+
+    /// Keeps Ruby Marshal constructs, that can't be parsed, like custom classes, structs and object links, as opaque objects through read and write, instead of failing. Text inside them isn't translatable. Pass it to `read`, `write` and `purge` alike
+    #[arg(long, action = ArgAction::SetTrue, display_order = 95)]
+    lenient_marshal: bool,
+
+    /// Uses settings from the command line, when they conflict with the ones, that were recorded in metadata by read
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with = "prefer_metadata", display_order = 96)]
+    prefer_cli: bool,
+
+    /// Uses settings from metadata, when they conflict with the ones from the command line, without warning. This is the default, but conflicts are warned about
+    #[arg(long, action = ArgAction::SetTrue, display_order = 96)]
+    prefer_metadata: bool,
+}
+
+impl SharedArgs {
+    /// Resolves conflicts between settings from the command line and the ones, that were recorded in `metadata`. Metadata takes precedence, unless `--prefer-cli` is set. Conflicts are warned about, unless `--prefer-metadata` is set.
+    ///
+    /// Flags can only be enabled from the command line, so a flag conflicts, when it's set, but isn't recorded.
+    ///
+    /// Returns the resolved metadata, and names of command line settings, that were used instead of recorded ones and change sources of entries.
+    fn resolve(&self, mut metadata: Metadata) -> (Metadata, Vec<&'static str>) {
+        let mut conflicts = Vec::new();
+        let mut overridden = Vec::new();
+
+        // Extraction flags only add files, so existing entries still match.
+        for (name, passed, recorded, changes_sources) in [
+            ("--romanize", self.romanize, &mut metadata.romanize, true),
+            ("--trim", self.trim, &mut metadata.trim, true),
+            (
+                "--disable-custom-processing",
+                self.disable_custom_processing,
+                &mut metadata.disable_custom_processing,
+                true,
+            ),
+            (
+                "--common-event-names",
+                self.common_event_names,
+                &mut metadata.common_event_names,
+                false,
+            ),
+            (
+                "--troop-names",
+                self.troop_names,
+                &mut metadata.troop_names,
+                false,
+            ),
+            ("--notes", self.notes, &mut metadata.notes, false),
+            (
+                "--speaker-names",
+                self.speaker_names,
+                &mut metadata.speaker_names,
+                false,
+            ),
+        ] {
+            if passed && !*recorded {
+                conflicts
+                    .push(format!("{name} is set, but wasn't used in read"));
+
+                if self.prefer_cli {
+                    *recorded = true;
+
+                    if changes_sources {
+                        overridden.push(name);
+                    }
+                }
+            }
+        }
+
+        if let Some(duplicate_mode) = self.duplicate_mode
+            && u8::from(duplicate_mode) != u8::from(metadata.duplicate_mode)
+        {
+            conflicts.push(format!(
+                "--duplicate-mode is {}, but read used {}",
+                format!("{duplicate_mode:?}").to_lowercase(),
+                format!("{:?}", metadata.duplicate_mode).to_lowercase()
+            ));
+
+            if self.prefer_cli {
+                metadata.duplicate_mode = duplicate_mode;
+                overridden.push("--duplicate-mode");
+            }
+        }
+
+        if !self.note_tags.is_empty() && self.note_tags != metadata.note_tags {
+            conflicts.push(format!(
+                "--note-tags are {}, but read used {}",
+                self.note_tags.join(","),
+                metadata.note_tags.join(",")
+            ));
+
+            if self.prefer_cli {
+                metadata.note_tags.clone_from(&self.note_tags);
+                overridden.push("--note-tags");
+            }
+        }
+
+        if !self.prefer_metadata {
+            for conflict in conflicts {
+                if self.prefer_cli {
+                    warn!("{conflict}. The command line value is used.");
+                } else {
+                    warn!(
+                        "{conflict}. The value from metadata is used. Pass `--prefer-cli` to use the command line value, or `--prefer-metadata` to silence this warning."
+                    );
+                }
+            }
+        }
+
+        (metadata, overridden)
+    }
+
+    const fn parse_mode(&self) -> ParseMode {
+        if self.strict_parse {
+            ParseMode::Strict
+        } else if self.salvage {
+            ParseMode::Salvage
+        } else if self.lenient_parse {
+            ParseMode::Lenient
+        } else {
+            ParseMode::Default
+        }
+    }
 }
 
 #[derive(Debug, Args)]
+#[allow(clippy::struct_excessive_bools)]
 struct ReadArgs {
     #[arg(short = 'S', long, hide = true, action = ArgAction::SetTrue)]
     silent: bool,
@@ -249,6 +617,107 @@ struct ReadArgs {
     #[arg(long, alias = "so", action = ArgAction::SetTrue, requires_if("append", "read_mode"), requires_if("force-append", "read_mode"))]
     skip_obsolete: bool,
 
+    /// Comma-separated wildcard patterns of `.rgss` archive entries to extract, e.g. `Data/*`.
+    /// Patterns are case-insensitive and use `/` as separator. By default, all entries are extracted.
+    #[arg(
+        long,
+        alias = "af",
+        value_name = "PATTERNS",
+        default_value = "",
+        value_parser = value_parser!(ArchiveFilter)
+    )]
+    archive_filter: ArchiveFilter,
+
+    /// Extracts `.rgss` archive, even if it was already extracted and hasn't changed since
+    #[arg(long, action = ArgAction::SetTrue)]
+    re_extract: bool,
+
+    /// Annotates entries, that use `\N[n]` and `\V[n]` control codes, with names of the actors and variables from the database, e.g. `<!-- CODES: \N[3] = Harold -->`.
+    #[arg(long, alias = "rc", action = ArgAction::SetTrue)]
+    resolve_codes: bool,
+
+    /// Annotates messages with their face graphic, window position and background, and conditions of the map event page, that shows them, e.g. `<!-- CONTEXT: face Actor1 #2; window top -->`.
+    #[arg(long, alias = "mc", action = ArgAction::SetTrue)]
+    message_context: bool,
+
+    /// Writes context of messages to `maps.context.json`, `commonevents.context.json` and `troops.context.json` next to translation files, instead of comments: speaker, location, event name, face, page conditions and surrounding messages.
+    #[arg(long, alias = "cf", action = ArgAction::SetTrue)]
+    context_files: bool,
+
+    /// Precedes entries with their paths in game data, e.g. `<!-- ANCHOR: Map001/EV003/p0/c12 -->`, which don't change, when nearby content or the text itself changes.
+    /// Will be automatically set if it was used in read.
+    #[arg(long, action = ArgAction::SetTrue)]
+    anchors: bool,
+
+    #[command(flatten)]
+    archive: ArchiveArgs,
+
+    #[command(flatten)]
+    shared: SharedArgs,
+}
+
+#[derive(Debug, Args)]
+struct ArchiveArgs {
+    /// Encoding of file paths inside `.rgss` archive.
+    /// `auto` uses UTF-8, and falls back to Shift-JIS (CP932) for paths that aren't valid UTF-8. Also accepts any encoding label, e.g. `shift_jis`, `gbk`, `euc-kr`.
+    #[arg(
+        long,
+        alias = "ae",
+        value_name = "ENCODING",
+        default_value = "auto",
+        value_parser = value_parser!(PathEncoding)
+    )]
+    archive_encoding: PathEncoding,
+
+    /// Decryption key of `.rgss` archive in hex, e.g. `0xDEADCAFE`. Use it for games, that ship archives with modified keys.
+    /// For XP/VX archives, it replaces the initial key. For VX Ace archives, it replaces the key derived from the archive header.
+    #[arg(
+        long,
+        alias = "ak",
+        value_name = "KEY",
+        value_parser = value_parser!(ArchiveKey)
+    )]
+    archive_key: Option<ArchiveKey>,
+}
+
+#[derive(Debug, Args)]
+struct WriteArgs {
+    /// JSON file with replacement rules, that are applied to translations right before writing, e.g. to convert straight quotes to curly ones. Translation files aren't changed.
+    /// Rules are an array of `{"pattern": REGEX, "replacement": TEXT, "files": [FILES]}` objects, applied in order. `files` is optional
+    #[arg(long, value_name = "FILE", display_order = 96)]
+    rules: Option<PathBuf>,
+
+    /// What to do with blocks of plural forms in translations, like `{{%1: монета|монеты|монет}}`, whose forms go in CLDR order of `--plural-language`.
+    /// `keep` - Validates blocks and writes them as is, for plugins, that choose the form at runtime.
+    /// Name of the form, e.g. `many` - Replaces blocks with this form, for games without such plugins.
+    /// Blocks are written as is without validation by default
+    #[arg(
+        long,
+        value_name = "MODE",
+        requires = "plural_language",
+        display_order = 96
+    )]
+    plurals: Option<String>,
+
+    /// Target language of plural forms, e.g. `ru` or `pl`
+    #[arg(
+        long,
+        value_name = "LANGUAGE",
+        requires = "plurals",
+        display_order = 96
+    )]
+    plural_language: Option<String>,
+
+    /// Layout of `output` directory.
+    /// `flat` - `data` and `js` directories directly in `output`.
+    /// `game` - The game's own structure, including `www` of deployed MV games, with `Game.ini` or `package.json`, that show the translated title, so the directory can be dropped onto the game as is
+    #[arg(long, value_enum, default_value = "flat", display_order = 97)]
+    layout: layout::Layout,
+
+    /// Also places every game file, that the translation doesn't modify, in `output`, so it's a complete playable build. Assets are hard-linked, when possible, and other files are copied. Implies `--layout game`
+    #[arg(long, action = ArgAction::SetTrue, display_order = 98)]
+    full_output: bool,
+
     #[command(flatten)]
     shared: SharedArgs,
 }
@@ -259,6 +728,28 @@ struct PurgeArgs {
     #[arg(short, long, action = ArgAction::SetTrue, display_order = 23)]
     create_ignore: bool,
 
+    /// Removes entries, whose source matches the regular expression, regardless of their translation, instead of removing entries without translation.
+    /// Combine with `--create-ignore` to move matched entries to the ignore file
+    #[arg(long, value_name = "REGEX", value_parser = value_parser!(Regex), display_order = 24)]
+    pattern: Option<Regex>,
+
+    /// Removes sections of `maps.txt`, whose map no longer exists in the game, instead of removing entries without translation
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with = "pattern", display_order = 25)]
+    stale_maps: bool,
+
+    /// Appends sections, removed with `--stale-maps`, to this file instead of discarding them
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires = "stale_maps",
+        display_order = 26
+    )]
+    stale_archive: Option<PathBuf>,
+
+    /// Clears translations of entries, that `translate` marked as machine-translated, and their marks, instead of removing entries without translation
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with_all = ["pattern", "stale_maps"], display_order = 27)]
+    machine_translated: bool,
+
     #[command(flatten)]
     shared: SharedArgs,
 }
@@ -297,6 +788,502 @@ enum JsonSubcommand {
     Write,
 }
 
+#[derive(Debug, Args)]
+struct ExportArgs {
+    /// Format to export to
+    #[arg(value_enum)]
+    format: ExportFormat,
+
+    /// Directory to export files to. Defaults to `export` directory in the output directory
+    #[arg(long, value_name = "EXPORT_PATH", value_parser = value_parser!(PathBuf))]
+    export_dir: Option<PathBuf>,
+
+    /// Language of the game, that formats with languages record, e.g. XLIFF
+    #[arg(long, value_name = "LANGUAGE", default_value = "ja")]
+    source_language: String,
+
+    /// Language of the translation, that formats with languages record, e.g. XLIFF and PO
+    #[arg(long, value_name = "LANGUAGE")]
+    target_language: Option<String>,
+
+    /// Exports only untranslated entries, and entries, that import marked fuzzy, as a compact work package for a translator. Translated entries are left out, but are still matched on import
+    #[arg(long)]
+    untranslated_only: bool,
+
+    /// Imports exported files back into a temporary copy of translation files, and fails, if any translation is lost or changed, e.g. by escaping
+    #[arg(long, conflicts_with = "untranslated_only")]
+    verify: bool,
+}
+
+#[derive(Debug, Args)]
+struct ImportArgs {
+    /// Format to import from
+    #[arg(value_enum)]
+    format: ImportFormat,
+
+    /// Directory to import files from, or a single Translator++ project or `MTool` file. Defaults to `export` directory in the output directory
+    #[arg(long, value_name = "IMPORT_PATH", value_parser = value_parser!(PathBuf))]
+    import_dir: Option<PathBuf>,
+
+    /// Language of the translation, for formats, that store translations of each language separately, e.g. Weblate layout
+    #[arg(long, value_name = "LANGUAGE")]
+    target_language: Option<String>,
+
+    /// Attributes imported translations to this translator, e.g. initials. Attribution is recorded in `.rvpacker-attribution` file in `translation` directory, and shown with `attribution` command
+    #[arg(long, value_name = "NAME")]
+    translator: Option<String>,
+
+    /// What to do with entries, that already have a different translation. `mark-fuzzy` keeps existing translations, and records imported ones in `<!-- FUZZY: ... -->` comments above the entries. With `--yes`, `prompt` marks conflicts as fuzzy
+    #[arg(long, value_enum, value_name = "POLICY", default_value_t)]
+    on_conflict: ConflictPolicy,
+}
+
+#[derive(Debug, Args)]
+struct TranslateArgs {
+    /// Machine translation service to translate with
+    #[arg(value_enum)]
+    provider: translate::Provider,
+
+    /// Language to translate to, e.g. `en` or `en-us`
+    #[arg(long, value_name = "LANGUAGE")]
+    target_language: String,
+
+    /// Language of the game, e.g. `ja`. Providers detect it by default
+    #[arg(long, value_name = "LANGUAGE")]
+    source_language: Option<String>,
+
+    /// Key of the provider's API. Defaults to `DEEPL_API_KEY` environment variable for `DeepL`, and `OPENAI_API_KEY` for `OpenAI`
+    #[arg(long, value_name = "KEY")]
+    api_key: Option<String>,
+
+    /// Translation files to translate, comma-separated, e.g. `maps,actors`. Translates all files by default
+    #[arg(long, value_name = "FILES", value_delimiter = ',')]
+    files: Vec<String>,
+
+    /// Model to translate with, for language model providers. Defaults to `gpt-4o-mini` for `openai`, and is required for `ollama`
+    #[arg(long, value_name = "MODEL")]
+    model: Option<String>,
+
+    /// Approximate number of tokens of sources in a request to language model providers. Sections with more sources are split into several requests
+    #[arg(long, value_name = "TOKENS", default_value_t = 2000)]
+    max_tokens: usize,
+
+    /// Number of times a failed request is repeated, waiting 1, 2, 4 and so on seconds before each retry
+    #[arg(long, value_name = "NUMBER", default_value_t = 3)]
+    retries: u32,
+
+    /// Base URL of the API, for language model providers, e.g. `http://localhost:8080/v1` of llama.cpp server with `openai`, which then needs no key. Defaults to `http://localhost:11434` for `ollama`
+    #[arg(long, value_name = "URL")]
+    endpoint: Option<String>,
+
+    /// Sampling temperature, for language model providers. Lower values give more literal and consistent translations. Defaults to the model's default
+    #[arg(long, value_name = "TEMPERATURE")]
+    temperature: Option<f64>,
+
+    /// Number of requests, that are sent at once. Local servers process requests in parallel only if they're configured to, e.g. with `OLLAMA_NUM_PARALLEL`
+    #[arg(long, value_name = "NUMBER", default_value_t = 1, value_parser = value_parser!(u16).range(1..))]
+    concurrency: u16,
+
+    /// JSON key of a service account with access to Cloud Translation, for `google`. Defaults to `GOOGLE_APPLICATION_CREDENTIALS` environment variable
+    #[arg(long, value_name = "KEY_PATH", value_parser = value_parser!(PathBuf))]
+    credentials: Option<PathBuf>,
+
+    /// Google Cloud project, for `google`. Defaults to the project of the service account
+    #[arg(long, value_name = "PROJECT")]
+    project: Option<String>,
+
+    /// Google Cloud location, for `google`. Defaults to `us-central1`, since glossaries aren't available in `global`
+    #[arg(long, value_name = "LOCATION")]
+    location: Option<String>,
+
+    /// TSV file, e.g. `glossary.tsv`, with source terms in the first column, and their required translations in the second. Language models receive terms of sources with the prompt, `deepl` and `google` create glossaries from it, which needs `--source-language`. Machine translations, that don't use required translations, are reported. Cached translations don't follow changes of the glossary, unless `--no-cache` is passed
+    #[arg(long, value_name = "TSV_PATH", value_parser = value_parser!(PathBuf))]
+    glossary: Option<PathBuf>,
+
+    /// Cloud Storage bucket, that the service account can write to, to upload `--glossary` to, for `google`
+    #[arg(long, value_name = "BUCKET", requires = "glossary")]
+    bucket: Option<String>,
+
+    /// Requests translations again, instead of reusing ones, that are cached in `.rvpacker-mt-cache` file in `translation` directory. New translations replace cached ones
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Maximal number of requests per minute, for plans and servers with rate limits. Requests of all `--concurrency` threads are spaced evenly
+    #[arg(long, value_name = "REQUESTS", value_parser = value_parser!(u32).range(1..))]
+    rate_limit: Option<u32>,
+
+    /// TSV file with names of characters and locations, one per line, that providers never translate. A spelling in the second column, e.g. `Alice` after `アリス` and a tab, replaces the name in all machine translations. Translations, that lose names, are rejected
+    #[arg(long, value_name = "TSV_PATH", value_parser = value_parser!(PathBuf))]
+    protected_terms: Option<PathBuf>,
+
+    /// Shows each machine translation next to its source, and asks to accept it with Enter, edit, skip it, or stop, before it's written. Edited translations aren't marked as machine-translated, and skipped ones stay cached for the next run
+    #[arg(long, action = ArgAction::SetTrue)]
+    review: bool,
+}
+
+#[derive(Debug, Args)]
+struct PretranslateArgs {
+    /// Translation memories, comma-separated: TMX files, or translation directories of other projects, e.g. of an earlier game of the series. Earlier memories take precedence over later ones
+    #[arg(long, value_name = "PATHS", value_delimiter = ',', required = true, value_parser = value_parser!(PathBuf))]
+    memory: Vec<PathBuf>,
+
+    /// Language of sources in TMX files, e.g. `ja`. Defaults to `srclang` of TMX header
+    #[arg(long, value_name = "LANGUAGE")]
+    source_language: Option<String>,
+
+    /// Language of translations in TMX files, e.g. `en`. Defaults to the other language of each translation unit
+    #[arg(long, value_name = "LANGUAGE")]
+    target_language: Option<String>,
+
+    /// Translation files to fill, comma-separated, e.g. `maps,actors`. Fills all files by default
+    #[arg(long, value_name = "FILES", value_delimiter = ',')]
+    files: Vec<String>,
+
+    /// Also fills entries without exact matches with translations of the most similar sources, which similarity from 0 to 1 is at least this, e.g. `0.85`. Such entries are marked with `<!-- FUZZY: ... -->` comments for review
+    #[arg(long, value_name = "SIMILARITY", value_parser = parse_similarity)]
+    fuzzy: Option<f64>,
+}
+
+#[derive(Debug, Args)]
+struct AttributionArgs {
+    /// Shows only entries, which source or translation match the regular expression
+    #[arg(value_parser = value_parser!(Regex))]
+    pattern: Option<Regex>,
+
+    /// Shows only entries of this translator
+    #[arg(long, value_name = "NAME")]
+    translator: Option<String>,
+
+    /// Translation files to show, comma-separated, e.g. `maps,actors`. Shows all files by default
+    #[arg(long, value_name = "FILES", value_delimiter = ',')]
+    files: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+struct ReplaceArgs {
+    /// Regular expression to search for in translations
+    #[arg(value_parser = value_parser!(Regex))]
+    pattern: Regex,
+
+    /// Replacement text. Use `$1` or `${name}` to insert capture groups
+    replacement: String,
+
+    /// Prints the replacements, without writing them to translation files
+    #[arg(long, action = ArgAction::SetTrue)]
+    dry_run: bool,
+
+    /// Translation files to process, comma-separated, e.g. `maps,actors`. Processes all files by default
+    #[arg(long, value_name = "FILES", value_delimiter = ',')]
+    files: Vec<String>,
+
+    /// Applies replacements, that change control codes and placeholders of translation so they no longer match the source. By default, such replacements are skipped
+    #[arg(long, action = ArgAction::SetTrue)]
+    allow_placeholder_mismatch: bool,
+}
+
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+struct LintArgs {
+    #[command(subcommand)]
+    subcommand: Option<LintSubcommand>,
+
+    /// Fixes trailing whitespace, double spaces and tabs. Mismatched brackets and quotes, and leading punctuation are only reported, since fixing them needs a translator
+    #[arg(long, action = ArgAction::SetTrue)]
+    fix: bool,
+
+    /// Translation files to check, comma-separated, e.g. `maps,actors`. Checks all files by default
+    #[arg(long, value_name = "FILES", value_delimiter = ',')]
+    files: Vec<String>,
+}
+
+#[derive(Debug, Subcommand)]
+enum LintSubcommand {
+    /// Reports identical sources, that are translated differently, with files and lines of each translation, so terminology stays consistent. Most useful, when duplicates are kept with `--duplicate-mode allow`
+    Consistency {
+        /// Translation files to check, comma-separated, e.g. `maps,actors`. Checks all files by default
+        #[arg(long, value_name = "FILES", value_delimiter = ',')]
+        files: Vec<String>,
+    },
+}
+
+#[derive(Debug, Args)]
+struct RemapArgs {
+    /// Translation file to remap, e.g. `maps` or `commonevents.txt`
+    file: String,
+
+    /// Old section IDs and their new IDs, e.g. `3:5,4:6`
+    #[arg(long, value_name = "OLD:NEW,...", value_parser = value_parser!(Mapping), required_unless_present = "auto", conflicts_with = "auto")]
+    mapping: Option<Mapping>,
+
+    /// Derives the mapping by comparing sources of sections with a fresh read of the current game data
+    #[arg(long, action = ArgAction::SetTrue)]
+    auto: bool,
+
+    /// Minimal similarity of sections from 0 to 1, that `--auto` matches
+    #[arg(long, default_value_t = 0.5, value_parser = parse_similarity, requires = "auto")]
+    threshold: f64,
+
+    /// Prints the mapping, without changing files
+    #[arg(long, action = ArgAction::SetTrue)]
+    dry_run: bool,
+}
+
+#[derive(Debug, Args)]
+struct OverflowArgs {
+    /// Width limits of text categories, comma-separated, e.g. `message=40,description=60`. Categories are `message`, `choice`, `name`, `description` and `battle`.
+    /// Categories, that aren't given, keep their defaults: 54, 24, 16, 58 and 48 characters. Control codes don't count, and full-width characters count twice
+    #[arg(
+        long,
+        value_name = "CATEGORY=LIMIT,...",
+        default_value = "",
+        value_parser = value_parser!(Limits)
+    )]
+    limits: Limits,
+}
+
+#[derive(Debug, Args)]
+struct UpgradeArgs {
+    /// Minimal similarity of edited sources from 0 to 1, that translations are carried over to
+    #[arg(long, default_value_t = 0.8, value_parser = parse_similarity)]
+    threshold: f64,
+
+    /// Prints the statistics and writes the report, without changing translation files
+    #[arg(long, action = ArgAction::SetTrue)]
+    dry_run: bool,
+
+    /// File to write the report of fuzzy matches and unmatched translations to. Defaults to `upgrade-report.txt` in the output directory
+    #[arg(long, value_name = "REPORT_PATH", value_parser = value_parser!(PathBuf))]
+    report: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+struct DedupArgs {
+    /// Duplicate mode to apply. Defaults to the mode, that was used in read
+    #[arg(
+        short,
+        long,
+        alias = "dup-mode",
+        value_parser = PossibleValuesParser::new(DuplicateMode::VARIANTS).map(|s| DuplicateMode::from_str(&s).unwrap())
+    )]
+    duplicate_mode: Option<DuplicateMode>,
+
+    /// Prints the statistics, without changing translation files
+    #[arg(long, action = ArgAction::SetTrue)]
+    dry_run: bool,
+}
+
+#[derive(Debug, Args)]
+struct TrimArgs {
+    /// Undoes trimming, restoring leading and trailing whitespace of sources around translations
+    #[arg(long, action = ArgAction::SetTrue)]
+    undo: bool,
+
+    /// Prints the statistics, without changing translation files
+    #[arg(long, action = ArgAction::SetTrue)]
+    dry_run: bool,
+}
+
+#[derive(Debug, Args)]
+struct RomanizeArgs {
+    /// Undoes romanization, restoring the original symbols in sources
+    #[arg(long, action = ArgAction::SetTrue)]
+    undo: bool,
+
+    /// Prints the statistics, without changing translation files
+    #[arg(long, action = ArgAction::SetTrue)]
+    dry_run: bool,
+}
+
+#[derive(Debug, Args)]
+struct AlignArgs {
+    /// Directory of the translated copy of the game, as it would be passed to `--input-dir`. Its data must be extracted from an archive beforehand
+    #[arg(value_name = "TRANSLATED_PATH", value_parser = value_parser!(PathBuf))]
+    translated_dir: PathBuf,
+
+    /// Replaces existing translations too, instead of filling only untranslated entries
+    #[arg(long, action = ArgAction::SetTrue)]
+    overwrite: bool,
+
+    /// Prints the statistics, without changing translation files
+    #[arg(long, action = ArgAction::SetTrue)]
+    dry_run: bool,
+}
+
+#[derive(Debug, Args)]
+struct SheetArgs {
+    /// ID of the spreadsheet, from its URL: `https://docs.google.com/spreadsheets/d/<ID>/edit`
+    #[arg(long, value_name = "ID")]
+    spreadsheet: String,
+
+    /// JSON key of a service account, that the spreadsheet is shared with as an editor. Defaults to `GOOGLE_APPLICATION_CREDENTIALS` environment variable
+    #[arg(long, value_name = "KEY_PATH", value_parser = value_parser!(PathBuf))]
+    credentials: Option<PathBuf>,
+}
+
+#[derive(Debug, Subcommand)]
+enum SheetsSubcommand {
+    /// Uploads untranslated entries to a tab per translation file. Contents of the tabs are replaced
+    Push {
+        #[command(flatten)]
+        sheet: SheetArgs,
+    },
+
+    /// Writes filled translations of the tabs back to translation files
+    Pull {
+        #[command(flatten)]
+        sheet: SheetArgs,
+
+        /// Attributes pulled translations to this translator, e.g. initials
+        #[arg(long, value_name = "NAME")]
+        translator: Option<String>,
+
+        /// What to do with entries, that already have a different translation. See `import --on-conflict`
+        #[arg(long, value_enum, value_name = "POLICY", default_value_t)]
+        on_conflict: ConflictPolicy,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ReportSubcommand {
+    /// Writes a static HTML page with progress of each translation file, duplicate statistics and recently changed entries, for publishing on a project page
+    Html {
+        /// HTML file to write. Defaults to `report.html` in the output directory
+        #[arg(value_name = "HTML_PATH", value_parser = value_parser!(PathBuf))]
+        path: Option<PathBuf>,
+
+        /// Number of recently changed entries to list. Changes are known only for translations, that were imported with `--translator`
+        #[arg(long, value_name = "NUMBER", default_value_t = 20)]
+        recent: usize,
+    },
+
+    /// Writes a small SVG badge with the percentage of translated entries, e.g. `translation 73%`, for embedding in a project page
+    Badge {
+        /// SVG file to write. Defaults to `badge.svg` in the output directory
+        #[arg(value_name = "SVG_PATH", value_parser = value_parser!(PathBuf))]
+        path: Option<PathBuf>,
+
+        /// Text of the badge's left half
+        #[arg(long, value_name = "LABEL", default_value = "translation")]
+        label: String,
+    },
+
+    /// Writes a Markdown changelog of source lines, that were added, removed and modified in each file, after the game was updated. Existing translation files serve as the snapshot of the previous version. Run it before `upgrade`
+    Changes {
+        /// Markdown file to write. Defaults to `changes.md` in the output directory
+        #[arg(value_name = "MARKDOWN_PATH", value_parser = value_parser!(PathBuf))]
+        path: Option<PathBuf>,
+
+        /// Minimal similarity of a removed and an added source of a section from 0 to 1, that makes them a modified source
+        #[arg(long, default_value_t = 0.8, value_parser = parse_similarity)]
+        threshold: f64,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum TmSubcommand {
+    /// Merges translation directories of completed projects, and TMX files, into one deduplicated translation memory, for `pretranslate --memory` and CAT tools. Sources, that have different translations, keep the translation of the earliest memory
+    Build {
+        /// Translation directories of projects, or TMX files, comma-separated. Earlier memories take precedence over later ones
+        #[arg(value_name = "PATHS", value_delimiter = ',', required = true, value_parser = value_parser!(PathBuf))]
+        paths: Vec<PathBuf>,
+
+        /// TMX file to write. Defaults to `memory.tmx` in the output directory
+        #[arg(long, value_name = "TMX_PATH", value_parser = value_parser!(PathBuf))]
+        tmx: Option<PathBuf>,
+
+        /// Language of sources, e.g. `ja`. Also selects sources of TMX files
+        #[arg(long, value_name = "LANGUAGE")]
+        source_language: String,
+
+        /// Language of translations, e.g. `en`. Also selects translations of TMX files
+        #[arg(long, value_name = "LANGUAGE")]
+        target_language: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ArchiveSubcommand {
+    /// Lists entries of `.rgss` archive with their sizes
+    List {
+        #[command(flatten)]
+        archive: ArchiveArgs,
+    },
+
+    /// Validates the structure of `.rgss` archive without extracting it
+    Check {
+        #[command(flatten)]
+        archive: ArchiveArgs,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum BundleSubcommand {
+    /// Packs the translation directory, with metadata, ignore and attribution files, into one compressed file
+    Export {
+        /// Bundle file to create, e.g. `project.rvpack`
+        #[arg(value_name = "BUNDLE_PATH", value_parser = value_parser!(PathBuf))]
+        path: PathBuf,
+    },
+
+    /// Unpacks a bundle to the translation directory, after checking, that it was created for this game by a compatible version of the tool
+    Import {
+        /// Bundle file to unpack
+        #[arg(value_name = "BUNDLE_PATH", value_parser = value_parser!(PathBuf))]
+        path: PathBuf,
+
+        /// Replaces the existing translation directory
+        #[arg(long, action = ArgAction::SetTrue)]
+        force: bool,
+    },
+}
+
+#[derive(Debug, Args)]
+#[allow(clippy::struct_excessive_bools)]
+struct PackageArgs {
+    /// Version of the patch, that's recorded in the manifest, e.g. `1.0`
+    #[arg(long, value_name = "VERSION")]
+    patch_version: String,
+
+    /// Directory to build the patch in. Defaults to `patch` directory in the output directory
+    #[arg(long, value_name = "PATCH_PATH", value_parser = value_parser!(PathBuf))]
+    patch_dir: Option<PathBuf>,
+
+    /// Stores files bigger than this number of bytes as binary deltas of their originals, which `apply-patch` applies. By default, all files are stored as is
+    #[arg(long, value_name = "BYTES")]
+    delta_threshold: Option<usize>,
+
+    /// Stores all files, that the game has, as VCDIFF deltas of their originals, however big they are, so the patch doesn't redistribute any game data. Players apply them with `apply-patch` or any VCDIFF tool. Requires `xdelta3`
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with = "delta_threshold")]
+    vcdiff: bool,
+
+    /// Adds `install.sh` and `install.bat` scripts, that copy the files over the game for players without the tool. Scripts can't apply deltas
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with_all = ["delta_threshold", "vcdiff"])]
+    install_scripts: bool,
+
+    /// Also packs the patch directory into a ZIP archive next to it, e.g. `patch.zip`, that's ready to publish
+    #[arg(long, action = ArgAction::SetTrue)]
+    zip: bool,
+
+    /// Replaces the existing patch directory
+    #[arg(long, action = ArgAction::SetTrue)]
+    force: bool,
+}
+
+#[derive(Debug, Args)]
+struct ApplyPatchArgs {
+    /// Patch directory, that contains `patch.json`
+    #[arg(value_name = "PATCH_PATH", value_parser = value_parser!(PathBuf), required_unless_present = "undo")]
+    path: Option<PathBuf>,
+
+    /// Replaces files, that differ from the originals, that the patch was built for, unless they're stored as deltas. By default, such files fail the patch
+    #[arg(long, action = ArgAction::SetTrue, conflicts_with = "undo")]
+    force: bool,
+
+    /// Restores the original files of the game, that patches changed, from `.rvpacker-backup` directory, and removes files, that they added
+    #[arg(long, action = ArgAction::SetTrue)]
+    undo: bool,
+}
+
 #[derive(Debug, Subcommand, EnumIs)]
 enum GenericSubcommand {
     Read {
@@ -333,13 +1320,14 @@ enum GenericSubcommand {
     },
 }
 
-#[derive(Debug, Subcommand, EnumIs)]
+#[derive(Debug, Subcommand, EnumIs, IntoStaticStr)]
+#[strum(serialize_all = "kebab-case")]
 enum Command {
     /// Parses game files to `.txt` format, and decrypts any `.rgss` archive if it's present
     Read(ReadArgs),
 
     /// Writes translated game files to the output directory
-    Write(SharedArgs),
+    Write(WriteArgs),
 
     /// Purges lines without translation from translation files
     Purge(PurgeArgs),
@@ -355,20 +1343,170 @@ enum Command {
         #[command(subcommand)]
         subcommand: JsonSubcommand,
     },
-}
+
+    /// Exports translation files to other formats
+    Export(ExportArgs),
+
+    /// Imports translations back from exported files to translation files
+    Import(ImportArgs),
+
+    /// Replaces text in translations, using regular expression. Sources are never changed
+    Replace(ReplaceArgs),
+
+    /// Checks translations for trailing whitespace, double spaces, tabs, mismatched brackets and quotes, and suspicious leading punctuation. Issues, that the source has itself, aren't reported
+    Lint(LintArgs),
+
+    /// Moves translations to new section IDs, after the game renumbered maps, common events or other entries
+    Remap(RemapArgs),
+
+    /// Migrates translations to a new version of the game. Existing translation files serve as the snapshot of the previous version. Moved, renumbered and edited content is matched by similarity
+    Upgrade(UpgradeArgs),
+
+    /// Re-applies a duplicate mode to existing translation files, for projects, that were read in the wrong mode. Duplicates, that were translated differently, are reconciled interactively
+    Dedup(DedupArgs),
+
+    /// Applies `--trim` to existing translation files, or undoes it, for projects, that changed their mind about trimming. Translations are carried over to the new sources
+    Trim(TrimArgs),
+
+    /// Applies `--romanize` to existing translation files, or undoes it, without a force re-read. Translations are carried over to the new sources
+    Romanize(RomanizeArgs),
+
+    /// Fills translations from an already translated copy of the game, matching texts of both copies by their positions. Run `read` on the original game first
+    Align(AlignArgs),
+
+    /// Checks translations for lines, that overflow the window they're displayed in
+    Overflow(OverflowArgs),
+
+    /// Shows who translated entries and when, as recorded by `import --translator`
+    Attribution(AttributionArgs),
+
+    /// Provides `export` and `import` subcommands for sharing the whole translation project as one file
+    Bundle {
+        #[command(subcommand)]
+        subcommand: BundleSubcommand,
+    },
+
+    /// Provides `push` and `pull` subcommands for translating in a Google Sheet. Requires `curl` and `openssl`
+    Sheets {
+        #[command(subcommand)]
+        subcommand: SheetsSubcommand,
+    },
+
+    /// Fills untranslated entries with machine translations of an online service or a local model, and marks them with `<!-- MACHINE TRANSLATION: ... -->` comments, so they can be reviewed or purged with `purge --machine-translated`. Requires `curl`, and `openssl` for `google`
+    Translate(TranslateArgs),
+
+    /// Fills untranslated entries, which sources exactly match sources of translation memories, e.g. TMX files or translation directories of earlier releases, and marks them with `<!-- PRETRANSLATED: ... -->` comments, that name the memory
+    Pretranslate(PretranslateArgs),
+
+    /// Provides `build` subcommand for pooling translations of several projects into one translation memory
+    Tm {
+        #[command(subcommand)]
+        subcommand: TmSubcommand,
+    },
+
+    /// Provides `html` and `badge` subcommands for publishing translation progress, and `changes` subcommand for reviewing game updates
+    Report {
+        #[command(subcommand)]
+        subcommand: ReportSubcommand,
+    },
+
+    /// Builds a distributable patch with the files of `output` directory, that differ from the game's originals, in the game's own structure, `patch.json` manifest and `README.txt` stub. Run `write` first
+    Package(PackageArgs),
+
+    /// Applies a patch to the game in the input directory, after checking, that its files are the originals, that the patch was built for. Originals are backed up, so the patch can be undone
+    ApplyPatch(ApplyPatchArgs),
+
+    /// Provides `list` and `check` subcommands for inspecting `.rgss` archive
+    Archive {
+        #[command(subcommand)]
+        subcommand: ArchiveSubcommand,
+    },
+}
 
 /// This tool allows to parse RPG Maker XP/VX/VXAce/MV/MZ games text to `.txt` files and write them back to their initial form. The program uses `data` or `Data` directories for source files, and `translation` directory to operate with translation files. It will also decrypt any `.rgss` archive if it's present.
 #[derive(Parser, Debug)]
 #[command(version = crate_version!(), next_line_help = true, term_width = 120)]
+#[allow(clippy::struct_excessive_bools)]
 struct Cli {
     /// Input directory, containing game files
     #[arg(short, long, global = true, default_value = "./", value_name = "INPUT_PATH", value_parser = value_parser!(PathBuf), display_order = 1)]
     input_dir: PathBuf,
 
-    /// Output directory to output files to
+    /// Output directory to output files to. Created, if it doesn't exist
     #[arg(short, long, global = true, value_name = "OUTPUT_PATH", value_parser = value_parser!(PathBuf), display_order = 2)]
     output_dir: Option<PathBuf>,
 
+    /// Fails, if the output directory doesn't exist, instead of creating it
+    #[arg(long, global = true, action = ArgAction::SetTrue, display_order = 2)]
+    no_create: bool,
+
+    /// Directory to extract `.rgss` archive to, and to look for `data`/`Data` directory in. Keeps the game directory pristine. Defaults to input directory.
+    /// Pass the same directory to `write` and `purge`, so they can find extracted source files.
+    #[arg(long, global = true, value_name = "WORK_PATH", value_parser = value_parser!(PathBuf), display_order = 3)]
+    work_dir: Option<PathBuf>,
+
+    /// Additional directory with data files, that overlay the game's data, as official patches and DLC do. Can be passed multiple times, later directories take precedence.
+    /// `write` outputs patched files of each directory to a subdirectory of `output`, named after the directory
+    #[arg(long, global = true, value_name = "EXTRA_SOURCE_PATH", value_parser = value_parser!(PathBuf), action = ArgAction::Append, display_order = 3)]
+    extra_source: Vec<PathBuf>,
+
+    /// JSON file, that maps standard data file names to the ones, that the game uses, for games with renamed or relocated data files, e.g. `{"Map%03d.json": "maps/m_%d.bin"}`. Paths are relative to the data directory.
+    /// `write` gives output files the game's names
+    #[arg(long, global = true, value_name = "FILE_MAP_PATH", value_parser = value_parser!(PathBuf), display_order = 3)]
+    file_map: Option<PathBuf>,
+
+    /// Automatically answers `Y` to all confirmations
+    #[arg(short, long, global = true, alias = "assume-yes", action = ArgAction::SetTrue, display_order = 4)]
+    yes: bool,
+
+    /// Fails instead of prompting, when a confirmation is required. Use it in CI jobs and GUI wrappers, which can't answer prompts
+    #[arg(long, global = true, action = ArgAction::SetTrue, conflicts_with = "yes", display_order = 4)]
+    no_input: bool,
+
+    /// Compresses translation files with this format after the command finishes. Compressed `.txt.gz` and `.txt.zst` files are detected and unpacked automatically, so it's only needed once, to compress an existing translation
+    #[arg(
+        long,
+        global = true,
+        value_name = "FORMAT",
+        value_enum,
+        display_order = 5
+    )]
+    compress: Option<Compression>,
+
+    /// Shell command to run before `read`, `write` and `purge`. Can be passed multiple times. If it fails, the command isn't run.
+    /// Paths are passed in `RVPACKER_COMMAND`, `RVPACKER_INPUT_DIR`, `RVPACKER_TRANSLATION_DIR` and `RVPACKER_OUTPUT_DIR` environment variables
+    #[arg(long, global = true, value_name = "COMMAND", action = ArgAction::Append, display_order = 6)]
+    pre_hook: Vec<String>,
+
+    /// Shell command to run after `read`, `write` and `purge`, even if they failed. Can be passed multiple times.
+    /// In addition to the variables of `--pre-hook`, receives `RVPACKER_STATUS`, `RVPACKER_EXIT_CODE`, `RVPACKER_ERROR` and `RVPACKER_ELAPSED`, and the run summary as JSON on stdin
+    #[arg(long, global = true, value_name = "COMMAND", action = ArgAction::Append, display_order = 6)]
+    post_hook: Vec<String>,
+
+    /// Mirrors the log to this file, without colors, to attach it to bug reports. The file is overwritten
+    #[arg(long, global = true, value_name = "LOG_PATH", value_parser = value_parser!(PathBuf), display_order = 7)]
+    log_file: Option<PathBuf>,
+
+    /// Level of messages, that are written to `--log-file`, independent of `-v` and `-q`
+    #[arg(
+        long,
+        global = true,
+        default_value = "debug",
+        value_name = "LEVEL",
+        value_parser = PossibleValuesParser::new(["error", "warn", "info", "debug", "trace"]).map(|s| LevelFilter::from_str(&s).unwrap()),
+        requires = "log_file",
+        display_order = 7
+    )]
+    log_level: LevelFilter,
+
+    /// Writes the summary of the run, with its outcome, counts and warnings, as JSON to this file. With `-q`, the summary is printed instead of the per-file table
+    #[arg(long, global = true, value_name = "SUMMARY_PATH", value_parser = value_parser!(PathBuf), display_order = 7)]
+    summary_file: Option<PathBuf>,
+
+    /// Prints CPU time and peak memory of each stage at the end of the run, to tell which stages need the memory. Only available on Linux
+    #[arg(long, global = true, action = ArgAction::SetTrue, display_order = 7)]
+    stats_resources: bool,
+
     #[command(subcommand)]
     command: Command,
 
@@ -386,6 +1524,33 @@ fn parse_metadata(metadata_file_path: &Path) -> Result<Option<Metadata>> {
     Ok(Some(metadata))
 }
 
+#[allow(clippy::fn_params_excessive_bools)]
+fn get_extra_kinds(
+    common_event_names: bool,
+    troop_names: bool,
+    notes: bool,
+    speaker_names: bool,
+) -> Vec<ExtraKind> {
+    [
+        (ExtraKind::CommonEventNames, common_event_names),
+        (ExtraKind::TroopNames, troop_names),
+        (ExtraKind::TilesetNotes, notes),
+        (ExtraKind::SystemNotes, notes),
+        (ExtraKind::SpeakerNames, speaker_names),
+    ]
+    .into_iter()
+    .filter_map(|(kind, enabled)| enabled.then_some(kind))
+    .collect()
+}
+
+/// Returns names of translation files of all extra fields.
+fn extra_translation_files() -> Vec<&'static str> {
+    get_extra_kinds(true, true, true, true)
+        .into_iter()
+        .map(ExtraKind::translation_file)
+        .collect()
+}
+
 fn get_game_type(
     game_title: &str,
     disable_custom_processing: bool,
@@ -419,11 +1584,54 @@ struct Processor<'a> {
 
     archive_path: Option<PathBuf>,
     output_dir: PathBuf,
+    work_dir: PathBuf,
+
+    layers: Option<Layers>,
+
+    /// Data with the game's file names mapped to the standard ones.
+    mapped: Option<Mapped>,
+
+    /// Marshal constructs, that were cut from the data in lenient Marshal mode.
+    opaque: Opaque,
+
+    yes: bool,
+    no_input: bool,
+
+    /// Level of messages, that are printed to the terminal.
+    verbosity: LevelFilter,
 
     start_time: &'a mut Instant,
 }
 
+/// Returns the canonical form of `path`, that may not exist yet: its existing ancestor is canonicalized, and the rest is appended as is.
+fn resolve_path(path: &Path) -> Result<PathBuf> {
+    let mut existing = path;
+    let mut rest = Vec::new();
+
+    while !existing.exists() {
+        let (Some(parent), Some(name)) =
+            (existing.parent(), existing.file_name())
+        else {
+            break;
+        };
+
+        rest.push(name);
+        existing = parent;
+    }
+
+    let existing = if existing.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        existing
+    };
+
+    let mut resolved = existing.canonicalize()?;
+    resolved.extend(rest.into_iter().rev());
+    Ok(resolved)
+}
+
 impl<'a> Processor<'a> {
+    #[allow(clippy::too_many_lines)]
     pub fn new(
         cli: &mut Cli,
         start_time: &'a mut Instant,
@@ -437,25 +1645,65 @@ impl<'a> Processor<'a> {
         let output_dir =
             take(&mut cli.output_dir).unwrap_or_else(|| input_dir.clone());
 
-        if !output_dir.exists() {
+        if !output_dir.exists() && cli.no_create {
             bail!("Output directory does not exist.");
         }
 
-        let source_path = if !cli.command.is_generic() {
-            ["data", "Data"]
+        let work_dir =
+            take(&mut cli.work_dir).unwrap_or_else(|| input_dir.clone());
+
+        if !work_dir.exists() {
+            create_dir_all(&work_dir)?;
+        }
+
+        let mut source_path = if cli.command.is_generic() {
+            take(&mut input_dir)
+        } else {
+            // Games, that ship only an archive, have no data directory until it's extracted.
+            [&work_dir, &input_dir]
                 .into_iter()
-                .find_map(|dir| {
-                    let path = input_dir.join(dir);
+                .flat_map(|dir| [dir.join("data"), dir.join("Data")])
+                .find(|path| path.exists())
+                .unwrap_or_else(|| work_dir.join("Data"))
+        };
 
-                    if path.exists() {
-                        return Some(path);
-                    }
+        // Output nested in the game directory is fine, but read would pick up anything written to the data directory as game data.
+        if !cli.command.is_generic()
+            && source_path.exists()
+            && resolve_path(&output_dir)?
+                .starts_with(source_path.canonicalize()?)
+        {
+            bail!(
+                "Output directory can't be inside the game's data directory {}.",
+                source_path.display()
+            );
+        }
 
-                    None
-                })
-                .context("Could not found `data`/`Data` directory.")?
+        if !output_dir.exists() {
+            create_dir_all(&output_dir)?;
+        }
+
+        let mapped = match &cli.file_map {
+            Some(file_map_path) if !cli.command.is_generic() => {
+                let mapped = FileMap::load(file_map_path)?
+                    .apply(&source_path, FileMap::temp_path())?;
+                source_path = mapped.data_path(&source_path);
+                Some(mapped)
+            }
+            _ => None,
+        };
+
+        let layers = if cli.extra_source.is_empty() || cli.command.is_generic()
+        {
+            None
         } else {
-            take(&mut input_dir)
+            let layers = Layers::merge(
+                &source_path,
+                &cli.extra_source,
+                Layers::temp_path(),
+            )?;
+            source_path = layers.data_path(&source_path);
+            Some(layers)
         };
 
         let translation_path = output_dir.join("translation");
@@ -463,7 +1711,9 @@ impl<'a> Processor<'a> {
         let ignore_file_path = translation_path.join(RVPACKER_IGNORE_FILE);
 
         let (engine_type, system_file_path, archive_path, ini_file_path) =
-            if !cli.command.is_generic() {
+            if cli.command.is_generic() || cli.command.is_tm() {
+                Default::default()
+            } else {
                 let type_paths = [
                     (EngineType::New, source_path.join("System.json"), None),
                     (
@@ -498,18 +1748,23 @@ impl<'a> Processor<'a> {
                         },
                     )
                 else {
-                    bail!(
+                    return Err(anyhow!(
                         "Couldn't determine game engine. Check the existence of `System` file inside `data`/`Data` directory, or `.rgss` archive."
-                    );
+                    ))
+                    .kind(ErrorKind::EngineNotDetected);
                 };
 
                 let ini_file_path = input_dir.join("Game.ini");
 
                 (engine_type, system_file_path, archive_path, ini_file_path)
-            } else {
-                Default::default()
             };
 
+        crash::set_project(
+            format!("{engine_type:?}"),
+            &input_dir,
+            &metadata_file_path,
+        );
+
         Ok(Self {
             engine_type,
             input_dir,
@@ -521,10 +1776,82 @@ impl<'a> Processor<'a> {
             ignore_file_path,
             archive_path,
             output_dir,
+            work_dir,
+            layers,
+            mapped,
+            opaque: Opaque::default(),
+            yes: cli.yes,
+            no_input: cli.no_input,
+            verbosity: cli.verbosity.tracing_level_filter(),
             start_time,
         })
     }
 
+    /// Prints `message` and waits for the user to input `Y`. Returns whether the user confirmed.
+    ///
+    /// Doesn't prompt with `--yes`. Fails with `--no-input`, or when stdin is closed.
+    fn confirm(&mut self, message: &str) -> Result<bool> {
+        if self.yes {
+            return Ok(true);
+        }
+
+        if self.no_input {
+            return Err(anyhow!(
+                "Confirmation is required, but `--no-input` is set. Pass `--yes` to confirm automatically."
+            ))
+            .kind(ErrorKind::Aborted);
+        }
+
+        let start = Instant::now();
+        println!("{message}");
+
+        let mut buf = String::with_capacity(4);
+
+        if stdin().read_line(&mut buf)? == 0 {
+            return Err(anyhow!(
+                "Confirmation is required, but no input is available. Pass `--yes` to confirm automatically."
+            ))
+            .kind(ErrorKind::Aborted);
+        }
+
+        *self.start_time -= start.elapsed();
+        Ok(buf.trim_end() == "Y")
+    }
+
+    /// Parses the project's metadata, and resolves its conflicts with `shared` settings.
+    ///
+    /// Settings, that change sources, can't be taken from the command line silently: sources of existing entries were extracted with the recorded ones, so entries wouldn't match and their translations would be dropped on append. The user has to confirm it.
+    fn resolve_metadata(
+        &mut self,
+        shared: &SharedArgs,
+    ) -> Result<Option<Metadata>> {
+        let Some(metadata) = parse_metadata(&self.metadata_file_path)? else {
+            return Ok(None);
+        };
+
+        let (metadata, overridden) = shared.resolve(metadata);
+
+        if !overridden.is_empty()
+            && !self.confirm(&format!(
+                "WARNING! Command line settings {} differ from the ones, that translation was read with. Sources of existing entries won't match the game data, and their translations will be dropped on append. Use `trim`, `romanize` or `dedup` commands to migrate the translation instead. Input 'Y' to continue.",
+                overridden.join(", ")
+            ))?
+        {
+            bail!(ErrorKind::Aborted);
+        }
+
+        Ok(Some(metadata))
+    }
+
+    /// Prints per-file summary table, unless logging is quieter than `info`.
+    fn print_summary(&self) -> Result<()> {
+        if self.verbosity >= LevelFilter::INFO {
+            report::print_summary(&self.translation_path)?;
+        }
+
+        Ok(())
+    }
+
     fn get_game_title(&self) -> Result<String> {
         Ok(if self.engine_type.is_new() {
             get_system_title(&read_to_string(&self.system_file_path)?)?
@@ -536,86 +1863,235 @@ impl<'a> Processor<'a> {
         })
     }
 
+    /// Resolves `--skip-event-names` patterns to indices of common events and troops, and appends them to `skip_events`.
+    fn resolve_skip_event_names(
+        &self,
+        patterns: &[String],
+        skip_events: &mut Vec<(RPGMFileType, Vec<u16>)>,
+    ) -> Result<()> {
+        if patterns.is_empty() {
+            return Ok(());
+        }
+
+        let extension = get_engine_extension(self.engine_type);
+
+        for (file_type, filename) in [
+            (RPGMFileType::Events, "CommonEvents"),
+            (RPGMFileType::Troops, "Troops"),
+        ] {
+            let path = self.source_path.join(format!("{filename}.{extension}"));
+
+            if !path.exists() {
+                continue;
+            }
+
+            let value = data::load_rpgm_file(&path, self.engine_type)?;
+            let indices: Vec<u16> = data::named_entries(&value)
+                .into_iter()
+                .filter(|(_, name)| {
+                    patterns
+                        .iter()
+                        .any(|pattern| matches_pattern(pattern, name))
+                })
+                .map(|(id, _)| id)
+                .collect();
+
+            if !indices.is_empty() {
+                skip_events.push((file_type, indices));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes map events, which names match `--skip-event-names` patterns, from a merged copy of the data, so the library doesn't read them.
+    fn remove_named_map_events(&mut self, patterns: &[String]) -> Result<()> {
+        if patterns.is_empty() {
+            return Ok(());
+        }
+
+        self.ensure_merged()?;
+
+        let mut removed = 0;
+
+        for (_, path) in data::map_files(&self.source_path, self.engine_type)? {
+            let mut map = data::load_rpgm_file(&path, self.engine_type)?;
+            let count = data::remove_named_events(&mut map, |name| {
+                patterns
+                    .iter()
+                    .any(|pattern| matches_pattern(pattern, name))
+            });
+
+            if count != 0 {
+                data::save_rpgm_file(&path, map, self.engine_type)?;
+                removed += count;
+            }
+        }
+
+        debug!("Skipped {removed} map events by their names.");
+        Ok(())
+    }
+
+    /// Appends maps, that aren't listed in `only_maps`, to `skip_maps`.
+    fn skip_other_maps(
+        &self,
+        only_maps: Option<&SkipMaps>,
+        skip_maps: &mut Vec<u16>,
+    ) -> Result<()> {
+        let Some(only_maps) = only_maps else {
+            return Ok(());
+        };
+
+        let only: HashSet<u16> = only_maps.0.iter().copied().collect();
+
+        skip_maps.extend(
+            data::map_files(&self.source_path, self.engine_type)?
+                .into_iter()
+                .map(|(id, _)| id)
+                .filter(|id| !only.contains(id)),
+        );
+
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_lines)]
     pub fn execute_read(
         &mut self,
         args: ReadArgs,
     ) -> Result<(), anyhow::Error> {
+        let parse_mode = args.shared.parse_mode();
+        let metadata = if args.shared.read_mode.is_append() {
+            self.resolve_metadata(&args.shared)?
+        } else {
+            None
+        };
+
         let SharedArgs {
             skip_files,
             read_mode,
             mut romanize,
             mut trim,
-            mut duplicate_mode,
+            duplicate_mode,
             mut disable_custom_processing,
-            skip_maps,
-            skip_events,
+            mut skip_maps,
+            mut skip_events,
+            skip_event_names,
             map_events,
+            mut common_event_names,
+            mut troop_names,
+            mut notes,
+            mut note_tags,
+            mut speaker_names,
+            romanize_table,
+            protected_terms,
+            lenient_marshal,
+            only_maps,
+            ..
         } = args.shared;
 
         let file_flags = FileFlags::all() & !skip_files.0;
+        let mut duplicate_mode =
+            duplicate_mode.unwrap_or(DuplicateMode::Remove);
         let silent = args.silent;
         let ignore = args.ignore;
         let skip_obsolete = args.skip_obsolete;
+        let mut anchors = args.anchors;
 
         let game_title = self.get_game_title()?;
         let game_type = get_game_type(&game_title, disable_custom_processing);
 
         let mut hashes = None;
+        let mut romanize_table_hash = None;
 
-        if read_mode.is_append()
-            && let Some(metadata) = parse_metadata(&self.metadata_file_path)?
-        {
+        if let Some(metadata) = metadata {
             Metadata {
                 romanize,
                 trim,
                 duplicate_mode,
                 disable_custom_processing,
                 hashes,
+                common_event_names,
+                troop_names,
+                romanize_table_hash,
+                notes,
+                note_tags,
+                speaker_names,
+                anchors,
+                archive_hash: _,
             } = metadata;
         }
 
         let hashes = hashes.unwrap_or_default();
 
-        if read_mode.is_force() && !silent {
-            let start = Instant::now();
-            println!(
-                "WARNING! Force mode will forcefully rewrite all your translation files. Input 'Y' to continue."
-            );
-
-            let mut buf = String::with_capacity(4);
-            stdin().read_line(&mut buf)?;
-
-            if buf.trim_end() != "Y" {
-                exit(0);
-            }
-
-            *self.start_time -= start.elapsed();
+        if read_mode.is_force()
+            && !silent
+            && !self.confirm(
+                "WARNING! Force mode will forcefully rewrite all your translation files. Input 'Y' to continue.",
+            )?
+        {
+            bail!(ErrorKind::Aborted);
         }
 
         if read_mode.is_append() && ignore && !self.ignore_file_path.exists() {
-            bail!(
+            return Err(anyhow!(
                 "`.rvpacker-ignore` file does not exist. Aborting execution."
-            );
+            ))
+            .kind(ErrorKind::TranslationMissing);
         }
 
-        if let Some(archive_path) = &self.archive_path
-            && !self.system_file_path.exists()
-        {
-            let archive_data = read(archive_path)?;
-            let decrypted_files = Decrypter::new().decrypt(&archive_data)?;
-
-            for file in decrypted_files {
-                let path = String::from_utf8_lossy(&file.path);
-                let output_file_path = self.input_dir.join(path.as_ref());
+        let mut archive_hash = None;
 
-                if let Some(parent) = output_file_path.parent() {
-                    create_dir_all(parent)?;
+        if let Some(archive_path) =
+            self.archive_path.as_ref().filter(|path| path.exists())
+        {
+            let recorded_hash = parse_metadata(&self.metadata_file_path)?
+                .and_then(|metadata| metadata.archive_hash);
+            let hash = archive::hash(archive_path)?;
+
+            // Data, that was extracted before hashes were recorded, is assumed to be up to date.
+            let changed = recorded_hash
+                .as_ref()
+                .is_some_and(|recorded_hash| *recorded_hash != hash);
+
+            if args.re_extract || changed || !self.system_file_path.exists() {
+                if changed {
+                    info!("Archive changed since the last extraction.");
                 }
 
-                write(output_file_path, file.data)?;
+                report::stage("Archive extraction", || {
+                    archive::extract(
+                        archive_path,
+                        &self.work_dir,
+                        args.archive.archive_encoding,
+                        args.archive.archive_key,
+                        &args.archive_filter,
+                    )
+                })?;
+                archive_hash = Some(hash);
+            } else {
+                debug!("Archive is already extracted, skipping extraction.");
+                archive_hash = recorded_hash;
             }
         }
 
+        self.exclude_unsupported_maps()?;
+        self.extract_opaque(lenient_marshal)?;
+        self.check_structure(parse_mode)?;
+        let romanize_table_hash = self.romanize_with_table(
+            romanize_table.as_deref(),
+            protected_terms.as_deref(),
+            romanize_table_hash.as_deref(),
+        )?;
+
+        report::stage("Event name resolution", || {
+            self.remove_named_map_events(&skip_event_names.0)?;
+            self.resolve_skip_event_names(
+                &skip_event_names.0,
+                &mut skip_events.0,
+            )
+        })?;
+        self.skip_other_maps(only_maps.as_ref(), &mut skip_maps.0)?;
+
         let mut flags = BaseFlags::empty();
         flags.set(BaseFlags::Romanize, romanize);
         flags.set(BaseFlags::Ignore, ignore);
@@ -634,11 +2110,76 @@ impl<'a> Processor<'a> {
             .map_events(map_events)
             .build();
 
-        reader.read(
-            &self.source_path,
-            &self.translation_path,
-            self.engine_type,
-        )?;
+        report::stage("Library read", || {
+            reader.read(
+                &self.source_path,
+                &self.translation_path,
+                self.engine_type,
+            )
+        })?;
+
+        create_dir_all(&self.translation_path)?;
+
+        report::stage("Extra fields read", || {
+            for kind in get_extra_kinds(
+                common_event_names,
+                troop_names,
+                notes,
+                speaker_names,
+            ) {
+                extra::read(
+                    kind,
+                    &self.source_path,
+                    &self.translation_path,
+                    self.engine_type,
+                    read_mode,
+                    &note_tags,
+                )?;
+            }
+
+            anyhow::Ok(())
+        })?;
+
+        if args.message_context {
+            report::stage("Message context annotation", || {
+                context::annotate(
+                    &self.source_path,
+                    &self.translation_path,
+                    self.engine_type,
+                )
+            })?;
+        }
+
+        if args.context_files {
+            report::stage("Context files write", || {
+                sidecar::write_context_files(&export::Project {
+                    source_path: &self.source_path,
+                    translation_path: &self.translation_path,
+                    engine_type: self.engine_type,
+                    untranslated_only: false,
+                })
+            })?;
+        }
+
+        if anchors {
+            report::stage("Anchors annotation", || {
+                anchors::annotate(
+                    &self.source_path,
+                    &self.translation_path,
+                    self.engine_type,
+                )
+            })?;
+        }
+
+        if args.resolve_codes {
+            report::stage("Control codes annotation", || {
+                codes::annotate(
+                    &self.source_path,
+                    &self.translation_path,
+                    self.engine_type,
+                )
+            })?;
+        }
 
         let metadata = Metadata {
             romanize,
@@ -646,120 +2187,650 @@ impl<'a> Processor<'a> {
             trim,
             duplicate_mode,
             hashes: Some(reader.hashes()),
+            common_event_names,
+            troop_names,
+            romanize_table_hash,
+            notes,
+            note_tags,
+            speaker_names,
+            anchors,
+            archive_hash,
         };
 
-        create_dir_all(&self.translation_path)?;
         write(&self.metadata_file_path, to_string(&metadata)?)?;
 
-        Ok(())
+        self.print_summary()
     }
 
-    pub fn execute_write(&self, args: SharedArgs) -> Result<(), anyhow::Error> {
-        if !self.translation_path.exists() {
-            bail!(
-                "`translation` directory in the input directory does not exist."
-            );
+    /// Reads game data in `source_path` from scratch to `snapshot_path`, with settings from `metadata` and the project's ignore file, so fresh translation files can be compared with existing ones. Returns hashes of the read.
+    fn read_snapshot(
+        &self,
+        source_path: &Path,
+        snapshot_path: &Path,
+        metadata: &Metadata,
+    ) -> Result<Vec<u128>> {
+        let game_title = self.get_game_title()?;
+
+        let mut flags = BaseFlags::empty();
+        flags.set(BaseFlags::Romanize, metadata.romanize);
+        flags.set(BaseFlags::Trim, metadata.trim);
+
+        create_dir_all(snapshot_path)?;
+
+        if self.ignore_file_path.exists() {
+            copy(
+                &self.ignore_file_path,
+                snapshot_path.join(RVPACKER_IGNORE_FILE),
+            )?;
+            flags.insert(BaseFlags::Ignore);
         }
 
-        let SharedArgs {
-            skip_files,
-            mut romanize,
-            mut trim,
-            mut duplicate_mode,
-            mut disable_custom_processing,
-            skip_maps,
-            skip_events,
-            ..
-        } = args;
+        let mut reader = ReaderBuilder::new()
+            .with_flags(flags)
+            .game_type(get_game_type(
+                &game_title,
+                metadata.disable_custom_processing,
+            ))
+            .duplicate_mode(metadata.duplicate_mode)
+            .build();
 
-        let file_flags = FileFlags::all() & !skip_files.0;
+        reader.read(
+            &source_path.to_path_buf(),
+            &snapshot_path.to_path_buf(),
+            self.engine_type,
+        )?;
 
-        if let Some(metadata) = parse_metadata(&self.metadata_file_path)? {
-            Metadata {
-                romanize,
-                trim,
-                duplicate_mode,
-                disable_custom_processing,
-                hashes: _,
-            } = metadata;
+        for kind in get_extra_kinds(
+            metadata.common_event_names,
+            metadata.troop_names,
+            metadata.notes,
+            metadata.speaker_names,
+        ) {
+            extra::read(
+                kind,
+                source_path,
+                snapshot_path,
+                self.engine_type,
+                ReadMode::Default(false),
+                &metadata.note_tags,
+            )?;
         }
 
-        let game_title = self.get_game_title()?;
+        Ok(reader.hashes())
+    }
 
-        let game_type = get_game_type(&game_title, disable_custom_processing);
+    /// Checks the structure of game data, before the library processes it.
+    ///
+    /// In lenient mode, repaired files replace the damaged ones in a merged copy of the data, so the game's own files stay untouched.
+    fn check_structure(&mut self, mode: ParseMode) -> Result<()> {
+        let repaired = report::stage("Structure check", || {
+            structure::check(&self.source_path, self.engine_type, mode)
+        })?;
 
-        let mut flags = BaseFlags::empty();
-        flags.set(BaseFlags::Romanize, romanize);
-        flags.set(BaseFlags::Trim, trim);
+        if repaired.is_empty() {
+            return Ok(());
+        }
 
-        WriterBuilder::new()
-            .with_files(file_flags)
-            .with_flags(flags)
-            .game_type(game_type)
-            .duplicate_mode(duplicate_mode)
-            .skip_maps(skip_maps.0)
-            .skip_events(skip_events.0)
-            .build()
-            .write(
-                &self.source_path,
-                &self.translation_path,
-                &self.output_dir.join("output"),
+        self.ensure_merged()?;
+
+        for (name, value) in repaired {
+            data::save_rpgm_file(
+                &self.source_path.join(&name),
+                value,
                 self.engine_type,
             )?;
+            warn!("{name}: Damaged parts are dropped.");
+        }
 
         Ok(())
     }
 
-    pub fn execute_purge(&self, args: PurgeArgs) -> Result<(), anyhow::Error> {
+    /// Leaves files, that the library would take for maps, but can't process, out of a merged copy of the data.
+    fn exclude_unsupported_maps(&mut self) -> Result<()> {
+        if !self.source_path.exists() {
+            return Ok(());
+        }
+
+        let unsupported =
+            data::unsupported_map_files(&self.source_path, self.engine_type)?;
+
+        if unsupported.is_empty() {
+            return Ok(());
+        }
+
+        self.ensure_merged()?;
+
+        for (name, reason) in unsupported {
+            remove_file(self.source_path.join(&name))?;
+            warn!("{name}: Skipped, since {reason}.");
+        }
+
+        Ok(())
+    }
+
+    /// Replaces Marshal constructs, that the library can't process, with placeholders in a merged copy of the data, if `lenient` is set.
+    fn extract_opaque(&mut self, lenient: bool) -> Result<()> {
+        if !lenient {
+            return Ok(());
+        }
+
+        if self.engine_type as u8 == EngineType::New as u8 {
+            warn!("`--lenient-marshal` only affects games on Marshal engines.");
+            return Ok(());
+        }
+
+        self.ensure_merged()?;
+        self.opaque = report::stage("Marshal extraction", || {
+            Opaque::extract(
+                &self.source_path,
+                get_engine_extension(self.engine_type),
+            )
+        })?;
+
+        Ok(())
+    }
+
+    /// Makes the data a merged copy, if it isn't one already, so it can be modified without touching the game's own files.
+    fn ensure_merged(&mut self) -> Result<()> {
+        if self.layers.is_none() {
+            let layers =
+                Layers::merge(&self.source_path, &[], Layers::temp_path())?;
+            self.source_path = layers.data_path(&self.source_path);
+            self.layers = Some(layers);
+        }
+
+        Ok(())
+    }
+
+    /// Romanizes a merged copy of the data with the table at `table_path`, that skips protected terms at `terms_path`. `recorded_hash` is the hash of the table, that the translation was read with, and the table must match it. Returns the hash of the table.
+    fn romanize_with_table(
+        &mut self,
+        table_path: Option<&Path>,
+        terms_path: Option<&Path>,
+        recorded_hash: Option<&str>,
+    ) -> Result<Option<String>> {
+        let terms = terms_path.map(terms::ProtectedTerms::load).transpose()?;
+        let table = table_path
+            .map(|path| {
+                romanize::Table::load(path).map(|table| match &terms {
+                    Some(terms) => table.protect(terms),
+                    None => table,
+                })
+            })
+            .transpose()?;
+
+        match (recorded_hash, &table) {
+            (Some(_), None) => {
+                return Err(anyhow!(
+                    "Translation was read with a romanization table. Pass the same table with `--romanize-table`."
+                ))
+                .kind(ErrorKind::ValidationFailed);
+            }
+            (Some(hash), Some(table)) if table.hash() != hash => {
+                return Err(anyhow!(
+                    "Romanization table or its protected terms differ from the ones, that translation was read with. Their hash is {}, expected {hash}.",
+                    table.hash()
+                ))
+                .kind(ErrorKind::ValidationFailed);
+            }
+            _ => {}
+        }
+
+        let Some(table) = table else {
+            return Ok(None);
+        };
+
+        self.ensure_merged()?;
+
+        let changed = report::stage("Romanization", || {
+            table.romanize_data(&self.source_path, self.engine_type)
+        })?;
+        debug!("Romanized text in {changed} data files.");
+
+        Ok(Some(table.hash().to_string()))
+    }
+
+    /// Runs `f` with a fresh snapshot of the current game data in a temporary directory, and removes the directory afterwards.
+    fn with_snapshot<T>(
+        &self,
+        f: impl FnOnce(&Path, Vec<u128>) -> Result<T>,
+    ) -> Result<T> {
+        let metadata =
+            parse_metadata(&self.metadata_file_path)?.unwrap_or_default();
+        self.with_snapshot_of(&metadata, f)
+    }
+
+    /// Same as [`Self::with_snapshot`], but reads the snapshot with settings from `metadata` instead of the project's ones.
+    fn with_snapshot_of<T>(
+        &self,
+        metadata: &Metadata,
+        f: impl FnOnce(&Path, Vec<u128>) -> Result<T>,
+    ) -> Result<T> {
+        self.with_snapshot_from(&self.source_path, metadata, f)
+    }
+
+    /// Same as [`Self::with_snapshot_of`], but reads game data in `source_path`, e.g. of another copy of the game.
+    fn with_snapshot_from<T>(
+        &self,
+        source_path: &Path,
+        metadata: &Metadata,
+        f: impl FnOnce(&Path, Vec<u128>) -> Result<T>,
+    ) -> Result<T> {
+        let snapshot_path = std::env::temp_dir()
+            .join(format!("rvpacker-snapshot-{}", std::process::id()));
+
+        let result = report::stage("Snapshot read", || {
+            self.read_snapshot(source_path, &snapshot_path, metadata)
+        })
+        .and_then(|hashes| f(&snapshot_path, hashes));
+
+        let _ = remove_dir_all(&snapshot_path);
+        result
+    }
+
+    /// Reads a snapshot with settings from `metadata`, and returns its parsed translation files by name, with hashes of the read.
+    fn read_snapshot_files(
+        &self,
+        metadata: &Metadata,
+    ) -> Result<(SnapshotFiles, Vec<u128>)> {
+        self.read_snapshot_files_from(&self.source_path, metadata)
+    }
+
+    /// Same as [`Self::read_snapshot_files`], but reads game data in `source_path`.
+    fn read_snapshot_files_from(
+        &self,
+        source_path: &Path,
+        metadata: &Metadata,
+    ) -> Result<(SnapshotFiles, Vec<u128>)> {
+        self.with_snapshot_from(
+            source_path,
+            metadata,
+            |snapshot_path, hashes| {
+                let mut files = Vec::new();
+
+                for name in translation::translation_files(snapshot_path)? {
+                    let file = TranslationFile::parse(&read_to_string(
+                        snapshot_path.join(&name),
+                    )?);
+                    files.push((name, file));
+                }
+
+                Ok((files, hashes))
+            },
+        )
+    }
+
+    /// Parses the project's translation file `name`, if it exists.
+    fn read_translation_file(
+        &self,
+        name: &str,
+    ) -> Result<Option<TranslationFile>> {
+        let path = self.translation_path.join(name);
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        Ok(Some(TranslationFile::parse(&read_to_string(path)?)))
+    }
+
+    #[allow(clippy::too_many_lines)]
+    pub fn execute_write(
+        &mut self,
+        args: WriteArgs,
+    ) -> Result<(), anyhow::Error> {
+        if !self.translation_path.exists() {
+            return Err(anyhow!(
+                "`translation` directory in the input directory does not exist."
+            ))
+            .kind(ErrorKind::TranslationMissing);
+        }
+
+        let parse_mode = args.shared.parse_mode();
+        let metadata = self.resolve_metadata(&args.shared)?;
+
+        let SharedArgs {
+            skip_files,
+            mut romanize,
+            mut trim,
+            duplicate_mode,
+            mut disable_custom_processing,
+            mut skip_maps,
+            mut skip_events,
+            skip_event_names,
+            mut common_event_names,
+            mut troop_names,
+            mut notes,
+            mut note_tags,
+            mut speaker_names,
+            romanize_table,
+            protected_terms,
+            lenient_marshal,
+            only_maps,
+            ..
+        } = args.shared;
+
+        let file_flags = FileFlags::all() & !skip_files.0;
+        let mut duplicate_mode =
+            duplicate_mode.unwrap_or(DuplicateMode::Remove);
+        let mut romanize_table_hash = None;
+
+        if let Some(metadata) = metadata {
+            Metadata {
+                romanize,
+                trim,
+                duplicate_mode,
+                disable_custom_processing,
+                hashes: _,
+                common_event_names,
+                troop_names,
+                romanize_table_hash,
+                notes,
+                note_tags,
+                speaker_names,
+                anchors: _,
+                archive_hash: _,
+            } = metadata;
+        }
+
+        self.exclude_unsupported_maps()?;
+        self.extract_opaque(lenient_marshal)?;
+        self.check_structure(parse_mode)?;
+        self.romanize_with_table(
+            romanize_table.as_deref(),
+            protected_terms.as_deref(),
+            romanize_table_hash.as_deref(),
+        )?;
+
+        let game_title = self.get_game_title()?;
+
+        let game_type = get_game_type(&game_title, disable_custom_processing);
+
+        self.resolve_skip_event_names(&skip_event_names.0, &mut skip_events.0)?;
+        self.skip_other_maps(only_maps.as_ref(), &mut skip_maps.0)?;
+
+        let mut flags = BaseFlags::empty();
+        flags.set(BaseFlags::Romanize, romanize);
+        flags.set(BaseFlags::Trim, trim);
+
+        let layout = if args.full_output {
+            layout::Layout::Game
+        } else {
+            args.layout
+        };
+
+        let output_root = self.output_dir.join("output");
+        let output_path = layout.library_path(&output_root, &self.input_dir);
+
+        let mut writer = WriterBuilder::new()
+            .with_files(file_flags)
+            .with_flags(flags)
+            .game_type(game_type)
+            .duplicate_mode(duplicate_mode)
+            .skip_maps(skip_maps.0)
+            .skip_events(skip_events.0)
+            .build();
+
+        let staged = args
+            .rules
+            .map(|rules_path| {
+                report::stage("Write rules", || {
+                    rules::Rules::load(&rules_path)?.stage(
+                        &self.translation_path,
+                        std::env::temp_dir().join(format!(
+                            "rvpacker-rules-{}",
+                            std::process::id()
+                        )),
+                    )
+                })
+            })
+            .transpose()?;
+        let translation_path = staged
+            .as_ref()
+            .map_or(self.translation_path.as_path(), rules::Staged::path);
+
+        let plurals_staged = args
+            .plurals
+            .zip(args.plural_language)
+            .map(|(mode, language)| {
+                report::stage("Plural forms", || {
+                    plural::Plurals::new(&language, &mode)?.stage(
+                        translation_path,
+                        std::env::temp_dir().join(format!(
+                            "rvpacker-plurals-{}",
+                            std::process::id()
+                        )),
+                    )
+                })
+            })
+            .transpose()?;
+        let translation_path = plurals_staged
+            .as_ref()
+            .map_or(translation_path, rules::Staged::path);
+
+        report::stage("Library write", || {
+            writer.write(
+                &self.source_path,
+                &translation_path.to_path_buf(),
+                &output_path,
+                self.engine_type,
+            )
+        })?;
+
+        let output_data_path = output_path.join(if self.engine_type.is_new() {
+            "data"
+        } else {
+            "Data"
+        });
+
+        report::stage("Extra fields write", || {
+            for kind in get_extra_kinds(
+                common_event_names,
+                troop_names,
+                notes,
+                speaker_names,
+            ) {
+                extra::write_back(
+                    kind,
+                    &self.source_path,
+                    translation_path,
+                    &output_data_path,
+                    self.engine_type,
+                    &note_tags,
+                )
+                .kind(ErrorKind::PartialFailure)?;
+            }
+
+            anyhow::Ok(())
+        })?;
+
+        self.opaque.restore(&output_data_path)?;
+
+        if let Some(layers) = &self.layers {
+            layers.split_output(&output_data_path, &output_path)?;
+        }
+
+        if let Some(mapped) = &self.mapped {
+            mapped.restore(&output_data_path)?;
+        }
+
+        if layout == layout::Layout::Game {
+            layout::copy_game_files(
+                &output_root,
+                &output_data_path,
+                &self.input_dir,
+                self.engine_type,
+            )?;
+        }
+
+        if args.full_output {
+            let placed = report::stage("Full output", || {
+                layout::fill_output(
+                    &output_root,
+                    &output_data_path,
+                    &self.input_dir,
+                    &self.source_path,
+                    &[&output_root, &self.translation_path],
+                )
+            })?;
+            info!("Placed {placed} unchanged game files in the output.");
+        }
+
+        self.print_summary()
+    }
+
+    #[allow(clippy::too_many_lines)]
+    pub fn execute_purge(
+        &mut self,
+        args: PurgeArgs,
+    ) -> Result<(), anyhow::Error> {
+        if !self.translation_path.exists() {
+            return Err(anyhow!(
+                "`translation` directory in the input directory does not exist."
+            ))
+            .kind(ErrorKind::TranslationMissing);
+        }
+
+        let parse_mode = args.shared.parse_mode();
+        let metadata = self.resolve_metadata(&args.shared)?;
+
         let SharedArgs {
             skip_files,
             mut romanize,
             mut trim,
-            mut duplicate_mode,
+            duplicate_mode,
             mut disable_custom_processing,
-            skip_maps,
-            skip_events,
+            mut skip_maps,
+            mut skip_events,
+            skip_event_names,
+            mut common_event_names,
+            mut troop_names,
+            mut notes,
+            mut speaker_names,
+            romanize_table,
+            protected_terms,
+            lenient_marshal,
+            only_maps,
             ..
         } = args.shared;
 
         let file_flags = FileFlags::all() & !skip_files.0;
+        let mut duplicate_mode =
+            duplicate_mode.unwrap_or(DuplicateMode::Remove);
         let create_ignore = args.create_ignore;
+        let mut romanize_table_hash = None;
 
-        if let Some(metadata) = parse_metadata(&self.metadata_file_path)? {
+        if let Some(metadata) = metadata {
             Metadata {
                 romanize,
                 trim,
                 duplicate_mode,
                 disable_custom_processing,
                 hashes: _,
+                common_event_names,
+                troop_names,
+                romanize_table_hash,
+                notes,
+                note_tags: _,
+                speaker_names,
+                anchors: _,
+                archive_hash: _,
             } = metadata;
         }
 
+        if args.machine_translated {
+            return report::stage("Machine translation purge", || {
+                purge::machine_translations(&self.translation_path)
+            });
+        }
+
+        if args.stale_maps {
+            return report::stage("Stale maps purge", || {
+                purge::stale_maps(
+                    &self.source_path,
+                    &self.translation_path,
+                    self.engine_type,
+                    args.stale_archive.as_deref(),
+                )
+            });
+        }
+
+        if let Some(pattern) = &args.pattern {
+            let extra_files: Vec<&str> = get_extra_kinds(
+                common_event_names,
+                troop_names,
+                notes,
+                speaker_names,
+            )
+            .into_iter()
+            .map(ExtraKind::translation_file)
+            .collect();
+
+            return report::stage("Pattern purge", || {
+                purge::by_pattern(
+                    &self.translation_path,
+                    pattern,
+                    file_flags,
+                    &extra_files,
+                    create_ignore,
+                )
+            });
+        }
+
+        self.exclude_unsupported_maps()?;
+        self.extract_opaque(lenient_marshal)?;
+        self.check_structure(parse_mode)?;
+        self.romanize_with_table(
+            romanize_table.as_deref(),
+            protected_terms.as_deref(),
+            romanize_table_hash.as_deref(),
+        )?;
+
         let game_title = self.get_game_title()?;
         let game_type = get_game_type(&game_title, disable_custom_processing);
 
+        self.resolve_skip_event_names(&skip_event_names.0, &mut skip_events.0)?;
+        self.skip_other_maps(only_maps.as_ref(), &mut skip_maps.0)?;
+
         let mut flags: BaseFlags = BaseFlags::empty();
         flags.set(BaseFlags::Romanize, romanize);
         flags.set(BaseFlags::Trim, trim);
         flags.set(BaseFlags::CreateIgnore, create_ignore);
 
-        PurgerBuilder::new()
+        let mut purger = PurgerBuilder::new()
             .with_files(file_flags)
             .with_flags(flags)
             .game_type(game_type)
             .duplicate_mode(duplicate_mode)
             .skip_maps(skip_maps.0)
             .skip_events(skip_events.0)
-            .build()
-            .purge(
+            .build();
+
+        report::stage("Library purge", || {
+            purger.purge(
                 &self.source_path,
                 &self.translation_path,
                 self.engine_type,
-            )?;
+            )
+        })?;
+
+        report::stage("Extra fields purge", || {
+            for kind in get_extra_kinds(
+                common_event_names,
+                troop_names,
+                notes,
+                speaker_names,
+            ) {
+                extra::purge(kind, &self.translation_path)?;
+            }
+
+            anyhow::Ok(())
+        })?;
 
         Ok(())
     }
 
+    #[allow(clippy::too_many_lines)]
     pub fn execute_generic(
         &self,
         subcommand: &GenericSubcommand,
@@ -924,34 +2995,1632 @@ impl<'a> Processor<'a> {
 
         Ok(())
     }
-}
 
-fn main() -> Result<()> {
-    let mut start_time = Instant::now();
-    let mut cli = Cli::parse();
-
-    tracing_subscriber::fmt()
-        .without_time()
-        .with_target(false)
-        .with_level(true)
-        .with_thread_names(false)
-        .with_thread_ids(false)
-        .with_ansi(true)
-        .with_max_level(cli.verbosity)
-        .init();
+    pub fn execute_export(
+        &self,
+        args: ExportArgs,
+    ) -> Result<(), anyhow::Error> {
+        if !self.translation_path.exists() {
+            return Err(anyhow!(
+                "`translation` directory in the input directory does not exist."
+            ))
+            .kind(ErrorKind::TranslationMissing);
+        }
+
+        let export_path = args
+            .export_dir
+            .unwrap_or_else(|| self.output_dir.join("export"));
+
+        export::export(
+            args.format,
+            &export::Project {
+                source_path: &self.source_path,
+                translation_path: &self.translation_path,
+                engine_type: self.engine_type,
+                untranslated_only: args.untranslated_only,
+            },
+            &export::Languages {
+                source: &args.source_language,
+                target: args.target_language.as_deref(),
+            },
+            &export_path,
+        )?;
+
+        if !args.verify {
+            return Ok(());
+        }
 
-    let mut processor = Processor::new(&mut cli, &mut start_time)?;
+        let (verified, mismatches) = export::verify(
+            args.format,
+            &self.translation_path,
+            &export_path,
+            args.target_language.as_deref(),
+        )?;
 
-    match cli.command {
-        Command::Read(args) => processor.execute_read(args)?,
-        Command::Write(args) => processor.execute_write(args)?,
-        Command::Purge(args) => processor.execute_purge(args)?,
-        Command::Generic { subcommand } => {
-            processor.execute_generic(&subcommand)?
+        if mismatches != 0 {
+            return Err(anyhow!(
+                "{mismatches} entries don't survive the round trip through exported files."
+            ))
+            .kind(ErrorKind::ValidationFailed);
         }
-        Command::Json { subcommand } => processor.execute_json(&subcommand)?,
+
+        info!(
+            "Verified the round trip. All {verified} translations are the same after import."
+        );
+        Ok(())
     }
 
-    println!("Elapsed: {:.2}s", start_time.elapsed().as_secs_f32());
-    Ok(())
+    /// Asks, what to do with the imported translation of `conflict`. Returns the resolution, and whether to apply it to the rest of conflicts. With `--yes`, the conflict is marked fuzzy, so neither translation is lost.
+    fn ask_conflict(
+        &mut self,
+        conflict: &Conflict,
+    ) -> Result<(Resolution, bool)> {
+        if self.yes {
+            return Ok((Resolution::MarkFuzzy, false));
+        }
+
+        let location = conflict.section.map_or_else(String::new, |section| {
+            format!(" (section {section})")
+        });
+
+        if self.no_input {
+            return Err(anyhow!(
+                "{}{location}: `{}` has a different existing translation, but `--no-input` is set. Pass `--on-conflict` with a policy, that doesn't prompt.",
+                conflict.file,
+                conflict.source
+            ))
+            .kind(ErrorKind::Aborted);
+        }
+
+        let start = Instant::now();
+        println!(
+            "{}{location}: `{}` is already translated differently:\n  Existing: {}\n  Imported: {}",
+            conflict.file,
+            conflict.source,
+            conflict.existing,
+            conflict.imported
+        );
+
+        let answer = loop {
+            println!(
+                "Input `k` to keep the existing translation, `o` to overwrite it, or `f` to mark it fuzzy. Uppercase letters apply to the rest of conflicts."
+            );
+
+            let mut buf = String::with_capacity(4);
+
+            if stdin().read_line(&mut buf)? == 0 {
+                return Err(anyhow!(
+                    "Choice is required, but no input is available. Pass `--on-conflict` with a policy, that doesn't prompt."
+                ))
+                .kind(ErrorKind::Aborted);
+            }
+
+            let answer = match buf.trim() {
+                "k" => (Resolution::Keep, false),
+                "K" => (Resolution::Keep, true),
+                "o" => (Resolution::Overwrite, false),
+                "O" => (Resolution::Overwrite, true),
+                "f" => (Resolution::MarkFuzzy, false),
+                "F" => (Resolution::MarkFuzzy, true),
+                _ => continue,
+            };
+
+            break answer;
+        };
+
+        *self.start_time -= start.elapsed();
+        Ok(answer)
+    }
+
+    /// Returns resolver of import conflicts, that follows `policy`.
+    fn conflict_resolver(
+        &mut self,
+        policy: ConflictPolicy,
+    ) -> impl FnMut(&Conflict) -> Result<Resolution> + '_ {
+        let mut rest = None;
+
+        move |conflict| match policy {
+            ConflictPolicy::KeepExisting => Ok(Resolution::Keep),
+            ConflictPolicy::Overwrite => Ok(Resolution::Overwrite),
+            ConflictPolicy::MarkFuzzy => Ok(Resolution::MarkFuzzy),
+            ConflictPolicy::Prompt => {
+                if let Some(resolution) = rest {
+                    return Ok(resolution);
+                }
+
+                let (resolution, all) = self.ask_conflict(conflict)?;
+
+                if all {
+                    rest = Some(resolution);
+                }
+
+                Ok(resolution)
+            }
+        }
+    }
+
+    pub fn execute_import(
+        &mut self,
+        args: ImportArgs,
+    ) -> Result<(), anyhow::Error> {
+        if !self.translation_path.exists() {
+            return Err(anyhow!(
+                "`translation` directory in the input directory does not exist."
+            ))
+            .kind(ErrorKind::TranslationMissing);
+        }
+
+        let import_path = args
+            .import_dir
+            .unwrap_or_else(|| self.output_dir.join("export"));
+
+        let translation_path = self.translation_path.clone();
+        let changed = export::import(
+            args.format,
+            &translation_path,
+            &import_path,
+            args.target_language.as_deref(),
+            self.conflict_resolver(args.on_conflict),
+        )?;
+
+        if let Some(translator) = &args.translator {
+            attribution::record(&translation_path, translator, &changed)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn execute_overflow(
+        &self,
+        args: &OverflowArgs,
+    ) -> Result<(), anyhow::Error> {
+        if !self.translation_path.exists() {
+            return Err(anyhow!(
+                "`translation` directory in the input directory does not exist."
+            ))
+            .kind(ErrorKind::TranslationMissing);
+        }
+
+        overflow::check(
+            &self.source_path,
+            &self.translation_path,
+            self.engine_type,
+            &args.limits,
+        )
+    }
+
+    pub fn execute_attribution(
+        &self,
+        args: &AttributionArgs,
+    ) -> Result<(), anyhow::Error> {
+        if !self.translation_path.exists() {
+            return Err(anyhow!(
+                "`translation` directory in the input directory does not exist."
+            ))
+            .kind(ErrorKind::TranslationMissing);
+        }
+
+        attribution::show(
+            &self.translation_path,
+            &attribution::Filter {
+                pattern: args.pattern.as_ref(),
+                translator: args.translator.as_deref(),
+                files: &args.files,
+            },
+        )
+    }
+
+    pub fn execute_replace(
+        &self,
+        args: &ReplaceArgs,
+    ) -> Result<(), anyhow::Error> {
+        if !self.translation_path.exists() {
+            return Err(anyhow!(
+                "`translation` directory in the input directory does not exist."
+            ))
+            .kind(ErrorKind::TranslationMissing);
+        }
+
+        replace::replace(
+            &self.translation_path,
+            &replace::Options {
+                pattern: &args.pattern,
+                replacement: &args.replacement,
+                files: &args.files,
+                dry_run: args.dry_run,
+                allow_placeholder_mismatch: args.allow_placeholder_mismatch,
+            },
+        )
+    }
+
+    pub fn execute_lint(&self, args: &LintArgs) -> Result<(), anyhow::Error> {
+        if !self.translation_path.exists() {
+            return Err(anyhow!(
+                "`translation` directory in the input directory does not exist."
+            ))
+            .kind(ErrorKind::TranslationMissing);
+        }
+
+        match &args.subcommand {
+            Some(LintSubcommand::Consistency { files }) => {
+                let metadata = parse_metadata(&self.metadata_file_path)?
+                    .unwrap_or_default();
+
+                if !metadata.duplicate_mode.is_allow() {
+                    info!(
+                        "Duplicates were removed in read, so only sources, that repeat across files, are compared."
+                    );
+                }
+
+                lint::consistency(
+                    &self.translation_path,
+                    &lint::Options { files, fix: false },
+                )
+            }
+            None => lint::lint(
+                &self.translation_path,
+                &lint::Options {
+                    files: &args.files,
+                    fix: args.fix,
+                },
+            ),
+        }
+    }
+
+    pub fn execute_remap(&self, args: &RemapArgs) -> Result<(), anyhow::Error> {
+        let name = if Path::new(&args.file)
+            .extension()
+            .is_some_and(|ext| ext == "txt")
+        {
+            args.file.clone()
+        } else {
+            format!("{}.txt", args.file)
+        };
+        let path = self.translation_path.join(&name);
+
+        if !path.exists() {
+            return Err(anyhow!("{name}: Translation file does not exist."))
+                .kind(ErrorKind::TranslationMissing);
+        }
+
+        let mut file = TranslationFile::parse(&read_to_string(&path)?);
+
+        let mapping: Vec<(u16, u16)> = if let Some(mapping) = &args.mapping {
+            mapping.0.clone()
+        } else {
+            self.with_snapshot(|snapshot_path, _| {
+                let snapshot_file_path = snapshot_path.join(&name);
+
+                if !snapshot_file_path.exists() {
+                    bail!("{name}: Current game data doesn't produce this file.");
+                }
+
+                let snapshot =
+                    TranslationFile::parse(&read_to_string(snapshot_file_path)?);
+
+                Ok(remap::derive(&file, &snapshot, args.threshold)
+                    .into_iter()
+                    .map(|(old_id, new_id, similarity)| {
+                        if old_id != new_id {
+                            info!(
+                                "{name}: Section {old_id} matches section {new_id} by {:.0}%.",
+                                similarity * 100.0
+                            );
+                        }
+
+                        (old_id, new_id)
+                    })
+                    .collect())
+            })?
+        };
+
+        let mapping: HashMap<u16, u16> = mapping
+            .into_iter()
+            .filter(|(old_id, new_id)| old_id != new_id)
+            .collect();
+
+        if mapping.is_empty() {
+            info!("{name}: No sections to remap.");
+            return Ok(());
+        }
+
+        if args.dry_run {
+            let mut pairs: Vec<_> = mapping.iter().collect();
+            pairs.sort_unstable();
+
+            for (old_id, new_id) in pairs {
+                println!("{name}: {old_id} -> {new_id}");
+            }
+
+            return Ok(());
+        }
+
+        remap::apply(&mut file, &mapping)
+            .with_context(|| format!("{name}: Mapping can't be applied."))?;
+        write(&path, file.serialize())?;
+
+        // Ignore entries of extra fields' files aren't tracked, since library doesn't read these files.
+        let is_extra = [ExtraKind::CommonEventNames, ExtraKind::TroopNames]
+            .iter()
+            .any(|kind| kind.translation_file() == name);
+
+        if !is_extra && self.ignore_file_path.exists() {
+            let mut ignore_file = IgnoreFile::load(&self.translation_path);
+            ignore_file.remap(RPGMFileType::from_filename(&name), &mapping);
+            ignore_file.save(&self.translation_path)?;
+        }
+
+        info!("{name}: Successfully remapped {} sections.", mapping.len());
+        Ok(())
+    }
+
+    pub fn execute_upgrade(
+        &self,
+        args: UpgradeArgs,
+    ) -> Result<(), anyhow::Error> {
+        if !self.translation_path.exists() {
+            return Err(anyhow!(
+                "`translation` directory in the input directory does not exist."
+            ))
+            .kind(ErrorKind::TranslationMissing);
+        }
+
+        let report_path = args
+            .report
+            .unwrap_or_else(|| self.output_dir.join("upgrade-report.txt"));
+
+        let (files, hashes) = self.with_snapshot(|snapshot_path, hashes| {
+            let mut files = Vec::new();
+
+            for name in translation::translation_files(snapshot_path)? {
+                let mut new = TranslationFile::parse(&read_to_string(
+                    snapshot_path.join(&name),
+                )?);
+
+                let old_path = self.translation_path.join(&name);
+                let old = if old_path.exists() {
+                    TranslationFile::parse(&read_to_string(old_path)?)
+                } else {
+                    TranslationFile::default()
+                };
+
+                let report = upgrade::migrate(&old, &mut new, args.threshold);
+
+                info!(
+                    "{name}: {} kept, {} moved, {} fuzzy, {} unmatched.",
+                    report.exact,
+                    report.moved,
+                    report.fuzzy.len(),
+                    report.lost.len()
+                );
+
+                files.push((name, new, report));
+            }
+
+            Ok((files, hashes))
+        })?;
+
+        let reports: Vec<(String, upgrade::FileReport)> = if args.dry_run {
+            files
+                .into_iter()
+                .map(|(name, _, report)| (name, report))
+                .collect()
+        } else {
+            let mut reports = Vec::with_capacity(files.len());
+
+            for (name, file, report) in files {
+                write(self.translation_path.join(&name), file.serialize())?;
+                info!("{name}: Successfully upgraded.");
+                reports.push((name, report));
+            }
+
+            // Translation files now correspond to the current game data.
+            if let Some(mut metadata) =
+                parse_metadata(&self.metadata_file_path)?
+            {
+                metadata.hashes = Some(hashes);
+                write(&self.metadata_file_path, to_string(&metadata)?)?;
+            }
+
+            reports
+        };
+
+        write(&report_path, upgrade::format_report(&reports))?;
+        info!("Report is written to {}.", report_path.display());
+
+        Ok(())
+    }
+
+    /// Asks to choose one of `candidates` translations of `source`. Returns the index of the chosen one. With `--yes`, the first one is chosen.
+    fn choose_translation(
+        &mut self,
+        file: &str,
+        source: &str,
+        candidates: &[&str],
+    ) -> Result<usize> {
+        if self.yes {
+            return Ok(0);
+        }
+
+        if self.no_input {
+            return Err(anyhow!(
+                "{file}: `{source}` has conflicting translations, but `--no-input` is set. Pass `--yes` to choose the first one automatically."
+            ))
+            .kind(ErrorKind::Aborted);
+        }
+
+        let start = Instant::now();
+        println!("{file}: `{source}` is translated differently:");
+
+        for (index, candidate) in candidates.iter().enumerate() {
+            println!("  {}. {candidate}", index + 1);
+        }
+
+        let index = loop {
+            println!("Input the number of the translation to use.");
+
+            let mut buf = String::with_capacity(4);
+
+            if stdin().read_line(&mut buf)? == 0 {
+                return Err(anyhow!(
+                    "Choice is required, but no input is available. Pass `--yes` to choose the first translation automatically."
+                ))
+                .kind(ErrorKind::Aborted);
+            }
+
+            if let Ok(number) = buf.trim().parse::<usize>()
+                && (1..=candidates.len()).contains(&number)
+            {
+                break number - 1;
+            }
+        };
+
+        *self.start_time -= start.elapsed();
+        Ok(index)
+    }
+
+    pub fn execute_dedup(
+        &mut self,
+        args: &DedupArgs,
+    ) -> Result<(), anyhow::Error> {
+        if !self.translation_path.exists() {
+            return Err(anyhow!(
+                "`translation` directory in the input directory does not exist."
+            ))
+            .kind(ErrorKind::TranslationMissing);
+        }
+
+        let mut metadata =
+            parse_metadata(&self.metadata_file_path)?.unwrap_or_default();
+        if let Some(duplicate_mode) = args.duplicate_mode {
+            metadata.duplicate_mode = duplicate_mode;
+        }
+
+        let duplicate_mode = metadata.duplicate_mode;
+
+        let (files, hashes) = self.read_snapshot_files(&metadata)?;
+
+        for (name, mut new) in files {
+            // Extra files don't depend on the duplicate mode.
+            if extra_translation_files().contains(&name.as_str()) {
+                continue;
+            }
+
+            let Some(old) = self.read_translation_file(&name)? else {
+                continue;
+            };
+
+            let report = dedup::reconcile(
+                &old,
+                &mut new,
+                duplicate_mode.is_allow(),
+                |source, candidates| {
+                    self.choose_translation(&name, source, candidates)
+                },
+            )?;
+
+            info!(
+                "{name}: {} entries instead of {}, {} translations carried over, {} conflicts.",
+                new.entries().count(),
+                old.entries().count(),
+                report.carried,
+                report.conflicts
+            );
+
+            if !args.dry_run {
+                write(self.translation_path.join(&name), new.serialize())?;
+            }
+        }
+
+        if !args.dry_run {
+            metadata.hashes = Some(hashes);
+            write(&self.metadata_file_path, to_string(&metadata)?)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn execute_trim(&self, args: &TrimArgs) -> Result<(), anyhow::Error> {
+        if !self.translation_path.exists() {
+            return Err(anyhow!(
+                "`translation` directory in the input directory does not exist."
+            ))
+            .kind(ErrorKind::TranslationMissing);
+        }
+
+        let mut metadata =
+            parse_metadata(&self.metadata_file_path)?.unwrap_or_default();
+
+        if metadata.trim != args.undo {
+            info!(
+                "Translation is already {}trimmed.",
+                if args.undo { "un" } else { "" }
+            );
+            return Ok(());
+        }
+
+        metadata.trim = !args.undo;
+
+        let (files, hashes) = self.read_snapshot_files(&metadata)?;
+
+        for (name, mut new) in files {
+            let Some(old) = self.read_translation_file(&name)? else {
+                continue;
+            };
+
+            let carried = trim::reconcile(&old, &mut new);
+
+            info!(
+                "{name}: {} entries instead of {}, {carried} translations carried over.",
+                new.entries().count(),
+                old.entries().count(),
+            );
+
+            if !args.dry_run {
+                write(self.translation_path.join(&name), new.serialize())?;
+            }
+        }
+
+        if !args.dry_run {
+            metadata.hashes = Some(hashes);
+            write(&self.metadata_file_path, to_string(&metadata)?)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn execute_romanize(
+        &self,
+        args: &RomanizeArgs,
+    ) -> Result<(), anyhow::Error> {
+        if !self.translation_path.exists() {
+            return Err(anyhow!(
+                "`translation` directory in the input directory does not exist."
+            ))
+            .kind(ErrorKind::TranslationMissing);
+        }
+
+        let mut metadata =
+            parse_metadata(&self.metadata_file_path)?.unwrap_or_default();
+
+        if metadata.romanize_table_hash.is_some() {
+            return Err(anyhow!(
+                "Translation was read with a romanization table, which can't be applied retroactively. Re-read it with `--read-mode force`."
+            ))
+            .kind(ErrorKind::ValidationFailed);
+        }
+
+        if metadata.romanize != args.undo {
+            info!(
+                "Translation is already {}romanized.",
+                if args.undo { "un" } else { "" }
+            );
+            return Ok(());
+        }
+
+        // Entries of both reads are at the same positions only in `allow` mode.
+        let duplicate_mode = metadata.duplicate_mode;
+        metadata.duplicate_mode = DuplicateMode::Allow;
+
+        let (current, _) = report::stage("Current read", || {
+            self.read_snapshot_files(&metadata)
+        })?;
+
+        metadata.romanize = !args.undo;
+
+        let (target, target_hashes) = report::stage("Romanized read", || {
+            self.read_snapshot_files(&metadata)
+        })?;
+
+        let mut sources: HashMap<String, reromanize::Sources> = HashMap::new();
+
+        for (name, current) in &current {
+            let Some((_, target)) =
+                target.iter().find(|(target_name, _)| target_name == name)
+            else {
+                continue;
+            };
+
+            if let Some(file_sources) = reromanize::align(current, target) {
+                sources.insert(name.clone(), file_sources);
+            } else {
+                warn!(
+                    "{name}: Entries don't align, translations are carried by unchanged sources only."
+                );
+            }
+        }
+
+        metadata.duplicate_mode = duplicate_mode;
+
+        let (files, hashes) = if duplicate_mode.is_allow() {
+            (target, target_hashes)
+        } else {
+            self.read_snapshot_files(&metadata)?
+        };
+
+        for (name, mut new) in files {
+            let Some(old) = self.read_translation_file(&name)? else {
+                continue;
+            };
+
+            let carried = reromanize::reconcile(
+                &old,
+                &mut new,
+                &sources.remove(&name).unwrap_or_default(),
+            );
+
+            info!(
+                "{name}: {} entries instead of {}, {carried} translations carried over.",
+                new.entries().count(),
+                old.entries().count(),
+            );
+
+            if !args.dry_run {
+                write(self.translation_path.join(&name), new.serialize())?;
+            }
+        }
+
+        if !args.dry_run {
+            metadata.hashes = Some(hashes);
+            write(&self.metadata_file_path, to_string(&metadata)?)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn execute_align(&self, args: &AlignArgs) -> Result<(), anyhow::Error> {
+        if !self.translation_path.exists() {
+            return Err(anyhow!(
+                "`translation` directory in the input directory does not exist. Run `read` on the original game first."
+            ))
+            .kind(ErrorKind::TranslationMissing);
+        }
+
+        let Some(translated_source_path) = ["data", "Data"]
+            .into_iter()
+            .map(|dir| args.translated_dir.join(dir))
+            .find(|path| path.exists())
+        else {
+            bail!(
+                "{}: Translated copy has no `data`/`Data` directory.",
+                args.translated_dir.display()
+            );
+        };
+
+        let system_file_name =
+            self.system_file_path.file_name().unwrap_or_default();
+
+        if !translated_source_path.join(system_file_name).exists() {
+            bail!(
+                "{}: Translated copy has no `{}` file. It must be the same game on the same engine, with data extracted from an archive.",
+                translated_source_path.display(),
+                system_file_name.display()
+            );
+        }
+
+        // Entries of both reads are at the same positions only in `allow` mode.
+        let mut metadata =
+            parse_metadata(&self.metadata_file_path)?.unwrap_or_default();
+        metadata.duplicate_mode = DuplicateMode::Allow;
+
+        let (original, _) = report::stage("Original read", || {
+            self.read_snapshot_files(&metadata)
+        })?;
+        let (translated, _) = report::stage("Translated read", || {
+            self.read_snapshot_files_from(&translated_source_path, &metadata)
+        })?;
+
+        let mut total = 0;
+
+        for (name, original) in &original {
+            let Some((_, translated)) = translated
+                .iter()
+                .find(|(translated_name, _)| translated_name == name)
+            else {
+                warn!("{name}: Translated copy doesn't have this file.");
+                continue;
+            };
+
+            let Some(mut file) = self.read_translation_file(name)? else {
+                continue;
+            };
+
+            let (aligned, report) = align::align(original, translated);
+            let filled = align::fill(&mut file, &aligned, args.overwrite);
+
+            if !report.skipped.is_empty() {
+                warn!(
+                    "{name}: {} sections don't align, and were skipped: {}.",
+                    report.skipped.len(),
+                    report
+                        .skipped
+                        .iter()
+                        .map(|section| section.map_or_else(
+                            || String::from("none"),
+                            |id| id.to_string()
+                        ))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+            }
+
+            if report.conflicts != 0 {
+                warn!(
+                    "{name}: {} sources have different translations in the same section. The first translation is used.",
+                    report.conflicts
+                );
+            }
+
+            info!(
+                "{name}: {} translations found, {filled} entries filled.",
+                report.aligned
+            );
+
+            if filled != 0 && !args.dry_run {
+                write(self.translation_path.join(name), file.serialize())?;
+            }
+
+            total += filled;
+        }
+
+        info!("Filled {total} entries from the translated copy.");
+        Ok(())
+    }
+
+    pub fn execute_sheets(
+        &mut self,
+        subcommand: &SheetsSubcommand,
+    ) -> Result<(), anyhow::Error> {
+        if !self.translation_path.exists() {
+            return Err(anyhow!(
+                "`translation` directory in the input directory does not exist."
+            ))
+            .kind(ErrorKind::TranslationMissing);
+        }
+
+        match subcommand {
+            SheetsSubcommand::Push { sheet } => sheets::Sheets::connect(
+                &sheet.spreadsheet,
+                sheet.credentials.as_deref(),
+            )?
+            .push(&self.translation_path),
+            SheetsSubcommand::Pull {
+                sheet,
+                translator,
+                on_conflict,
+            } => {
+                let translation_path = self.translation_path.clone();
+                let changed = sheets::Sheets::connect(
+                    &sheet.spreadsheet,
+                    sheet.credentials.as_deref(),
+                )?
+                .pull(
+                    &translation_path,
+                    self.conflict_resolver(*on_conflict),
+                )?;
+
+                if let Some(translator) = translator {
+                    attribution::record(
+                        &translation_path,
+                        translator,
+                        &changed,
+                    )?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Shows machine translation of `source` of translation file `file`, and asks whether to accept, edit or skip it, or to stop the run.
+    ///
+    /// Accepts without prompting with `--yes`. Fails with `--no-input`, or when stdin is closed.
+    fn review_translation(
+        &mut self,
+        file: &str,
+        source: &str,
+        translation: &str,
+    ) -> Result<translate::Review> {
+        if self.yes {
+            return Ok(translate::Review::Accept);
+        }
+
+        if self.no_input {
+            return Err(anyhow!(
+                "Machine translations need review, but `--no-input` is set."
+            ))
+            .kind(ErrorKind::Aborted);
+        }
+
+        let start = Instant::now();
+        println!("\n{file}:\n{source}\n->\n{translation}");
+
+        let read_line = || -> Result<String> {
+            let mut buf = String::new();
+
+            if stdin().read_line(&mut buf)? == 0 {
+                return Err(anyhow!(
+                    "Review is required, but no input is available. Pass `--yes` to accept all machine translations."
+                ))
+                .kind(ErrorKind::Aborted);
+            }
+
+            Ok(buf.trim_end_matches(['\r', '\n']).to_string())
+        };
+
+        let review = 'review: loop {
+            println!(
+                "Press Enter to accept, or input `e` to edit, `s` to skip, `q` to stop."
+            );
+
+            match read_line()?.trim() {
+                "" | "a" => break translate::Review::Accept,
+                "s" => break translate::Review::Skip,
+                "q" => break translate::Review::Stop,
+                "e" => loop {
+                    println!(
+                        "Input the translation. Use `\\#` for line breaks."
+                    );
+                    let edited = read_line()?;
+
+                    if !edited.trim().is_empty() {
+                        break 'review translate::Review::Edit(edited);
+                    }
+                },
+                _ => {}
+            }
+        };
+
+        *self.start_time -= start.elapsed();
+        Ok(review)
+    }
+
+    pub fn execute_translate(
+        &mut self,
+        args: &TranslateArgs,
+    ) -> Result<(), anyhow::Error> {
+        if !self.translation_path.exists() {
+            return Err(anyhow!(
+                "`translation` directory in the input directory does not exist."
+            ))
+            .kind(ErrorKind::TranslationMissing);
+        }
+
+        if args.review && self.no_input && !self.yes {
+            bail!("`--review` needs input, but `--no-input` is set.");
+        }
+
+        let glossary = args
+            .glossary
+            .as_deref()
+            .map(translate::Glossary::load)
+            .transpose()?;
+        let protected_terms = args
+            .protected_terms
+            .as_deref()
+            .map(terms::ProtectedTerms::load)
+            .transpose()?;
+
+        let translation_path = self.translation_path.clone();
+        let mut reviewer = |file: &str, source: &str, translation: &str| {
+            self.review_translation(file, source, translation)
+        };
+
+        let changed = translate::translate(
+            &translation_path,
+            &translate::Settings {
+                provider: args.provider,
+                api_key: args.api_key.as_deref(),
+                source_language: args.source_language.as_deref(),
+                target_language: &args.target_language,
+                files: &args.files,
+                model: args.model.as_deref(),
+                max_tokens: args.max_tokens,
+                retries: args.retries,
+                endpoint: args.endpoint.as_deref(),
+                temperature: args.temperature,
+                concurrency: usize::from(args.concurrency),
+                credentials: args.credentials.as_deref(),
+                project: args.project.as_deref(),
+                location: args.location.as_deref(),
+                glossary: glossary.as_ref(),
+                bucket: args.bucket.as_deref(),
+                cache: !args.no_cache,
+                rate_limit: args.rate_limit,
+                protected_terms: protected_terms.as_ref(),
+            },
+            args.review
+                .then_some(&mut reviewer as &mut translate::Reviewer),
+        )?;
+
+        info!(
+            "Machine-translated {} entries. Review them before release.",
+            changed.len()
+        );
+        Ok(())
+    }
+
+    pub fn execute_pretranslate(
+        &self,
+        args: &PretranslateArgs,
+    ) -> Result<(), anyhow::Error> {
+        if !self.translation_path.exists() {
+            return Err(anyhow!(
+                "`translation` directory in the input directory does not exist."
+            ))
+            .kind(ErrorKind::TranslationMissing);
+        }
+
+        let memory = memory::Memory::load(
+            &args.memory,
+            &memory::Languages {
+                source: args.source_language.as_deref(),
+                target: args.target_language.as_deref(),
+            },
+        )?;
+
+        if memory.conflicts != 0 {
+            warn!(
+                "{} sources have different translations in translation memories. The first translation is used.",
+                memory.conflicts
+            );
+        }
+
+        let changed = memory::pretranslate(
+            &self.translation_path,
+            &memory,
+            &memory::Options {
+                files: &args.files,
+                fuzzy: args.fuzzy,
+            },
+        )?;
+
+        info!(
+            "Filled {} entries from translation memories.",
+            changed.len()
+        );
+        Ok(())
+    }
+
+    pub fn execute_tm(
+        &self,
+        subcommand: &TmSubcommand,
+    ) -> Result<(), anyhow::Error> {
+        match subcommand {
+            TmSubcommand::Build {
+                paths,
+                tmx,
+                source_language,
+                target_language,
+            } => {
+                let memory = memory::Memory::load(
+                    paths,
+                    &memory::Languages {
+                        source: Some(source_language.as_str()),
+                        target: Some(target_language.as_str()),
+                    },
+                )?;
+
+                let tmx_path = tmx
+                    .clone()
+                    .unwrap_or_else(|| self.output_dir.join("memory.tmx"));
+                let provided = memory::write_tmx(
+                    &memory,
+                    source_language,
+                    target_language,
+                    &tmx_path,
+                )?;
+
+                for (origin, units) in &provided {
+                    info!("{origin}: Provided {units} translation units.");
+                }
+
+                if memory.conflicts != 0 {
+                    warn!(
+                        "{} sources have different translations. The translation of the earliest memory is kept.",
+                        memory.conflicts
+                    );
+                }
+
+                info!(
+                    "{}: Wrote {} translation units. {} duplicate translations were merged, {} conflicting translations were dropped.",
+                    tmx_path.display(),
+                    provided.iter().map(|(_, units)| units).sum::<usize>(),
+                    memory.duplicates,
+                    memory.conflicts
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn execute_bundle(
+        &self,
+        subcommand: &BundleSubcommand,
+    ) -> Result<(), anyhow::Error> {
+        let manifest = bundle::Manifest {
+            version: crate_version!().to_string(),
+            engine_type: self.engine_type,
+            game_title: self.get_game_title()?,
+        };
+
+        match subcommand {
+            BundleSubcommand::Export { path } => {
+                if !self.translation_path.exists() {
+                    return Err(anyhow!(
+                        "`translation` directory in the input directory does not exist."
+                    ))
+                    .kind(ErrorKind::TranslationMissing);
+                }
+
+                let packed =
+                    bundle::export(&self.translation_path, path, &manifest)?;
+                info!("Packed {packed} files to {}.", path.display());
+            }
+            BundleSubcommand::Import { path, force } => {
+                let unpacked = bundle::import(
+                    path,
+                    &self.translation_path,
+                    &manifest,
+                    *force,
+                )?;
+                info!("Unpacked {unpacked} files from {}.", path.display());
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn execute_report(
+        &self,
+        subcommand: &ReportSubcommand,
+    ) -> Result<(), anyhow::Error> {
+        if !self.translation_path.exists() {
+            return Err(anyhow!(
+                "`translation` directory in the input directory does not exist."
+            ))
+            .kind(ErrorKind::TranslationMissing);
+        }
+
+        match subcommand {
+            ReportSubcommand::Html { path, recent } => {
+                let path = path
+                    .clone()
+                    .unwrap_or_else(|| self.output_dir.join("report.html"));
+                let html = progress::html(
+                    &self.translation_path,
+                    &self.get_game_title()?,
+                    *recent,
+                )?;
+
+                write(&path, html)?;
+                info!("Wrote the progress report to {}.", path.display());
+            }
+            ReportSubcommand::Badge { path, label } => {
+                let path = path
+                    .clone()
+                    .unwrap_or_else(|| self.output_dir.join("badge.svg"));
+
+                write(&path, progress::badge(&self.translation_path, label)?)?;
+                info!("Wrote the progress badge to {}.", path.display());
+            }
+            ReportSubcommand::Changes { path, threshold } => {
+                let path = path
+                    .clone()
+                    .unwrap_or_else(|| self.output_dir.join("changes.md"));
+
+                self.report_changes(&path, *threshold)?;
+                info!("Wrote the changelog to {}.", path.display());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compares existing translation files with a snapshot of the current game data, and writes the changelog to `path`.
+    fn report_changes(&self, path: &Path, threshold: f64) -> Result<()> {
+        let metadata =
+            parse_metadata(&self.metadata_file_path)?.unwrap_or_default();
+        let (snapshot, hashes) = self.read_snapshot_files(&metadata)?;
+
+        // Hashes cover game files, that the last read processed, so they tell, whether the game changed at all.
+        let unchanged_data = metadata.hashes.is_some_and(|stored| {
+            stored.into_iter().collect::<HashSet<_>>()
+                == hashes.into_iter().collect()
+        });
+
+        let mut files = Vec::with_capacity(snapshot.len());
+
+        for (name, new) in &snapshot {
+            let old = self.read_translation_file(name)?.unwrap_or_default();
+            let changes = changes::diff(&old, new, threshold);
+
+            info!(
+                "{name}: {} added, {} removed, {} modified.",
+                changes.added.len(),
+                changes.removed.len(),
+                changes.modified.len()
+            );
+            files.push((name.clone(), changes));
+        }
+
+        // Files, that the new version doesn't produce, were removed entirely.
+        for name in translation::translation_files(&self.translation_path)? {
+            if snapshot.iter().any(|(new_name, _)| *new_name == name) {
+                continue;
+            }
+
+            if let Some(old) = self.read_translation_file(&name)? {
+                let changes =
+                    changes::diff(&old, &TranslationFile::default(), threshold);
+                info!("{name}: {} removed.", changes.removed.len());
+                files.push((name, changes));
+            }
+        }
+
+        write(
+            path,
+            changes::format_changelog(
+                &self.get_game_title()?,
+                &files,
+                unchanged_data,
+            ),
+        )?;
+        Ok(())
+    }
+
+    pub fn execute_package(
+        &self,
+        args: PackageArgs,
+    ) -> Result<(), anyhow::Error> {
+        let patch_path = args
+            .patch_dir
+            .unwrap_or_else(|| self.output_dir.join("patch"));
+
+        let manifest = patch::Manifest {
+            version: args.patch_version,
+            tool_version: crate_version!().to_string(),
+            engine_type: self.engine_type,
+            game_title: self.get_game_title()?,
+            delta_format: patch::DeltaFormat::default(),
+            files: Vec::new(),
+        };
+
+        let report = patch::create(
+            &self.output_dir.join("output"),
+            &patch_path,
+            &patch::Game::new(
+                &self.input_dir,
+                &self.source_path,
+                self.engine_type,
+            )?,
+            manifest,
+            if args.vcdiff {
+                patch::Deltas::Vcdiff
+            } else {
+                args.delta_threshold
+                    .map_or(patch::Deltas::None, patch::Deltas::Above)
+            },
+            args.install_scripts,
+            args.force,
+        )?;
+
+        info!(
+            "Packaged {} changed files, {} of them as deltas, to {}. {} unchanged files were left out.",
+            report.packaged,
+            report.deltas,
+            patch_path.display(),
+            report.unchanged
+        );
+
+        if args.zip {
+            let mut archive_path = patch_path.clone().into_os_string();
+            archive_path.push(".zip");
+            let archive_path = PathBuf::from(archive_path);
+
+            let packed = patch::archive(&patch_path, &archive_path)?;
+            info!("Packed {packed} files to {}.", archive_path.display());
+        }
+
+        Ok(())
+    }
+
+    pub fn execute_apply_patch(
+        &mut self,
+        args: &ApplyPatchArgs,
+    ) -> Result<(), anyhow::Error> {
+        let Some(path) = &args.path else {
+            if !self.confirm(
+                "WARNING! Files, that patches changed, will be restored to the originals. Input 'Y' to continue.",
+            )? {
+                bail!(ErrorKind::Aborted);
+            }
+
+            let undone = patch::undo(&patch::Game::new(
+                &self.input_dir,
+                &self.source_path,
+                self.engine_type,
+            )?)?;
+            info!("Restored {undone} files of the game.");
+
+            return Ok(());
+        };
+
+        let manifest = patch::load(path)?;
+
+        if !self.confirm(&format!(
+            "WARNING! Patch {} will overwrite {} files of the game. Input 'Y' to continue.",
+            manifest.version,
+            manifest.files.len()
+        ))? {
+            bail!(ErrorKind::Aborted);
+        }
+
+        let applied = patch::apply(
+            path,
+            &manifest,
+            &patch::Game::new(
+                &self.input_dir,
+                &self.source_path,
+                self.engine_type,
+            )?,
+            self.engine_type,
+            &self.get_game_title()?,
+            args.force,
+        )?;
+        info!("Applied {applied} files of patch {}.", manifest.version);
+
+        Ok(())
+    }
+
+    pub fn execute_archive(
+        &self,
+        subcommand: &ArchiveSubcommand,
+    ) -> Result<(), anyhow::Error> {
+        let Some(archive_path) =
+            self.archive_path.as_ref().filter(|path| path.exists())
+        else {
+            bail!("`.rgss` archive does not exist in the input directory.");
+        };
+
+        match subcommand {
+            ArchiveSubcommand::List { archive } => archive::list(
+                archive_path,
+                archive.archive_encoding,
+                archive.archive_key,
+            )?,
+            ArchiveSubcommand::Check { archive } => {
+                archive::check(archive_path, archive.archive_key)
+                    .kind(ErrorKind::ValidationFailed)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Warns about text in translation files, that isn't UTF-8. `write` fails on it.
+fn check_encoding(translation_path: &Path, command: &Command) -> Result<()> {
+    if !translation_path.exists() || command.is_generic() {
+        return Ok(());
+    }
+
+    let problems =
+        report::stage("Encoding check", || encoding::check(translation_path))?;
+
+    if problems != 0 && command.is_write() {
+        return Err(anyhow!(
+            "Translation files contain {problems} lines, that aren't UTF-8. Convert them to UTF-8, so they don't end up as mojibake in game files."
+        ))
+        .kind(ErrorKind::ValidationFailed);
+    }
+
+    Ok(())
+}
+
+/// Prints the run summary, if `quiet`, and writes it to `summary_file`.
+fn emit_summary(
+    command: &str,
+    summary_file: Option<&Path>,
+    quiet: bool,
+    error: Option<&anyhow::Error>,
+    elapsed: f64,
+    translation_path: &Path,
+) -> Result<()> {
+    if !quiet && summary_file.is_none() {
+        return Ok(());
+    }
+
+    let summary =
+        report::run_summary(command, error, elapsed, translation_path)?;
+
+    if let Some(summary_file) = summary_file {
+        write(summary_file, serde_json::to_string_pretty(&summary)?)
+            .with_context(|| format!("Writing {}", summary_file.display()))?;
+    }
+
+    if quiet {
+        println!("{summary}");
+    }
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_lines)]
+fn run(args: Vec<OsString>) -> Result<()> {
+    let mut start_time = Instant::now();
+    let mut cli = Cli::parse_from(args);
+    let verbosity = cli.verbosity.tracing_level_filter();
+
+    if cli.stats_resources {
+        resources::enable();
+    }
+
+    let log_file = cli
+        .log_file
+        .as_deref()
+        .map(|path| log_file::layer(path, cli.log_level))
+        .transpose()?;
+
+    if let Some(path) = &cli.log_file {
+        crash::set_log_file(path);
+    }
+
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .without_time()
+                .with_target(false)
+                .with_level(true)
+                .with_thread_names(false)
+                .with_thread_ids(false)
+                .with_ansi(true)
+                .with_filter(verbosity)
+                .with_filter(filter_fn(|metadata| {
+                    metadata.target() != log_file::TARGET
+                })),
+        )
+        .with(log_file)
+        .with(report::WarningCounter.with_filter(LevelFilter::WARN))
+        .with(crash::Recorder.with_filter(LevelFilter::DEBUG))
+        .with(audit::Recorder.with_filter(LevelFilter::INFO))
+        .with(report::FileTimer.with_filter(verbosity))
+        .init();
+
+    log_file::record_start();
+
+    // Quiet runs replace logs with the summary, unless they're silenced completely.
+    let quiet = verbosity < LevelFilter::INFO && verbosity > LevelFilter::OFF;
+    let command_name: &'static str = (&cli.command).into();
+    let summary_file = take(&mut cli.summary_file);
+
+    let requested_compression = cli.compress;
+    let pre_hooks = take(&mut cli.pre_hook);
+    let post_hooks = take(&mut cli.post_hook);
+    let mut processor = match Processor::new(&mut cli, &mut start_time) {
+        Ok(processor) => processor,
+        Err(err) => {
+            emit_summary(
+                command_name,
+                summary_file.as_deref(),
+                quiet,
+                Some(&err),
+                start_time.elapsed().as_secs_f64(),
+                Path::new(""),
+            )?;
+            return Err(err);
+        }
+    };
+    let translation_path = processor.translation_path.clone();
+    let input_dir = processor.input_dir.clone();
+    let output_dir = processor.output_dir.clone();
+
+    let hook_context = match cli.command {
+        Command::Read(_) => Some("read"),
+        Command::Write(_) => Some("write"),
+        Command::Purge(_) => Some("purge"),
+        _ => None,
+    }
+    .map(|command| hooks::Context {
+        command,
+        input_dir: &input_dir,
+        translation_dir: &translation_path,
+        output_dir: &output_dir,
+    });
+
+    if let Some(context) = &hook_context {
+        hooks::run_pre(&pre_hooks, context)?;
+    }
+    let compression =
+        compression::unpack(&translation_path, requested_compression)?;
+
+    let audit = match &cli.command {
+        Command::Read(args) => {
+            args.shared.read_mode.is_force()
+                || args.shared.read_mode.is_force_append()
+        }
+        Command::Write(_) | Command::Purge(_) => true,
+        Command::ApplyPatch(args) => args.undo,
+        _ => false,
+    }
+    .then(|| audit::Audit::start(command_name, &translation_path))
+    .transpose()?;
+
+    let result = check_encoding(&translation_path, &cli.command).and_then(
+        |()| match cli.command {
+            Command::Read(args) => processor.execute_read(args),
+            Command::Write(args) => processor.execute_write(args),
+            Command::Purge(args) => processor.execute_purge(args),
+            Command::Generic { subcommand } => {
+                processor.execute_generic(&subcommand)
+            }
+            Command::Json { subcommand } => processor.execute_json(&subcommand),
+            Command::Export(args) => processor.execute_export(args),
+            Command::Import(args) => processor.execute_import(args),
+            Command::Replace(args) => processor.execute_replace(&args),
+            Command::Lint(args) => processor.execute_lint(&args),
+            Command::Remap(args) => processor.execute_remap(&args),
+            Command::Upgrade(args) => processor.execute_upgrade(args),
+            Command::Dedup(args) => processor.execute_dedup(&args),
+            Command::Trim(args) => processor.execute_trim(&args),
+            Command::Romanize(args) => processor.execute_romanize(&args),
+            Command::Align(args) => processor.execute_align(&args),
+            Command::Attribution(args) => processor.execute_attribution(&args),
+            Command::Overflow(args) => processor.execute_overflow(&args),
+            Command::Bundle { subcommand } => {
+                processor.execute_bundle(&subcommand)
+            }
+            Command::Sheets { subcommand } => {
+                processor.execute_sheets(&subcommand)
+            }
+            Command::Translate(args) => processor.execute_translate(&args),
+            Command::Pretranslate(args) => {
+                processor.execute_pretranslate(&args)
+            }
+            Command::Tm { subcommand } => processor.execute_tm(&subcommand),
+            Command::Report { subcommand } => {
+                processor.execute_report(&subcommand)
+            }
+            Command::Package(args) => processor.execute_package(args),
+            Command::ApplyPatch(args) => processor.execute_apply_patch(&args),
+            Command::Archive { subcommand } => {
+                processor.execute_archive(&subcommand)
+            }
+        },
+    );
+
+    if let Some(audit) = audit {
+        audit.finish(&translation_path, result.as_ref().err())?;
+    }
+
+    // Pack files back even if the command failed, so the translation doesn't stay half-unpacked.
+    if let Some(compression) = compression {
+        compression::pack(&translation_path, compression)?;
+    }
+
+    let hooks_result = hook_context.as_ref().map_or(Ok(()), |context| {
+        hooks::run_post(
+            &post_hooks,
+            context,
+            &hooks::Outcome {
+                error: result.as_ref().err(),
+                elapsed: start_time.elapsed().as_secs_f64(),
+            },
+        )
+    });
+
+    emit_summary(
+        command_name,
+        summary_file.as_deref(),
+        quiet,
+        result.as_ref().err(),
+        start_time.elapsed().as_secs_f64(),
+        &translation_path,
+    )?;
+
+    result?;
+    hooks_result?;
+
+    if verbosity >= LevelFilter::DEBUG {
+        report::print_timings();
+    }
+
+    if cli.stats_resources {
+        resources::print();
+    }
+
+    if !quiet {
+        println!("Elapsed: {:.2}s", start_time.elapsed().as_secs_f32());
+    }
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    crash::install();
+
+    let args: Vec<OsString> = std::env::args_os().collect();
+
+    if !wizard::is_requested(&args) {
+        return match run(args) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                log_file::record_error(&err);
+                crash::report_error(&err);
+                eprintln!("Error: {err:?}");
+                ExitCode::from(ErrorKind::exit_code_of(&err))
+            }
+        };
+    }
+
+    let result = wizard::run(&args).and_then(|plan| {
+        let Some(plan) = plan else {
+            return Ok(None);
+        };
+
+        run(plan.args)?;
+        Ok(Some(plan.next_step))
+    });
+
+    let code = match result {
+        Ok(next_step) => {
+            if let Some(next_step) = next_step {
+                println!("\n{next_step}");
+            }
+
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            log_file::record_error(&err);
+            crash::report_error(&err);
+            eprintln!("Error: {err:?}");
+            ExitCode::from(ErrorKind::exit_code_of(&err))
+        }
+    };
+
+    wizard::pause();
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_skipped_maps() {
+        assert_eq!(SkipMaps::from_str("1, 3-5").unwrap().0, [1, 3, 4, 5]);
+        assert_eq!(
+            SkipMaps::from_str("9998-").unwrap().0,
+            [9998, data::MAX_MAP_ID]
+        );
+    }
+
+    #[test]
+    fn rejects_map_ids_above_maximum() {
+        assert!(SkipMaps::from_str("10000").is_err());
+        assert!(SkipMaps::from_str("1-10000").is_err());
+        assert!(SkipMaps::from_str("10000-").is_err());
+        assert!(SkipMaps::from_str("70000").is_err());
+    }
 }
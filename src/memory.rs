@@ -0,0 +1,562 @@
+//! Translation memories, that untranslated entries are filled from.
+//!
+//! A memory is a TMX file, e.g. of a CAT tool or of `export omegat`, or a translation directory of another project, e.g. of an earlier game of the series or an earlier release. Sources are matched exactly, and the first memory, that has a source, provides its translation. Filled entries are preceded by `<!-- PRETRANSLATED: memory -->` comments, that record where translations came from.
+//!
+//! Memories of several projects can also be merged into one TMX file, so a translation group can pool all their finished projects. Units of the merged memory hold the names of memories, that they came from, in `x-origin` properties.
+//!
+//! With a similarity threshold, entries without exact matches are filled with translations of the most similar sources too, e.g. of recycled dialogue, that the developer slightly reworded. Such entries are also marked as fuzzy, so they're reviewed like conflicting imports, and their marks hold the similarity.
+
+use crate::{
+    attribution::Changed,
+    export::FUZZY_COMMENT_PREFIX,
+    fuzzy::similarity,
+    translation::{
+        Line, TranslationFile, denormalize, effective_translation, normalize,
+        translation_files,
+    },
+    xml::{attributes, escape, unescape},
+};
+use anyhow::{Context, Result, bail};
+use regex::Regex;
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    fs::{read_to_string, write},
+    mem::take,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+use tracing::info;
+
+/// Comment, that precedes entries, which translation was taken from a translation memory, and holds the name of the memory. The library ignores such comments on write.
+pub const PRETRANSLATION_COMMENT_PREFIX: &str = "<!-- PRETRANSLATED: ";
+
+static HEADER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<header\b([^>]*)>").unwrap());
+static UNIT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?s)<tu\b[^>]*>(.*?)</tu>").unwrap());
+static VARIANT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?s)<tuv\b([^>]*)>.*?<seg\b[^>]*>(.*?)</seg>").unwrap()
+});
+
+/// Inline elements of segments. Their contents are the codes, that they stand for, e.g. `\C[1]` in `<ph>`.
+static TAG_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<[^>]*>").unwrap());
+
+/// Languages of translation units of TMX memories.
+pub struct Languages<'a> {
+    /// Language of sources. Defaults to `srclang` of the TMX header.
+    pub source: Option<&'a str>,
+
+    /// Language of translations. Defaults to the first language of each unit, that isn't the source language.
+    pub target: Option<&'a str>,
+}
+
+/// Returns `true` if `language` is `wanted`, or its regional variant, e.g. `en-US` for `en`.
+fn language_matches(language: &str, wanted: &str) -> bool {
+    language.eq_ignore_ascii_case(wanted)
+        || language
+            .get(..wanted.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(wanted))
+            && language[wanted.len()..].starts_with(['-', '_'])
+}
+
+/// Returns the text of TMX segment, with line breaks as `\#` markers.
+fn segment_text(segment: &str) -> String {
+    normalize(&unescape(&TAG_RE.replace_all(segment, "")).replace("\r\n", "\n"))
+}
+
+#[derive(Default)]
+pub struct Memory {
+    /// Names of memories, in their order.
+    origins: Vec<String>,
+
+    /// Translations by sources, and indices of memories, that they came from.
+    translations: HashMap<String, (String, usize)>,
+
+    /// Sources, that have different translations. The first translation is used.
+    pub conflicts: usize,
+
+    /// Sources, that have the same translation in several places.
+    pub duplicates: usize,
+}
+
+impl Memory {
+    /// Loads memories at `paths`, that are TMX files or translation directories.
+    pub fn load(paths: &[PathBuf], languages: &Languages) -> Result<Self> {
+        let mut memory = Self::default();
+
+        for path in paths {
+            let origin = memory.origins.len();
+            let loaded = if path.is_dir() {
+                memory.load_project(path, origin)?
+            } else if path.extension().is_some_and(|ext| ext == "tmx") {
+                memory.load_tmx(path, languages, origin)?
+            } else {
+                bail!(
+                    "{}: Translation memory must be a TMX file or a translation directory.",
+                    path.display()
+                );
+            };
+
+            info!("{}: Loaded {loaded} translations.", path.display());
+        }
+
+        Ok(memory)
+    }
+
+    fn insert(&mut self, source: String, translation: String, origin: usize) {
+        if source.trim().is_empty() || translation.is_empty() {
+            return;
+        }
+
+        match self.translations.get(&source) {
+            Some((existing, _)) => {
+                if *existing == translation {
+                    self.duplicates += 1;
+                } else {
+                    self.conflicts += 1;
+                }
+            }
+            None => {
+                self.translations.insert(source, (translation, origin));
+            }
+        }
+    }
+
+    /// Returns the name, that marks translations of memory at `path`. Translation directories are named after their projects.
+    fn origin_name(path: &Path) -> String {
+        let name = |path: &Path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        };
+
+        name(path)
+            .filter(|name| name != "translation")
+            .or_else(|| path.parent().and_then(name))
+            .unwrap_or_else(|| path.display().to_string())
+    }
+
+    /// Loads translated entries of translation directory at `path`, or of `translation` directory in it. Returns the number of translations.
+    fn load_project(&mut self, path: &Path, origin: usize) -> Result<usize> {
+        let translation_path = if path.join("translation").is_dir() {
+            path.join("translation")
+        } else {
+            path.to_path_buf()
+        };
+
+        let names = translation_files(&translation_path)?;
+
+        if names.is_empty() {
+            bail!("{}: No translation files.", translation_path.display());
+        }
+
+        self.origins.push(Self::origin_name(&translation_path));
+        let mut loaded = 0;
+
+        for name in names {
+            let file = TranslationFile::parse(&read_to_string(
+                translation_path.join(&name),
+            )?);
+
+            for (_, source, translation) in file.entries() {
+                if !translation.is_empty() {
+                    self.insert(
+                        source.to_string(),
+                        translation.to_string(),
+                        origin,
+                    );
+                    loaded += 1;
+                }
+            }
+        }
+
+        Ok(loaded)
+    }
+
+    /// Loads translation units of TMX file at `path` in `languages`. Returns the number of translations.
+    fn load_tmx(
+        &mut self,
+        path: &Path,
+        languages: &Languages,
+        origin: usize,
+    ) -> Result<usize> {
+        let xml = read_to_string(path)
+            .with_context(|| format!("Reading {}", path.display()))?;
+
+        let header_language = HEADER_RE.captures(&xml).and_then(|header| {
+            attributes(header.get(1)?.as_str())
+                .get("srclang")
+                .filter(|language| **language != "*all*")
+                .map(ToString::to_string)
+        });
+
+        let Some(source_language) = languages
+            .source
+            .map(ToString::to_string)
+            .or(header_language)
+        else {
+            bail!(
+                "{}: TMX header has no source language. Pass it with `--source-language`.",
+                path.display()
+            );
+        };
+
+        self.origins.push(Self::origin_name(path));
+        let mut loaded = 0;
+
+        for unit in UNIT_RE.captures_iter(&xml) {
+            let mut source = None;
+            let mut translation = None;
+
+            for variant in VARIANT_RE.captures_iter(&unit[1]) {
+                let variant_attributes = attributes(&variant[1]);
+                let Some(language) = variant_attributes
+                    .get("xml:lang")
+                    .or_else(|| variant_attributes.get("lang"))
+                else {
+                    continue;
+                };
+
+                if language_matches(language, &source_language) {
+                    source.get_or_insert_with(|| segment_text(&variant[2]));
+                } else if languages
+                    .target
+                    .is_none_or(|target| language_matches(language, target))
+                {
+                    translation
+                        .get_or_insert_with(|| segment_text(&variant[2]));
+                }
+            }
+
+            if let (Some(source), Some(translation)) = (source, translation) {
+                self.insert(source, translation, origin);
+                loaded += 1;
+            }
+        }
+
+        if loaded == 0 {
+            bail!(
+                "{}: No translation units with `{source_language}` sources{}.",
+                path.display(),
+                languages
+                    .target
+                    .map(|target| format!(" and `{target}` translations"))
+                    .unwrap_or_default()
+            );
+        }
+
+        Ok(loaded)
+    }
+
+    /// Returns the exact match of `source`.
+    fn get(&self, source: &str) -> Option<Match<'_>> {
+        self.translations
+            .get(source)
+            .map(|(translation, origin)| Match {
+                translation,
+                origin: &self.origins[*origin],
+                similarity: None,
+            })
+    }
+
+    /// Returns sources with their lengths in characters, sorted by lengths, and by the order of memories.
+    fn candidates(&self) -> Vec<(usize, &str)> {
+        let mut candidates: Vec<(usize, usize, &str)> = self
+            .translations
+            .iter()
+            .map(|(source, (_, origin))| {
+                (source.chars().count(), *origin, source.as_str())
+            })
+            .collect();
+        candidates.sort_unstable();
+
+        candidates
+            .into_iter()
+            .map(|(length, _, source)| (length, source))
+            .collect()
+    }
+
+    /// Returns the translation of the source of `candidates`, that is the most similar to `source`, if its similarity is at least `threshold`.
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    fn fuzzy_match<'a>(
+        &'a self,
+        candidates: &[(usize, &'a str)],
+        source: &str,
+        threshold: f64,
+    ) -> Option<Match<'a>> {
+        // Sources, which lengths differ more, than the threshold allows, can't be similar enough.
+        let length = source.chars().count() as f64;
+        let start = candidates.partition_point(|(candidate_length, _)| {
+            (*candidate_length as f64) < (length * threshold).floor()
+        });
+        let end = candidates.partition_point(|(candidate_length, _)| {
+            (*candidate_length as f64) <= (length / threshold).ceil()
+        });
+
+        let mut best: Option<(f64, &str)> = None;
+
+        for &(_, candidate) in &candidates[start..end.max(start)] {
+            let score = similarity(source, candidate, threshold);
+
+            if score >= threshold && best.is_none_or(|(best, _)| score > best) {
+                best = Some((score, candidate));
+            }
+        }
+
+        let (score, candidate) = best?;
+        let (translation, origin) = &self.translations[candidate];
+
+        Some(Match {
+            translation,
+            origin: &self.origins[*origin],
+            similarity: Some(score),
+        })
+    }
+}
+
+/// Writes translations of `memory` to TMX file at `path`, with `source` and `target` languages. Units are sorted by memories, that they came from, and by sources. Returns the number of units, that each memory provided, in the order of memories.
+pub fn write_tmx<'a>(
+    memory: &'a Memory,
+    source: &str,
+    target: &str,
+    path: &Path,
+) -> Result<Vec<(&'a str, usize)>> {
+    let mut units: Vec<(usize, &str, &str)> = memory
+        .translations
+        .iter()
+        .map(|(source, (translation, origin))| {
+            (*origin, source.as_str(), translation.as_str())
+        })
+        .collect();
+    units.sort_unstable();
+
+    let mut output = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<tmx version="1.4">
+  <header creationtool="rvpacker-txt-rs" creationtoolversion="{}" segtype="paragraph" o-tmf="rvpacker-txt-rs" adminlang="en" srclang="{}" datatype="plaintext"/>
+  <body>
+"#,
+        env!("CARGO_PKG_VERSION"),
+        escape(source)
+    );
+    let mut provided: Vec<(&str, usize)> = memory
+        .origins
+        .iter()
+        .map(|origin| (origin.as_str(), 0))
+        .collect();
+
+    for (origin, unit_source, translation) in units {
+        let _ = write!(
+            output,
+            "    <tu>\n      <prop type=\"x-origin\">{}</prop>\n      <tuv xml:lang=\"{}\"><seg>{}</seg></tuv>\n      <tuv xml:lang=\"{}\"><seg>{}</seg></tuv>\n    </tu>\n",
+            escape(&memory.origins[origin]),
+            escape(source),
+            escape(&denormalize(unit_source)),
+            escape(target),
+            escape(&denormalize(translation))
+        );
+        provided[origin].1 += 1;
+    }
+
+    output.push_str("  </body>\n</tmx>\n");
+    write(path, output)
+        .with_context(|| format!("Writing {}", path.display()))?;
+    Ok(provided)
+}
+
+/// Translation of a source of a memory, that matches a source of a project.
+struct Match<'a> {
+    translation: &'a str,
+
+    /// Name of the memory.
+    origin: &'a str,
+
+    /// Similarity of the sources, if they aren't the same.
+    similarity: Option<f64>,
+}
+
+/// Options of a single `pretranslate` run.
+pub struct Options<'a> {
+    /// Names or stems of translation files to fill. All files are filled if empty.
+    pub files: &'a [String],
+
+    /// Minimal similarity from 0 to 1 of sources, that fuzzy matches fill entries with. Only exact matches are used, if it's not given.
+    pub fuzzy: Option<f64>,
+}
+
+/// Fills untranslated entries of translation files in `translation_path`, which sources are in `memory`, and marks them with the names of memories. Returns filled entries.
+pub fn pretranslate(
+    translation_path: &Path,
+    memory: &Memory,
+    options: &Options,
+) -> Result<Vec<Changed>> {
+    let files = options.files;
+    let candidates = options
+        .fuzzy
+        .map(|_| memory.candidates())
+        .unwrap_or_default();
+    let mut changed = Vec::new();
+
+    for name in translation_files(translation_path)? {
+        if !files.is_empty()
+            && !files.iter().any(|file| {
+                *file == name || name.strip_suffix(".txt") == Some(file)
+            })
+        {
+            continue;
+        }
+
+        let path = translation_path.join(&name);
+        let mut file = TranslationFile::parse(&read_to_string(&path)?);
+        let mut section = None;
+        let mut filled = 0;
+        let mut fuzzy = 0;
+        let mut lines = Vec::with_capacity(file.lines.len());
+
+        for mut line in take(&mut file.lines) {
+            if let Line::Id(id) = line {
+                section = Some(id);
+            }
+
+            if let Line::Entry {
+                source,
+                translation,
+            } = &mut line
+                && effective_translation(translation).is_empty()
+                && let Some(found) = memory.get(source).or_else(|| {
+                    options.fuzzy.and_then(|threshold| {
+                        memory.fuzzy_match(&candidates, source, threshold)
+                    })
+                })
+            {
+                match found.similarity {
+                    Some(similarity) => {
+                        lines.push(Line::Comment(format!(
+                            "{PRETRANSLATION_COMMENT_PREFIX}{}, {:.0}% match -->",
+                            found.origin,
+                            similarity * 100.0
+                        )));
+                        lines.push(Line::Comment(format!(
+                            "{FUZZY_COMMENT_PREFIX}{} -->",
+                            found.translation
+                        )));
+                        fuzzy += 1;
+                    }
+                    None => lines.push(Line::Comment(format!(
+                        "{PRETRANSLATION_COMMENT_PREFIX}{} -->",
+                        found.origin
+                    ))),
+                }
+
+                *translation = found.translation.to_string();
+                filled += 1;
+                changed.push((name.clone(), section, source.clone()));
+            }
+
+            lines.push(line);
+        }
+
+        if filled != 0 {
+            file.lines = lines;
+            write(&path, file.serialize())?;
+            info!(
+                "{name}: Filled {filled} entries from translation memories, {fuzzy} of them with fuzzy matches."
+            );
+        }
+    }
+
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("rvpacker-{}-{name}", std::process::id()))
+    }
+
+    #[test]
+    fn reads_inline_elements_and_regional_languages() {
+        let path = temp_path("inline.tmx");
+        std::fs::write(
+            &path,
+            r#"<tmx version="1.4"><header srclang="ja-JP"/><body>
+<tu><tuv xml:lang="ja-JP"><seg><ph>\C[1]</ph>猫&amp;犬</seg></tuv><tuv xml:lang="en-US"><seg><ph>\C[1]</ph>Cat &amp; dog</seg></tuv><tuv xml:lang="de"><seg>Katze</seg></tuv></tu>
+</body></tmx>"#,
+        )
+        .unwrap();
+
+        let memory = Memory::load(
+            std::slice::from_ref(&path),
+            &Languages {
+                source: Some("ja"),
+                target: Some("en"),
+            },
+        );
+        std::fs::remove_file(&path).unwrap();
+        let memory = memory.unwrap();
+
+        assert_eq!(
+            memory.get(r"\C[1]猫&犬").map(|found| found.translation),
+            Some(r"\C[1]Cat & dog")
+        );
+    }
+
+    #[test]
+    fn matches_languages() {
+        assert!(language_matches("en", "EN"));
+        assert!(language_matches("en-US", "en"));
+        assert!(language_matches("pt_BR", "pt"));
+        assert!(!language_matches("eng", "en"));
+        assert!(!language_matches("en", "en-US"));
+    }
+
+    #[test]
+    fn round_trips_tmx() {
+        let mut memory = Memory::default();
+        memory.origins.push("first".into());
+        memory.origins.push("second & <third>".into());
+        memory.insert(r"こんにちは\#世界".into(), r"Hello\#world".into(), 0);
+        memory.insert(r"\C[2]アリス\C[0]".into(), r"\C[2]Alice\C[0]".into(), 1);
+        memory.insert("<tag> & \"quotes\"".into(), "a < b".into(), 1);
+
+        let path = temp_path("memory.tmx");
+        let provided = write_tmx(&memory, "ja", "en", &path).unwrap();
+        let loaded = Memory::load(
+            std::slice::from_ref(&path),
+            &Languages {
+                source: None,
+                target: None,
+            },
+        );
+        std::fs::remove_file(&path).unwrap();
+        let loaded = loaded.unwrap();
+
+        assert_eq!(provided, [("first", 1), ("second & <third>", 2)]);
+        assert_eq!(loaded.translations.len(), 3);
+
+        for (source, (translation, _)) in &memory.translations {
+            assert_eq!(&loaded.translations[source].0, translation);
+        }
+    }
+
+    #[test]
+    fn counts_conflicts_and_duplicates() {
+        let mut memory = Memory::default();
+        memory.insert("a".into(), "1".into(), 0);
+        memory.insert("a".into(), "1".into(), 0);
+        memory.insert("a".into(), "2".into(), 0);
+        memory.insert(" ".into(), "3".into(), 0);
+
+        assert_eq!((memory.duplicates, memory.conflicts), (1, 1));
+        assert_eq!(memory.translations["a"].0, "1");
+        assert_eq!(memory.translations.len(), 1);
+    }
+}
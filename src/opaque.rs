@@ -0,0 +1,884 @@
+//! Preservation of Ruby Marshal constructs, that `marshal-rs` can't process, as opaque blobs.
+//!
+//! Some games ship data with uncommon constructs, like instances of custom classes, that subclass `String` or `Array`, structs, objects with custom `_dump` or `marshal_dump`, and links to objects, that appeared earlier. `marshal-rs` miscounts or drops some of them, so the linked objects come out wrong, or parsing aborts without an error.
+//!
+//! Files are parsed by an independent reader, that tracks Ruby's object and symbol tables exactly. Links are expanded into copies, and unsupported subtrees are replaced with placeholders, that the library passes through untouched. After the library writes the output, the placeholders are replaced back with the original subtrees. The text inside them isn't translated.
+
+use crate::error::{ErrorKind, WithKind};
+use anyhow::{Context, Result, anyhow, bail};
+use std::{
+    collections::HashMap,
+    fs::{read, read_dir, write},
+    path::Path,
+    rc::Rc,
+};
+use tracing::{debug, warn};
+
+/// First two bytes of Marshal 4.8 data.
+const VERSION: [u8; 2] = [4, 8];
+
+/// Class of placeholders. They're plain objects, that the library doesn't take for text, with the index of the blob.
+const PLACEHOLDER_CLASS: &[u8] = b"RvpackerOpaque";
+const PLACEHOLDER_INDEX: &[u8] = b"@index";
+
+/// Instance variables of strings, that `marshal-rs` understands.
+const ENCODING_IVARS: [&[u8]; 2] = [b"E", b"encoding"];
+
+type Name = Vec<u8>;
+type Ivars = Vec<(Name, Rc<Node>)>;
+
+/// Marshal value, as Ruby reads it.
+#[derive(Debug)]
+enum Node {
+    Nil,
+    True,
+    False,
+    Fixnum(i32),
+    Symbol(Name),
+    Float(Vec<u8>),
+    Bignum {
+        sign: u8,
+        digits: Vec<u8>,
+    },
+    String(Vec<u8>),
+    Regexp {
+        source: Vec<u8>,
+        options: u8,
+    },
+    Array(Vec<Rc<Node>>),
+    Hash {
+        pairs: Vec<(Rc<Node>, Rc<Node>)>,
+        default: Option<Rc<Node>>,
+    },
+    Object {
+        class: Name,
+        ivars: Ivars,
+    },
+    Struct {
+        class: Name,
+        members: Ivars,
+    },
+    Class(Vec<u8>),
+    Module(Vec<u8>),
+    OldModule(Vec<u8>),
+    UserDefined {
+        class: Name,
+        data: Vec<u8>,
+    },
+    UserMarshal {
+        class: Name,
+        data: Rc<Node>,
+    },
+    Data {
+        class: Name,
+        data: Rc<Node>,
+    },
+    UserClass {
+        class: Name,
+        inner: Rc<Node>,
+    },
+    Extended {
+        module: Name,
+        inner: Rc<Node>,
+    },
+    Ivars {
+        inner: Rc<Node>,
+        ivars: Ivars,
+    },
+}
+
+impl Node {
+    /// Returns whether `marshal-rs` reads and dumps the node itself correctly. Children aren't checked.
+    fn is_supported(&self) -> bool {
+        match self {
+            Self::Struct { .. }
+            | Self::UserMarshal { .. }
+            | Self::Data { .. }
+            | Self::UserClass { .. }
+            | Self::Extended { .. } => false,
+            // Instance variables are only kept for strings, and only the ones, that define encoding.
+            Self::Ivars { inner, ivars } => {
+                matches!(**inner, Node::String(_))
+                    && ivars
+                        .iter()
+                        .all(|(name, _)| ENCODING_IVARS.contains(&&name[..]))
+            }
+            _ => true,
+        }
+    }
+
+    fn placeholder(index: usize) -> Result<Self> {
+        Ok(Self::Object {
+            class: PLACEHOLDER_CLASS.to_vec(),
+            ivars: vec![(
+                PLACEHOLDER_INDEX.to_vec(),
+                Rc::new(Self::Fixnum(i32::try_from(index)?)),
+            )],
+        })
+    }
+
+    /// Returns the blob index, if the node is a placeholder.
+    fn placeholder_index(&self) -> Option<usize> {
+        match self {
+            Self::Object { class, ivars } if class == PLACEHOLDER_CLASS => {
+                match ivars.as_slice() {
+                    [(name, index)] if name == PLACEHOLDER_INDEX => {
+                        match **index {
+                            Self::Fixnum(index) => usize::try_from(index).ok(),
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Reads Marshal data the way Ruby does.
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+    symbols: Vec<Name>,
+
+    /// Registered objects. Objects are registered before their children are read, so links to objects, that are still being read, point to `None`.
+    objects: Vec<Option<Rc<Node>>>,
+
+    /// Number of object links, that were expanded.
+    links: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> Result<u8> {
+        let byte = *self
+            .data
+            .get(self.position)
+            .ok_or_else(|| anyhow!("Unexpected end of data."))?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + length)
+            .ok_or_else(|| anyhow!("Unexpected end of data."))?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn int(&mut self) -> Result<i32> {
+        let first = self.byte()?.cast_signed();
+
+        Ok(match first {
+            0 => 0,
+            1..=4 => {
+                let mut value = 0;
+
+                for (i, byte) in self.bytes(first as usize)?.iter().enumerate()
+                {
+                    value |= i32::from(*byte) << (8 * i);
+                }
+
+                value
+            }
+            -4..=-1 => {
+                let size = first.unsigned_abs() as usize;
+                let mut value = -1;
+
+                for (i, byte) in self.bytes(size)?.iter().enumerate() {
+                    value &= !(0xFF << (8 * i));
+                    value |= i32::from(*byte) << (8 * i);
+                }
+
+                value
+            }
+            5.. => i32::from(first) - 5,
+            _ => i32::from(first) + 5,
+        })
+    }
+
+    fn length(&mut self) -> Result<usize> {
+        let length = self.int()?;
+        usize::try_from(length)
+            .map_err(|_| anyhow!("Negative length {length} in data."))
+    }
+
+    fn chunk(&mut self) -> Result<Vec<u8>> {
+        let length = self.length()?;
+        Ok(self.bytes(length)?.to_vec())
+    }
+
+    fn register(&mut self) -> usize {
+        self.objects.push(None);
+        self.objects.len() - 1
+    }
+
+    fn fill(&mut self, index: usize, node: Node) -> Rc<Node> {
+        let node = Rc::new(node);
+        self.objects[index] = Some(node.clone());
+        node
+    }
+
+    /// Reads a symbol, that names a class, a module or an instance variable.
+    fn symbol(&mut self) -> Result<Name> {
+        match self.byte()? {
+            b':' => {
+                let name = self.chunk()?;
+                self.symbols.push(name.clone());
+                Ok(name)
+            }
+            b';' => self.symbol_link(),
+            // Symbols with encoding. Names are ASCII in practice, so encoding is dropped.
+            b'I' => {
+                let name = self.symbol()?;
+                self.ivars()?;
+                Ok(name)
+            }
+            byte => bail!(
+                "Expected a symbol at offset {}, found type {:#04x}.",
+                self.position - 1,
+                byte
+            ),
+        }
+    }
+
+    fn symbol_link(&mut self) -> Result<Name> {
+        let index = self.length()?;
+        self.symbols
+            .get(index)
+            .cloned()
+            .ok_or_else(|| anyhow!("Symbol link {index} points to nothing."))
+    }
+
+    fn ivars(&mut self) -> Result<Ivars> {
+        let count = self.length()?;
+        let mut ivars = Vec::with_capacity(count.min(1024));
+
+        for _ in 0..count {
+            let name = self.symbol()?;
+            ivars.push((name, self.node()?));
+        }
+
+        Ok(ivars)
+    }
+
+    fn registered(
+        &mut self,
+        read: impl FnOnce(&mut Self) -> Result<Node>,
+    ) -> Result<Rc<Node>> {
+        let index = self.register();
+        let node = read(self)?;
+        Ok(self.fill(index, node))
+    }
+
+    #[allow(clippy::too_many_lines)]
+    fn node(&mut self) -> Result<Rc<Node>> {
+        let offset = self.position;
+        let kind = self.byte()?;
+
+        let node = match kind {
+            b'0' => Node::Nil,
+            b'T' => Node::True,
+            b'F' => Node::False,
+            b'i' => Node::Fixnum(self.int()?),
+            b':' => {
+                let name = self.chunk()?;
+                self.symbols.push(name.clone());
+                Node::Symbol(name)
+            }
+            b';' => Node::Symbol(self.symbol_link()?),
+            b'@' => {
+                let index = self.length()?;
+                self.links += 1;
+
+                return match self.objects.get(index) {
+                    Some(Some(node)) => Ok(node.clone()),
+                    Some(None) => bail!(
+                        "Object link at offset {offset} points to its own container. Recursive structures aren't supported."
+                    ),
+                    None => {
+                        bail!(
+                            "Object link {index} at offset {offset} points to nothing."
+                        )
+                    }
+                };
+            }
+            b'I' => {
+                let index = self.objects.len();
+                let inner = self.node()?;
+                let ivars = self.ivars()?;
+                let registered = self
+                    .objects
+                    .get(index)
+                    .and_then(Option::as_ref)
+                    .is_some_and(|object| Rc::ptr_eq(object, &inner));
+                let node = Rc::new(Node::Ivars { inner, ivars });
+
+                // Instance variables belong to the inner object, so links to it get them too.
+                if registered {
+                    self.objects[index] = Some(node.clone());
+                }
+
+                return Ok(node);
+            }
+            b'e' => {
+                let module = self.symbol()?;
+                let inner = self.node()?;
+                Node::Extended { module, inner }
+            }
+            b'C' => {
+                let class = self.symbol()?;
+                let inner = self.node()?;
+                Node::UserClass { class, inner }
+            }
+            b'"' => {
+                return self
+                    .registered(|reader| Ok(Node::String(reader.chunk()?)));
+            }
+            b'f' => {
+                return self
+                    .registered(|reader| Ok(Node::Float(reader.chunk()?)));
+            }
+            b'l' => {
+                return self.registered(|reader| {
+                    let sign = reader.byte()?;
+                    let shorts = reader.length()?;
+                    let digits = reader.bytes(shorts * 2)?.to_vec();
+                    Ok(Node::Bignum { sign, digits })
+                });
+            }
+            b'/' => {
+                return self.registered(|reader| {
+                    let source = reader.chunk()?;
+                    let options = reader.byte()?;
+                    Ok(Node::Regexp { source, options })
+                });
+            }
+            b'[' => {
+                return self.registered(|reader| {
+                    let length = reader.length()?;
+                    let mut elements = Vec::with_capacity(length.min(1024));
+
+                    for _ in 0..length {
+                        elements.push(reader.node()?);
+                    }
+
+                    Ok(Node::Array(elements))
+                });
+            }
+            b'{' | b'}' => {
+                return self.registered(|reader| {
+                    let length = reader.length()?;
+                    let mut pairs = Vec::with_capacity(length.min(1024));
+
+                    for _ in 0..length {
+                        let key = reader.node()?;
+                        pairs.push((key, reader.node()?));
+                    }
+
+                    let default = if kind == b'}' {
+                        Some(reader.node()?)
+                    } else {
+                        None
+                    };
+
+                    Ok(Node::Hash { pairs, default })
+                });
+            }
+            b'o' => {
+                let class = self.symbol()?;
+
+                return self.registered(|reader| {
+                    Ok(Node::Object {
+                        class,
+                        ivars: reader.ivars()?,
+                    })
+                });
+            }
+            b'S' => {
+                let class = self.symbol()?;
+
+                return self.registered(|reader| {
+                    Ok(Node::Struct {
+                        class,
+                        members: reader.ivars()?,
+                    })
+                });
+            }
+            b'c' | b'm' | b'M' => {
+                return self.registered(|reader| {
+                    let name = reader.chunk()?;
+
+                    Ok(match kind {
+                        b'c' => Node::Class(name),
+                        b'm' => Node::Module(name),
+                        _ => Node::OldModule(name),
+                    })
+                });
+            }
+            b'u' => {
+                let class = self.symbol()?;
+
+                return self.registered(|reader| {
+                    Ok(Node::UserDefined {
+                        class,
+                        data: reader.chunk()?,
+                    })
+                });
+            }
+            b'U' => {
+                let class = self.symbol()?;
+
+                return self.registered(|reader| {
+                    Ok(Node::UserMarshal {
+                        class,
+                        data: reader.node()?,
+                    })
+                });
+            }
+            b'd' => {
+                let class = self.symbol()?;
+
+                return self.registered(|reader| {
+                    Ok(Node::Data {
+                        class,
+                        data: reader.node()?,
+                    })
+                });
+            }
+            _ => bail!("Unknown type {kind:#04x} at offset {offset}."),
+        };
+
+        Ok(Rc::new(node))
+    }
+}
+
+/// Parses Marshal `data`, and returns the root value and the number of expanded object links.
+fn parse(data: &[u8]) -> Result<(Rc<Node>, usize)> {
+    if !data.starts_with(&VERSION) {
+        bail!("Not a Marshal 4.8 file.");
+    }
+
+    let mut reader = Reader {
+        data,
+        position: VERSION.len(),
+        symbols: Vec::new(),
+        objects: Vec::new(),
+        links: 0,
+    };
+
+    let root = reader.node()?;
+    Ok((root, reader.links))
+}
+
+/// Dumps Marshal data without object links, so anything, that's inserted into it, doesn't shift indices of the links.
+#[derive(Default)]
+struct Writer {
+    data: Vec<u8>,
+    symbols: HashMap<Name, usize>,
+}
+
+impl Writer {
+    fn int(&mut self, value: i32) {
+        match value {
+            0 => self.data.push(0),
+            1..=122 => self.data.push((value + 5) as u8),
+            -123..=-1 => self.data.push((value - 5).cast_unsigned() as u8),
+            _ => {
+                let bytes = value.to_le_bytes();
+                let mut size = 4;
+
+                // Leading bytes, that only extend the sign, are omitted.
+                let fill = if value < 0 { 0xFF } else { 0 };
+
+                while size > 1 && bytes[size - 1] == fill {
+                    size -= 1;
+                }
+
+                let size = size as i8;
+                self.data
+                    .push(if value < 0 { -size } else { size }.cast_unsigned());
+                self.data.extend_from_slice(&bytes[..size as usize]);
+            }
+        }
+    }
+
+    fn length(&mut self, length: usize) -> Result<()> {
+        let Ok(length) = i32::try_from(length) else {
+            bail!("Length {length} doesn't fit into Marshal data.");
+        };
+
+        self.int(length);
+        Ok(())
+    }
+
+    fn chunk(&mut self, bytes: &[u8]) -> Result<()> {
+        self.length(bytes.len())?;
+        self.data.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn symbol(&mut self, name: &[u8]) -> Result<()> {
+        if let Some(&index) = self.symbols.get(name) {
+            self.data.push(b';');
+            return self.length(index);
+        }
+
+        self.symbols.insert(name.to_vec(), self.symbols.len());
+        self.data.push(b':');
+        self.chunk(name)
+    }
+
+    fn ivars(&mut self, ivars: &Ivars) -> Result<()> {
+        self.length(ivars.len())?;
+
+        for (name, value) in ivars {
+            self.symbol(name)?;
+            self.node(value)?;
+        }
+
+        Ok(())
+    }
+
+    fn node(&mut self, node: &Node) -> Result<()> {
+        match node {
+            Node::Nil => self.data.push(b'0'),
+            Node::True => self.data.push(b'T'),
+            Node::False => self.data.push(b'F'),
+            Node::Fixnum(value) => {
+                self.data.push(b'i');
+                self.int(*value);
+            }
+            Node::Symbol(name) => self.symbol(name)?,
+            Node::Float(digits) => {
+                self.data.push(b'f');
+                self.chunk(digits)?;
+            }
+            Node::Bignum { sign, digits } => {
+                self.data.extend_from_slice(&[b'l', *sign]);
+                self.length(digits.len() / 2)?;
+                self.data.extend_from_slice(digits);
+            }
+            Node::String(bytes) => {
+                self.data.push(b'"');
+                self.chunk(bytes)?;
+            }
+            Node::Regexp { source, options } => {
+                self.data.push(b'/');
+                self.chunk(source)?;
+                self.data.push(*options);
+            }
+            Node::Array(elements) => {
+                self.data.push(b'[');
+                self.length(elements.len())?;
+
+                for element in elements {
+                    self.node(element)?;
+                }
+            }
+            Node::Hash { pairs, default } => {
+                self.data.push(if default.is_some() { b'}' } else { b'{' });
+                self.length(pairs.len())?;
+
+                for (key, value) in pairs {
+                    self.node(key)?;
+                    self.node(value)?;
+                }
+
+                if let Some(default) = default {
+                    self.node(default)?;
+                }
+            }
+            Node::Object { class, ivars }
+            | Node::Struct {
+                class,
+                members: ivars,
+            } => {
+                self.data.push(if matches!(node, Node::Object { .. }) {
+                    b'o'
+                } else {
+                    b'S'
+                });
+                self.symbol(class)?;
+                self.ivars(ivars)?;
+            }
+            Node::Class(name) | Node::Module(name) | Node::OldModule(name) => {
+                self.data.push(match node {
+                    Node::Class(_) => b'c',
+                    Node::Module(_) => b'm',
+                    _ => b'M',
+                });
+                self.chunk(name)?;
+            }
+            Node::UserDefined { class, data } => {
+                self.data.push(b'u');
+                self.symbol(class)?;
+                self.chunk(data)?;
+            }
+            Node::UserMarshal { class, data: inner }
+            | Node::Data { class, data: inner }
+            | Node::UserClass { class, inner }
+            | Node::Extended {
+                module: class,
+                inner,
+            } => {
+                self.data.push(match node {
+                    Node::UserMarshal { .. } => b'U',
+                    Node::Data { .. } => b'd',
+                    Node::UserClass { .. } => b'C',
+                    _ => b'e',
+                });
+                self.symbol(class)?;
+                self.node(inner)?;
+            }
+            Node::Ivars { inner, ivars } => {
+                // Ruby links symbols, that were already dumped, without their encoding.
+                if let Node::Symbol(name) = &**inner
+                    && self.symbols.contains_key(name)
+                {
+                    return self.symbol(name);
+                }
+
+                self.data.push(b'I');
+                self.node(inner)?;
+                self.ivars(ivars)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn dump(root: &Node) -> Result<Vec<u8>> {
+    let mut writer = Writer {
+        data: VERSION.to_vec(),
+        ..Default::default()
+    };
+
+    writer.node(root)?;
+    Ok(writer.data)
+}
+
+/// Returns the copy of `node`, which children are replaced with the results of `map`. Nodes without children are returned as is.
+fn map_children(
+    node: &Rc<Node>,
+    map: &mut impl FnMut(&Rc<Node>) -> Result<Rc<Node>>,
+) -> Result<Rc<Node>> {
+    let mut ivars = |ivars: &Ivars| -> Result<Ivars> {
+        ivars
+            .iter()
+            .map(|(name, value)| Ok((name.clone(), map(value)?)))
+            .collect()
+    };
+
+    Ok(Rc::new(match &**node {
+        Node::Object {
+            class,
+            ivars: fields,
+        } => Node::Object {
+            class: class.clone(),
+            ivars: ivars(fields)?,
+        },
+        Node::Ivars {
+            inner,
+            ivars: fields,
+        } => Node::Ivars {
+            inner: inner.clone(),
+            ivars: ivars(fields)?,
+        },
+        Node::Array(elements) => {
+            Node::Array(elements.iter().map(&mut *map).collect::<Result<_>>()?)
+        }
+        Node::Hash { pairs, default } => Node::Hash {
+            pairs: pairs
+                .iter()
+                .map(|(key, value)| Ok((map(key)?, map(value)?)))
+                .collect::<Result<_>>()?,
+            default: default.as_ref().map(&mut *map).transpose()?,
+        },
+        _ => return Ok(node.clone()),
+    }))
+}
+
+/// Returns the copy of `node`, where unsupported subtrees are replaced with placeholders, and the subtrees are pushed to `blobs`.
+fn extract(node: &Rc<Node>, blobs: &mut Vec<Rc<Node>>) -> Result<Rc<Node>> {
+    if !node.is_supported() {
+        blobs.push(node.clone());
+        return Ok(Rc::new(Node::placeholder(blobs.len() - 1)?));
+    }
+
+    map_children(node, &mut |child| extract(child, blobs))
+}
+
+/// Returns the copy of `node`, where placeholders are replaced with `blobs`. Placeholders, that are restored, are counted in `restored`.
+fn restore(
+    node: &Rc<Node>,
+    blobs: &[Rc<Node>],
+    restored: &mut usize,
+) -> Result<Rc<Node>> {
+    if let Some(index) = node.placeholder_index() {
+        let Some(blob) = blobs.get(index) else {
+            bail!("Placeholder {index} doesn't have a blob.");
+        };
+
+        *restored += 1;
+        return Ok(blob.clone());
+    }
+
+    map_children(node, &mut |child| restore(child, blobs, restored))
+}
+
+/// Blobs, that were extracted from data files, by file name.
+#[derive(Default)]
+pub struct Opaque {
+    blobs: HashMap<String, Vec<Rc<Node>>>,
+}
+
+impl Opaque {
+    /// Rewrites Marshal files with `extension` in `data_path` in place, so the library can process them. Files, that don't need it, are left as is.
+    ///
+    /// `data_path` must be a copy of the data, since opaque parts are removed from the files until [`Opaque::restore`].
+    pub fn extract(data_path: &Path, extension: &str) -> Result<Self> {
+        let mut opaque = Self::default();
+
+        for entry in read_dir(data_path)? {
+            let path = entry?.path();
+
+            if path.extension().is_none_or(|ext| ext != extension) {
+                continue;
+            }
+
+            let name = path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+
+            let (root, links) = parse(&read(&path)?)
+                .with_context(|| format!("Parsing {name} as Marshal data"))
+                .kind(ErrorKind::ValidationFailed)?;
+
+            let mut blobs = Vec::new();
+            let sanitized = extract(&root, &mut blobs)?;
+
+            if links == 0 && blobs.is_empty() {
+                continue;
+            }
+
+            debug!(
+                "{name}: Expanded {links} object links, kept {} opaque objects.",
+                blobs.len()
+            );
+
+            write(&path, dump(&sanitized)?)?;
+
+            if !blobs.is_empty() {
+                opaque.blobs.insert(name, blobs);
+            }
+        }
+
+        Ok(opaque)
+    }
+
+    /// Puts opaque parts back into the files, that were written to `output_data_path`.
+    pub fn restore(&self, output_data_path: &Path) -> Result<()> {
+        for (name, blobs) in &self.blobs {
+            let path = output_data_path.join(name);
+
+            if !path.exists() {
+                continue;
+            }
+
+            let (root, _) = parse(&read(&path)?)
+                .with_context(|| format!("Parsing written {name}"))?;
+
+            let mut restored = 0;
+            let root = restore(&root, blobs, &mut restored)?;
+
+            if restored != blobs.len() {
+                warn!(
+                    "{name}: {} of {} opaque objects were lost, since the library didn't keep their placeholders.",
+                    blobs.len() - restored,
+                    blobs.len()
+                );
+            }
+
+            write(&path, dump(&root)?)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `["a", "a"]`, where the second string is a link to the first one.
+    const LINKED: &[u8] = b"\x04\x08[\x07I\"\x06a\x06:\x06ET@\x06";
+
+    /// `[Struct::P.new(1), 5]`.
+    const STRUCT: &[u8] = b"\x04\x08[\x07S:\x06P\x06:\x06xi\x06i\x0a";
+
+    #[test]
+    fn round_trips_supported_data() {
+        let data = b"\x04\x08[\x0di\x00i\x7fi\xff\x000TF{\x06:\x06ki\x06I\"\x06b\x06:\x06ET";
+        let (root, links) = parse(data).unwrap();
+
+        assert_eq!(links, 0);
+        assert_eq!(dump(&root).unwrap(), data);
+    }
+
+    #[test]
+    fn expands_links() {
+        let (root, links) = parse(LINKED).unwrap();
+        let dumped = dump(&root).unwrap();
+
+        assert_eq!(links, 1);
+        assert_eq!(
+            dumped,
+            b"\x04\x08[\x07I\"\x06a\x06:\x06ETI\"\x06a\x06;\x00T"
+        );
+        assert_eq!(parse(&dumped).unwrap().1, 0);
+    }
+
+    #[test]
+    fn restores_extracted_subtrees() {
+        let (root, _) = parse(STRUCT).unwrap();
+        let mut blobs = Vec::new();
+        let sanitized = extract(&root, &mut blobs).unwrap();
+
+        assert_eq!(blobs.len(), 1);
+        assert_eq!(sanitized.placeholder_index(), None);
+
+        let (written, _) = parse(&dump(&sanitized).unwrap()).unwrap();
+        let mut restored = 0;
+        let root = restore(&written, &blobs, &mut restored).unwrap();
+
+        assert_eq!(restored, 1);
+        assert_eq!(dump(&root).unwrap(), STRUCT);
+    }
+
+    #[test]
+    fn rejects_missing_blobs() {
+        let placeholder = Rc::new(Node::placeholder(3).unwrap());
+
+        assert_eq!(placeholder.placeholder_index(), Some(3));
+        assert!(restore(&placeholder, &[], &mut 0).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_data() {
+        for data in [
+            &b"\x04\x07["[..],
+            b"\x04\x08[\x07i\x06",
+            b"\x04\x08@\x06",
+            b"\x04\x08[\x06@\x00",
+            b"\x04\x08\"\xfa",
+        ] {
+            assert!(parse(data).is_err(), "{data:?}");
+        }
+    }
+}
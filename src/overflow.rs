@@ -0,0 +1,304 @@
+//! Detection of translations, that overflow the window they're displayed in.
+//!
+//! Windows differ in width, so limits are set per category of text: message box, choices, name box, descriptions and battle log. Categories are determined from game data, since translation files don't tell, which window an entry is displayed in. Width is measured in visible characters: control codes don't count, and full-width characters count twice.
+
+use crate::{
+    data::{data_file_path, load_rpgm_file},
+    dialogue,
+    error::{ErrorKind, WithKind},
+    translation::{
+        Line, TranslationFile, effective_translation, normalize,
+        strip_placeholders, translation_files,
+    },
+};
+use anyhow::{Result, anyhow};
+use rvpacker_lib::{NEW_LINE, types::EngineType};
+use std::{collections::HashMap, fs::read_to_string, path::Path, str::FromStr};
+use tracing::{info, warn};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    Message,
+    Choice,
+    Name,
+    Description,
+    Battle,
+}
+
+impl Category {
+    const ALL: [Self; 5] = [
+        Self::Message,
+        Self::Choice,
+        Self::Name,
+        Self::Description,
+        Self::Battle,
+    ];
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Message => "message",
+            Self::Choice => "choice",
+            Self::Name => "name",
+            Self::Description => "description",
+            Self::Battle => "battle",
+        }
+    }
+
+    /// Limits of default MV/MZ windows at the default font size.
+    const fn default_limit(self) -> usize {
+        match self {
+            Self::Message => 54,
+            Self::Choice => 24,
+            Self::Name => 16,
+            Self::Description => 58,
+            Self::Battle => 48,
+        }
+    }
+}
+
+/// Width limits of each category.
+#[derive(Debug, Clone)]
+pub struct Limits(HashMap<Category, usize>);
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self(
+            Category::ALL
+                .into_iter()
+                .map(|category| (category, category.default_limit()))
+                .collect(),
+        )
+    }
+}
+
+impl FromStr for Limits {
+    type Err = anyhow::Error;
+
+    /// Parses comma-separated `category=limit` pairs. Categories, that aren't given, keep their default limits.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut limits = Self::default();
+
+        for pair in s.split(',').map(str::trim).filter(|pair| !pair.is_empty())
+        {
+            let (name, limit) = pair.split_once('=').ok_or_else(|| {
+                anyhow!("`{pair}` is not `category=limit` pair.")
+            })?;
+
+            let category = Category::ALL
+                .into_iter()
+                .find(|category| category.name() == name.trim())
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Unknown category `{name}`. Expected one of: {}.",
+                        Category::ALL.map(Category::name).join(", ")
+                    )
+                })?;
+
+            limits.0.insert(category, limit.trim().parse()?);
+        }
+
+        Ok(limits)
+    }
+}
+
+/// Database files with descriptions.
+const DESCRIBED_FILES: &[&str] = &["Items", "Skills", "Weapons", "Armors"];
+
+/// Database files with battle messages.
+const BATTLE_FILES: &[&str] = &["Skills", "States"];
+
+const BATTLE_FIELDS: &[&str] =
+    &["message1", "message2", "message3", "message4"];
+
+/// Categories of sources by translation file name.
+type Categories = HashMap<String, HashMap<String, Category>>;
+
+fn insert(
+    categories: &mut Categories,
+    file: &str,
+    source: String,
+    category: Category,
+) {
+    if source.is_empty() {
+        return;
+    }
+
+    categories
+        .entry(file.to_string())
+        .or_default()
+        .entry(source)
+        .or_insert(category);
+}
+
+/// Collects database fields of `stems` files, that belong to `category`.
+fn database_categories(
+    categories: &mut Categories,
+    source_path: &Path,
+    engine_type: EngineType,
+    stems: &[&str],
+    fields: &[&str],
+    category: Category,
+) -> Result<()> {
+    for stem in stems {
+        let path = data_file_path(source_path, stem, engine_type);
+
+        if !path.exists() {
+            continue;
+        }
+
+        let value = load_rpgm_file(&path, engine_type)?;
+        let file = format!("{}.txt", stem.to_lowercase());
+
+        for object in value.as_array().into_iter().flatten() {
+            let Some(object) = object.as_object() else {
+                continue;
+            };
+
+            for field in fields {
+                if let Some(text) = object.get(*field).and_then(|v| v.as_str())
+                {
+                    insert(categories, &file, normalize(text), category);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn categories(
+    source_path: &Path,
+    engine_type: EngineType,
+) -> Result<Categories> {
+    let mut categories = Categories::new();
+
+    for (location, message) in dialogue::messages(source_path, engine_type)? {
+        insert(
+            &mut categories,
+            location.file,
+            message.source(),
+            Category::Message,
+        );
+        insert(
+            &mut categories,
+            location.file,
+            message.speaker_name,
+            Category::Name,
+        );
+    }
+
+    for (location, choice) in dialogue::choices(source_path, engine_type)? {
+        insert(
+            &mut categories,
+            location.file,
+            choice.text,
+            Category::Choice,
+        );
+    }
+
+    database_categories(
+        &mut categories,
+        source_path,
+        engine_type,
+        DESCRIBED_FILES,
+        &["description"],
+        Category::Description,
+    )?;
+    database_categories(
+        &mut categories,
+        source_path,
+        engine_type,
+        BATTLE_FILES,
+        BATTLE_FIELDS,
+        Category::Battle,
+    )?;
+
+    Ok(categories)
+}
+
+/// Returns the width of `line`, as the game displays it.
+fn width(line: &str) -> usize {
+    strip_placeholders(line)
+        .chars()
+        .map(|char| {
+            if matches!(
+                char,
+                '\u{1100}'..='\u{115F}'
+                    | '\u{2E80}'..='\u{A4CF}'
+                    | '\u{AC00}'..='\u{D7A3}'
+                    | '\u{F900}'..='\u{FAFF}'
+                    | '\u{FE30}'..='\u{FE4F}'
+                    | '\u{FF00}'..='\u{FF60}'
+                    | '\u{FFE0}'..='\u{FFE6}'
+            ) {
+                2
+            } else {
+                1
+            }
+        })
+        .sum()
+}
+
+/// Checks translations in `translation_path` against `limits`, and warns about lines, that exceed them. Fails, if there are any.
+pub fn check(
+    source_path: &Path,
+    translation_path: &Path,
+    engine_type: EngineType,
+    limits: &Limits,
+) -> Result<()> {
+    let categories = categories(source_path, engine_type)?;
+    let mut overflows = 0;
+
+    for name in translation_files(translation_path)? {
+        let Some(file_categories) = categories.get(&name) else {
+            continue;
+        };
+
+        let file = TranslationFile::parse(&read_to_string(
+            translation_path.join(&name),
+        )?);
+
+        for (line_index, line) in file.lines.iter().enumerate() {
+            let Line::Entry {
+                source,
+                translation,
+            } = line
+            else {
+                continue;
+            };
+
+            let Some(&category) = file_categories.get(source) else {
+                continue;
+            };
+
+            let limit = limits.0[&category];
+
+            for (index, text_line) in effective_translation(translation)
+                .split(NEW_LINE)
+                .enumerate()
+            {
+                let width = width(text_line);
+
+                if width > limit {
+                    overflows += 1;
+                    warn!(
+                        "{name}:{}: Line {} of {} is {width} characters wide, while the limit is {limit}.\n{text_line}",
+                        line_index + 1,
+                        index + 1,
+                        category.name()
+                    );
+                }
+            }
+        }
+    }
+
+    if overflows != 0 {
+        return Err(anyhow!(
+            "{overflows} lines of translation overflow their windows."
+        ))
+        .kind(ErrorKind::ValidationFailed);
+    }
+
+    info!("No lines overflow their windows.");
+    Ok(())
+}
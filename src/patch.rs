@@ -0,0 +1,663 @@
+//! Distributable patches of translated games.
+//!
+//! A patch is built from `write` output, in either layout. It's a directory, that mirrors the game's own structure and holds only the files, that the translation changed, so it's ready to be zipped and published. `patch.json` manifest in it describes the patch:
+//!
+//! ```json
+//! {
+//!     "version": "1.0",
+//!     "toolVersion": "11.2.0",
+//!     "engineType": 0,
+//!     "gameTitle": "My Game",
+//!     "deltaFormat": "rvpacker",
+//!     "files": [{ "path": "www/data/Map001.json", "size": 2048, "crc32": "0a1b2c3d", "base": { "size": 1024, "crc32": "4e5f6a7b" }, "delta": false }]
+//! }
+//! ```
+//!
+//! `base` is the checksum of the game's original file, if there's one. Big files may be stored as binary deltas of their originals, with `.rvdelta` extension, or all files, that have originals, as VCDIFF deltas with `.vcdiff` extension, so the patch doesn't redistribute any data of the game; `apply-patch` applies them after checking, that the original matches `base`. Other files can be copied over the game as is, even without the tool, but `apply-patch` checks all originals, and backs them up to `.rvpacker-backup` directory in the game root, so the patch can be undone.
+
+use crate::{
+    delta,
+    error::{ErrorKind, WithKind},
+    layout,
+};
+use anyhow::{Context, Result, anyhow, bail};
+use rvpacker_lib::types::EngineType;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeSet,
+    fmt::Write as _,
+    fs::{
+        File, copy, create_dir_all, read, read_dir, read_to_string,
+        remove_dir_all, remove_file, write,
+    },
+    io::Write as _,
+    path::{Component, Path, PathBuf},
+};
+use tracing::{info, warn};
+use zip::{CompressionMethod, DateTime, ZipWriter, write::SimpleFileOptions};
+
+const MANIFEST_FILE: &str = "patch.json";
+const BACKUP_DIR: &str = ".rvpacker-backup";
+const BACKUP_MANIFEST_FILE: &str = "backup.json";
+const README_FILE: &str = "README.txt";
+const SHELL_SCRIPT_FILE: &str = "install.sh";
+const BATCH_SCRIPT_FILE: &str = "install.bat";
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Checksum {
+    pub size: usize,
+    pub crc32: String,
+}
+
+impl Checksum {
+    fn of(content: &[u8]) -> Self {
+        Self {
+            size: content.len(),
+            crc32: format!("{:08x}", crc32fast::hash(content)),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct PatchFile {
+    /// Path of the file relative to the game root, with `/` separators.
+    pub path: String,
+
+    #[serde(flatten)]
+    pub checksum: Checksum,
+
+    pub base: Option<Checksum>,
+
+    /// Whether the file is stored as a delta of its original.
+    pub delta: bool,
+}
+
+/// Format of deltas in a patch.
+#[derive(
+    Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum DeltaFormat {
+    /// The tool's own format, that [`delta::encode`] produces.
+    #[default]
+    Rvpacker,
+
+    /// VCDIFF, that `xdelta3` produces.
+    Vcdiff,
+}
+
+impl DeltaFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Rvpacker => "rvdelta",
+            Self::Vcdiff => "vcdiff",
+        }
+    }
+}
+
+/// Files of a patch, that are stored as deltas of their originals.
+#[derive(Clone, Copy)]
+pub enum Deltas {
+    /// No files. All files are stored as is.
+    None,
+
+    /// Files bigger than this number of bytes, when a delta in the tool's format makes them smaller.
+    Above(usize),
+
+    /// All files, that have an original, as VCDIFF deltas, however big they are. Files, that the game doesn't have, are stored as is.
+    Vcdiff,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    /// Version of the patch, e.g. `1.0`.
+    pub version: String,
+    pub tool_version: String,
+    pub engine_type: EngineType,
+    pub game_title: String,
+
+    /// Patches, that were built before VCDIFF support, have deltas in the tool's format.
+    #[serde(default)]
+    pub delta_format: DeltaFormat,
+    pub files: Vec<PatchFile>,
+}
+
+#[derive(Default)]
+pub struct Report {
+    /// Files, that were placed in the patch.
+    pub packaged: usize,
+
+    /// Packaged files, that were stored as deltas.
+    pub deltas: usize,
+
+    /// Files of the output, that don't differ from the game's originals, and were left out.
+    pub unchanged: usize,
+}
+
+/// The game, that a patch is built for or applied to.
+pub struct Game<'a> {
+    input_dir: &'a Path,
+    root: PathBuf,
+
+    /// Data directory relative to the game root.
+    data_dir: PathBuf,
+
+    /// Directory, that original data files are read from. It's outside of the game, when `.rgss` archive was extracted to a work directory.
+    source_path: &'a Path,
+}
+
+impl<'a> Game<'a> {
+    pub fn new(
+        input_dir: &'a Path,
+        source_path: &'a Path,
+        engine_type: EngineType,
+    ) -> Result<Self> {
+        let data_dir = if engine_type.is_new() { "data" } else { "Data" };
+
+        Ok(Self {
+            input_dir,
+            root: layout::game_root(input_dir)?,
+            data_dir: layout::game_relative(Path::new(data_dir), input_dir),
+            source_path,
+        })
+    }
+
+    /// Returns the path of the original of `path` file, relative to the game root.
+    fn original(&self, path: &Path) -> PathBuf {
+        match path.strip_prefix(&self.data_dir) {
+            Ok(relative) => self.source_path.join(relative),
+            Err(_) => self.root.join(path),
+        }
+    }
+}
+
+/// Collects files of `dir` recursively, in a stable order.
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries: Vec<_> = read_dir(dir)?.flatten().collect();
+    entries.sort_by_key(std::fs::DirEntry::file_name);
+
+    for entry in entries {
+        let path = entry.path();
+
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns `README.txt` stub of the patch with `manifest`, for the translator to fill in before publishing. Installation instructions mention install scripts, if the patch has them.
+fn readme(manifest: &Manifest, install_scripts: bool) -> String {
+    let mut output = format!(
+        "{} translation patch {}\n\n<Describe the translation, its authors and the game version it's for.>\n\nThe patch changes {} files of the game:\n\n",
+        manifest.game_title,
+        manifest.version,
+        manifest.files.len()
+    );
+
+    for file in &manifest.files {
+        let _ = writeln!(output, "  {}", file.path);
+    }
+
+    output.push_str("\nInstallation\n\nBack up the game directory first.\n\n");
+
+    if manifest.files.iter().all(|file| !file.delta) {
+        output.push_str("Extract the patch into the game directory, replacing its files.\n\n");
+    }
+
+    if install_scripts {
+        let _ = write!(
+            output,
+            "Or run `{BATCH_SCRIPT_FILE}` on Windows, or `sh {SHELL_SCRIPT_FILE} <game directory>` on Linux and macOS, from the patch directory. The script copies the files over the game.\n\n"
+        );
+    }
+
+    if manifest.delta_format == DeltaFormat::Vcdiff {
+        output.push_str("Files with `.vcdiff` extension are VCDIFF deltas of the game's original files. Apply them with `xdelta3 -d -s <original file> <file>.vcdiff <file>`, or with any other VCDIFF tool.\n\n");
+    }
+
+    let _ = writeln!(
+        output,
+        "With rvpacker-txt-rs {} or newer, `rvpacker-txt-rs apply-patch <patch directory> -i <game directory>` checks, that the game's files are the ones the patch was built for, and backs them up, so `apply-patch --undo` restores them.",
+        manifest.tool_version
+    );
+
+    output
+}
+
+/// Returns `sh` script, that copies files of the patch with `manifest` to the game directory in its argument.
+fn shell_script(manifest: &Manifest) -> String {
+    let mut output = format!(
+        "#!/bin/sh\n# Installs {} translation patch {}. Usage: sh {SHELL_SCRIPT_FILE} <game directory>\nset -e\n\nif [ -z \"$1\" ]; then\n    echo \"Usage: sh $0 <game directory>\" >&2\n    exit 1\nfi\n\n# Relative game directory is resolved before changing to the patch directory.\ngame=\"$(cd \"$1\" && pwd)\"\ncd \"$(dirname \"$0\")\"\n\ninstall_file() {{\n    mkdir -p \"$game/$(dirname \"$1\")\"\n    cp \"$1\" \"$game/$1\"\n}}\n\n",
+        manifest.game_title.replace('\n', " "),
+        manifest.version
+    );
+
+    for file in &manifest.files {
+        let _ = writeln!(
+            output,
+            "install_file '{}'",
+            file.path.replace('\'', r"'\''")
+        );
+    }
+
+    output.push_str("\necho \"Patch is installed.\"\n");
+    output
+}
+
+/// Returns Windows batch script, that copies files of the patch with `manifest` to the game directory in its argument, or the one, that the user inputs.
+fn batch_script(manifest: &Manifest) -> String {
+    let mut output = format!(
+        "@echo off\r\nrem Installs {} translation patch {}. Usage: {BATCH_SCRIPT_FILE} <game directory>\r\nsetlocal\r\n\r\nset \"game=%~1\"\r\nif \"%game%\"==\"\" set /p \"game=Game directory: \"\r\nrem Relative game directory is resolved before changing to the patch directory.\r\nfor %%i in (\"%game%\") do set \"game=%%~fi\"\r\ncd /d \"%~dp0\"\r\n\r\nif not exist \"%game%\\\" (\r\n    echo %game% doesn't exist.\r\n    pause\r\n    exit /b 1\r\n)\r\n\r\n",
+        manifest.game_title.replace('\n', " ").replace('%', "%%"),
+        manifest.version.replace('%', "%%")
+    );
+
+    for file in &manifest.files {
+        let windows_path =
+            |path: &str| path.replace('/', "\\").replace('%', "%%");
+        let path = windows_path(&file.path);
+        let dir = file
+            .path
+            .rsplit_once('/')
+            .map(|(dir, _)| windows_path(dir))
+            .unwrap_or_default();
+
+        if !dir.is_empty() {
+            let _ = write!(
+                output,
+                "if not exist \"%game%\\{dir}\\\" mkdir \"%game%\\{dir}\"\r\n"
+            );
+        }
+
+        let _ = write!(
+            output,
+            "copy /y \"{path}\" \"%game%\\{path}\" >nul || goto failed\r\n"
+        );
+    }
+
+    output.push_str("\r\necho Patch is installed.\r\npause\r\nexit /b 0\r\n\r\n:failed\r\necho Failed to copy files to %game%.\r\npause\r\nexit /b 1\r\n");
+    output
+}
+
+/// Packs the patch directory at `patch_path` into ZIP archive at `archive_path`, with the files at the root of the archive, so it can be extracted into the game directory. Returns the number of packed files.
+pub fn archive(patch_path: &Path, archive_path: &Path) -> Result<usize> {
+    let mut files = Vec::new();
+    collect_files(patch_path, &mut files)?;
+
+    let mut writer = ZipWriter::new(File::create(archive_path)?);
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .last_modified_time(DateTime::DEFAULT);
+
+    for file in &files {
+        let name = file
+            .strip_prefix(patch_path)?
+            .to_string_lossy()
+            .replace('\\', "/");
+        writer.start_file(name, options)?;
+        writer.write_all(&read(file)?)?;
+    }
+
+    writer.finish()?;
+    Ok(files.len())
+}
+
+/// Builds the patch at `patch_path` from `write` output in `output_root`, completing `manifest` with its files, and writes `README.txt` stub next to `patch.json`. Files are stored as deltas according to `deltas`. With `install_scripts`, scripts, that copy the files over the game without the tool, are added. Existing patch is replaced only if `force` is set.
+pub fn create(
+    output_root: &Path,
+    patch_path: &Path,
+    game: &Game,
+    mut manifest: Manifest,
+    deltas: Deltas,
+    install_scripts: bool,
+    force: bool,
+) -> Result<Report> {
+    if !output_root.exists() {
+        bail!("`output` directory does not exist. Run `write` first.");
+    }
+
+    if patch_path.exists() {
+        if !force {
+            return Err(anyhow!(
+                "{} already exists. Pass `--force` to replace it.",
+                patch_path.display()
+            ))
+            .kind(ErrorKind::Aborted);
+        }
+
+        remove_dir_all(patch_path)?;
+    }
+
+    let mut files = Vec::new();
+    collect_files(output_root, &mut files)?;
+
+    manifest.delta_format = match deltas {
+        Deltas::Vcdiff => DeltaFormat::Vcdiff,
+        Deltas::None | Deltas::Above(_) => DeltaFormat::Rvpacker,
+    };
+
+    let mut report = Report::default();
+
+    for file in files {
+        let relative = file.strip_prefix(output_root)?;
+        let path = layout::game_relative(relative, game.input_dir);
+        let content = read(&file)?;
+        let base = read(game.original(&path)).ok();
+
+        if base.as_deref() == Some(content.as_slice()) {
+            report.unchanged += 1;
+            continue;
+        }
+
+        let delta = match (&base, deltas) {
+            (Some(base), Deltas::Above(threshold))
+                if content.len() > threshold =>
+            {
+                Some(delta::encode(base, &content)?)
+                    .filter(|delta| delta.len() < content.len())
+            }
+            (Some(base), Deltas::Vcdiff) => Some(
+                delta::encode_vcdiff(base, &content)
+                    .with_context(|| format!("Encoding {}", path.display()))?,
+            ),
+            _ => None,
+        };
+
+        let mut target = patch_path.join(&path);
+
+        if delta.is_some() {
+            target
+                .as_mut_os_string()
+                .push(format!(".{}", manifest.delta_format.extension()));
+        }
+
+        if let Some(parent) = target.parent() {
+            create_dir_all(parent)?;
+        }
+
+        write(&target, delta.as_deref().unwrap_or(&content))?;
+
+        manifest.files.push(PatchFile {
+            path: path.to_string_lossy().replace('\\', "/"),
+            checksum: Checksum::of(&content),
+            base: base.as_deref().map(Checksum::of),
+            delta: delta.is_some(),
+        });
+
+        report.packaged += 1;
+        report.deltas += usize::from(delta.is_some());
+    }
+
+    if install_scripts {
+        if manifest.files.iter().any(|file| file.delta) {
+            bail!(
+                "Install scripts only copy files, so they can't apply deltas."
+            );
+        }
+
+        write(patch_path.join(SHELL_SCRIPT_FILE), shell_script(&manifest))?;
+        write(patch_path.join(BATCH_SCRIPT_FILE), batch_script(&manifest))?;
+    }
+
+    write(
+        patch_path.join(README_FILE),
+        readme(&manifest, install_scripts),
+    )?;
+    write(
+        patch_path.join(MANIFEST_FILE),
+        serde_json::to_string_pretty(&manifest)?,
+    )?;
+
+    Ok(report)
+}
+
+/// Reads the manifest of the patch at `patch_path`.
+pub fn load(patch_path: &Path) -> Result<Manifest> {
+    let manifest_path = patch_path.join(MANIFEST_FILE);
+
+    let content = read_to_string(&manifest_path).with_context(|| {
+        format!(
+            "{} is not a patch: {MANIFEST_FILE} is missing.",
+            patch_path.display()
+        )
+    })?;
+
+    serde_json::from_str(&content)
+        .with_context(|| format!("{MANIFEST_FILE} is malformed."))
+}
+
+/// Originals of patched files, that `apply-patch` keeps in the game root, so patches can be undone.
+#[derive(Default, Deserialize, Serialize)]
+struct Backup {
+    /// Files, which originals are backed up.
+    restored: BTreeSet<String>,
+
+    /// Files, that patches added to the game.
+    added: BTreeSet<String>,
+}
+
+impl Backup {
+    fn load(backup_path: &Path) -> Result<Self> {
+        let manifest_path = backup_path.join(BACKUP_MANIFEST_FILE);
+
+        if !manifest_path.exists() {
+            return Ok(Self::default());
+        }
+
+        serde_json::from_str(&read_to_string(&manifest_path)?)
+            .with_context(|| format!("{BACKUP_MANIFEST_FILE} is malformed."))
+    }
+
+    /// Backs up the original of `path` file at `target`, unless it's backed up already.
+    fn add(
+        &mut self,
+        backup_path: &Path,
+        path: &str,
+        target: &Path,
+    ) -> Result<()> {
+        if self.restored.contains(path) || self.added.contains(path) {
+            return Ok(());
+        }
+
+        if target.exists() {
+            let backup_file = backup_path.join(path);
+
+            if let Some(parent) = backup_file.parent() {
+                create_dir_all(parent)?;
+            }
+
+            copy(target, &backup_file)?;
+            self.restored.insert(path.to_string());
+        } else {
+            self.added.insert(path.to_string());
+        }
+
+        Ok(())
+    }
+
+    fn save(&self, backup_path: &Path) -> Result<()> {
+        create_dir_all(backup_path)?;
+        write(
+            backup_path.join(BACKUP_MANIFEST_FILE),
+            serde_json::to_string_pretty(self)?,
+        )?;
+        Ok(())
+    }
+
+    /// Returns the original of `path` file, before any patch was applied.
+    fn original(
+        &self,
+        backup_path: &Path,
+        game: &Game,
+        path: &str,
+    ) -> Option<Vec<u8>> {
+        if self.restored.contains(path) {
+            read(backup_path.join(path)).ok()
+        } else if self.added.contains(path) {
+            None
+        } else {
+            read(game.original(Path::new(path))).ok()
+        }
+    }
+}
+
+/// Applies the patch at `patch_path` with `manifest` to `game`, that has `engine_type` and `game_title`.
+///
+/// Originals of all files are checked against their checksums in the manifest before anything is written. Mismatching originals fail the patch, unless `force` is set, in which case only files, that are stored as deltas, can't be applied. Originals are backed up, so `undo` restores them, and a newer version of the patch can be applied over an older one. Files, that already match the patch, are skipped. Returns the number of applied files.
+pub fn apply(
+    patch_path: &Path,
+    manifest: &Manifest,
+    game: &Game,
+    engine_type: EngineType,
+    game_title: &str,
+    force: bool,
+) -> Result<usize> {
+    if manifest.engine_type as u8 != engine_type as u8 {
+        return Err(anyhow!("Patch was built for a game on another engine."))
+            .kind(ErrorKind::ValidationFailed);
+    }
+
+    if manifest.game_title != game_title {
+        warn!(
+            "Patch was built for \"{}\", but the game is \"{game_title}\".",
+            manifest.game_title
+        );
+    }
+
+    for file in &manifest.files {
+        if !Path::new(&file.path)
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+        {
+            bail!(
+                "{}: Path in patch points outside of the game directory.",
+                file.path
+            );
+        }
+    }
+
+    let backup_path = game.root.join(BACKUP_DIR);
+    let mut backup = Backup::load(&backup_path)?;
+
+    let mut pending = Vec::new();
+    let mut mismatches = 0;
+
+    for file in &manifest.files {
+        let target = game.root.join(&file.path);
+
+        if read(&target)
+            .is_ok_and(|current| Checksum::of(&current) == file.checksum)
+        {
+            info!("{}: Already patched.", file.path);
+            continue;
+        }
+
+        let original = backup.original(&backup_path, game, &file.path);
+
+        if file.base != original.as_deref().map(Checksum::of) {
+            if file.delta || !force {
+                warn!(
+                    "{}: File differs from the original, that the patch was built for.",
+                    file.path
+                );
+                mismatches += 1;
+            } else {
+                warn!(
+                    "{}: File differs from the original, that the patch was built for. Replacing it, since `--force` is set.",
+                    file.path
+                );
+            }
+        }
+
+        pending.push((file, target, original));
+    }
+
+    if mismatches != 0 {
+        return Err(anyhow!(
+            "{mismatches} files of the game differ from the originals, that the patch was built for. Ensure the game version matches the patch. Pass `--force` to replace files, that aren't stored as deltas, anyway."
+        ))
+        .kind(ErrorKind::ValidationFailed);
+    }
+
+    let mut contents = Vec::with_capacity(pending.len());
+
+    for (file, target, original) in &pending {
+        let content = if file.delta {
+            let mut delta_path = patch_path.join(&file.path);
+            delta_path
+                .as_mut_os_string()
+                .push(format!(".{}", manifest.delta_format.extension()));
+
+            let original = original.as_deref().unwrap_or_default();
+            let delta = read(&delta_path)?;
+
+            match manifest.delta_format {
+                DeltaFormat::Rvpacker => delta::decode(original, &delta),
+                DeltaFormat::Vcdiff => delta::decode_vcdiff(original, &delta),
+            }
+            .with_context(|| format!("Applying {}", delta_path.display()))?
+        } else {
+            read(patch_path.join(&file.path))?
+        };
+
+        if Checksum::of(&content) != file.checksum {
+            return Err(anyhow!("{}: Patched file is corrupted.", file.path))
+                .kind(ErrorKind::ValidationFailed);
+        }
+
+        contents.push((file, target, content));
+    }
+
+    // Everything is backed up before the first file is written, so a failed patch can be undone too.
+    if !contents.is_empty() {
+        for (file, target, _) in &contents {
+            backup.add(&backup_path, &file.path, target)?;
+        }
+
+        backup.save(&backup_path)?;
+    }
+
+    for (file, target, content) in &contents {
+        if let Some(parent) = target.parent() {
+            create_dir_all(parent)?;
+        }
+
+        write(target, content)?;
+        info!("{}: Patched.", file.path);
+    }
+
+    Ok(contents.len())
+}
+
+/// Restores originals of files, that patches changed in `game`, and removes files, that they added. Returns the number of restored and removed files.
+pub fn undo(game: &Game) -> Result<usize> {
+    let backup_path = game.root.join(BACKUP_DIR);
+
+    if !backup_path.exists() {
+        bail!("No patch was applied to the game.");
+    }
+
+    let backup = Backup::load(&backup_path)?;
+
+    for path in &backup.restored {
+        copy(backup_path.join(path), game.root.join(path))?;
+        info!("{path}: Restored.");
+    }
+
+    for path in &backup.added {
+        let target = game.root.join(path);
+
+        if target.exists() {
+            remove_file(target)?;
+            info!("{path}: Removed.");
+        }
+    }
+
+    remove_dir_all(&backup_path)?;
+    Ok(backup.restored.len() + backup.added.len())
+}
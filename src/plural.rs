@@ -0,0 +1,207 @@
+//! Plural forms in translations, for target languages, whose grammatical number can't be forced into one string.
+//!
+//! Translation can have blocks of plural forms in CLDR order of the target language, with the placeholder of the count before the colon: `У вас {{%1: монета|монеты|монет}}`. Form, that fits the count, can only be chosen at runtime, so at write time blocks are either validated and kept as is, for plugins, that consume them, or collapsed to one form for games without such plugins.
+
+use crate::{
+    error::{ErrorKind, WithKind},
+    rules::{Staged, stage_translations},
+    translation::placeholders,
+};
+use anyhow::{Result, anyhow, bail};
+use regex::Regex;
+use std::{
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+use tracing::{info, warn};
+
+static BLOCK_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{([^{}]*)\}\}").unwrap());
+
+/// Plural forms of languages, in CLDR order.
+const LANGUAGES: &[(&[&str], &[&str])] = &[
+    (&["ja", "zh", "ko", "vi", "th", "id", "ms"], &["other"]),
+    (
+        &[
+            "en", "de", "nl", "sv", "da", "nb", "nn", "no", "fi", "et", "it",
+            "es", "pt", "fr", "el", "hu", "bg", "ca", "tr",
+        ],
+        &["one", "other"],
+    ),
+    (
+        &["ru", "uk", "be", "pl", "hr", "sr", "bs"],
+        &["one", "few", "many"],
+    ),
+    (&["cs", "sk", "lt"], &["one", "few", "other"]),
+    (&["ar"], &["zero", "one", "two", "few", "many", "other"]),
+];
+
+/// What write does with blocks of plural forms.
+enum Mode {
+    /// Validates blocks and keeps them as is.
+    Keep,
+
+    /// Replaces blocks with the form of this index.
+    Collapse(usize),
+}
+
+pub struct Plurals {
+    language: String,
+    forms: &'static [&'static str],
+    mode: Mode,
+}
+
+impl Plurals {
+    /// Creates plural handling for `language`, e.g. `ru` or `pt-BR`, in `mode`, that's either `keep` or the name of the form, that blocks are collapsed to.
+    pub fn new(language: &str, mode: &str) -> Result<Self> {
+        let code = language
+            .split(['-', '_'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+
+        let Some(&(_, forms)) =
+            LANGUAGES.iter().find(|(codes, _)| codes.contains(&&*code))
+        else {
+            bail!(
+                "Plural forms of `{language}` are unknown. Expected one of: {}.",
+                LANGUAGES
+                    .iter()
+                    .flat_map(|(codes, _)| codes.iter())
+                    .copied()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        };
+
+        let mode = if mode == "keep" {
+            Mode::Keep
+        } else if let Some(index) = forms.iter().position(|form| *form == mode)
+        {
+            Mode::Collapse(index)
+        } else {
+            bail!(
+                "`{mode}` is not a plural form of `{language}`. Expected `keep` or one of: {}.",
+                forms.join(", ")
+            );
+        };
+
+        Ok(Self {
+            language: language.to_string(),
+            forms,
+            mode,
+        })
+    }
+
+    /// Validates blocks of plural forms in `translation`, and collapses them, if needed. Returns descriptions of invalid blocks as errors.
+    fn apply(
+        &self,
+        source: &str,
+        translation: &str,
+    ) -> Result<String, Vec<String>> {
+        let mut problems = Vec::new();
+
+        let result =
+            BLOCK_RE.replace_all(translation, |captures: &regex::Captures| {
+                let block = &captures[0];
+
+                let Some((count, forms)) = captures[1].split_once(':') else {
+                    problems.push(format!("`{block}` has no count"));
+                    return block.to_string();
+                };
+
+                let count = count.trim();
+
+                if placeholders(count) != [count] {
+                    problems.push(format!(
+                        "Count `{count}` of `{block}` is not a placeholder"
+                    ));
+                } else if !source.contains(count) {
+                    problems.push(format!(
+                        "Count `{count}` of `{block}` is not in the source"
+                    ));
+                }
+
+                let forms: Vec<&str> =
+                    forms.split('|').map(str::trim).collect();
+
+                if forms.len() != self.forms.len() {
+                    problems.push(format!(
+                        "`{block}` has {} forms, while `{}` needs {}: {}",
+                        forms.len(),
+                        self.language,
+                        self.forms.len(),
+                        self.forms.join(", ")
+                    ));
+                }
+
+                match self.mode {
+                    Mode::Collapse(index)
+                        if forms.len() == self.forms.len() =>
+                    {
+                        forms[index].to_string()
+                    }
+                    _ => block.to_string(),
+                }
+            });
+
+        let rest = BLOCK_RE.replace_all(translation, "");
+
+        if rest.contains("{{") || rest.contains("}}") {
+            problems.push(String::from("Plural block is not terminated"));
+        }
+
+        if problems.is_empty() {
+            Ok(result.into_owned())
+        } else {
+            Err(problems)
+        }
+    }
+
+    /// Copies files of `translation_path` to `staging_path`, and validates or collapses blocks of plural forms in translations of the copies. Fails, if any block is invalid.
+    pub fn stage(
+        &self,
+        translation_path: &Path,
+        staging_path: PathBuf,
+    ) -> Result<Staged> {
+        let mut invalid = 0;
+
+        let (staged, changed) = stage_translations(
+            translation_path,
+            staging_path,
+            |name, line_number, source, translation| {
+                if !translation.contains("{{") && !translation.contains("}}") {
+                    return translation.to_string();
+                }
+
+                match self.apply(source, translation) {
+                    Ok(result) => result,
+                    Err(problems) => {
+                        for problem in &problems {
+                            warn!("{name}:{line_number}: {problem}.");
+                        }
+
+                        invalid += problems.len();
+                        translation.to_string()
+                    }
+                }
+            },
+        )?;
+
+        if invalid != 0 {
+            return Err(anyhow!(
+                "{invalid} problems in plural forms of translations."
+            ))
+            .kind(ErrorKind::ValidationFailed);
+        }
+
+        if let Mode::Collapse(index) = self.mode {
+            info!(
+                "Collapsed plural forms of {changed} translations to `{}` form.",
+                self.forms[index]
+            );
+        }
+
+        Ok(staged)
+    }
+}
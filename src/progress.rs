@@ -0,0 +1,235 @@
+//! Translation progress reports, for publishing on a project page.
+//!
+//! HTML report is a single static page without scripts or external resources, so it can be uploaded anywhere as is. Badge is a small SVG image in the style of shields.io badges, with the percentage of translated entries, for embedding in a README. Progress is counted per translation file, and per map for `maps.txt`, like the summary after read and write. Recently changed entries are taken from attribution, so only translations, that were imported or pulled with `--translator`, are listed.
+
+use crate::{
+    attribution::{self, Attributed},
+    report::entry_counts,
+    translation::{TranslationFile, denormalize, translation_files},
+    xml::{escape, escape_lines},
+};
+use anyhow::Result;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Write,
+    fs::read_to_string,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const STYLE: &str = r"body { font-family: sans-serif; max-width: 960px; margin: 2em auto; padding: 0 1em; color: #222; }
+table { border-collapse: collapse; width: 100%; margin-bottom: 2em; }
+th, td { text-align: left; padding: 0.3em 0.6em; border-bottom: 1px solid #ddd; vertical-align: top; }
+td.number { text-align: right; font-variant-numeric: tabular-nums; }
+.bar { background: #eee; border-radius: 3px; height: 1em; min-width: 8em; }
+.bar > div { background: #4c1; border-radius: 3px; height: 100%; }
+tr.total { font-weight: bold; }
+.muted { color: #777; }";
+
+/// Returns the percentage of `translated` entries out of `entries`, rounded down, so only complete files are at 100%.
+#[must_use]
+pub fn percent(translated: usize, entries: usize) -> usize {
+    if entries == 0 {
+        return 100;
+    }
+
+    translated * 100 / entries
+}
+
+/// Approximate width in pixels of a character of 11px Verdana, that badges use. Badges don't embed fonts, so text is sized, rather than measured.
+const BADGE_CHAR_WIDTH: usize = 7;
+
+/// Horizontal padding in pixels of each half of a badge.
+const BADGE_PADDING: usize = 6;
+
+/// Returns the color of a badge for `percent`, from red for barely started translations to bright green for complete ones.
+fn badge_color(percent: usize) -> &'static str {
+    match percent {
+        100.. => "#4c1",
+        80..=99 => "#97ca00",
+        60..=79 => "#a4a61d",
+        40..=59 => "#dfb317",
+        20..=39 => "#fe7d37",
+        _ => "#e05d44",
+    }
+}
+
+/// Returns entry and translated entry counts of all `counts` together.
+fn total(counts: &BTreeMap<String, (usize, usize)>) -> (usize, usize) {
+    counts.values().fold(
+        (0, 0),
+        |(entries, translated), (file_entries, file_translated)| {
+            (entries + file_entries, translated + file_translated)
+        },
+    )
+}
+
+/// Statistics of sources, that occur in more than one entry across translation files.
+#[derive(Default)]
+struct Duplicates {
+    /// Sources with more than one entry.
+    sources: usize,
+
+    /// Entries beyond the first of each source.
+    entries: usize,
+
+    /// Sources, which entries have different translations.
+    inconsistent: usize,
+}
+
+fn count_duplicates(translation_path: &Path) -> Result<Duplicates> {
+    let mut translations: HashMap<String, Vec<String>> = HashMap::new();
+
+    for name in translation_files(translation_path)? {
+        let file = TranslationFile::parse(&read_to_string(
+            translation_path.join(name),
+        )?);
+
+        for (_, source, translation) in file.entries() {
+            translations
+                .entry(source.to_string())
+                .or_default()
+                .push(translation.to_string());
+        }
+    }
+
+    let mut duplicates = Duplicates::default();
+
+    for translations in translations.values().filter(|list| list.len() > 1) {
+        duplicates.sources += 1;
+        duplicates.entries += translations.len() - 1;
+
+        let distinct: HashSet<&str> = translations
+            .iter()
+            .map(String::as_str)
+            .filter(|translation| !translation.is_empty())
+            .collect();
+        duplicates.inconsistent += usize::from(distinct.len() > 1);
+    }
+
+    Ok(duplicates)
+}
+
+/// Returns escaped text of a translation file, with its line breaks.
+fn escape_text(text: &str) -> String {
+    escape_lines(&denormalize(text), "<br>")
+}
+
+fn progress_cell(translated: usize, entries: usize) -> String {
+    let percent = percent(translated, entries);
+    format!(
+        "<td><div class=\"bar\" title=\"{percent}%\"><div style=\"width: {percent}%\"></div></div></td><td class=\"number\">{percent}%</td>"
+    )
+}
+
+/// Returns HTML page with progress of translation files in `translation_path` of game `title`, with up to `recent` most recently changed entries.
+pub fn html(
+    translation_path: &Path,
+    title: &str,
+    recent: usize,
+) -> Result<String> {
+    let counts = entry_counts(translation_path)?;
+    let duplicates = count_duplicates(translation_path)?;
+    let changed = attribution::recent(translation_path, recent)?;
+
+    let title = escape(title);
+    let today = attribution::date(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs()),
+    );
+
+    let mut output = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<title>{title}: Translation progress</title>\n<style>\n{STYLE}\n</style>\n</head>\n<body>\n<h1>{title}</h1>\n<p class=\"muted\">Translation progress as of {today}.</p>\n"
+    );
+
+    let (entries, translated) = total(&counts);
+
+    output.push_str("<h2>Files</h2>\n<table>\n<tr><th>File</th><th>Entries</th><th>Translated</th><th>Untranslated</th><th colspan=\"2\">Progress</th></tr>\n");
+
+    for (name, (entries, translated)) in &counts {
+        let _ = writeln!(
+            output,
+            "<tr><td>{}</td><td class=\"number\">{entries}</td><td class=\"number\">{translated}</td><td class=\"number\">{}</td>{}</tr>",
+            escape(name),
+            entries - translated,
+            progress_cell(*translated, *entries)
+        );
+    }
+
+    let _ = writeln!(
+        output,
+        "<tr class=\"total\"><td>Total</td><td class=\"number\">{entries}</td><td class=\"number\">{translated}</td><td class=\"number\">{}</td>{}</tr>\n</table>",
+        entries - translated,
+        progress_cell(translated, entries)
+    );
+
+    let _ = writeln!(
+        output,
+        "<h2>Duplicates</h2>\n<table>\n<tr><td>Sources, that occur more than once</td><td class=\"number\">{}</td></tr>\n<tr><td>Duplicate entries</td><td class=\"number\">{}</td></tr>\n<tr><td>Sources with inconsistent translations</td><td class=\"number\">{}</td></tr>\n</table>",
+        duplicates.sources, duplicates.entries, duplicates.inconsistent
+    );
+
+    output.push_str("<h2>Recently changed</h2>\n");
+
+    if changed.is_empty() {
+        output.push_str("<p class=\"muted\">No attributed changes.</p>\n");
+    } else {
+        output.push_str("<table>\n<tr><th>Date</th><th>Translator</th><th>File</th><th>Source</th><th>Translation</th></tr>\n");
+
+        for Attributed {
+            file,
+            source,
+            translation,
+            attribution,
+        } in &changed
+        {
+            let _ = writeln!(
+                output,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                attribution.date,
+                escape(&attribution.translator),
+                escape(file),
+                escape_text(source),
+                escape_text(translation)
+            );
+        }
+
+        output.push_str("</table>\n");
+    }
+
+    output.push_str("</body>\n</html>\n");
+    Ok(output)
+}
+
+/// Returns SVG badge with `label`, e.g. `translation`, and the percentage of translated entries of translation files in `translation_path`.
+pub fn badge(translation_path: &Path, label: &str) -> Result<String> {
+    let (entries, translated) = total(&entry_counts(translation_path)?);
+
+    let value = format!("{}%", percent(translated, entries));
+    let label_width =
+        label.chars().count() * BADGE_CHAR_WIDTH + BADGE_PADDING * 2;
+    let value_width =
+        value.chars().count() * BADGE_CHAR_WIDTH + BADGE_PADDING * 2;
+    let width = label_width + value_width;
+
+    // Text is drawn at 10 times the scale and scaled down, which positions it more precisely, as shields.io does.
+    let label_x = label_width * 5;
+    let value_x = (label_width + value_width / 2) * 10;
+    let label = escape(label);
+    let color = badge_color(percent(translated, entries));
+
+    Ok(format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{label}: {value}">
+<title>{label}: {value}</title>
+<linearGradient id="gradient" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient>
+<clipPath id="round"><rect width="{width}" height="20" rx="3" fill="#fff"/></clipPath>
+<g clip-path="url(#round)"><rect width="{label_width}" height="20" fill="#555"/><rect x="{label_width}" width="{value_width}" height="20" fill="{color}"/><rect width="{width}" height="20" fill="url(#gradient)"/></g>
+<g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="110">
+<text x="{label_x}" y="150" fill="#010101" fill-opacity=".3" transform="scale(.1)">{label}</text><text x="{label_x}" y="140" transform="scale(.1)">{label}</text>
+<text x="{value_x}" y="150" fill="#010101" fill-opacity=".3" transform="scale(.1)">{value}</text><text x="{value_x}" y="140" transform="scale(.1)">{value}</text>
+</g>
+</svg>
+"##
+    ))
+}
@@ -0,0 +1,348 @@
+//! Purge modes, that remove entries from translation files by criteria other than missing translation.
+
+use crate::{
+    data::map_files,
+    ignore::IgnoreFile,
+    translate::MACHINE_TRANSLATION_COMMENT_PREFIX,
+    translation::{Line, TranslationFile, is_annotation, translation_files},
+};
+use anyhow::{Result, bail};
+use regex::Regex;
+use rvpacker_lib::types::{EngineType, FileFlags, RPGMFileType};
+use std::{
+    collections::HashSet,
+    fs::{OpenOptions, read_dir, read_to_string, write},
+    io::Write,
+    path::Path,
+};
+use tracing::{debug, info};
+
+/// Returns the flag, that selects the translation file of `file_type`.
+const fn file_flag(file_type: RPGMFileType) -> FileFlags {
+    match file_type {
+        RPGMFileType::Actors => FileFlags::Actors,
+        RPGMFileType::Armors => FileFlags::Armors,
+        RPGMFileType::Classes => FileFlags::Classes,
+        RPGMFileType::Events => FileFlags::CommonEvents,
+        RPGMFileType::Enemies => FileFlags::Enemies,
+        RPGMFileType::Items => FileFlags::Items,
+        RPGMFileType::Map => FileFlags::Map,
+        RPGMFileType::Skills => FileFlags::Skills,
+        RPGMFileType::States => FileFlags::States,
+        RPGMFileType::System => FileFlags::System,
+        RPGMFileType::Troops => FileFlags::Troops,
+        RPGMFileType::Weapons => FileFlags::Weapons,
+        RPGMFileType::Scripts | RPGMFileType::Plugins => FileFlags::Scripts,
+        RPGMFileType::Invalid => FileFlags::empty(),
+    }
+}
+
+/// Appends the pending section ID and `annotations` to `purged`.
+fn flush(
+    purged: &mut TranslationFile,
+    pending_id: &mut Option<u16>,
+    annotations: &mut Vec<Line>,
+) {
+    if let Some(id) = pending_id.take() {
+        purged.lines.push(Line::Id(id));
+    }
+
+    purged.lines.append(annotations);
+}
+
+/// Removes entries, whose source matches `pattern`, from translation files, regardless of their translation.
+///
+/// Files in `extra_files` are purged as well, but their entries are never added to the ignore file, since library doesn't read them.
+pub fn by_pattern(
+    translation_path: &Path,
+    pattern: &Regex,
+    file_flags: FileFlags,
+    extra_files: &[&str],
+    create_ignore: bool,
+) -> Result<()> {
+    let mut ignore_file = if create_ignore {
+        IgnoreFile::load(translation_path)
+    } else {
+        IgnoreFile::default()
+    };
+
+    let mut paths: Vec<_> = read_dir(translation_path)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+
+        let is_extra = extra_files.contains(&name);
+        let file_type = RPGMFileType::from_filename(name);
+
+        if !is_extra && !file_flags.intersects(file_flag(file_type)) {
+            continue;
+        }
+
+        debug!("{name}: Started purging.");
+
+        let file = TranslationFile::parse(&read_to_string(&path)?);
+        let mut purged = TranslationFile::default();
+        let mut pending_id = None;
+        let mut current_id = 0;
+        let mut removed = 0;
+
+        // Annotations of the next entry, that are removed together with it.
+        let mut annotations = Vec::new();
+
+        for line in file.lines {
+            match line {
+                Line::Id(id) => {
+                    // Annotations without an entry stay where they are.
+                    if !annotations.is_empty() {
+                        flush(&mut purged, &mut pending_id, &mut annotations);
+                    }
+
+                    pending_id = Some(id);
+                    current_id = id;
+                }
+                Line::Comment(comment) if is_annotation(&comment) => {
+                    annotations.push(Line::Comment(comment));
+                }
+                Line::Entry { source, .. } if pattern.is_match(&source) => {
+                    annotations.clear();
+                    removed += 1;
+
+                    if create_ignore && !is_extra {
+                        ignore_file.insert(file_type, current_id, source);
+                    }
+                }
+                line => {
+                    flush(&mut purged, &mut pending_id, &mut annotations);
+                    purged.lines.push(line);
+                }
+            }
+        }
+
+        if !annotations.is_empty() {
+            flush(&mut purged, &mut pending_id, &mut annotations);
+        }
+
+        if removed != 0 {
+            write(&path, purged.serialize())?;
+        }
+
+        info!("{name}: Successfully purged. Removed {removed} entries.");
+    }
+
+    if create_ignore {
+        ignore_file.save(translation_path)?;
+    }
+
+    Ok(())
+}
+
+/// Clears translations of entries, that are marked as machine-translated, and removes their marks, so they can be translated again.
+pub fn machine_translations(translation_path: &Path) -> Result<()> {
+    for name in translation_files(translation_path)? {
+        let path = translation_path.join(&name);
+        let file = TranslationFile::parse(&read_to_string(&path)?);
+        let mut purged = TranslationFile::default();
+        let mut marked = false;
+        let mut cleared = 0;
+
+        for mut line in file.lines {
+            match &mut line {
+                Line::Comment(comment)
+                    if comment
+                        .starts_with(MACHINE_TRANSLATION_COMMENT_PREFIX) =>
+                {
+                    marked = true;
+                    continue;
+                }
+                Line::Entry { translation, .. } if marked => {
+                    translation.clear();
+                    cleared += 1;
+                }
+                _ => {}
+            }
+
+            // A mark belongs to the entry after it and its other annotations.
+            if !matches!(&line, Line::Comment(comment) if is_annotation(comment))
+            {
+                marked = false;
+            }
+
+            purged.lines.push(line);
+        }
+
+        if cleared != 0 {
+            write(&path, purged.serialize())?;
+            info!(
+                "{name}: Successfully purged. Cleared {cleared} machine translations."
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Removes sections of `maps.txt`, whose map file no longer exists in `source_path`.
+///
+/// If `archive_path` is given, removed sections are appended to it in the translation file format, so their translations can be restored if the maps come back.
+pub fn stale_maps(
+    source_path: &Path,
+    translation_path: &Path,
+    engine_type: EngineType,
+    archive_path: Option<&Path>,
+) -> Result<()> {
+    let maps_path = translation_path.join("maps.txt");
+
+    if !maps_path.exists() {
+        return Ok(());
+    }
+
+    debug!("maps.txt: Started purging.");
+
+    let existing: HashSet<u16> = map_files(source_path, engine_type)?
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect();
+
+    // Most likely, data files weren't extracted from the archive, rather than the whole game lost its maps.
+    if existing.is_empty() {
+        bail!(
+            "No map files found in {}. Refusing to purge all maps.",
+            source_path.display()
+        );
+    }
+
+    let file = TranslationFile::parse(&read_to_string(&maps_path)?);
+    let mut purged = TranslationFile::default();
+    let mut stale = TranslationFile::default();
+    let mut stale_ids = Vec::new();
+    let mut is_stale = false;
+
+    for line in file.lines {
+        if let Line::Id(id) = line {
+            is_stale = !existing.contains(&id);
+
+            if is_stale {
+                stale_ids.push(id);
+            }
+        }
+
+        if is_stale {
+            stale.lines.push(line);
+        } else {
+            purged.lines.push(line);
+        }
+    }
+
+    if stale_ids.is_empty() {
+        info!("maps.txt: Successfully purged. No stale maps found.");
+        return Ok(());
+    }
+
+    if let Some(archive_path) = archive_path {
+        let mut archive = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(archive_path)?;
+
+        if archive.metadata()?.len() != 0 {
+            archive.write_all(b"\n")?;
+        }
+
+        archive.write_all(stale.serialize().as_bytes())?;
+    }
+
+    write(&maps_path, purged.serialize())?;
+
+    let ids: Vec<String> = stale_ids.iter().map(u16::to_string).collect();
+    info!(
+        "maps.txt: Successfully purged. Removed stale maps: {}.",
+        ids.join(", ")
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        fs::{create_dir_all, remove_dir_all},
+        path::PathBuf,
+    };
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("rvpacker-{}-{name}", std::process::id()));
+        let _ = remove_dir_all(&dir);
+        create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn removes_annotations_with_purged_entries() {
+        let dir = temp_dir("purge-pattern");
+        let path = dir.join("maps.txt");
+        write(
+            &path,
+            "<!-- ID --><#>1\n<!-- EVENT NAME --><#>EV001\n<!-- MACHINE TRANSLATION: deepl -->\n<!-- CODES: \\C[1] -->\nDEBUG text<#>Отладка\nHuman<#>Человек\n<!-- ID --><#>2\n<!-- FUZZY: Отладка -->\nDEBUG only<#>",
+        )
+        .unwrap();
+
+        by_pattern(
+            &dir,
+            &Regex::new("^DEBUG").unwrap(),
+            FileFlags::all(),
+            &[],
+            false,
+        )
+        .unwrap();
+        let purged = read_to_string(&path);
+        remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            purged.unwrap(),
+            "<!-- ID --><#>1\n<!-- EVENT NAME --><#>EV001\nHuman<#>Человек"
+        );
+    }
+
+    #[test]
+    fn clears_marked_machine_translations() {
+        let dir = temp_dir("purge-machine");
+        let path = dir.join("system.txt");
+        write(
+            &path,
+            "<!-- MACHINE TRANSLATION: deepl -->\n<!-- CODES: \\C[1] -->\n<!-- CONTEXT: face Actor1 #2 -->\nMachine<#>Машина\nHuman<#>Человек\n<!-- MACHINE TRANSLATION: openai -->\n<!-- FUZZY: Нечётко -->\nFuzzy<#>Машина",
+        )
+        .unwrap();
+
+        machine_translations(&dir).unwrap();
+        let purged = read_to_string(&path);
+        remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            purged.unwrap(),
+            "<!-- CODES: \\C[1] -->\n<!-- CONTEXT: face Actor1 #2 -->\nMachine<#>\nHuman<#>Человек\n<!-- FUZZY: Нечётко -->\nFuzzy<#>"
+        );
+    }
+
+    #[test]
+    fn keeps_translations_without_marks() {
+        let dir = temp_dir("purge-unmarked");
+        let path = dir.join("system.txt");
+        let content = "<!-- ANCHOR: intro -->\nHuman<#>Человек\n<!-- EVENT NAME --><#>EV001\nOther<#>Другой";
+        write(&path, content).unwrap();
+
+        machine_translations(&dir).unwrap();
+        let purged = read_to_string(&path);
+        remove_dir_all(&dir).unwrap();
+
+        assert_eq!(purged.unwrap(), content);
+    }
+}
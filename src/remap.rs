@@ -0,0 +1,159 @@
+//! Moving translations between sections, when the game renumbers maps, common events or other entries with IDs.
+
+use crate::translation::{Line, TranslationFile};
+use anyhow::{Result, anyhow, bail};
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
+
+/// Pairs of old section IDs and new ones.
+#[derive(Debug, Clone, Default)]
+pub struct Mapping(pub Vec<(u16, u16)>);
+
+impl FromStr for Mapping {
+    type Err = anyhow::Error;
+
+    /// Parses comma-separated `old:new` pairs.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (old, new) = pair.split_once(':').ok_or_else(|| {
+                    anyhow!("`{pair}` is not `old:new` pair.")
+                })?;
+                Ok((old.trim().parse()?, new.trim().parse()?))
+            })
+            .collect::<Result<_>>()
+            .map(Self)
+    }
+}
+
+/// Returns sources of each section of the file.
+fn section_sources(file: &TranslationFile) -> HashMap<u16, HashSet<&str>> {
+    let mut sections: HashMap<u16, HashSet<&str>> = HashMap::new();
+
+    for (id, source, _) in file.entries() {
+        if let Some(id) = id {
+            sections.entry(id).or_default().insert(source);
+        }
+    }
+
+    sections
+}
+
+/// Derives the mapping of `old` file sections to `new` file sections by similarity of their sources.
+///
+/// Similarity is the Jaccard index of sets of sources. Pairs are matched greedily, most similar first, and pairs below `threshold` are never matched. Returns `(old, new, similarity)` of each matched pair, sorted by old ID.
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn derive(
+    old: &TranslationFile,
+    new: &TranslationFile,
+    threshold: f64,
+) -> Vec<(u16, u16, f64)> {
+    let old_sections = section_sources(old);
+    let new_sections = section_sources(new);
+
+    let mut index: HashMap<&str, Vec<u16>> = HashMap::new();
+
+    for (id, sources) in &new_sections {
+        for source in sources {
+            index.entry(source).or_default().push(*id);
+        }
+    }
+
+    let mut candidates = Vec::new();
+
+    for (old_id, old_sources) in &old_sections {
+        let mut intersections: HashMap<u16, usize> = HashMap::new();
+
+        for source in old_sources {
+            for new_id in index.get(source).into_iter().flatten() {
+                *intersections.entry(*new_id).or_default() += 1;
+            }
+        }
+
+        for (new_id, intersection) in intersections {
+            let union =
+                old_sources.len() + new_sections[&new_id].len() - intersection;
+            let similarity = intersection as f64 / union as f64;
+
+            if similarity >= threshold {
+                candidates.push((*old_id, new_id, similarity));
+            }
+        }
+    }
+
+    // Most similar first. On ties, keeping the section in place wins.
+    candidates.sort_by(|a, b| {
+        b.2.partial_cmp(&a.2)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| (b.0 == b.1).cmp(&(a.0 == a.1)))
+            .then_with(|| a.0.cmp(&b.0))
+    });
+
+    let mut used_old = HashSet::new();
+    let mut used_new = HashSet::new();
+    let mut mapping = Vec::new();
+
+    for (old_id, new_id, similarity) in candidates {
+        if used_old.contains(&old_id) || used_new.contains(&new_id) {
+            continue;
+        }
+
+        used_old.insert(old_id);
+        used_new.insert(new_id);
+        mapping.push((old_id, new_id, similarity));
+    }
+
+    mapping.sort_unstable_by_key(|(old_id, _, _)| *old_id);
+    mapping
+}
+
+/// Changes section IDs of `file` according to `mapping`.
+///
+/// Fails if a section would end up with the same ID as another section, that isn't remapped.
+pub fn apply(
+    file: &mut TranslationFile,
+    mapping: &HashMap<u16, u16>,
+) -> Result<()> {
+    let ids: HashSet<u16> = file
+        .lines
+        .iter()
+        .filter_map(|line| match line {
+            Line::Id(id) => Some(*id),
+            _ => None,
+        })
+        .collect();
+
+    let mut targets = HashSet::new();
+
+    for (old_id, new_id) in mapping {
+        if !ids.contains(old_id) {
+            bail!("Section {old_id} doesn't exist.");
+        }
+
+        if !targets.insert(*new_id) {
+            bail!("Several sections are mapped to section {new_id}.");
+        }
+
+        if ids.contains(new_id) && !mapping.contains_key(new_id) {
+            bail!(
+                "Section {old_id} would collide with section {new_id}, which isn't remapped."
+            );
+        }
+    }
+
+    for line in &mut file.lines {
+        if let Line::Id(id) = line
+            && let Some(new_id) = mapping.get(id)
+        {
+            *id = *new_id;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,131 @@
+//! Search-and-replace over translations. Sources are never touched.
+
+use crate::translation::{
+    Line, TranslationFile, effective_translation, map_effective_translation,
+    placeholders, translation_files,
+};
+use anyhow::{Result, bail};
+use regex::Regex;
+use std::{
+    fs::{read_to_string, write},
+    path::Path,
+};
+use tracing::{info, warn};
+
+/// Options of a single `replace` run.
+pub struct Options<'a> {
+    pub pattern: &'a Regex,
+    pub replacement: &'a str,
+
+    /// Names or stems of translation files to process. All files are processed if empty.
+    pub files: &'a [String],
+    pub dry_run: bool,
+
+    /// Applies replacements, that break placeholder parity, instead of skipping them.
+    pub allow_placeholder_mismatch: bool,
+}
+
+impl Options<'_> {
+    fn includes(&self, name: &str) -> bool {
+        self.files.is_empty()
+            || self.files.iter().any(|file| {
+                file == name || name.strip_suffix(".txt") == Some(file)
+            })
+    }
+
+    /// Returns `true` if replacing `old` translation with `new` one keeps placeholders consistent.
+    ///
+    /// Replacement is fine if it doesn't change placeholders, or if it makes them match the source's.
+    fn keeps_placeholders(source: &str, old: &str, new: &str) -> bool {
+        let new = placeholders(new);
+        new == placeholders(old) || new == placeholders(source)
+    }
+}
+
+/// Applies the replacement of `options` to translations in `translation_path`.
+pub fn replace(translation_path: &Path, options: &Options) -> Result<()> {
+    let names: Vec<String> = translation_files(translation_path)?
+        .into_iter()
+        .filter(|name| options.includes(name))
+        .collect();
+
+    if names.is_empty() {
+        bail!("No translation files match the given `--files`.");
+    }
+
+    let mut matched = 0;
+
+    for name in names {
+        let path = translation_path.join(&name);
+        let mut file = TranslationFile::parse(&read_to_string(&path)?);
+        let mut replaced = 0;
+        let mut skipped = 0;
+
+        for (line_index, line) in file.lines.iter_mut().enumerate() {
+            let Line::Entry {
+                source,
+                translation,
+            } = line
+            else {
+                continue;
+            };
+
+            let old = effective_translation(translation);
+
+            if !options.pattern.is_match(old) {
+                continue;
+            }
+
+            let new = options
+                .pattern
+                .replace_all(old, options.replacement)
+                .into_owned();
+
+            if new == old {
+                continue;
+            }
+
+            let line_number = line_index + 1;
+
+            if !options.allow_placeholder_mismatch
+                && !Options::keeps_placeholders(source, old, &new)
+            {
+                warn!(
+                    "{name}:{line_number}: Replacement breaks placeholders. Skipping it.\nTranslation: {old}\nReplaced: {new}"
+                );
+                skipped += 1;
+                continue;
+            }
+
+            if options.dry_run {
+                println!("{name}:{line_number}:\n  - {old}\n  + {new}");
+            }
+
+            *translation = map_effective_translation(translation, |_| new);
+            replaced += 1;
+        }
+
+        if replaced != 0 && !options.dry_run {
+            write(&path, file.serialize())?;
+        }
+
+        if replaced != 0 || skipped != 0 {
+            info!(
+                "{name}: {} {replaced} translations. Skipped {skipped}.",
+                if options.dry_run {
+                    "Would replace"
+                } else {
+                    "Replaced"
+                }
+            );
+        }
+
+        matched += replaced + skipped;
+    }
+
+    if matched == 0 {
+        info!("No translations matched the pattern.");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,433 @@
+//! Per-file summary, that's printed after read and write, timing breakdown, that's printed with `-v`, and run summary, that's printed with `-q`, written to `--summary-file` and passed to post-hooks.
+//!
+//! Entry counts are taken from the translation files themselves. Warnings and timings are collected by [`WarningCounter`] and [`FileTimer`], which intercept the library's log messages.
+
+use crate::{
+    error::ErrorKind,
+    resources,
+    translation::{Line, TranslationFile, effective_translation},
+};
+use anyhow::Result;
+use rvpacker_lib::RVPACKER_IGNORE_FILE;
+use serde::Serialize;
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+    fmt::{self, Write},
+    fs::{read_dir, read_to_string},
+    path::Path,
+    sync::{
+        LazyLock, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+use tracing::{Event, Level, Subscriber, debug, field::Field};
+use tracing_subscriber::{field::Visit, layer::Context, layer::Layer};
+
+/// Library's warnings contain `In file: name` line.
+const IN_FILE_PREFIX: &str = "In file: ";
+
+static WARNINGS: LazyLock<Mutex<HashMap<String, usize>>> =
+    LazyLock::new(Mutex::default);
+
+/// Warnings of the run, including ones, that don't refer to a file.
+static TOTAL_WARNINGS: AtomicUsize = AtomicUsize::new(0);
+
+/// [`Layer`], that counts warnings per translation file, and in total.
+pub struct WarningCounter;
+
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.0, "{value:?}");
+        }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for WarningCounter {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if *event.metadata().level() != Level::WARN {
+            return;
+        }
+
+        TOTAL_WARNINGS.fetch_add(1, Ordering::Relaxed);
+
+        let mut visitor = MessageVisitor(String::new());
+        event.record(&mut visitor);
+
+        if let Some(file) = visitor
+            .0
+            .lines()
+            .find_map(|line| line.trim().strip_prefix(IN_FILE_PREFIX))
+            && let Ok(mut warnings) = WARNINGS.lock()
+        {
+            *warnings.entry(file.trim().to_string()).or_default() += 1;
+        }
+    }
+}
+
+/// Library logs `name: Started ...` before processing each file, and `name: Successfully ...` after.
+const STARTED_PREFIX: &str = "Started ";
+const FINISHED_PREFIX: &str = "Successfully ";
+
+#[derive(Default)]
+struct Timings {
+    /// Start instants of files, that are being processed, keyed by file stem, since library may log `plugins.txt` on start and `plugins.js` on finish.
+    started: HashMap<String, Instant>,
+    files: Vec<(String, Duration)>,
+    stages: Vec<(&'static str, Duration)>,
+}
+
+static TIMINGS: LazyLock<Mutex<Timings>> = LazyLock::new(Mutex::default);
+
+/// [`Layer`], that measures time spent on each file between library's start and finish messages.
+///
+/// Start messages are logged at `debug` level, so it only collects anything with `-v`.
+pub struct FileTimer;
+
+fn file_stem(name: &str) -> &str {
+    name.split_once('.').map_or(name, |(stem, _)| stem)
+}
+
+impl<S: Subscriber> Layer<S> for FileTimer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let level = *event.metadata().level();
+
+        if level != Level::DEBUG && level != Level::INFO {
+            return;
+        }
+
+        let mut visitor = MessageVisitor(String::new());
+        event.record(&mut visitor);
+
+        let Some((name, message)) = visitor.0.split_once(": ") else {
+            return;
+        };
+
+        let Ok(mut timings) = TIMINGS.lock() else {
+            return;
+        };
+
+        if message.starts_with(STARTED_PREFIX) {
+            timings
+                .started
+                .insert(file_stem(name).to_string(), Instant::now());
+        } else if message.starts_with(FINISHED_PREFIX)
+            && let Some(start) = timings.started.remove(file_stem(name))
+        {
+            timings.files.push((name.to_string(), start.elapsed()));
+        }
+    }
+}
+
+/// Runs a pipeline stage, and records the time spent on it.
+pub fn stage<T>(name: &'static str, f: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = resources::measure(name, f);
+    let elapsed = start.elapsed();
+
+    debug!("{name} took {elapsed:.2?}.");
+
+    if let Ok(mut timings) = TIMINGS.lock() {
+        timings.stages.push((name, elapsed));
+    }
+
+    result
+}
+
+/// Prints time spent on each pipeline stage and each file, slowest files first.
+pub fn print_timings() {
+    let Ok(mut timings) = TIMINGS.lock() else {
+        return;
+    };
+
+    if timings.stages.is_empty() && timings.files.is_empty() {
+        return;
+    }
+
+    timings.files.sort_by_key(|(_, elapsed)| Reverse(*elapsed));
+
+    let width = timings
+        .stages
+        .iter()
+        .map(|(name, _)| name.chars().count())
+        .chain(timings.files.iter().map(|(name, _)| name.chars().count()))
+        .max()
+        .unwrap_or_default();
+
+    let mut output = String::from("Stages:\n");
+
+    for (name, elapsed) in &timings.stages {
+        let _ = writeln!(output, "  {name:<width$}  {elapsed:>10.2?}");
+    }
+
+    if !timings.files.is_empty() {
+        output.push_str("Files:\n");
+
+        for (name, elapsed) in &timings.files {
+            let _ = writeln!(output, "  {name:<width$}  {elapsed:>10.2?}");
+        }
+    }
+
+    print!("{output}");
+}
+
+#[derive(Debug, Default, Serialize)]
+struct FileStats {
+    name: String,
+    entries: usize,
+    translated: usize,
+    ignored: usize,
+    warnings: usize,
+}
+
+/// Returns the name of translation file, that ignore entry comment `<!-- Ignore Entry --><#>Type: id` refers to.
+fn ignore_entry_file(comment: &str) -> Option<(String, Option<u16>)> {
+    let (_, entry) = comment.split_once(rvpacker_lib::SEPARATOR)?;
+    let (file_type, id) = match entry.split_once(':') {
+        Some((file_type, id)) => (file_type, id.trim().parse().ok()),
+        None => (entry, None),
+    };
+
+    let file = match file_type.trim() {
+        "Map" => "maps".to_string(),
+        "Events" => "commonevents".to_string(),
+        other => other.to_lowercase(),
+    };
+
+    Some((format!("{file}.txt"), id))
+}
+
+/// Counts ignored entries per translation file, and per map for `maps.txt`.
+fn count_ignored(
+    translation_path: &Path,
+) -> HashMap<(String, Option<u16>), usize> {
+    let mut counts = HashMap::new();
+
+    let Ok(content) =
+        read_to_string(translation_path.join(RVPACKER_IGNORE_FILE))
+    else {
+        return counts;
+    };
+
+    let mut current = None;
+
+    for line in content.lines().filter(|line| !line.is_empty()) {
+        if line.starts_with(crate::translation::COMMENT_PREFIX) {
+            current = ignore_entry_file(line);
+        } else if let Some((file, id)) = &current {
+            *counts.entry((file.clone(), *id)).or_default() += 1;
+        }
+    }
+
+    counts
+}
+
+/// Returns entry and translated entry counts of each translation file in `translation_path`, and of each map of `maps.txt`.
+pub fn entry_counts(
+    translation_path: &Path,
+) -> Result<BTreeMap<String, (usize, usize)>> {
+    Ok(collect_stats(translation_path)?
+        .into_iter()
+        .map(|stats| (stats.name, (stats.entries, stats.translated)))
+        .collect())
+}
+
+fn collect_stats(translation_path: &Path) -> Result<Vec<FileStats>> {
+    let ignored = count_ignored(translation_path);
+    let warnings = WARNINGS.lock().map(|w| w.clone()).unwrap_or_default();
+
+    let mut files: Vec<_> = read_dir(translation_path)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .collect();
+    files.sort();
+
+    let mut stats = Vec::with_capacity(files.len());
+
+    for path in files {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let file = TranslationFile::parse(&read_to_string(&path)?);
+
+        if name == "maps.txt" {
+            let mut map_stats: Option<FileStats> = None;
+
+            for line in &file.lines {
+                match line {
+                    Line::Id(id) => {
+                        stats.extend(map_stats.take());
+                        map_stats = Some(FileStats {
+                            name: format!("{name}: Map{id:03}"),
+                            ignored: ignored
+                                .get(&(name.clone(), Some(*id)))
+                                .copied()
+                                .unwrap_or_default(),
+                            ..Default::default()
+                        });
+                    }
+                    Line::Entry { translation, .. } => {
+                        if let Some(map_stats) = &mut map_stats {
+                            map_stats.entries += 1;
+                            map_stats.translated += usize::from(
+                                !effective_translation(translation).is_empty(),
+                            );
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            stats.extend(map_stats);
+
+            // Warnings are reported for the whole file, so the last row of the file holds them.
+            if let Some(last) = stats.last_mut() {
+                last.warnings =
+                    warnings.get(&name).copied().unwrap_or_default();
+            }
+
+            continue;
+        }
+
+        let mut file_stats = FileStats {
+            ignored: ignored
+                .iter()
+                .filter(|((file, _), _)| *file == name)
+                .map(|(_, count)| count)
+                .sum(),
+            warnings: warnings.get(&name).copied().unwrap_or_default(),
+            name,
+            ..Default::default()
+        };
+
+        for (_, _, translation) in file.entries() {
+            file_stats.entries += 1;
+            file_stats.translated += usize::from(!translation.is_empty());
+        }
+
+        stats.push(file_stats);
+    }
+
+    Ok(stats)
+}
+
+/// Prints the summary table of translation files in `translation_path`.
+///
+/// Rows without entries are marked, since they usually mean that something wasn't extracted.
+pub fn print_summary(translation_path: &Path) -> Result<()> {
+    let stats = collect_stats(translation_path)?;
+
+    if stats.is_empty() {
+        return Ok(());
+    }
+
+    let headers = ["File", "Entries", "Translated", "Ignored", "Warnings"];
+    let name_width = stats
+        .iter()
+        .map(|stats| stats.name.chars().count())
+        .max()
+        .unwrap_or_default()
+        .max(headers[0].len());
+
+    let mut output = String::new();
+    let _ = writeln!(
+        output,
+        "{:<name_width$}  {:>8}  {:>10}  {:>8}  {:>8}",
+        headers[0], headers[1], headers[2], headers[3], headers[4]
+    );
+
+    let mut total = FileStats {
+        name: String::from("Total"),
+        ..Default::default()
+    };
+
+    for stats in &stats {
+        let _ = writeln!(
+            output,
+            "{:<name_width$}  {:>8}  {:>10}  {:>8}  {:>8}{}",
+            stats.name,
+            stats.entries,
+            stats.translated,
+            stats.ignored,
+            stats.warnings,
+            if stats.entries == 0 { "  (empty)" } else { "" }
+        );
+
+        total.entries += stats.entries;
+        total.translated += stats.translated;
+        total.ignored += stats.ignored;
+        total.warnings += stats.warnings;
+    }
+
+    let _ = write!(
+        output,
+        "{:<name_width$}  {:>8}  {:>10}  {:>8}  {:>8}",
+        total.name,
+        total.entries,
+        total.translated,
+        total.ignored,
+        total.warnings
+    );
+
+    println!("{output}");
+    Ok(())
+}
+
+/// Returns the summary of the run of `command` as JSON object: its outcome, total counts and warnings, and the summary of translation files in `translation_path`, if it exists:
+///
+/// ```json
+/// {
+///     "command": "write",
+///     "status": "success",
+///     "exitCode": 0,
+///     "error": null,
+///     "elapsed": 0.42,
+///     "warnings": 1,
+///     "total": { "entries": 10, "translated": 8, "ignored": 0 },
+///     "files": [{ "name": "maps.txt: Map001", "entries": 10, "translated": 8, "ignored": 0, "warnings": 0 }]
+/// }
+/// ```
+pub fn run_summary(
+    command: &str,
+    error: Option<&anyhow::Error>,
+    elapsed: f64,
+    translation_path: &Path,
+) -> Result<serde_json::Value> {
+    let files = if translation_path.exists() {
+        collect_stats(translation_path)?
+    } else {
+        Vec::new()
+    };
+
+    let (entries, translated, ignored) =
+        files
+            .iter()
+            .fold((0, 0, 0), |(entries, translated, ignored), file| {
+                (
+                    entries + file.entries,
+                    translated + file.translated,
+                    ignored + file.ignored,
+                )
+            });
+
+    Ok(serde_json::json!({
+        "command": command,
+        "status": if error.is_some() { "failure" } else { "success" },
+        "exitCode": error.map_or(0, ErrorKind::exit_code_of),
+        "error": error.map(|err| format!("{err:#}")),
+        "elapsed": (elapsed * 1000.0).round() / 1000.0,
+        "warnings": TOTAL_WARNINGS.load(Ordering::Relaxed),
+        "total": {
+            "entries": entries,
+            "translated": translated,
+            "ignored": ignored,
+        },
+        "files": files,
+    }))
+}
@@ -0,0 +1,111 @@
+//! Retroactive application of romanization to existing translation files.
+//!
+//! `--romanize` changes sources of entries, so translation files, that were read without it, don't match the game data read with it, and vice versa. The game data is read twice in `allow` duplicate mode, with the current setting and with the wanted one, so entries of both reads are at the same positions, and each source in its current form is mapped to its form with the wanted setting. Translations of existing files are then carried over to files, that were read with the wanted setting, by mapped sources. Translations themselves aren't romanized.
+
+use crate::{
+    dedup::{display_names, split_display_name},
+    translation::{Line, TranslationFile, effective_translation},
+};
+use rvpacker_lib::SEPARATOR;
+use std::collections::HashMap;
+
+/// Sources in their wanted form by section and source in their current form.
+pub type Sources = HashMap<(Option<u16>, String), String>;
+
+/// Maps sources of `current` file to sources of `target` file at the same positions. Both files must be read from the same data in `allow` duplicate mode. Returns `None`, if entries of the files don't align, which happens, when romanization makes two sources of a section equal.
+#[must_use]
+pub fn align(
+    current: &TranslationFile,
+    target: &TranslationFile,
+) -> Option<Sources> {
+    let current: Vec<_> = current.entries().collect();
+    let target: Vec<_> = target.entries().collect();
+
+    if current.len() != target.len() {
+        return None;
+    }
+
+    let mut sources = Sources::new();
+
+    for ((current_id, current_source, _), (target_id, target_source, _)) in
+        current.into_iter().zip(target)
+    {
+        if current_id != target_id {
+            return None;
+        }
+
+        sources
+            .entry((current_id, current_source.to_string()))
+            .or_insert_with(|| target_source.to_string());
+    }
+
+    Some(sources)
+}
+
+/// Carries translations of `old` file over to `new` file, that was read with the wanted romanization, mapping sources with `sources`. Sources, that aren't mapped, are carried as is. Translation of the same section takes precedence over translations of the same source in other sections. Returns the number of carried translations.
+pub fn reconcile(
+    old: &TranslationFile,
+    new: &mut TranslationFile,
+    sources: &Sources,
+) -> usize {
+    let mut by_section: HashMap<(Option<u16>, &str), &str> = HashMap::new();
+    let mut by_source: HashMap<&str, &str> = HashMap::new();
+
+    for (id, source, translation) in old.entries() {
+        if translation.is_empty() {
+            continue;
+        }
+
+        let source = sources
+            .get(&(id, source.to_string()))
+            .map_or(source, String::as_str);
+
+        by_section.entry((id, source)).or_insert(translation);
+        by_source.entry(source).or_insert(translation);
+    }
+
+    // Romanization changes map names too, so they're carried by section.
+    let display_names: HashMap<Option<u16>, &str> = display_names(old)
+        .into_iter()
+        .map(|((section, _), translation)| (section, translation))
+        .collect();
+
+    let mut carried = 0;
+    let mut section = None;
+
+    for line in &mut new.lines {
+        match line {
+            Line::Id(id) => section = Some(*id),
+            Line::Comment(comment) => {
+                if let Some((name, translation)) = split_display_name(comment)
+                    && translation.is_empty()
+                    && let Some(old_translation) = display_names.get(&section)
+                {
+                    *comment = format!("{name}{SEPARATOR}{old_translation}");
+                    carried += 1;
+                }
+            }
+            Line::Entry {
+                source,
+                translation,
+            } => {
+                if !effective_translation(translation).is_empty() {
+                    continue;
+                }
+
+                let Some(found) = by_section
+                    .get(&(section, source.as_str()))
+                    .or_else(|| by_source.get(source.as_str()))
+                else {
+                    continue;
+                };
+
+                *translation = (*found).to_string();
+                carried += 1;
+            }
+            Line::Raw(_) => {}
+        }
+    }
+
+    carried
+}
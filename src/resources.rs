@@ -0,0 +1,163 @@
+//! Resource usage of pipeline stages, that's printed with `--stats-resources`, so users on low-memory machines can tell, which stages need the memory.
+//!
+//! CPU time and peak memory are read from `/proc/self`, so they're only available on Linux. Peak memory is reset before each stage, so it's the peak of the stage itself, rather than of the run so far.
+
+use std::{
+    fmt::Write,
+    fs::{read_to_string, write},
+    sync::{
+        LazyLock, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+/// Clock ticks per second, that `/proc/self/stat` reports CPU time in. It's 100 on all Linux platforms, that RPG Maker games run on.
+const TICKS_PER_SECOND: u64 = 100;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Default)]
+struct Usage {
+    /// CPU time and peak memory of finished stages.
+    stages: Vec<(&'static str, Duration, Option<u64>)>,
+
+    /// Peak memory of stages, that are running, before nested stages reset it.
+    running: Vec<u64>,
+
+    /// Peak memory of the run, before stages reset it.
+    peak: u64,
+}
+
+static USAGE: LazyLock<Mutex<Usage>> = LazyLock::new(Mutex::default);
+
+/// Starts recording resource usage of stages.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Returns CPU time of the process in all threads.
+fn cpu_time() -> Option<Duration> {
+    let stat = read_to_string("/proc/self/stat").ok()?;
+
+    // Fields after the executable name, that may contain spaces itself. `utime` and `stime` are fields 14 and 15.
+    let (_, fields) = stat.rsplit_once(')')?;
+    let mut fields = fields.split_whitespace().skip(11);
+    let user: u64 = fields.next()?.parse().ok()?;
+    let system: u64 = fields.next()?.parse().ok()?;
+
+    Some(Duration::from_millis(
+        (user + system) * 1000 / TICKS_PER_SECOND,
+    ))
+}
+
+/// Returns the peak resident memory of the process in bytes, since the start or the last reset.
+fn peak_memory() -> Option<u64> {
+    let status = read_to_string("/proc/self/status").ok()?;
+    let kilobytes: u64 = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse()
+        .ok()?;
+
+    Some(kilobytes * 1024)
+}
+
+/// Resets the peak memory to the current one.
+fn reset_peak(usage: &mut Usage) {
+    let Some(peak) = peak_memory() else {
+        return;
+    };
+
+    for running in &mut usage.running {
+        *running = (*running).max(peak);
+    }
+
+    usage.peak = usage.peak.max(peak);
+    let _ = write("/proc/self/clear_refs", "5");
+}
+
+/// Runs stage `name`, and records its CPU time and peak memory, if recording is enabled.
+pub fn measure<T>(name: &'static str, f: impl FnOnce() -> T) -> T {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return f();
+    }
+
+    if let Ok(mut usage) = USAGE.lock() {
+        reset_peak(&mut usage);
+        usage.running.push(0);
+    }
+
+    let start = cpu_time();
+    let result = f();
+    let cpu = start
+        .zip(cpu_time())
+        .map(|(start, end)| end.saturating_sub(start))
+        .unwrap_or_default();
+
+    if let Ok(mut usage) = USAGE.lock() {
+        let running = usage.running.pop().unwrap_or_default();
+        let peak = peak_memory().map(|peak| peak.max(running));
+
+        usage.peak = usage.peak.max(peak.unwrap_or_default());
+        usage.stages.push((name, cpu, peak));
+    }
+
+    result
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn format_memory(bytes: Option<u64>) -> String {
+    bytes.map_or_else(
+        || String::from("n/a"),
+        |bytes| format!("{:.1} MiB", bytes as f64 / 1024.0 / 1024.0),
+    )
+}
+
+/// Prints CPU time and peak memory of each stage, and of the whole run.
+pub fn print() {
+    let Some(total_cpu) = cpu_time() else {
+        println!("Resource usage is only available on Linux.");
+        return;
+    };
+
+    let Ok(usage) = USAGE.lock() else {
+        return;
+    };
+
+    let total_peak = peak_memory().map(|peak| peak.max(usage.peak));
+    let width = usage
+        .stages
+        .iter()
+        .map(|(name, ..)| name.chars().count())
+        .chain([5])
+        .max()
+        .unwrap_or_default();
+
+    let mut output = format!(
+        "Resources:\n  {:<width$}  {:>10}  {:>12}\n",
+        "Stage", "CPU time", "Peak memory"
+    );
+
+    for (name, cpu, peak) in &usage.stages {
+        let _ = writeln!(
+            output,
+            "  {name:<width$}  {:>10}  {:>12}",
+            format!("{:.2}s", cpu.as_secs_f64()),
+            format_memory(*peak)
+        );
+    }
+
+    let _ = writeln!(
+        output,
+        "  {:<width$}  {:>10}  {:>12}",
+        "Total",
+        format!("{:.2}s", total_cpu.as_secs_f64()),
+        format_memory(total_peak)
+    );
+
+    print!("{output}");
+}
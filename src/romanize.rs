@@ -0,0 +1,292 @@
+//! User-defined romanization tables.
+//!
+//! The library's `--romanize` replaces a fixed set of Japanese symbols. A table replaces it with a JSON object of sequences and their replacements, e.g. `{"「": "\"", "♥": "<3"}`, so teams decide themselves, how symbols are normalized. Protected terms, e.g. character names, are skipped. Text of game data is romanized in a merged copy of the data before the library processes it, and the hash of the table is recorded in metadata, so `write` and `purge` use the same table as `read`.
+
+use crate::{
+    data::{load_rpgm_file, save_rpgm_file},
+    terms::ProtectedTerms,
+};
+use anyhow::{Context, Result, bail};
+use marshal_rs::{Value, ValueType};
+use regex::Regex;
+use rvpacker_lib::{get_engine_extension, types::EngineType};
+use std::{borrow::Cow, collections::HashMap, fs::read_dir, path::Path};
+
+/// Object fields of database entries, maps and `System`, that hold text.
+const TEXT_FIELDS: &[&str] = &[
+    "name",
+    "nickname",
+    "description",
+    "profile",
+    "message1",
+    "message2",
+    "message3",
+    "message4",
+    "displayName",
+    "gameTitle",
+    "currencyUnit",
+];
+
+/// `System` fields, all strings of which are text.
+const TEXT_CONTAINERS: &[&str] = &[
+    "armorTypes",
+    "elements",
+    "equipTypes",
+    "skillTypes",
+    "weaponTypes",
+    "terms",
+    "words",
+];
+
+/// Event commands and indices of their parameters, that hold text. `Show Choices` holds an array of choices.
+const TEXT_PARAMETERS: &[(i32, usize)] = &[
+    (101, 4),
+    (102, 0),
+    (320, 1),
+    (324, 1),
+    (325, 1),
+    (401, 0),
+    (402, 1),
+    (405, 0),
+];
+
+/// Data files, that don't hold any text.
+const SKIPPED_FILES: &[&str] = &["Scripts", "Tilesets", "Animations"];
+
+pub struct Table<'a> {
+    pattern: Regex,
+    replacements: HashMap<String, String>,
+    protected: Option<&'a ProtectedTerms>,
+    hash: String,
+}
+
+impl<'a> Table<'a> {
+    /// Loads the table from `path`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read(path)
+            .with_context(|| format!("Reading {}", path.display()))?;
+        let replacements: HashMap<String, String> = serde_json::from_slice(
+            &content,
+        )
+        .with_context(|| {
+            format!(
+                "{} must be a JSON object of sequences and their replacements.",
+                path.display()
+            )
+        })?;
+
+        if replacements.keys().any(String::is_empty) {
+            bail!("{}: Sequences can't be empty.", path.display());
+        }
+
+        // Longer sequences take precedence over their prefixes.
+        let mut sequences: Vec<&String> = replacements.keys().collect();
+        sequences.sort_by_key(|sequence| std::cmp::Reverse(sequence.len()));
+
+        let pattern = if sequences.is_empty() {
+            // Never matches.
+            Regex::new(r"[^\s\S]")?
+        } else {
+            Regex::new(
+                &sequences
+                    .iter()
+                    .map(|sequence| regex::escape(sequence))
+                    .collect::<Vec<_>>()
+                    .join("|"),
+            )?
+        };
+
+        Ok(Self {
+            pattern,
+            replacements,
+            protected: None,
+            hash: format!("{:08x}", crc32fast::hash(&content)),
+        })
+    }
+
+    /// Makes the table skip `terms`. Their hash becomes a part of the table's one, so changing them is detected like changing the table.
+    #[must_use]
+    pub fn protect(self, terms: &'a ProtectedTerms) -> Self {
+        Self {
+            hash: format!("{}-{}", self.hash, terms.hash()),
+            protected: Some(terms),
+            ..self
+        }
+    }
+
+    /// Returns the hash of the table file, that is recorded in metadata.
+    #[must_use]
+    pub fn hash(&self) -> &str {
+        &self.hash
+    }
+
+    /// Returns `text` with sequences replaced.
+    fn replace<'t>(&self, text: &'t str) -> Cow<'t, str> {
+        self.pattern
+            .replace_all(text, |captures: &regex::Captures| {
+                self.replacements[&captures[0]].clone()
+            })
+    }
+
+    fn apply(&self, text: &mut String) {
+        let terms = self
+            .protected
+            .map(|terms| terms.find(text))
+            .unwrap_or_default();
+
+        if terms.is_empty() {
+            if let Cow::Owned(replaced) = self.replace(text) {
+                *text = replaced;
+            }
+
+            return;
+        }
+
+        // Only text between terms is romanized.
+        let mut romanized = String::with_capacity(text.len());
+        let mut end = 0;
+
+        for (range, _) in terms {
+            romanized.push_str(&self.replace(&text[end..range.start]));
+            romanized.push_str(&text[range.clone()]);
+            end = range.end;
+        }
+
+        romanized.push_str(&self.replace(&text[end..]));
+        *text = romanized;
+    }
+
+    fn apply_value(&self, value: &mut Value) {
+        if let ValueType::String(text) = &mut **value {
+            self.apply(text);
+        }
+    }
+
+    /// Romanizes all strings in `value`.
+    fn apply_all(&self, value: &mut Value) {
+        match &mut **value {
+            ValueType::String(text) => self.apply(text),
+            ValueType::Array(array) => {
+                for value in array {
+                    self.apply_all(value);
+                }
+            }
+            ValueType::Object(object) => {
+                for value in object.values_mut() {
+                    self.apply_all(value);
+                }
+            }
+            ValueType::HashMap(hashmap) => {
+                for value in hashmap.values_mut() {
+                    self.apply_all(value);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn apply_list(&self, list: &mut [Value]) {
+        for command in list {
+            let Some(object) = command.as_object_mut() else {
+                continue;
+            };
+
+            let Some(code) = object.get("code").and_then(|code| code.as_int())
+            else {
+                continue;
+            };
+
+            let Some(&(_, index)) =
+                TEXT_PARAMETERS.iter().find(|(c, _)| *c == code)
+            else {
+                continue;
+            };
+
+            if let Some(parameter) = object
+                .get_mut("parameters")
+                .and_then(|parameters| parameters.as_array_mut())
+                .and_then(|parameters| parameters.get_mut(index))
+            {
+                self.apply_all(parameter);
+            }
+        }
+    }
+
+    /// Romanizes text fields and text of event commands in `value`.
+    fn apply_data(&self, value: &mut Value) {
+        match &mut **value {
+            ValueType::Array(array) => {
+                for value in array {
+                    self.apply_data(value);
+                }
+            }
+            ValueType::HashMap(hashmap) => {
+                for value in hashmap.values_mut() {
+                    self.apply_data(value);
+                }
+            }
+            ValueType::Object(object) => {
+                // Audio files are also named by `name` field.
+                let is_audio = object.contains_key("volume");
+
+                for (key, value) in object.iter_mut() {
+                    if key == "list"
+                        && let Some(list) = value.as_array_mut()
+                    {
+                        self.apply_list(list);
+                    } else if TEXT_CONTAINERS.contains(&key.as_str()) {
+                        self.apply_all(value);
+                    } else if TEXT_FIELDS.contains(&key.as_str()) {
+                        if !is_audio {
+                            self.apply_value(value);
+                        }
+                    } else {
+                        self.apply_data(value);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Romanizes text of all data files in `source_path` in place. Returns the number of changed files.
+    pub fn romanize_data(
+        &self,
+        source_path: &Path,
+        engine_type: EngineType,
+    ) -> Result<usize> {
+        let extension = get_engine_extension(engine_type);
+        let mut changed = 0;
+
+        for entry in read_dir(source_path)
+            .with_context(|| format!("Reading {}", source_path.display()))?
+            .flatten()
+        {
+            let path = entry.path();
+
+            let Some(stem) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix(extension))
+                .and_then(|name| name.strip_suffix('.'))
+            else {
+                continue;
+            };
+
+            if SKIPPED_FILES.contains(&stem) {
+                continue;
+            }
+
+            let original = load_rpgm_file(&path, engine_type)?;
+            let mut value = original.clone();
+            self.apply_data(&mut value);
+
+            if value != original {
+                save_rpgm_file(&path, value, engine_type)?;
+                changed += 1;
+            }
+        }
+
+        Ok(changed)
+    }
+}
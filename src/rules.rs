@@ -0,0 +1,208 @@
+//! Replacement rules, that are applied to translations at write time.
+//!
+//! Rules are typographic conventions of the target language, like curly quotes or narrow spaces before French punctuation, that translators shouldn't have to type. They're applied to copies of translation files, right before the library writes them to game files, so translation files keep the plain text.
+//!
+//! Rules file is a JSON array of rules, which are applied in order:
+//!
+//! ```json
+//! [
+//!     { "pattern": "\"([^\"]*)\"", "replacement": "“$1”" },
+//!     { "pattern": " ?([!?;:])", "replacement": " $1", "files": ["maps", "commonevents"] }
+//! ]
+//! ```
+
+use crate::translation::{
+    Line, TranslationFile, effective_translation, map_effective_translation,
+    placeholders,
+};
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+use std::{
+    fs::{
+        copy, create_dir_all, read_dir, read_to_string, remove_dir_all, write,
+    },
+    path::{Path, PathBuf},
+};
+use tracing::{info, warn};
+
+#[derive(Deserialize)]
+struct RawRule {
+    pattern: String,
+    replacement: String,
+
+    /// Names or stems of translation files, that the rule applies to. Applies to all files if empty.
+    #[serde(default)]
+    files: Vec<String>,
+}
+
+struct Rule {
+    pattern: Regex,
+    replacement: String,
+    files: Vec<String>,
+}
+
+impl Rule {
+    fn includes(&self, name: &str) -> bool {
+        self.files.is_empty()
+            || self.files.iter().any(|file| {
+                file == name || name.strip_suffix(".txt") == Some(file)
+            })
+    }
+}
+
+pub struct Rules(Vec<Rule>);
+
+/// Copy of translation files with rules applied. The copy is removed, when the value is dropped.
+pub struct Staged {
+    path: PathBuf,
+}
+
+impl Staged {
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for Staged {
+    fn drop(&mut self) {
+        let _ = remove_dir_all(&self.path);
+    }
+}
+
+impl Rules {
+    /// Loads rules from `path`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = read_to_string(path)
+            .with_context(|| format!("Reading {}", path.display()))?;
+        let raw: Vec<RawRule> = serde_json::from_str(&content)
+            .with_context(|| format!("Parsing {}", path.display()))?;
+
+        let rules = raw
+            .into_iter()
+            .enumerate()
+            .map(|(index, rule)| {
+                Ok(Rule {
+                    pattern: Regex::new(&rule.pattern).with_context(|| {
+                        format!("{}: Rule {}", path.display(), index + 1)
+                    })?,
+                    replacement: rule.replacement,
+                    files: rule.files,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self(rules))
+    }
+
+    /// Applies the rules to `translation`. Rules, that would break placeholders, are skipped.
+    fn apply(
+        &self,
+        name: &str,
+        line_number: usize,
+        translation: &str,
+    ) -> String {
+        let mut result = translation.to_string();
+
+        for (index, rule) in self.0.iter().enumerate() {
+            if !rule.includes(name) {
+                continue;
+            }
+
+            let replaced = rule.pattern.replace_all(&result, &rule.replacement);
+
+            if placeholders(&replaced) != placeholders(&result) {
+                warn!(
+                    "{name}:{line_number}: Rule {} breaks placeholders. Skipping it.",
+                    index + 1
+                );
+                continue;
+            }
+
+            result = replaced.into_owned();
+        }
+
+        result
+    }
+
+    /// Copies files of `translation_path` to `staging_path`, and applies the rules to translations of the copies.
+    pub fn stage(
+        &self,
+        translation_path: &Path,
+        staging_path: PathBuf,
+    ) -> Result<Staged> {
+        let (staged, changed) = stage_translations(
+            translation_path,
+            staging_path,
+            |name, line_number, _, translation| {
+                self.apply(name, line_number, translation)
+            },
+        )?;
+
+        info!("Write rules changed {changed} translations.");
+        Ok(staged)
+    }
+}
+
+/// Copies files of `translation_path` to `staging_path`, and replaces non-empty translations of the copies with `f(name, line_number, source, translation)`. Returns the copy and the number of changed translations.
+pub fn stage_translations(
+    translation_path: &Path,
+    staging_path: PathBuf,
+    mut f: impl FnMut(&str, usize, &str, &str) -> String,
+) -> Result<(Staged, usize)> {
+    if staging_path.exists() {
+        remove_dir_all(&staging_path)?;
+    }
+
+    create_dir_all(&staging_path)?;
+    let staged = Staged { path: staging_path };
+    let mut changed = 0;
+
+    for entry in read_dir(translation_path)?.flatten() {
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+
+        let path = entry.path();
+        let file_name = entry.file_name();
+        let target = staged.path.join(&file_name);
+
+        let Some(name) = file_name
+            .to_str()
+            .filter(|_| path.extension().is_some_and(|ext| ext == "txt"))
+        else {
+            copy(&path, &target)?;
+            continue;
+        };
+
+        let mut file = TranslationFile::parse(&read_to_string(&path)?);
+
+        for (line_index, line) in file.lines.iter_mut().enumerate() {
+            let Line::Entry {
+                source,
+                translation,
+            } = line
+            else {
+                continue;
+            };
+
+            let old = effective_translation(translation);
+
+            if old.is_empty() {
+                continue;
+            }
+
+            let new = f(name, line_index + 1, source, old);
+
+            if new != old {
+                *translation = map_effective_translation(translation, |_| new);
+                changed += 1;
+            }
+        }
+
+        write(&target, file.serialize())?;
+    }
+
+    Ok((staged, changed))
+}
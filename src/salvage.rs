@@ -0,0 +1,190 @@
+//! Salvaging of JSON data files, that fail to parse.
+//!
+//! Containers are split into their members lexically, so damage in one member doesn't take the rest of the file with it. Members, that still fail to parse, are skipped and reported with their byte ranges. Skipped array elements are replaced with `null`, so IDs of the following elements don't shift.
+
+use serde_json::{Map, Value};
+use tracing::warn;
+
+/// Depth of containers, which members are salvaged individually. Events of maps and entries of common events and troops are at this depth.
+const MAX_DEPTH: usize = 2;
+
+/// Returns the end of the string, that starts at `start` with `"`.
+fn string_end(content: &[u8], start: usize) -> usize {
+    let mut index = start + 1;
+
+    while let Some(&byte) = content.get(index) {
+        match byte {
+            b'\\' => index += 2,
+            b'"' => return index + 1,
+            _ => index += 1,
+        }
+    }
+
+    content.len()
+}
+
+/// Returns the end of the value, that starts at `start`. That's the position of the next `,` or of the closing bracket of the enclosing container.
+fn value_end(content: &[u8], start: usize) -> usize {
+    let mut depth = 0usize;
+    let mut index = start;
+
+    while let Some(&byte) = content.get(index) {
+        match byte {
+            b'"' => {
+                index = string_end(content, index);
+                continue;
+            }
+            b'[' | b'{' => depth += 1,
+            b']' | b'}' | b',' if depth == 0 => return index,
+            b']' | b'}' => depth -= 1,
+            _ => {}
+        }
+
+        index += 1;
+    }
+
+    content.len()
+}
+
+struct Salvager<'a> {
+    name: &'a str,
+    content: &'a [u8],
+    skipped: usize,
+}
+
+impl Salvager<'_> {
+    fn skip_whitespace(&self, mut index: usize) -> usize {
+        while self.content.get(index).is_some_and(u8::is_ascii_whitespace) {
+            index += 1;
+        }
+
+        index
+    }
+
+    fn skip(&mut self, start: usize, end: usize, location: &str) {
+        self.skipped += 1;
+        warn!(
+            "{}: {}: Bytes {start}..{end} are damaged. They're skipped.",
+            self.name,
+            if location.is_empty() {
+                "root"
+            } else {
+                location
+            }
+        );
+    }
+
+    /// Parses the value in `start..end`, and salvages its members, if it fails to parse.
+    fn value(
+        &mut self,
+        start: usize,
+        end: usize,
+        depth: usize,
+        location: &str,
+    ) -> Option<Value> {
+        if let Ok(value) = serde_json::from_slice(&self.content[start..end]) {
+            return Some(value);
+        }
+
+        let begin = self.skip_whitespace(start);
+
+        if depth < MAX_DEPTH
+            && begin < end
+            && matches!(self.content[begin], b'[' | b'{')
+        {
+            return Some(self.container(begin, end, depth, location));
+        }
+
+        self.skip(start, end, location);
+        None
+    }
+
+    fn container(
+        &mut self,
+        start: usize,
+        end: usize,
+        depth: usize,
+        location: &str,
+    ) -> Value {
+        let is_object = self.content[start] == b'{';
+        let mut array = Vec::new();
+        let mut object = Map::new();
+        let mut index = start + 1;
+
+        loop {
+            index = self.skip_whitespace(index);
+
+            match self.content.get(index) {
+                None | Some(b']' | b'}') => break,
+                Some(b',') => {
+                    index += 1;
+                    continue;
+                }
+                _ if index >= end => break,
+                _ => {}
+            }
+
+            if !is_object {
+                let value_end = value_end(self.content, index).min(end);
+                let location = format!("{location}[{}]", array.len());
+
+                array.push(
+                    self.value(index, value_end, depth + 1, &location)
+                        .unwrap_or(Value::Null),
+                );
+                index = value_end;
+                continue;
+            }
+
+            let key_end = if self.content[index] == b'"' {
+                string_end(self.content, index).min(end)
+            } else {
+                index
+            };
+            let colon = self.skip_whitespace(key_end);
+            let key: Option<String> =
+                serde_json::from_slice(&self.content[index..key_end]).ok();
+
+            let (Some(key), Some(b':')) = (key, self.content.get(colon)) else {
+                let member_end = value_end(self.content, key_end).min(end);
+                self.skip(index, member_end, location);
+                index = member_end;
+                continue;
+            };
+
+            let value_start = colon + 1;
+            let value_end = value_end(self.content, value_start).min(end);
+            let member_location = if location.is_empty() {
+                key.clone()
+            } else {
+                format!("{location}.{key}")
+            };
+
+            if let Some(value) =
+                self.value(value_start, value_end, depth + 1, &member_location)
+            {
+                object.insert(key, value);
+            }
+
+            index = value_end;
+        }
+
+        if is_object {
+            Value::Object(object)
+        } else {
+            Value::Array(array)
+        }
+    }
+}
+
+/// Salvages what parses correctly from JSON `content` of `name` file. Returns the salvaged value and the number of skipped parts, or `None`, if nothing could be salvaged.
+pub fn salvage(name: &str, content: &[u8]) -> Option<(Value, usize)> {
+    let mut salvager = Salvager {
+        name,
+        content,
+        skipped: 0,
+    };
+
+    let value = salvager.value(0, content.len(), 0, "")?;
+    Some((value, salvager.skipped))
+}
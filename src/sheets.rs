@@ -0,0 +1,358 @@
+//! Sync of translations with a Google Sheet, so translators, who don't work with translation files, can translate in the browser.
+//!
+//! Push uploads untranslated entries to a tab per translation file, and pull writes filled translations back to translation files. Requests are authorized with a service account, that the spreadsheet is shared with. `curl` sends requests, and `openssl` signs the token request with the account's key, so both must be installed.
+
+use crate::{
+    attribution::Changed,
+    export::{Conflict, Resolution, Row, apply_rows, rows},
+    http::{authorized_request, request},
+    process::{run, write_private},
+};
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::{
+    collections::BTreeMap,
+    fs::{read_to_string, remove_file},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tracing::{info, warn};
+
+const API: &str = "https://sheets.googleapis.com/v4/spreadsheets";
+const SCOPE: &str = "https://www.googleapis.com/auth/spreadsheets";
+const DEFAULT_TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+
+/// Environment variable with the path of the key, that Google tools use by default.
+const CREDENTIALS_VARIABLE: &str = "GOOGLE_APPLICATION_CREDENTIALS";
+
+const HEADERS: [&str; 4] = ["Section", "Context", "Source", "Translation"];
+
+/// Key of a service account, as Google Cloud Console downloads it.
+#[derive(Deserialize)]
+struct ServiceAccount {
+    client_email: String,
+    private_key: String,
+    token_uri: Option<String>,
+    project_id: Option<String>,
+}
+
+fn base64_url(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] =
+        b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let bits =
+            chunk.iter().enumerate().fold(0u32, |bits, (index, byte)| {
+                bits | u32::from(*byte) << (16 - index * 8)
+            });
+
+        for index in 0..=chunk.len() {
+            encoded.push(
+                ALPHABET[(bits >> (18 - index * 6) & 0x3f) as usize] as char,
+            );
+        }
+    }
+
+    encoded
+}
+
+/// Access token of a service account, and the project of the account.
+pub(crate) struct Authorization {
+    pub token: String,
+    pub project_id: Option<String>,
+}
+
+/// Authorizes the service account, which key is in `credentials`, or in `GOOGLE_APPLICATION_CREDENTIALS`, for `scope`. Tokens are valid for an hour.
+pub(crate) fn authorize(
+    credentials: Option<&Path>,
+    scope: &str,
+) -> Result<Authorization> {
+    let credentials = match credentials {
+        Some(path) => path.to_path_buf(),
+        None => std::env::var_os(CREDENTIALS_VARIABLE)
+            .map(Into::into)
+            .ok_or_else(|| {
+                anyhow!(
+                    "No service account key. Pass it with `--credentials`, or set `{CREDENTIALS_VARIABLE}`."
+                )
+            })?,
+    };
+
+    let account: ServiceAccount = serde_json::from_str(
+        &read_to_string(&credentials)
+            .with_context(|| format!("Reading {}", credentials.display()))?,
+    )
+    .with_context(|| format!("Parsing {}", credentials.display()))?;
+
+    let token_uri = account.token_uri.as_deref().unwrap_or(DEFAULT_TOKEN_URI);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+
+    let header = base64_url(br#"{"alg":"RS256","typ":"JWT"}"#);
+    let claims = base64_url(
+        json!({
+            "iss": account.client_email,
+            "scope": scope,
+            "aud": token_uri,
+            "iat": now,
+            "exp": now + 3600,
+        })
+        .to_string()
+        .as_bytes(),
+    );
+    let signing_input = format!("{header}.{claims}");
+
+    // `openssl` reads keys only from files.
+    let key_path = std::env::temp_dir()
+        .join(format!("rvpacker-sheets-{}.pem", std::process::id()));
+    write_private(&key_path, &account.private_key)?;
+
+    let signature = run(
+        "openssl",
+        &["dgst", "-sha256", "-sign", &key_path.to_string_lossy()],
+        signing_input.as_bytes(),
+    );
+    let _ = remove_file(&key_path);
+
+    let assertion = format!("{signing_input}.{}", base64_url(&signature?));
+    let response = request(
+        &[
+            "--data-urlencode",
+            "grant_type=urn:ietf:params:oauth:grant-type:jwt-bearer",
+            "--data-urlencode",
+            "assertion@-",
+        ],
+        token_uri,
+        assertion.as_bytes(),
+    )
+    .context("Authorizing the service account")?;
+
+    let Some(token) = response.get("access_token").and_then(Value::as_str)
+    else {
+        bail!("Authorization response has no access token.");
+    };
+
+    Ok(Authorization {
+        token: token.to_string(),
+        project_id: account.project_id,
+    })
+}
+
+/// Authorized connection to a spreadsheet.
+pub struct Sheets {
+    spreadsheet: String,
+    token: String,
+}
+
+impl Sheets {
+    /// Authorizes access to `spreadsheet` ID with the key of a service account in `credentials`, or in `GOOGLE_APPLICATION_CREDENTIALS`.
+    pub fn connect(
+        spreadsheet: &str,
+        credentials: Option<&Path>,
+    ) -> Result<Self> {
+        Ok(Self {
+            spreadsheet: spreadsheet.to_string(),
+            token: authorize(credentials, SCOPE)?.token,
+        })
+    }
+
+    fn call(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&Value>,
+    ) -> Result<Value> {
+        let mut arguments = vec!["-X", method];
+
+        let body = body.map(Value::to_string).unwrap_or_default();
+
+        if !body.is_empty() {
+            arguments.extend_from_slice(&[
+                "-H",
+                "Content-Type: application/json",
+                "--data-binary",
+                "@-",
+            ]);
+        }
+
+        authorized_request(
+            &format!("Authorization: Bearer {}", self.token),
+            &arguments,
+            &format!("{API}/{}{path}", self.spreadsheet),
+            body.as_bytes(),
+        )
+    }
+
+    /// Returns titles of the spreadsheet's tabs.
+    fn titles(&self) -> Result<Vec<String>> {
+        let response =
+            self.call("GET", "?fields=sheets.properties.title", None)?;
+
+        Ok(response
+            .get("sheets")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|sheet| sheet.pointer("/properties/title")?.as_str())
+            .map(str::to_string)
+            .collect())
+    }
+
+    /// Uploads untranslated entries of translation files in `translation_path`, replacing contents of their tabs. Tabs of files without untranslated entries are cleared.
+    pub fn push(&self, translation_path: &Path) -> Result<()> {
+        let mut files: BTreeMap<String, Vec<Row>> = BTreeMap::new();
+
+        for row in rows(translation_path)? {
+            let title = row
+                .file
+                .strip_suffix(".txt")
+                .unwrap_or(&row.file)
+                .to_string();
+            let rows = files.entry(title).or_default();
+
+            if row.translation.is_empty() {
+                rows.push(row);
+            }
+        }
+
+        let existing = self.titles()?;
+        let missing: Vec<Value> = files
+            .keys()
+            .filter(|title| !existing.contains(title))
+            .map(|title| json!({ "addSheet": { "properties": { "title": title } } }))
+            .collect();
+
+        if !missing.is_empty() {
+            self.call(
+                "POST",
+                ":batchUpdate",
+                Some(&json!({ "requests": missing })),
+            )?;
+        }
+
+        for (title, rows) in &files {
+            let range = quote_range(title);
+            self.call(
+                "POST",
+                &format!("/values/{range}:clear"),
+                Some(&json!({})),
+            )?;
+
+            let mut values = vec![json!(HEADERS)];
+            values.extend(rows.iter().map(|row| {
+                json!([
+                    row.section.map(|id| id.to_string()).unwrap_or_default(),
+                    row.context,
+                    row.source,
+                    "",
+                ])
+            }));
+
+            self.call(
+                "PUT",
+                &format!("/values/{range}?valueInputOption=RAW"),
+                Some(&json!({ "values": values })),
+            )?;
+
+            info!("{title}: Pushed {} untranslated entries.", rows.len());
+        }
+
+        Ok(())
+    }
+
+    /// Writes filled translations of the spreadsheet's tabs to translation files in `translation_path`. Empty cells don't change translations, and conflicts with existing translations are resolved with `resolve`.
+    pub fn pull(
+        &self,
+        translation_path: &Path,
+        resolve: impl FnMut(&Conflict) -> Result<Resolution>,
+    ) -> Result<Vec<Changed>> {
+        let mut rows = Vec::new();
+
+        for title in self.titles()? {
+            let name = format!("{title}.txt");
+
+            if !translation_path.join(&name).exists() {
+                continue;
+            }
+
+            let response = self.call(
+                "GET",
+                &format!("/values/{}", quote_range(&title)),
+                None,
+            )?;
+
+            let mut values = response
+                .get("values")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_array);
+
+            let Some(header) = values.next() else {
+                continue;
+            };
+
+            let column = |title: &str| {
+                header.iter().position(|cell| {
+                    cell.as_str().is_some_and(|cell| {
+                        cell.trim().eq_ignore_ascii_case(title)
+                    })
+                })
+            };
+
+            let (Some(section), Some(source), Some(translation)) =
+                (column("Section"), column("Source"), column("Translation"))
+            else {
+                warn!(
+                    "{title}: Tab doesn't have Section, Source and Translation columns. Skipping it."
+                );
+                continue;
+            };
+
+            let mut read = 0;
+
+            for cells in values {
+                let cell = |index: usize| {
+                    cells.get(index).and_then(Value::as_str).unwrap_or_default()
+                };
+
+                if cell(translation).is_empty() || cell(source).is_empty() {
+                    continue;
+                }
+
+                rows.push(Row {
+                    file: name.clone(),
+                    section: cell(section).trim().parse().ok(),
+                    context: String::new(),
+                    source: cell(source).to_string(),
+                    translation: cell(translation).to_string(),
+                });
+                read += 1;
+            }
+
+            info!("{title}: Pulled {read} translations.");
+        }
+
+        apply_rows(translation_path, rows, resolve)
+    }
+}
+
+/// Returns A1 notation of the whole tab `title`, encoded for URL path.
+fn quote_range(title: &str) -> String {
+    let quoted = format!("'{}'", title.replace('\'', "''"));
+
+    quoted
+        .bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte) {
+                (byte as char).to_string()
+            } else {
+                format!("%{byte:02X}")
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,106 @@
+//! Context of messages in JSON files, that accompany translation files.
+//!
+//! Comments inflate translation files, so context can be written to `maps.context.json`, `commonevents.context.json` and `troops.context.json` instead, for editor plugins and other tools to consume. Each file is a JSON object of entries keyed by `section:source`:
+//!
+//! ```json
+//! {
+//!     "1:Hello!": {
+//!         "speaker": "Harold",
+//!         "location": "maps.txt: 1",
+//!         "event": "EV001",
+//!         "face": "Actor1 #0",
+//!         "conditions": ["switch 5 ON"],
+//!         "previous": null,
+//!         "next": "How are you?"
+//!     }
+//! }
+//! ```
+
+use crate::{
+    dialogue::{self, Location, Message},
+    export::{Project, detect_speakers},
+};
+use anyhow::Result;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::write,
+};
+use tracing::info;
+
+#[derive(Serialize)]
+struct Context {
+    speaker: String,
+    location: String,
+    event: String,
+    face: Option<String>,
+    conditions: Vec<String>,
+
+    /// Source of the previous message of the same event.
+    previous: Option<String>,
+
+    /// Source of the next message of the same event.
+    next: Option<String>,
+}
+
+/// Returns whether both messages belong to the same event.
+fn same_event(
+    (a_location, a_message): &(Location, Message),
+    (b_location, b_message): &(Location, Message),
+) -> bool {
+    a_location.file == b_location.file
+        && a_location.id == b_location.id
+        && a_message.event_name == b_message.event_name
+}
+
+/// Writes `<name>.context.json` file next to each translation file of maps, common events and troops in `translation_path`.
+pub fn write_context_files(project: &Project) -> Result<()> {
+    let messages =
+        dialogue::messages(project.source_path, project.engine_type)?;
+    let speakers = detect_speakers(project, &messages)?;
+
+    let mut files: HashMap<&str, BTreeMap<String, Context>> = HashMap::new();
+
+    for (index, ((location, message), speaker)) in
+        messages.iter().zip(speakers).enumerate()
+    {
+        let neighbour = |other: Option<&(Location, Message)>| {
+            other
+                .filter(|other| same_event(other, &messages[index]))
+                .map(|(_, other)| other.source())
+        };
+
+        let context = Context {
+            speaker,
+            location: format!("{}: {}", location.file, location.id),
+            event: message.event_name.clone(),
+            face: (!message.face_name.is_empty()).then(|| {
+                format!("{} #{}", message.face_name, message.face_index)
+            }),
+            conditions: message.conditions.clone(),
+            previous: neighbour(
+                index.checked_sub(1).and_then(|index| messages.get(index)),
+            ),
+            next: neighbour(messages.get(index + 1)),
+        };
+
+        // The library removes duplicates, so the first message wins.
+        files
+            .entry(location.file)
+            .or_default()
+            .entry(format!("{}:{}", location.id, message.source()))
+            .or_insert(context);
+    }
+
+    for (file, contexts) in files {
+        let name = format!("{}.context.json", file.trim_end_matches(".txt"));
+
+        write(
+            project.translation_path.join(&name),
+            serde_json::to_string_pretty(&contexts)?,
+        )?;
+        info!("{name}: Successfully written.");
+    }
+
+    Ok(())
+}
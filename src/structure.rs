@@ -0,0 +1,473 @@
+//! Validation of game data structure, that the library relies on.
+//!
+//! The library silently skips structures it doesn't expect, like events that aren't an array, and can't process some others at all, like event commands without a code. Data files are checked before the library processes them, so both cases are reported with the file and location of the problem.
+
+use crate::{
+    crash,
+    data::map_files,
+    error::{ErrorKind, WithKind},
+    salvage::salvage,
+};
+use anyhow::{Context, Result, anyhow};
+use marshal_rs::{Value, ValueType, load_utf8};
+use rvpacker_lib::{get_engine_extension, types::EngineType};
+use std::{fs::read, path::Path};
+use tracing::warn;
+
+/// MZ includes Byte Order Mark in files.
+const BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+
+/// Database files, that consist of an array of objects.
+const DATABASE_FILES: &[&str] = &[
+    "Actors", "Armors", "Classes", "Enemies", "Items", "Skills", "States",
+    "Weapons",
+];
+
+/// `Show Choices` command.
+const CHOICES: i32 = 102;
+
+/// `When [Choice]` command.
+const CHOICE: i32 = 402;
+
+/// Line of `Show Text` and `Show Scrolling Text` commands.
+const TEXT_LINES: [i32; 2] = [401, 405];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ParseMode {
+    /// Fails on any problem.
+    Strict,
+
+    /// Reports skipped structures, and fails on structures, that can't be processed.
+    #[default]
+    Default,
+
+    /// Reports all problems, and recovers from the ones, that can't be processed.
+    Lenient,
+
+    /// Same as [`ParseMode::Lenient`], but also salvages JSON files, that fail to parse.
+    Salvage,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Severity {
+    /// Library skips the structure.
+    Skipped,
+
+    /// Library can't process the structure.
+    Fatal,
+}
+
+/// Walks the data file, reports problems, and repairs the ones, that the library can't process.
+struct Checker<'a> {
+    file: &'a str,
+    skipped: usize,
+    fatal: usize,
+}
+
+fn empty_array() -> Value {
+    Value::array(Vec::<Value>::new())
+}
+
+fn is_text(value: &Value) -> bool {
+    matches!(**value, ValueType::String(_) | ValueType::Bytes(_))
+}
+
+impl Checker<'_> {
+    fn report(&mut self, severity: Severity, location: &str, message: &str) {
+        match severity {
+            Severity::Skipped => {
+                self.skipped += 1;
+                warn!("{}: {location}: {message} It's skipped.", self.file);
+            }
+            Severity::Fatal => {
+                self.fatal += 1;
+                warn!("{}: {location}: {message}", self.file);
+            }
+        }
+    }
+
+    /// Checks a list of commands. Commands without a code or parameters are removed from it.
+    fn check_list(&mut self, list: &mut Value, location: &str) {
+        let Some(commands) = list.as_array_mut() else {
+            self.report(Severity::Fatal, location, "List isn't an array.");
+            *list = empty_array();
+            return;
+        };
+
+        let mut index = 0;
+
+        commands.retain(|command| {
+            let location = format!("{location}[{index}]");
+            index += 1;
+
+            let Some(object) = command.as_object() else {
+                self.report(
+                    Severity::Fatal,
+                    &location,
+                    "Command isn't an object.",
+                );
+                return false;
+            };
+
+            let (Some(code), Some(parameters)) = (
+                object.get("code").and_then(|code| code.as_int()),
+                object
+                    .get("parameters")
+                    .and_then(|parameters| parameters.as_array()),
+            ) else {
+                self.report(
+                    Severity::Fatal,
+                    &location,
+                    "Command has no code or parameters.",
+                );
+                return false;
+            };
+
+            let text = if TEXT_LINES.contains(&code) {
+                parameters.first()
+            } else if code == CHOICE {
+                parameters.get(1)
+            } else if code == CHOICES {
+                if let Some(choices) = parameters.first()
+                    && choices
+                        .as_array()
+                        .is_none_or(|choices| !choices.iter().all(is_text))
+                {
+                    self.report(
+                        Severity::Skipped,
+                        &location,
+                        "Choices aren't an array of strings.",
+                    );
+                }
+
+                None
+            } else {
+                None
+            };
+
+            if let Some(text) = text
+                && !is_text(text)
+            {
+                self.report(
+                    Severity::Skipped,
+                    &location,
+                    &format!("Text of command {code} isn't a string."),
+                );
+            }
+
+            true
+        });
+    }
+
+    /// Checks pages of a map event or a troop. Pages, that aren't objects, are removed.
+    fn check_pages(
+        &mut self,
+        pages: &mut Vec<Value>,
+        location: &str,
+        list_required: bool,
+    ) {
+        let mut index = 0;
+
+        pages.retain_mut(|page| {
+            let location = format!("{location}.pages[{index}]");
+            index += 1;
+
+            let Some(object) = page.as_object_mut() else {
+                self.report(
+                    Severity::Fatal,
+                    &location,
+                    "Page isn't an object.",
+                );
+                return false;
+            };
+
+            match object.get_mut("list") {
+                Some(list) => {
+                    self.check_list(list, &format!("{location}.list"));
+                }
+                None if list_required => {
+                    self.report(
+                        Severity::Fatal,
+                        &location,
+                        "Page has no list.",
+                    );
+                    object.insert("list".to_string(), empty_array());
+                }
+                None => self.report(
+                    Severity::Skipped,
+                    &location,
+                    "Page has no list.",
+                ),
+            }
+
+            true
+        });
+    }
+
+    fn check_map(&mut self, map: &mut Value) {
+        let Some(object) = map.as_object_mut() else {
+            self.report(Severity::Fatal, "root", "Map isn't an object.");
+            return;
+        };
+
+        let Some(events) = object.get_mut("events") else {
+            return;
+        };
+
+        let events: Vec<(String, &mut Value)> = match &mut **events {
+            ValueType::Array(array) => array
+                .iter_mut()
+                .enumerate()
+                .map(|(index, event)| (format!("events[{index}]"), event))
+                .collect(),
+            ValueType::HashMap(hashmap) => hashmap
+                .0
+                .iter_mut()
+                .map(|(id, event)| {
+                    (
+                        format!("events[{}]", id.as_int().unwrap_or_default()),
+                        event,
+                    )
+                })
+                .collect(),
+            _ => {
+                self.report(
+                    Severity::Skipped,
+                    "events",
+                    "Events aren't an array.",
+                );
+                return;
+            }
+        };
+
+        for (location, event) in events {
+            if event.is_null() {
+                continue;
+            }
+
+            let Some(object) = event.as_object_mut() else {
+                self.report(
+                    Severity::Fatal,
+                    &location,
+                    "Event isn't an object.",
+                );
+                *event = Value::null();
+                continue;
+            };
+
+            match object
+                .get_mut("pages")
+                .and_then(|pages| pages.as_array_mut())
+            {
+                Some(pages) => self.check_pages(pages, &location, true),
+                None => self.report(
+                    Severity::Skipped,
+                    &location,
+                    "Event has no pages.",
+                ),
+            }
+        }
+    }
+
+    /// Checks `CommonEvents`, which objects have a list, or `Troops`, which objects have pages.
+    fn check_events(&mut self, value: &mut Value, pages: bool) {
+        let Some(array) = value.as_array_mut() else {
+            self.report(Severity::Fatal, "root", "File isn't an array.");
+            *value = empty_array();
+            return;
+        };
+
+        for (index, entry) in array.iter_mut().enumerate() {
+            let location = format!("[{index}]");
+
+            if entry.is_null() {
+                continue;
+            }
+
+            let Some(object) = entry.as_object_mut() else {
+                self.report(
+                    Severity::Fatal,
+                    &location,
+                    "Entry isn't an object.",
+                );
+                *entry = Value::null();
+                continue;
+            };
+
+            if pages {
+                if let Some(pages) = object
+                    .get_mut("pages")
+                    .and_then(|pages| pages.as_array_mut())
+                {
+                    self.check_pages(pages, &location, false);
+                } else {
+                    self.report(
+                        Severity::Fatal,
+                        &location,
+                        "Troop has no pages.",
+                    );
+                    object.insert("pages".to_string(), empty_array());
+                }
+            } else {
+                let location = format!("{location}.list");
+
+                if let Some(list) = object.get_mut("list") {
+                    self.check_list(list, &location);
+                } else {
+                    self.report(
+                        Severity::Fatal,
+                        &location,
+                        "Common event has no list.",
+                    );
+                    object.insert("list".to_string(), empty_array());
+                }
+            }
+        }
+    }
+
+    fn check_database(&mut self, value: &mut Value) {
+        let Some(array) = value.as_array_mut() else {
+            self.report(Severity::Fatal, "root", "File isn't an array.");
+            *value = empty_array();
+            return;
+        };
+
+        for (index, entry) in array.iter_mut().enumerate() {
+            if !entry.is_null() && entry.as_object().is_none() {
+                self.report(
+                    Severity::Fatal,
+                    &format!("[{index}]"),
+                    "Entry isn't an object.",
+                );
+                *entry = Value::null();
+            }
+        }
+    }
+}
+
+/// Loads the data file. Trailing data after JSON is reported as a problem, that can't be processed, and is cut off. In salvage mode, JSON, that fails to parse, is salvaged.
+fn load(
+    path: &Path,
+    name: &str,
+    engine_type: EngineType,
+    mode: ParseMode,
+    fatal: &mut usize,
+) -> Result<Value> {
+    let _processing = crash::processing(path);
+    let content =
+        read(path).with_context(|| format!("Reading {}", path.display()))?;
+
+    if !engine_type.is_new() {
+        return load_utf8(&content, Some(""))
+            .with_context(|| format!("Loading {}", path.display()));
+    }
+
+    let content = content.strip_prefix(BOM).unwrap_or(&content);
+    let mut stream = serde_json::Deserializer::from_slice(content)
+        .into_iter::<serde_json::Value>();
+
+    let parsed = match stream.next() {
+        Some(Ok(parsed)) => parsed,
+        Some(Err(err)) if mode == ParseMode::Salvage => {
+            warn!("{name}: {err}. Salvaging the file.");
+
+            let Some((salvaged, skipped)) = salvage(name, content) else {
+                return Err(anyhow!("{name}: Nothing could be salvaged."));
+            };
+
+            *fatal += skipped.max(1);
+            return Ok(Value::from(salvaged));
+        }
+        Some(Err(err)) => {
+            return Err(anyhow!(
+                "{name}: {err}. Pass `--salvage` to extract what parses correctly."
+            ));
+        }
+        None => return Err(anyhow!("{name}: File is empty.")),
+    };
+
+    let end = stream.byte_offset();
+
+    if content[end..]
+        .iter()
+        .any(|byte| !byte.is_ascii_whitespace())
+    {
+        *fatal += 1;
+        warn!(
+            "{name}: Byte offset {end}: Unexpected data after the end of JSON."
+        );
+    }
+
+    Ok(Value::from(parsed))
+}
+
+/// Checks data files in `source_path`, that contain events or database entries, and reports their problems.
+///
+/// Fails in strict mode, if there are any problems, and in default mode, if there are problems, that library can't process. Returns names and repaired contents of files, that have such problems, in lenient mode.
+pub fn check(
+    source_path: &Path,
+    engine_type: EngineType,
+    mode: ParseMode,
+) -> Result<Vec<(String, Value)>> {
+    let extension = get_engine_extension(engine_type);
+    let mut files: Vec<(String, std::path::PathBuf)> =
+        map_files(source_path, engine_type)?
+            .into_iter()
+            .filter_map(|(_, path)| {
+                Some((path.file_name()?.to_str()?.to_string(), path))
+            })
+            .collect();
+
+    for stem in DATABASE_FILES.iter().chain(&["CommonEvents", "Troops"]) {
+        let name = format!("{stem}.{extension}");
+        let path = source_path.join(&name);
+
+        if path.exists() {
+            files.push((name, path));
+        }
+    }
+
+    let mut skipped = 0;
+    let mut fatal = 0;
+    let mut repaired = Vec::new();
+
+    for (name, path) in files {
+        let mut file_fatal = 0;
+        let mut value = load(&path, &name, engine_type, mode, &mut file_fatal)?;
+        let mut checker = Checker {
+            file: &name,
+            skipped: 0,
+            fatal: file_fatal,
+        };
+
+        if name.starts_with("Map") {
+            checker.check_map(&mut value);
+        } else if name.starts_with("CommonEvents") {
+            checker.check_events(&mut value, false);
+        } else if name.starts_with("Troops") {
+            checker.check_events(&mut value, true);
+        } else {
+            checker.check_database(&mut value);
+        }
+
+        skipped += checker.skipped;
+        fatal += checker.fatal;
+
+        if checker.fatal != 0 && mode >= ParseMode::Lenient {
+            repaired.push((name, value));
+        }
+    }
+
+    match mode {
+        ParseMode::Strict if skipped + fatal != 0 => Err(anyhow!(
+            "Game data contains {} structures, that can't be fully processed.",
+            skipped + fatal
+        ))
+        .kind(ErrorKind::ValidationFailed),
+        ParseMode::Default if fatal != 0 => Err(anyhow!(
+            "Game data contains {fatal} structures, that can't be processed. Pass `--lenient-parse` to recover from them."
+        ))
+        .kind(ErrorKind::ValidationFailed),
+        _ => Ok(repaired),
+    }
+}
@@ -0,0 +1,105 @@
+//! Protected terms, e.g. names of characters and locations, that machine translation and romanization never alter.
+//!
+//! Terms are listed in a TSV file, one per line, with an optional spelling in the second column, that machine translations use instead of the term, e.g. `Alice` after `アリス` and a tab. Empty lines and lines, that start with `#`, are skipped. Terms are matched case-sensitively, and longer terms take precedence over terms, that they contain.
+
+use anyhow::{Context, Result, bail};
+use regex::Regex;
+use std::{fs::read_to_string, ops::Range, path::Path};
+
+pub struct ProtectedTerms {
+    pattern: Regex,
+
+    /// Terms and their spellings, that replace them in translations.
+    spellings: Vec<(String, String)>,
+    hash: String,
+}
+
+impl ProtectedTerms {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = read_to_string(path)
+            .with_context(|| format!("Reading {}", path.display()))?;
+        let mut spellings: Vec<(String, String)> = Vec::new();
+
+        for (index, line) in content.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut columns = line.split('\t').map(str::trim);
+            let term = columns.next().unwrap_or_default();
+            let spelling =
+                columns.next().filter(|spelling| !spelling.is_empty());
+
+            if term.is_empty() {
+                bail!(
+                    "{}:{}: Protected term is empty.",
+                    path.display(),
+                    index + 1
+                );
+            }
+
+            spellings
+                .push((term.to_string(), spelling.unwrap_or(term).to_string()));
+        }
+
+        if spellings.is_empty() {
+            bail!("{} has no protected terms.", path.display());
+        }
+
+        let mut terms: Vec<&str> =
+            spellings.iter().map(|(term, _)| term.as_str()).collect();
+        terms.sort_by_key(|term| std::cmp::Reverse(term.len()));
+
+        let pattern = Regex::new(
+            &terms
+                .iter()
+                .map(|term| regex::escape(term))
+                .collect::<Vec<_>>()
+                .join("|"),
+        )?;
+
+        Ok(Self {
+            pattern,
+            spellings,
+            hash: format!("{:08x}", crc32fast::hash(content.as_bytes())),
+        })
+    }
+
+    /// Returns the hash of the terms file, that is recorded in metadata with the romanization table.
+    #[must_use]
+    pub fn hash(&self) -> &str {
+        &self.hash
+    }
+
+    /// Returns byte ranges of terms in `text`, in their order, with spellings, that replace them in translations.
+    #[must_use]
+    pub fn find<'a>(&'a self, text: &str) -> Vec<(Range<usize>, &'a str)> {
+        self.pattern
+            .find_iter(text)
+            .filter_map(|found| {
+                self.spellings
+                    .iter()
+                    .find(|(term, _)| term == found.as_str())
+                    .map(|(_, spelling)| (found.range(), spelling.as_str()))
+            })
+            .collect()
+    }
+
+    /// Returns spellings of terms of `source`, that don't occur in `translation`.
+    #[must_use]
+    pub fn missing<'a>(
+        &'a self,
+        source: &str,
+        translation: &str,
+    ) -> Vec<&'a str> {
+        let mut missing: Vec<&str> = self
+            .find(source)
+            .into_iter()
+            .map(|(_, spelling)| spelling)
+            .filter(|spelling| !translation.contains(spelling))
+            .collect();
+
+        missing.dedup();
+        missing
+    }
+}
@@ -0,0 +1,777 @@
+//! Machine translation of untranslated entries with online translation services.
+//!
+//! Untranslated sources of each translation file are sent to a provider in batches, and translations are written as soon as the file is translated. Translations are also cached, so an interrupted run resumes without requesting them again. Machine-translated entries are preceded by `<!-- MACHINE TRANSLATION: provider -->` comments, so reviewers can tell them from human translations, and `purge --machine-translated` can remove them. Importing a translation over such entry removes its mark. Translations can be reviewed one by one before they're written, and edited ones aren't marked. Providers can follow a glossary of required translations of terms, and machine translations, that don't, are reported. Placeholders and protected terms, e.g. character names, are protected from providers, and translations, that lose or add them, are rejected, so the entries stay untranslated. Providers receive sources with line breaks instead of `\#` markers, and `curl` sends requests, so it must be installed.
+
+mod cache;
+mod deepl;
+mod glossary;
+mod google;
+mod mask;
+mod ollama;
+mod openai;
+
+pub use glossary::Glossary;
+
+use crate::{
+    attribution::Changed,
+    terms::ProtectedTerms,
+    translate::{
+        cache::Cache,
+        mask::{Mask, unmask},
+    },
+    translation::{
+        Line, TranslationFile, denormalize, effective_translation, normalize,
+        placeholders, translation_files,
+    },
+};
+use anyhow::{Context, Result, anyhow, bail};
+use clap::ValueEnum;
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{read_to_string, write},
+    mem::take,
+    path::Path,
+    sync::{
+        Mutex, PoisonError,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    thread::{self, sleep},
+    time::{Duration, Instant},
+};
+use tracing::{info, warn};
+
+/// Comment, that precedes machine-translated entries, and holds the name of the provider. The library ignores such comments on write.
+pub const MACHINE_TRANSLATION_COMMENT_PREFIX: &str =
+    "<!-- MACHINE TRANSLATION: ";
+
+/// Maximal number of translated entries of a section, that are sent as context.
+const CONTEXT_ENTRIES: usize = 40;
+
+/// Machine translation service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Provider {
+    /// `DeepL` API. Keys of free accounts, that end with `:fx`, use the free API
+    Deepl,
+
+    /// `OpenAI` chat completions API, or any server with the same API, e.g. llama.cpp server, with `--endpoint`. Sources of each map or event are translated together, with its translated entries as context
+    Openai,
+
+    /// Local Ollama server. Translates like `openai`, with a model, that is passed with `--model`
+    Ollama,
+
+    /// Google Cloud Translation v3, authorized with a service account
+    Google,
+}
+
+impl Provider {
+    /// Returns the name of the provider, that marks its translations.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Deepl => "DeepL",
+            Self::Openai => "OpenAI",
+            Self::Ollama => "Ollama",
+            Self::Google => "Google",
+        }
+    }
+
+    /// Returns the maximal number of sources in a request.
+    const fn batch_size(self) -> usize {
+        match self {
+            Self::Deepl => deepl::BATCH_SIZE,
+            Self::Openai => openai::BATCH_SIZE,
+            Self::Ollama => ollama::BATCH_SIZE,
+            Self::Google => google::BATCH_SIZE,
+        }
+    }
+
+    /// Returns whether the provider is a language model, which requests are limited by tokens.
+    const fn is_model(self) -> bool {
+        matches!(self, Self::Openai | Self::Ollama)
+    }
+}
+
+pub struct Settings<'a> {
+    pub provider: Provider,
+
+    /// Key of the provider's API. Defaults to the provider's environment variable, e.g. `DEEPL_API_KEY`.
+    pub api_key: Option<&'a str>,
+
+    /// Language of sources. Providers detect it, if it's not given.
+    pub source_language: Option<&'a str>,
+
+    pub target_language: &'a str,
+
+    /// Names or stems of translation files to translate. All files if empty.
+    pub files: &'a [String],
+
+    /// Model of language model providers. Providers use their default model, if it's not given.
+    pub model: Option<&'a str>,
+
+    /// Approximate number of tokens of sources in a request to language model providers.
+    pub max_tokens: usize,
+
+    /// Number of times a failed request is repeated, with increasing delays.
+    pub retries: u32,
+
+    /// Base URL of the API of language model providers, e.g. of a local server. Providers use their default URL, if it's not given.
+    pub endpoint: Option<&'a str>,
+
+    /// Sampling temperature of language model providers. Providers use the model's default, if it's not given.
+    pub temperature: Option<f64>,
+
+    /// Number of requests, that are sent at once.
+    pub concurrency: usize,
+
+    /// Key of a service account, for Google. Defaults to `GOOGLE_APPLICATION_CREDENTIALS` environment variable.
+    pub credentials: Option<&'a Path>,
+
+    /// Google Cloud project. Defaults to the project of the service account.
+    pub project: Option<&'a str>,
+
+    /// Google Cloud location, e.g. `us-central1`.
+    pub location: Option<&'a str>,
+
+    /// Source terms and their required translations, that providers receive, and translations are checked for.
+    pub glossary: Option<&'a Glossary>,
+
+    /// Cloud Storage bucket, that Google uploads the glossary to.
+    pub bucket: Option<&'a str>,
+
+    /// Whether cached translations are reused. New translations are cached either way.
+    pub cache: bool,
+
+    /// Maximal number of requests per minute.
+    pub rate_limit: Option<u32>,
+
+    /// Names, that providers must keep as they are, or replace with their chosen spellings.
+    pub protected_terms: Option<&'a ProtectedTerms>,
+}
+
+/// Untranslated sources of a section, e.g. a map or an event, that are translated together.
+#[derive(Clone, Copy)]
+pub struct Batch<'a> {
+    /// Name of the translation file.
+    pub file: &'a str,
+
+    pub section: Option<u16>,
+
+    /// Translated entries of the section as `(source, translation)`, with line breaks, in their order.
+    pub context: &'a [(String, String)],
+
+    /// Sources with line breaks.
+    pub sources: &'a [String],
+}
+
+/// Untranslated sources and context of a section of a translation file.
+struct Section {
+    id: Option<u16>,
+    context: Vec<(String, String)>,
+
+    /// Sources, which aren't in earlier sections of the file.
+    sources: Vec<String>,
+}
+
+/// Returns sections of `file`, that have untranslated entries.
+fn sections(file: &TranslationFile) -> Vec<Section> {
+    let mut sections: Vec<Section> = Vec::new();
+    let mut seen = HashSet::new();
+
+    for (id, source, translation) in file.entries() {
+        let section = match sections.last_mut() {
+            Some(section) if section.id == id => section,
+            _ => {
+                sections.push(Section {
+                    id,
+                    context: Vec::new(),
+                    sources: Vec::new(),
+                });
+                sections.last_mut().unwrap()
+            }
+        };
+
+        if !translation.is_empty() {
+            if section.context.len() < CONTEXT_ENTRIES {
+                section
+                    .context
+                    .push((denormalize(source), denormalize(translation)));
+            }
+        } else if !source.trim().is_empty() && seen.insert(source) {
+            section.sources.push(source.to_string());
+        }
+    }
+
+    sections.retain(|section| !section.sources.is_empty());
+    sections
+}
+
+/// Returns the approximate number of tokens of `text`. Latin text takes about four characters per token, and other scripts about a character per token.
+fn estimate_tokens(text: &str) -> usize {
+    text.chars()
+        .map(|char| if char.is_ascii() { 1 } else { 4 })
+        .sum::<usize>()
+        .div_ceil(4)
+}
+
+/// Splits `sources` into chunks of up to `count` sources, which approximate tokens don't exceed `tokens`, if it's given. Sources, that exceed it alone, are chunks of their own.
+fn chunks(
+    sources: &[String],
+    count: usize,
+    tokens: Option<usize>,
+) -> Vec<&[String]> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut chunk_tokens = 0;
+
+    for (index, source) in sources.iter().enumerate() {
+        let source_tokens = estimate_tokens(source);
+
+        if index > start
+            && (index - start == count
+                || tokens.is_some_and(|tokens| {
+                    chunk_tokens + source_tokens > tokens
+                }))
+        {
+            chunks.push(&sources[start..index]);
+            start = index;
+            chunk_tokens = 0;
+        }
+
+        chunk_tokens += source_tokens;
+    }
+
+    if start < sources.len() {
+        chunks.push(&sources[start..]);
+    }
+
+    chunks
+}
+
+/// Provider with its credentials, that are resolved once per run.
+enum Client {
+    Deepl(deepl::Client),
+
+    /// Local servers need no key.
+    Openai(Option<String>),
+
+    Ollama,
+    Google(google::Client),
+}
+
+impl Client {
+    fn connect(settings: &Settings) -> Result<Self> {
+        // Returns the key from `settings`, or from `variable` environment variable.
+        let api_key = |variable: &str| {
+            settings
+                .api_key
+                .map(ToString::to_string)
+                .or_else(|| std::env::var(variable).ok())
+        };
+        let missing_key = |variable: &str| {
+            anyhow!(
+                "{} API key is missing. Pass `--api-key`, or set `{variable}` environment variable.",
+                settings.provider.name()
+            )
+        };
+
+        match settings.provider {
+            Provider::Deepl => Ok(Self::Deepl(deepl::Client::connect(
+                settings,
+                &api_key(deepl::KEY_VARIABLE)
+                    .ok_or_else(|| missing_key(deepl::KEY_VARIABLE))?,
+            )?)),
+            Provider::Openai => match api_key(openai::KEY_VARIABLE) {
+                None if settings.endpoint.is_none() => {
+                    Err(missing_key(openai::KEY_VARIABLE))
+                }
+                key => Ok(Self::Openai(key)),
+            },
+            Provider::Ollama => {
+                if settings.model.is_none() {
+                    bail!(
+                        "Ollama needs `--model` with the name of an installed model, e.g. `qwen2.5:7b`."
+                    );
+                }
+
+                Ok(Self::Ollama)
+            }
+            Provider::Google => {
+                Ok(Self::Google(google::Client::connect(settings)?))
+            }
+        }
+    }
+}
+
+/// Spaces requests of all threads evenly, so they don't exceed a number of requests per minute.
+struct RateLimiter {
+    interval: Duration,
+
+    /// Time, when the next request may be sent.
+    next: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    fn new(per_minute: u32) -> Self {
+        Self {
+            interval: Duration::from_mins(1) / per_minute.max(1),
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Waits, until a request may be sent.
+    fn wait(&self) {
+        let delay = {
+            let mut next =
+                self.next.lock().unwrap_or_else(PoisonError::into_inner);
+            let now = Instant::now();
+            let delay = next.saturating_duration_since(now);
+
+            *next = (*next).max(now) + self.interval;
+            delay
+        };
+
+        sleep(delay);
+    }
+}
+
+/// Returns translations of sources of `batch`, in their order. Failed requests are repeated up to `retries` times of `settings`.
+fn translate_batch(
+    settings: &Settings,
+    client: &Client,
+    limiter: Option<&RateLimiter>,
+    batch: Batch,
+) -> Result<Vec<String>> {
+    let mut attempt = 0;
+
+    loop {
+        if let Some(limiter) = limiter {
+            limiter.wait();
+        }
+
+        let result = match client {
+            Client::Deepl(client) => deepl::translate(settings, client, batch),
+            Client::Openai(key) => {
+                openai::translate(settings, key.as_deref(), batch)
+            }
+            Client::Ollama => ollama::translate(settings, batch),
+            Client::Google(client) => {
+                google::translate(settings, client, batch)
+            }
+        }
+        .and_then(|translations| {
+            if translations.len() != batch.sources.len() {
+                bail!(
+                    "{} returned {} translations for {} sources.",
+                    settings.provider.name(),
+                    translations.len(),
+                    batch.sources.len()
+                );
+            }
+
+            Ok(translations)
+        });
+
+        match result {
+            Err(error) if attempt < settings.retries => {
+                let delay = 1 << attempt;
+                attempt += 1;
+
+                warn!("{error:#}. Retrying in {delay} seconds.");
+                sleep(Duration::from_secs(delay));
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Returns whether `translation` keeps placeholders of `source`, and spellings of its protected terms of `settings`.
+fn keeps_codes(settings: &Settings, source: &str, translation: &str) -> bool {
+    placeholders(source) == placeholders(translation)
+        && settings
+            .protected_terms
+            .is_none_or(|terms| terms.missing(source, translation).is_empty())
+}
+
+/// Translates chunks of sources of `jobs` of translation file `name`, with up to `concurrency` of `settings` requests at once, and caches them. Placeholders are masked for language models, protected terms for all providers, and translations, that don't keep them, are rejected as empty ones. Returns normalized translations by indices of jobs.
+fn translate_jobs(
+    settings: &Settings,
+    client: &Client,
+    limiter: Option<&RateLimiter>,
+    cache: &Cache,
+    name: &str,
+    jobs: &[(&Section, &[String])],
+) -> Result<Vec<(usize, Vec<String>)>> {
+    // `DeepL` and Google protect placeholders with their own tags.
+    let mask = Mask {
+        placeholders: settings.provider.is_model(),
+        terms: settings.protected_terms,
+    };
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let rejected = AtomicUsize::new(0);
+
+    let translate_next = || -> Result<Vec<(usize, Vec<String>)>> {
+        let mut results = Vec::new();
+
+        while !failed.load(Ordering::Relaxed) {
+            let index = next.fetch_add(1, Ordering::Relaxed);
+
+            let Some((section, chunk)) = jobs.get(index) else {
+                break;
+            };
+
+            let (sources, codes): (Vec<String>, Vec<Vec<String>>) = chunk
+                .iter()
+                .map(|source| {
+                    let mut codes = Vec::new();
+                    (mask.apply(&denormalize(source), &mut codes), codes)
+                })
+                .unzip();
+
+            let context: Vec<(String, String)> = section
+                .context
+                .iter()
+                .map(|(source, translation)| {
+                    let mut codes = Vec::new();
+                    let source = mask.apply(source, &mut codes);
+                    (source, mask.apply(translation, &mut codes))
+                })
+                .collect();
+
+            let batch = Batch {
+                file: name,
+                section: section.id,
+                context: &context,
+                sources: &sources,
+            };
+
+            let translations =
+                match translate_batch(settings, client, limiter, batch) {
+                    Ok(translations) => translations
+                        .iter()
+                        .zip(chunk.iter().zip(&codes))
+                        .map(|(translation, (source, codes))| {
+                            let translation = normalize(
+                                unmask(translation, codes).trim_end(),
+                            );
+
+                            if keeps_codes(settings, source, &translation) {
+                                return translation;
+                            }
+
+                            warn!(
+                                "{name}: Machine translation doesn't keep placeholders or protected terms of its source, and is rejected.\nSource: {source}\nTranslation: {translation}"
+                            );
+                            rejected.fetch_add(1, Ordering::Relaxed);
+                            String::new()
+                        })
+                        .collect::<Vec<_>>(),
+                    Err(error) => {
+                        failed.store(true, Ordering::Relaxed);
+                        return Err(error);
+                    }
+                };
+
+            cache.insert(chunk, &translations)?;
+            results.push((index, translations));
+        }
+
+        Ok(results)
+    };
+
+    let results = thread::scope(|scope| {
+        let workers: Vec<_> = (0..settings.concurrency.min(jobs.len()).max(1))
+            .map(|_| scope.spawn(translate_next))
+            .collect();
+
+        let mut results = Vec::new();
+
+        for worker in workers {
+            results.extend(worker.join().map_err(|_| {
+                anyhow!("Machine translation thread panicked.")
+            })??);
+        }
+
+        Ok::<_, anyhow::Error>(results)
+    })?;
+
+    let rejected = rejected.into_inner();
+
+    if rejected != 0 {
+        warn!(
+            "{name}: Rejected {rejected} machine translations, that don't keep placeholders or protected terms. Their entries stay untranslated, and are requested again on the next run."
+        );
+    }
+
+    Ok(results)
+}
+
+/// Removes sources, that have cached translations, from `sections`. Returns their translations by sources.
+fn take_cached(
+    cache: &Cache,
+    sections: &mut [Section],
+) -> HashMap<String, String> {
+    let mut translations = HashMap::new();
+
+    for section in sections {
+        section.sources.retain(|source| match cache.get(source) {
+            Some(translation) => {
+                translations.insert(source.clone(), translation.to_string());
+                false
+            }
+            None => true,
+        });
+    }
+
+    translations
+}
+
+/// Decision of a reviewer about a machine translation.
+pub enum Review {
+    Accept,
+
+    /// Replaces the machine translation with the reviewer's one, which isn't marked as machine-translated.
+    Edit(String),
+
+    /// Leaves the entry untranslated. Its translation stays cached for the next run.
+    Skip,
+
+    /// Skips this and all remaining translations, and stops the run.
+    Stop,
+}
+
+/// Asks a reviewer about machine translation of a source of a translation file: `(file, source, translation)`.
+pub type Reviewer<'a> = dyn FnMut(&str, &str, &str) -> Result<Review> + 'a;
+
+/// Result of filling a translation file.
+struct Filled {
+    translated: usize,
+    violations: usize,
+
+    /// Whether the reviewer stopped the run.
+    stopped: bool,
+}
+
+/// Fills untranslated entries of translation file `name` with `translations` by their sources, marks them as machine-translated, and reports the ones, that don't follow the glossary. Translations, that don't preserve placeholders or protected terms, e.g. cached before the terms were added, are skipped. With `ask`, each source's translation is filled only after the reviewer accepts or edits it.
+fn fill(
+    settings: &Settings,
+    name: &str,
+    file: &mut TranslationFile,
+    translations: &HashMap<String, String>,
+    mut ask: Option<&mut Reviewer>,
+    changed: &mut Vec<Changed>,
+) -> Result<Filled> {
+    let provider = settings.provider.name();
+    let mut section = None;
+    let mut translated = 0;
+    let mut rejected = 0;
+    let mut violations = 0;
+    let mut stopped = false;
+
+    // Decisions by sources, so duplicates of a source are decided once.
+    let mut decisions: HashMap<String, Option<(String, bool)>> = HashMap::new();
+    let mut lines = Vec::with_capacity(file.lines.len());
+
+    for mut line in take(&mut file.lines) {
+        if let Line::Id(id) = line {
+            section = Some(id);
+        }
+
+        if let Line::Entry {
+            source,
+            translation,
+        } = &mut line
+            && !stopped
+            && effective_translation(translation).is_empty()
+            && let Some(new) = translations.get(source.as_str())
+        {
+            if !keeps_codes(settings, source, new) {
+                rejected += 1;
+                lines.push(line);
+                continue;
+            }
+
+            // Translation to fill, and whether it's machine-made.
+            let decision = match ask.as_deref_mut() {
+                None => Some((new.clone(), true)),
+                Some(ask) => {
+                    if let Some(decision) = decisions.get(source.as_str()) {
+                        decision.clone()
+                    } else {
+                        let decision = match ask(
+                            name,
+                            &denormalize(source),
+                            &denormalize(new),
+                        )? {
+                            Review::Accept => Some((new.clone(), true)),
+                            Review::Edit(edited) => {
+                                Some((normalize(&edited), false))
+                            }
+                            Review::Skip => None,
+                            Review::Stop => {
+                                stopped = true;
+                                None
+                            }
+                        };
+
+                        decisions.insert(source.clone(), decision.clone());
+                        decision
+                    }
+                }
+            };
+
+            let Some((new, machine)) = decision else {
+                lines.push(line);
+                continue;
+            };
+
+            if machine {
+                lines.push(Line::Comment(format!(
+                    "{MACHINE_TRANSLATION_COMMENT_PREFIX}{provider} -->"
+                )));
+            }
+
+            for term in settings
+                .glossary
+                .map(|glossary| glossary.violations(source, &new))
+                .unwrap_or_default()
+            {
+                warn!(
+                    "{name}:{}: Machine translation doesn't translate `{}` as `{}`.\nTranslation: {new}",
+                    lines.len() + 1,
+                    term.source,
+                    term.translation
+                );
+                violations += 1;
+            }
+
+            *translation = new;
+            translated += 1;
+            changed.push((name.to_string(), section, source.clone()));
+        }
+
+        lines.push(line);
+    }
+
+    file.lines = lines;
+
+    if rejected != 0 {
+        warn!(
+            "{name}: Rejected {rejected} cached machine translations, that don't preserve placeholders or protected terms of their sources. Pass `--no-cache` to request them again."
+        );
+    }
+
+    Ok(Filled {
+        translated,
+        violations,
+        stopped,
+    })
+}
+
+/// Translates untranslated entries of translation files in `translation_path` with `settings`, and marks them as machine-translated. Entries with the same source in a file are translated once. Translations of each file are filled, after `reviewer` decides on them, if it's given. Returns changed entries.
+pub fn translate(
+    translation_path: &Path,
+    settings: &Settings,
+    mut reviewer: Option<&mut Reviewer>,
+) -> Result<Vec<Changed>> {
+    let provider = settings.provider.name();
+    let client = Client::connect(settings)?;
+    let limiter = settings.rate_limit.map(RateLimiter::new);
+    let cache = Cache::load(
+        translation_path,
+        provider,
+        settings.target_language,
+        settings.cache,
+    )?;
+
+    let token_limit =
+        settings.provider.is_model().then_some(settings.max_tokens);
+    let mut changed = Vec::new();
+    let mut violations = 0;
+
+    for name in translation_files(translation_path)? {
+        if !settings.files.is_empty()
+            && !settings.files.iter().any(|file| {
+                *file == name || name.strip_suffix(".txt") == Some(file)
+            })
+        {
+            continue;
+        }
+
+        let path = translation_path.join(&name);
+        let mut file = TranslationFile::parse(&read_to_string(&path)?);
+        let mut sections = sections(&file);
+
+        if sections.is_empty() {
+            continue;
+        }
+
+        let mut translations = take_cached(&cache, &mut sections);
+
+        if !translations.is_empty() {
+            info!("{name}: Reused {} cached translations.", translations.len());
+        }
+
+        let jobs: Vec<(&Section, &[String])> = sections
+            .iter()
+            .flat_map(|section| {
+                chunks(
+                    &section.sources,
+                    settings.provider.batch_size(),
+                    token_limit,
+                )
+                .into_iter()
+                .map(move |chunk| (section, chunk))
+            })
+            .collect();
+
+        let results = translate_jobs(
+            settings,
+            &client,
+            limiter.as_ref(),
+            &cache,
+            &name,
+            &jobs,
+        )
+        .with_context(|| format!("Translating {name}"))?;
+
+        for (index, batch_translations) in results {
+            for (source, translation) in
+                jobs[index].1.iter().zip(batch_translations)
+            {
+                if !translation.is_empty() {
+                    translations.insert(source.clone(), translation);
+                }
+            }
+        }
+
+        let filled = fill(
+            settings,
+            &name,
+            &mut file,
+            &translations,
+            reviewer.as_deref_mut(),
+            &mut changed,
+        )?;
+        write(&path, file.serialize())?;
+
+        violations += filled.violations;
+        info!(
+            "{name}: Translated {} entries with {provider}.",
+            filled.translated
+        );
+
+        if filled.stopped {
+            info!("Review stopped. Remaining files aren't translated.");
+            break;
+        }
+    }
+
+    if violations != 0 {
+        warn!(
+            "{violations} machine translations don't follow the glossary. Fix them before release."
+        );
+    }
+
+    Ok(changed)
+}
@@ -0,0 +1,106 @@
+//! Persistent cache of machine translations, so re-runs don't request translations, that were already paid for.
+//!
+//! Cache is `.rvpacker-mt-cache` file in `translation` directory, with a JSON object per line. Lines are appended after every request, so a run, that was interrupted, resumes where it stopped. Translations are keyed by provider, target language and source, so they're reused for the same sources in other files, and after game updates.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions, read_to_string},
+    io::Write,
+    path::Path,
+    sync::{Mutex, PoisonError},
+};
+
+pub const CACHE_FILE: &str = ".rvpacker-mt-cache";
+
+#[derive(Serialize, Deserialize)]
+struct Record {
+    provider: String,
+    target_language: String,
+    source: String,
+    translation: String,
+}
+
+pub struct Cache {
+    provider: String,
+    target_language: String,
+
+    /// Cached translations of the provider and target language by source.
+    translations: HashMap<String, String>,
+
+    file: Mutex<File>,
+}
+
+impl Cache {
+    /// Loads translations of `provider` to `target_language` from the cache in `translation_path`. Without `reuse`, cached translations are ignored, but new ones are still recorded.
+    pub fn load(
+        translation_path: &Path,
+        provider: &str,
+        target_language: &str,
+        reuse: bool,
+    ) -> Result<Self> {
+        let path = translation_path.join(CACHE_FILE);
+        let target_language = target_language.to_lowercase();
+        let mut translations = HashMap::new();
+
+        if reuse && path.exists() {
+            // Lines of an interrupted write are incomplete, and are skipped.
+            for record in read_to_string(&path)?
+                .lines()
+                .filter_map(|line| serde_json::from_str::<Record>(line).ok())
+            {
+                if record.provider == provider
+                    && record.target_language == target_language
+                {
+                    translations.insert(record.source, record.translation);
+                }
+            }
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Opening {}", path.display()))?;
+
+        Ok(Self {
+            provider: provider.to_string(),
+            target_language,
+            translations,
+            file: Mutex::new(file),
+        })
+    }
+
+    #[must_use]
+    pub fn get(&self, source: &str) -> Option<&str> {
+        self.translations.get(source).map(String::as_str)
+    }
+
+    /// Appends `translations` of `sources` to the cache. Empty translations aren't cached.
+    pub fn insert(
+        &self,
+        sources: &[String],
+        translations: &[String],
+    ) -> Result<()> {
+        let mut lines = String::new();
+
+        for (source, translation) in sources.iter().zip(translations) {
+            if translation.is_empty() {
+                continue;
+            }
+
+            lines.push_str(&serde_json::to_string(&Record {
+                provider: self.provider.clone(),
+                target_language: self.target_language.clone(),
+                source: source.clone(),
+                translation: translation.clone(),
+            })?);
+            lines.push('\n');
+        }
+
+        let mut file = self.file.lock().unwrap_or_else(PoisonError::into_inner);
+        file.write_all(lines.as_bytes())?;
+        Ok(())
+    }
+}
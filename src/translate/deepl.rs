@@ -0,0 +1,292 @@
+//! [DeepL API](https://developers.deepl.com/docs/api-reference/translate) provider.
+//!
+//! Sources are sent as XML with `tag_handling=xml`, where placeholders and line breaks are empty tags, which `DeepL` keeps in place and never translates. Sources of translated entries of the section are sent as context, that isn't translated itself.
+//!
+//! A glossary is created as a `DeepL` glossary, that is named after a hash of its entries and languages, so unchanged glossaries are created only once.
+
+use super::{Batch, Glossary, Settings, mask::protected_ranges};
+use crate::{
+    export::fnv1a,
+    http::authorized_request,
+    xml::{escape_lines, unescape},
+};
+use anyhow::{Result, bail};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::{fmt::Write, sync::LazyLock};
+use tracing::info;
+
+const FREE_API: &str = "https://api-free.deepl.com/v2";
+const PRO_API: &str = "https://api.deepl.com/v2";
+
+/// Suffix of keys of free accounts, that only work with the free API.
+const FREE_KEY_SUFFIX: &str = ":fx";
+
+pub const KEY_VARIABLE: &str = "DEEPL_API_KEY";
+
+/// Maximal number of texts, that `DeepL` accepts in a single request.
+pub const BATCH_SIZE: usize = 50;
+
+/// Tags, that replace placeholders and line breaks.
+static MARKUP_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"<x i="(\d+)"\s*/>|<br\s*/>"#).unwrap());
+
+#[derive(Deserialize)]
+struct Response {
+    translations: Vec<Translation>,
+}
+
+#[derive(Deserialize)]
+struct Translation {
+    text: String,
+}
+
+/// Returns `text` as XML, where placeholders and tokens of protected terms are `<x i="n"/>` tags, and its placeholders by their index.
+fn to_xml(text: &str) -> (String, Vec<&str>) {
+    let mut xml = String::with_capacity(text.len());
+    let mut placeholders = Vec::new();
+    let mut end = 0;
+
+    for range in protected_ranges(text) {
+        xml.push_str(&escape_lines(&text[end..range.start], "<br/>"));
+        let _ = write!(xml, "<x i=\"{}\"/>", placeholders.len());
+        placeholders.push(&text[range.clone()]);
+        end = range.end;
+    }
+
+    xml.push_str(&escape_lines(&text[end..], "<br/>"));
+    (xml, placeholders)
+}
+
+/// Restores placeholders and line breaks of translated `xml`, and unescapes it. Tags of unknown placeholders are dropped.
+fn from_xml(xml: &str, placeholders: &[&str]) -> String {
+    let mut text = String::with_capacity(xml.len());
+    let mut end = 0;
+
+    for captures in MARKUP_RE.captures_iter(xml) {
+        let Some(tag) = captures.get(0) else {
+            continue;
+        };
+
+        text.push_str(&unescape(&xml[end..tag.start()]));
+
+        match captures.get(1) {
+            Some(index) => {
+                if let Some(placeholder) = index
+                    .as_str()
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| placeholders.get(index))
+                {
+                    text.push_str(placeholder);
+                }
+            }
+            None => text.push('\n'),
+        }
+
+        end = tag.end();
+    }
+
+    text.push_str(&unescape(&xml[end..]));
+    text
+}
+
+/// Authorized connection to `DeepL` API.
+pub struct Client {
+    api: &'static str,
+    authorization: String,
+
+    /// ID of the glossary, that translations follow.
+    glossary: Option<String>,
+}
+
+impl Client {
+    /// Creates the glossary of `settings`, if it's given and doesn't exist yet.
+    pub fn connect(settings: &Settings, key: &str) -> Result<Self> {
+        let mut client = Self {
+            api: if key.ends_with(FREE_KEY_SUFFIX) {
+                FREE_API
+            } else {
+                PRO_API
+            },
+            authorization: format!("Authorization: DeepL-Auth-Key {key}"),
+            glossary: None,
+        };
+
+        if let Some(glossary) = settings.glossary {
+            client.glossary = Some(client.create_glossary(settings, glossary)?);
+        }
+
+        Ok(client)
+    }
+
+    fn call(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<&Value>,
+    ) -> Result<Value> {
+        let mut arguments = vec!["-X", method];
+        let body = body.map(Value::to_string).unwrap_or_default();
+
+        if !body.is_empty() {
+            arguments.extend_from_slice(&[
+                "-H",
+                "Content-Type: application/json",
+                "--data-binary",
+                "@-",
+            ]);
+        }
+
+        authorized_request(
+            &self.authorization,
+            &arguments,
+            &format!("{}/{path}", self.api),
+            body.as_bytes(),
+        )
+    }
+
+    /// Creates a glossary from entries of `glossary`, unless the account already has one with the same name. Returns the ID of the glossary.
+    fn create_glossary(
+        &self,
+        settings: &Settings,
+        glossary: &Glossary,
+    ) -> Result<String> {
+        let Some(source_language) = settings.source_language else {
+            bail!("DeepL glossaries need `--source-language`.");
+        };
+
+        // Glossaries are defined for languages without regional variants, e.g. `en` for `EN-US`.
+        let language = |code: &str| {
+            code.split('-').next().unwrap_or_default().to_lowercase()
+        };
+        let source_language = language(source_language);
+        let target_language = language(settings.target_language);
+
+        let entries = glossary.tsv();
+        let name = format!(
+            "rvpacker-{:016x}",
+            fnv1a(
+                &[
+                    entries.as_bytes(),
+                    source_language.as_bytes(),
+                    target_language.as_bytes(),
+                ]
+                .join(&0)
+            )
+        );
+
+        let existing = self.call("GET", "glossaries", None)?;
+
+        if let Some(id) = existing
+            .get("glossaries")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .find(|glossary| {
+                glossary.get("name").and_then(Value::as_str)
+                    == Some(name.as_str())
+            })
+            .and_then(|glossary| glossary.get("glossary_id"))
+            .and_then(Value::as_str)
+        {
+            info!(
+                "Using glossary {name}, that was created from the same file."
+            );
+            return Ok(id.to_string());
+        }
+
+        let created = self.call(
+            "POST",
+            "glossaries",
+            Some(&json!({
+                "name": name,
+                "source_lang": source_language,
+                "target_lang": target_language,
+                "entries": entries,
+                "entries_format": "tsv",
+            })),
+        )?;
+
+        let Some(id) = created.get("glossary_id").and_then(Value::as_str)
+        else {
+            bail!("Glossary creation response has no glossary ID.");
+        };
+
+        info!("Created glossary {name} from {}.", glossary.path.display());
+        Ok(id.to_string())
+    }
+}
+
+pub fn translate(
+    settings: &Settings,
+    client: &Client,
+    batch: Batch,
+) -> Result<Vec<String>> {
+    let (texts, placeholders): (Vec<String>, Vec<Vec<&str>>) =
+        batch.sources.iter().map(|source| to_xml(source)).unzip();
+
+    let mut body = json!({
+        "text": texts,
+        "target_lang": settings.target_language.to_uppercase(),
+        "tag_handling": "xml",
+    });
+
+    if let Some(source_language) = settings.source_language {
+        body["source_lang"] = json!(source_language.to_uppercase());
+    }
+
+    if let Some(glossary) = &client.glossary {
+        body["glossary_id"] = json!(glossary);
+    }
+
+    if !batch.context.is_empty() {
+        let context: Vec<&str> = batch
+            .context
+            .iter()
+            .map(|(source, _)| source.as_str())
+            .collect();
+        body["context"] = json!(context.join("\n"));
+    }
+
+    let response: Response = serde_json::from_value(client.call(
+        "POST",
+        "translate",
+        Some(&body),
+    )?)?;
+
+    Ok(response
+        .translations
+        .iter()
+        .zip(&placeholders)
+        .map(|(translation, placeholders)| {
+            from_xml(&translation.text, placeholders)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_xml() {
+        let text = "\\C[2]Tom & <Jerry>\\C[0]\n\"Hi\" %1";
+        let (xml, placeholders) = to_xml(text);
+
+        assert_eq!(
+            xml,
+            "<x i=\"0\"/>Tom &amp; &lt;Jerry&gt;<x i=\"1\"/><br/>&quot;Hi&quot; <x i=\"2\"/>"
+        );
+        assert_eq!(from_xml(&xml, &placeholders), text);
+    }
+
+    #[test]
+    fn keeps_escaped_tags_as_text() {
+        assert_eq!(
+            from_xml("&lt;x i=\"0\"/&gt;<x i=\"0\" /><x i=\"5\"/>", &["%1"]),
+            "<x i=\"0\"/>%1"
+        );
+    }
+}
@@ -0,0 +1,115 @@
+//! Glossary of source terms and their required translations, that machine translations follow.
+//!
+//! Glossary is a TSV file with a source term in the first column, and its translation in the second. Empty lines and lines, that start with `#`, are skipped. Language models receive terms, that occur in sources of a request, with the prompt, while `DeepL` and Google create glossaries from the file. Translations are checked for terms afterwards either way, since neither guarantees them.
+
+use anyhow::{Context, Result, bail};
+use std::{
+    fmt::Write,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
+
+pub struct Term {
+    pub source: String,
+    pub translation: String,
+
+    /// Lowercase source and translation, that texts are matched with.
+    lowercase_source: String,
+    lowercase_translation: String,
+}
+
+pub struct Glossary {
+    pub path: PathBuf,
+    pub terms: Vec<Term>,
+}
+
+impl Glossary {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = read_to_string(path)
+            .with_context(|| format!("Reading {}", path.display()))?;
+        let mut terms = Vec::new();
+
+        for (index, line) in content.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut columns = line.split('\t').map(str::trim);
+
+            let (Some(source), Some(translation)) =
+                (columns.next(), columns.next())
+            else {
+                bail!(
+                    "{}:{}: Glossary entry needs a source term and its translation, separated by a tab.",
+                    path.display(),
+                    index + 1
+                );
+            };
+
+            if source.is_empty() || translation.is_empty() {
+                bail!(
+                    "{}:{}: Glossary entry has an empty column.",
+                    path.display(),
+                    index + 1
+                );
+            }
+
+            terms.push(Term {
+                source: source.to_string(),
+                translation: translation.to_string(),
+                lowercase_source: source.to_lowercase(),
+                lowercase_translation: translation.to_lowercase(),
+            });
+        }
+
+        if terms.is_empty() {
+            bail!("{} has no glossary entries.", path.display());
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            terms,
+        })
+    }
+
+    /// Returns entries as TSV without comments, that `DeepL` and Google accept.
+    #[must_use]
+    pub fn tsv(&self) -> String {
+        let mut tsv = String::new();
+
+        for term in &self.terms {
+            let _ = writeln!(tsv, "{}\t{}", term.source, term.translation);
+        }
+
+        tsv
+    }
+
+    /// Returns terms, that occur in any of `texts`. Terms are matched case-insensitively.
+    pub fn terms_in(&self, texts: &[String]) -> Vec<&Term> {
+        let texts: Vec<String> =
+            texts.iter().map(|text| text.to_lowercase()).collect();
+
+        self.terms
+            .iter()
+            .filter(|term| {
+                texts
+                    .iter()
+                    .any(|text| text.contains(&term.lowercase_source))
+            })
+            .collect()
+    }
+
+    /// Returns terms, that occur in `source`, but whose translations don't occur in `translation`.
+    pub fn violations(&self, source: &str, translation: &str) -> Vec<&Term> {
+        let source = source.to_lowercase();
+        let translation = translation.to_lowercase();
+
+        self.terms
+            .iter()
+            .filter(|term| {
+                source.contains(&term.lowercase_source)
+                    && !translation.contains(&term.lowercase_translation)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,323 @@
+//! [Google Cloud Translation v3](https://cloud.google.com/translate/docs/reference/rest/v3/projects/translateText) provider.
+//!
+//! Requests are authorized with a service account, like Google Sheet sync. Sources are sent as HTML, where placeholders are in `translate="no"` spans, and line breaks are `<br>` tags.
+//!
+//! A glossary is uploaded to a Cloud Storage bucket, and created as a glossary of the project, which translations then follow. Glossaries are named after hashes of their entries and languages, so unchanged glossaries are created only once.
+
+use super::{Batch, Glossary, Settings, mask::protected_ranges};
+use crate::{
+    export::fnv1a,
+    http::authorized_request,
+    sheets::authorize,
+    xml::{escape_lines, unescape},
+};
+use anyhow::{Context, Result, bail};
+use regex::Regex;
+use serde_json::{Value, json};
+use std::{sync::LazyLock, thread::sleep, time::Duration};
+use tracing::info;
+
+const API: &str = "https://translation.googleapis.com/v3";
+const STORAGE_UPLOAD_API: &str =
+    "https://storage.googleapis.com/upload/storage/v1/b";
+const SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+
+/// Location of requests. Glossaries aren't available in `global` location, so a regional one is the default.
+const DEFAULT_LOCATION: &str = "us-central1";
+
+/// Maximal number of texts in a request. Google accepts more, but recommends keeping requests small.
+pub const BATCH_SIZE: usize = 100;
+
+/// Interval and maximal number of checks, whether glossary creation finished.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const POLLS: usize = 150;
+
+/// Wrappers of placeholders, and line breaks.
+static MARKUP_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"<span translate="no">(.*?)</span>|<br\s*/?>"#).unwrap()
+});
+
+/// Returns `text` as HTML, where placeholders and tokens of protected terms are wrapped in spans, that aren't translated.
+fn to_html(text: &str) -> String {
+    let mut html = String::with_capacity(text.len());
+    let mut end = 0;
+
+    for range in protected_ranges(text) {
+        html.push_str(&escape_lines(&text[end..range.start], "<br>"));
+        html.push_str("<span translate=\"no\">");
+        html.push_str(&escape_lines(&text[range.clone()], "<br>"));
+        html.push_str("</span>");
+        end = range.end;
+    }
+
+    html.push_str(&escape_lines(&text[end..], "<br>"));
+    html
+}
+
+/// Restores placeholders and line breaks of translated `html`, and unescapes it.
+fn from_html(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut end = 0;
+
+    for captures in MARKUP_RE.captures_iter(html) {
+        let Some(tag) = captures.get(0) else {
+            continue;
+        };
+
+        text.push_str(&unescape(&html[end..tag.start()]));
+
+        match captures.get(1) {
+            Some(inner) => text.push_str(&unescape(inner.as_str())),
+            None => text.push('\n'),
+        }
+
+        end = tag.end();
+    }
+
+    text.push_str(&unescape(&html[end..]));
+    text
+}
+
+/// Authorized connection to Cloud Translation of a project.
+pub struct Client {
+    authorization: String,
+
+    /// `projects/<project>/locations/<location>`.
+    parent: String,
+
+    /// Resource name of the glossary, that translations follow.
+    glossary: Option<String>,
+}
+
+impl Client {
+    /// Authorizes the service account of `settings`, and creates its glossary, if it's given and doesn't exist yet.
+    pub fn connect(settings: &Settings) -> Result<Self> {
+        let authorization = authorize(settings.credentials, SCOPE)?;
+
+        let Some(project) = settings
+            .project
+            .map(ToString::to_string)
+            .or(authorization.project_id)
+        else {
+            bail!(
+                "Service account key has no project. Pass it with `--project`."
+            );
+        };
+
+        let mut client = Self {
+            authorization: format!(
+                "Authorization: Bearer {}",
+                authorization.token
+            ),
+            parent: format!(
+                "projects/{project}/locations/{}",
+                settings.location.unwrap_or(DEFAULT_LOCATION)
+            ),
+            glossary: None,
+        };
+
+        if let Some(glossary) = settings.glossary {
+            client.glossary = Some(client.create_glossary(settings, glossary)?);
+        }
+
+        Ok(client)
+    }
+
+    fn call(
+        &self,
+        method: &str,
+        url: &str,
+        body: Option<&Value>,
+    ) -> Result<Value> {
+        let mut arguments = vec!["-X", method];
+        let body = body.map(Value::to_string).unwrap_or_default();
+
+        if !body.is_empty() {
+            arguments.extend_from_slice(&[
+                "-H",
+                "Content-Type: application/json",
+                "--data-binary",
+                "@-",
+            ]);
+        }
+
+        authorized_request(
+            &self.authorization,
+            &arguments,
+            url,
+            body.as_bytes(),
+        )
+    }
+
+    /// Uploads entries of `glossary` to the bucket of `settings`, and creates a glossary from them. Returns the resource name of the glossary.
+    fn create_glossary(
+        &self,
+        settings: &Settings,
+        glossary: &Glossary,
+    ) -> Result<String> {
+        let Some(source_language) = settings.source_language else {
+            bail!("Google glossaries need `--source-language`.");
+        };
+
+        let Some(bucket) = settings.bucket else {
+            bail!(
+                "Uploading a glossary needs `--bucket` with a Cloud Storage bucket, that the service account can write to."
+            );
+        };
+
+        let content = glossary.tsv().into_bytes();
+        let id = format!(
+            "rvpacker-{:016x}",
+            fnv1a(
+                &[
+                    content.as_slice(),
+                    source_language.as_bytes(),
+                    settings.target_language.as_bytes(),
+                ]
+                .join(&0)
+            )
+        );
+        let name = format!("{}/glossaries/{id}", self.parent);
+
+        if self.call("GET", &format!("{API}/{name}"), None).is_ok() {
+            info!("Using glossary {id}, that was created from the same file.");
+            return Ok(name);
+        }
+
+        let object = format!("{id}.tsv");
+        authorized_request(
+            &self.authorization,
+            &[
+                "-X",
+                "POST",
+                "-H",
+                "Content-Type: text/tab-separated-values",
+                "--data-binary",
+                "@-",
+            ],
+            &format!(
+                "{STORAGE_UPLOAD_API}/{bucket}/o?uploadType=media&name={object}"
+            ),
+            &content,
+        )
+        .context("Uploading the glossary")?;
+
+        let mut operation = self.call(
+            "POST",
+            &format!("{API}/{}/glossaries", self.parent),
+            Some(&json!({
+                "name": name,
+                "languagePair": {
+                    "sourceLanguageCode": source_language,
+                    "targetLanguageCode": settings.target_language,
+                },
+                "inputConfig": {
+                    "gcsSource": {"inputUri": format!("gs://{bucket}/{object}")},
+                },
+            })),
+        )?;
+
+        for _ in 0..POLLS {
+            if operation.get("done").and_then(Value::as_bool) == Some(true) {
+                if let Some(error) =
+                    operation.pointer("/error/message").and_then(Value::as_str)
+                {
+                    bail!("Creating the glossary failed: {error}");
+                }
+
+                info!(
+                    "Created glossary {id} from {}.",
+                    glossary.path.display()
+                );
+                return Ok(name);
+            }
+
+            let Some(operation_name) =
+                operation.get("name").and_then(Value::as_str)
+            else {
+                bail!("Glossary creation response has no operation name.");
+            };
+
+            sleep(POLL_INTERVAL);
+            operation =
+                self.call("GET", &format!("{API}/{operation_name}"), None)?;
+        }
+
+        bail!(
+            "Creating the glossary didn't finish in time. Run the command again later."
+        )
+    }
+}
+
+pub fn translate(
+    settings: &Settings,
+    client: &Client,
+    batch: Batch,
+) -> Result<Vec<String>> {
+    let contents: Vec<String> =
+        batch.sources.iter().map(|source| to_html(source)).collect();
+
+    let mut body = json!({
+        "contents": contents,
+        "mimeType": "text/html",
+        "targetLanguageCode": settings.target_language,
+    });
+
+    if let Some(source_language) = settings.source_language {
+        body["sourceLanguageCode"] = json!(source_language);
+    }
+
+    if let Some(glossary) = &client.glossary {
+        body["glossaryConfig"] = json!({"glossary": glossary});
+    }
+
+    let response = client.call(
+        "POST",
+        &format!("{API}/{}:translateText", client.parent),
+        Some(&body),
+    )?;
+
+    // Glossary translations are returned next to regular ones.
+    let key = if client.glossary.is_some() {
+        "glossaryTranslations"
+    } else {
+        "translations"
+    };
+
+    Ok(response
+        .get(key)
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .map(|translation| {
+            from_html(
+                translation
+                    .get("translatedText")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default(),
+            )
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_html() {
+        let text = "\\C[2]Tom & <Jerry>\n%1";
+        let html = to_html(text);
+
+        assert_eq!(
+            html,
+            "<span translate=\"no\">\\C[2]</span>Tom &amp; &lt;Jerry&gt;<br><span translate=\"no\">%1</span>"
+        );
+        assert_eq!(from_html(&html), text);
+    }
+
+    #[test]
+    fn unescapes_numeric_entities() {
+        assert_eq!(from_html("It&#39;s<br/>&#x41;"), "It's\nA");
+    }
+}
@@ -0,0 +1,171 @@
+//! Protection of placeholders from language models, that tend to translate, reorder or drop control codes, e.g. `\C[2]`, `\V[12]`, `\I[64]` and `%1`, and of protected terms from all providers.
+//!
+//! Placeholders and protected terms of sources are replaced with numbered tokens, e.g. `⟦0⟧`, that models keep as they are, and tokens of translations are replaced back, terms with their spellings. Identical placeholders of a source and of its translation in the context get the same token. `DeepL` and Google protect placeholders with their own tags, and receive tokens as such tags too. Translations of any provider, that don't keep placeholders or terms of their sources, are rejected either way.
+
+use crate::{terms::ProtectedTerms, translation::placeholder_ranges};
+use regex::{Captures, Regex};
+use std::{fmt::Write, ops::Range, sync::LazyLock};
+
+/// Tokens, that replace placeholders. Models sometimes put spaces inside them.
+static TOKEN_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"⟦\s*(\d+)\s*⟧").unwrap());
+
+/// What's replaced with tokens.
+#[derive(Clone, Copy)]
+pub struct Mask<'a> {
+    /// Whether placeholders are replaced. Providers with their own protection don't need it.
+    pub placeholders: bool,
+
+    pub terms: Option<&'a ProtectedTerms>,
+}
+
+impl Mask<'_> {
+    /// Returns `text`, where placeholders and terms are replaced with tokens of their indices in `codes`, that hold placeholders and spellings of terms. Ones, that aren't there, are appended.
+    pub fn apply(self, text: &str, codes: &mut Vec<String>) -> String {
+        let mut spans: Vec<(Range<usize>, &str)> =
+            self.terms.map(|terms| terms.find(text)).unwrap_or_default();
+
+        if self.placeholders {
+            spans.extend(
+                placeholder_ranges(text)
+                    .into_iter()
+                    .map(|range| (range.clone(), &text[range])),
+            );
+        }
+
+        spans.sort_by_key(|(range, _)| range.start);
+
+        let mut masked = String::with_capacity(text.len());
+        let mut end = 0;
+
+        for (range, code) in spans {
+            // Placeholders inside terms stay in them.
+            if range.start < end {
+                continue;
+            }
+
+            let index = codes
+                .iter()
+                .position(|known| known == code)
+                .unwrap_or_else(|| {
+                    codes.push(code.to_string());
+                    codes.len() - 1
+                });
+
+            masked.push_str(&text[end..range.start]);
+            let _ = write!(masked, "⟦{index}⟧");
+            end = range.end;
+        }
+
+        masked.push_str(&text[end..]);
+        masked
+    }
+}
+
+/// Replaces tokens of `text` back with `codes`. Tokens of unknown codes are kept, so the translation fails verification.
+pub fn unmask(text: &str, codes: &[String]) -> String {
+    TOKEN_RE
+        .replace_all(text, |captures: &Captures| {
+            captures[1]
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| codes.get(index))
+                .map_or_else(|| captures[0].to_string(), Clone::clone)
+        })
+        .into_owned()
+}
+
+/// Returns byte ranges of placeholders and tokens of `text`, in their order, that providers with their own protection must keep as they are.
+pub fn protected_ranges(text: &str) -> Vec<Range<usize>> {
+    let mut ranges = placeholder_ranges(text);
+    ranges.extend(TOKEN_RE.find_iter(text).map(|token| token.range()));
+    ranges.sort_by_key(|range| range.start);
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLACEHOLDERS: Mask = Mask {
+        placeholders: true,
+        terms: None,
+    };
+
+    #[test]
+    fn round_trips_placeholders() {
+        let source = r"\C[2]Hello\C[0], \N[1]! %1";
+        let mut codes = Vec::new();
+        let masked = PLACEHOLDERS.apply(source, &mut codes);
+
+        assert_eq!(masked, "⟦0⟧Hello⟦1⟧, ⟦2⟧! ⟦3⟧");
+        assert_eq!(unmask(&masked, &codes), source);
+    }
+
+    #[test]
+    fn shares_tokens_of_identical_placeholders() {
+        let mut codes = Vec::new();
+        let source = PLACEHOLDERS.apply(r"\V[1] and \V[1]", &mut codes);
+        let context = PLACEHOLDERS.apply(r"\I[64]\V[1]", &mut codes);
+
+        assert_eq!(source, "⟦0⟧ and ⟦0⟧");
+        assert_eq!(context, "⟦1⟧⟦0⟧");
+        assert_eq!(codes, [r"\V[1]", r"\I[64]"]);
+    }
+
+    #[test]
+    fn unmasks_reordered_and_spaced_tokens() {
+        let codes = [r"\C[2]".to_string(), "%1".to_string()];
+
+        assert_eq!(unmask("⟦ 1 ⟧ then ⟦0⟧", &codes), r"%1 then \C[2]");
+    }
+
+    #[test]
+    fn keeps_unknown_tokens() {
+        let codes = [r"\C[2]".to_string()];
+
+        assert_eq!(unmask("⟦0⟧⟦5⟧", &codes), r"\C[2]⟦5⟧");
+    }
+
+    #[test]
+    fn leaves_text_without_placeholders_as_is() {
+        let mut codes = Vec::new();
+        let mask = Mask {
+            placeholders: false,
+            terms: None,
+        };
+
+        assert_eq!(mask.apply(r"\C[2]Text", &mut codes), r"\C[2]Text");
+        assert!(codes.is_empty());
+    }
+
+    #[test]
+    fn finds_protected_ranges_in_order() {
+        let text = r"⟦0⟧ and \C[1]";
+        let ranges: Vec<_> = protected_ranges(text)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect();
+
+        assert_eq!(ranges, ["⟦0⟧", r"\C[1]"]);
+    }
+
+    #[test]
+    fn replaces_terms_with_their_spellings() {
+        let path = std::env::temp_dir()
+            .join(format!("rvpacker-terms-{}.tsv", std::process::id()));
+        std::fs::write(&path, "アリス\tAlice\n").unwrap();
+        let terms = ProtectedTerms::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mask = Mask {
+            placeholders: true,
+            terms: Some(&terms),
+        };
+        let mut codes = Vec::new();
+        let masked = mask.apply(r"\C[2]アリスです", &mut codes);
+
+        assert_eq!(masked, "⟦0⟧⟦1⟧です");
+        assert_eq!(unmask("⟦0⟧⟦1⟧ is here", &codes), r"\C[2]Alice is here");
+    }
+}
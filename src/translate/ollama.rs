@@ -0,0 +1,61 @@
+//! [Ollama](https://github.com/ollama/ollama/blob/main/docs/api.md#generate-a-chat-completion) provider, for translating offline with local models.
+//!
+//! Prompts and answers are the same, as those of `OpenAI` provider. Ollama's default context window is too small for sources with their context, so a larger one is requested.
+
+use super::{Batch, Settings, openai::translate_chat};
+use crate::http::request;
+use anyhow::Result;
+use serde_json::{Value, json};
+
+const DEFAULT_ENDPOINT: &str = "http://localhost:11434";
+
+/// Context window in tokens, that is requested for the model.
+const CONTEXT_LENGTH: usize = 8192;
+
+/// Maximal number of sources in a request. Local models skip items of long lists more often, than hosted ones.
+pub const BATCH_SIZE: usize = 20;
+
+/// Returns the answer to chat `messages`, and whether it was cut off by the output limit of the model.
+fn complete(settings: &Settings, messages: &[Value]) -> Result<(String, bool)> {
+    let mut options = json!({"num_ctx": CONTEXT_LENGTH});
+
+    if let Some(temperature) = settings.temperature {
+        options["temperature"] = json!(temperature);
+    }
+
+    let body = json!({
+        "model": settings.model.unwrap_or_default(),
+        "messages": messages,
+        "stream": false,
+        "format": "json",
+        "options": options,
+    });
+
+    let endpoint = settings.endpoint.unwrap_or(DEFAULT_ENDPOINT);
+    let response = request(
+        &[
+            "-X",
+            "POST",
+            "-H",
+            "Content-Type: application/json",
+            "--data-binary",
+            "@-",
+        ],
+        &format!("{}/api/chat", endpoint.trim_end_matches('/')),
+        body.to_string().as_bytes(),
+    )?;
+
+    let content = response
+        .pointer("/message/content")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+    let truncated =
+        response.get("done_reason").and_then(Value::as_str) == Some("length");
+
+    Ok((content, truncated))
+}
+
+pub fn translate(settings: &Settings, batch: Batch) -> Result<Vec<String>> {
+    translate_chat(settings, batch, &|messages| complete(settings, messages))
+}
@@ -0,0 +1,209 @@
+//! [`OpenAI` chat completions](https://platform.openai.com/docs/api-reference/chat) provider.
+//!
+//! Each request translates sources of a single section, e.g. a map or an event, and carries translated entries of the section as context, so the model follows the conversation. The model answers with a JSON object, that holds translations by IDs of sources. If the answer is cut off by the output limit of the model, halves of the batch are translated separately. Local servers with the same API, e.g. llama.cpp server, work with `--endpoint`, and need no key.
+
+use super::{Batch, Settings};
+use crate::http::{authorized_request, request};
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+
+const DEFAULT_ENDPOINT: &str = "https://api.openai.com/v1";
+
+pub const KEY_VARIABLE: &str = "OPENAI_API_KEY";
+
+const DEFAULT_MODEL: &str = "gpt-4o-mini";
+
+/// Maximal number of sources in a request. Long lists make models skip or merge items.
+pub const BATCH_SIZE: usize = 40;
+
+#[derive(Deserialize)]
+struct Output {
+    translations: Vec<Item>,
+}
+
+#[derive(Deserialize)]
+struct Item {
+    id: usize,
+    text: String,
+}
+
+fn instructions(settings: &Settings) -> String {
+    let source_language = settings.source_language.map_or_else(
+        || "its original language".to_string(),
+        |language| format!("the language with code `{language}`"),
+    );
+
+    format!(
+        "You translate texts of an RPG Maker game from {source_language} to the language with code `{}`. \
+The user sends a JSON object with the name of the translation file, the section, e.g. a map or an event, \
+`context` with already translated entries of the section, and `translate` with texts to translate, in the order they appear in the game. \
+It may also have `glossary` with terms, that must be translated exactly as given there. \
+Translate each text of `translate` naturally, consistently with the context. \
+Control codes are replaced with numbered tokens, e.g. `⟦0⟧`. Keep the tokens, and any other control codes, e.g. `\\G`, and line breaks exactly as they are. \
+Answer only with a JSON object {{\"translations\": [{{\"id\": <id>, \"text\": <translation>}}]}} with every ID of `translate`.",
+        settings.target_language
+    )
+}
+
+/// Returns the system and user messages, that ask to translate sources of `batch`.
+fn messages(settings: &Settings, batch: Batch) -> Vec<Value> {
+    let translated: Vec<Value> = batch
+        .context
+        .iter()
+        .map(|(source, translation)| {
+            json!({"source": source, "translation": translation})
+        })
+        .collect();
+    let items: Vec<Value> = batch
+        .sources
+        .iter()
+        .enumerate()
+        .map(|(id, text)| json!({"id": id, "text": text}))
+        .collect();
+
+    let mut message = json!({
+        "file": batch.file,
+        "section": batch.section,
+        "context": translated,
+        "translate": items,
+    });
+
+    if let Some(glossary) = settings.glossary {
+        let terms: Vec<Value> = glossary
+            .terms_in(batch.sources)
+            .into_iter()
+            .map(|term| {
+                json!({"source": term.source, "translation": term.translation})
+            })
+            .collect();
+
+        if !terms.is_empty() {
+            message["glossary"] = json!(terms);
+        }
+    }
+
+    vec![
+        json!({"role": "system", "content": instructions(settings)}),
+        json!({"role": "user", "content": message.to_string()}),
+    ]
+}
+
+/// Returns the answer to chat `messages`, and whether it was cut off by the output limit of the model.
+fn complete(
+    settings: &Settings,
+    key: Option<&str>,
+    messages: &[Value],
+) -> Result<(String, bool)> {
+    let mut body = json!({
+        "model": settings.model.unwrap_or(DEFAULT_MODEL),
+        "messages": messages,
+        "response_format": {"type": "json_object"},
+    });
+
+    if let Some(temperature) = settings.temperature {
+        body["temperature"] = json!(temperature);
+    }
+
+    let arguments = [
+        "-X",
+        "POST",
+        "-H",
+        "Content-Type: application/json",
+        "--data-binary",
+        "@-",
+    ];
+    let endpoint = settings.endpoint.unwrap_or(DEFAULT_ENDPOINT);
+    let url = format!("{}/chat/completions", endpoint.trim_end_matches('/'));
+    let body = body.to_string();
+
+    // Local servers need no key.
+    let response = match key {
+        Some(key) => authorized_request(
+            &format!("Authorization: Bearer {key}"),
+            &arguments,
+            &url,
+            body.as_bytes(),
+        ),
+        None => request(&arguments, &url, body.as_bytes()),
+    }?;
+
+    let Some(choice) = response.pointer("/choices/0") else {
+        bail!("Response has no choices.");
+    };
+
+    let content = choice
+        .pointer("/message/content")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+    let truncated =
+        choice.get("finish_reason").and_then(Value::as_str) == Some("length");
+
+    Ok((content, truncated))
+}
+
+/// Translates sources of `batch` with a chat model, that `complete` sends messages to.
+pub(super) fn translate_chat(
+    settings: &Settings,
+    batch: Batch,
+    complete: &impl Fn(&[Value]) -> Result<(String, bool)>,
+) -> Result<Vec<String>> {
+    let (content, truncated) = complete(&messages(settings, batch))?;
+
+    if truncated {
+        if batch.sources.len() == 1 {
+            bail!(
+                "Translation doesn't fit the output limit of the model, even for a single source."
+            );
+        }
+
+        let (first, second) = batch.sources.split_at(batch.sources.len() / 2);
+        let mut translations = translate_chat(
+            settings,
+            Batch {
+                sources: first,
+                ..batch
+            },
+            complete,
+        )?;
+        translations.extend(translate_chat(
+            settings,
+            Batch {
+                sources: second,
+                ..batch
+            },
+            complete,
+        )?);
+
+        return Ok(translations);
+    }
+
+    let output: Output = serde_json::from_str(&content)
+        .context("Model answered with malformed JSON")?;
+
+    let mut translations: HashMap<usize, String> = output
+        .translations
+        .into_iter()
+        .map(|item| (item.id, item.text))
+        .collect();
+
+    (0..batch.sources.len())
+        .map(|id| {
+            translations.remove(&id).with_context(|| {
+                format!("Model didn't translate the source with ID {id}")
+            })
+        })
+        .collect()
+}
+
+pub fn translate(
+    settings: &Settings,
+    key: Option<&str>,
+    batch: Batch,
+) -> Result<Vec<String>> {
+    translate_chat(settings, batch, &|messages| {
+        complete(settings, key, messages)
+    })
+}
@@ -0,0 +1,425 @@
+use crate::{
+    anchors::ANCHOR_COMMENT_PREFIX, codes::CODES_COMMENT_PREFIX,
+    context::CONTEXT_COMMENT_PREFIX, export::FUZZY_COMMENT_PREFIX,
+    memory::PRETRANSLATION_COMMENT_PREFIX,
+    translate::MACHINE_TRANSLATION_COMMENT_PREFIX,
+};
+use anyhow::Result;
+use regex::Regex;
+use rvpacker_lib::{NEW_LINE, SEPARATOR};
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    fs::{read_dir, read_to_string},
+    ops::Range,
+    path::Path,
+    sync::LazyLock,
+};
+
+pub const COMMENT_PREFIX: &str = "<!-- ";
+pub const ID_COMMENT: &str = "<!-- ID -->";
+
+/// Prefix of the comment, that holds the in-game name of a map and its translation.
+pub const DISPLAY_NAME_COMMENT_PREFIX: &str = "<!-- IN-GAME DISPLAYED NAME: ";
+
+/// Prefixes of comments, that annotate the entry right after them, rather than the section. An entry may have several of them in any order.
+const ANNOTATION_PREFIXES: [&str; 6] = [
+    FUZZY_COMMENT_PREFIX,
+    MACHINE_TRANSLATION_COMMENT_PREFIX,
+    PRETRANSLATION_COMMENT_PREFIX,
+    CODES_COMMENT_PREFIX,
+    CONTEXT_COMMENT_PREFIX,
+    ANCHOR_COMMENT_PREFIX,
+];
+
+/// Returns whether `comment` annotates the entry after it, e.g. marks it as fuzzy or machine-translated.
+#[must_use]
+pub fn is_annotation(comment: &str) -> bool {
+    ANNOTATION_PREFIXES
+        .iter()
+        .any(|prefix| comment.starts_with(prefix))
+}
+
+/// Removes annotations, that satisfy `f`, of the entry, that follows `lines`, i.e. of the annotations at the end of `lines`.
+pub fn remove_annotations(lines: &mut Vec<Line>, f: impl Fn(&str) -> bool) {
+    let start = lines
+        .iter()
+        .rposition(|line| !matches!(line, Line::Comment(comment) if is_annotation(comment)))
+        .map_or(0, |index| index + 1);
+    let mut annotations = lines.split_off(start);
+
+    annotations
+        .retain(|line| !matches!(line, Line::Comment(comment) if f(comment)));
+    lines.append(&mut annotations);
+}
+
+/// A single line of a translation file.
+#[derive(Debug, Clone)]
+pub enum Line {
+    /// `<!-- ID --><#>n` line, that starts a new section.
+    Id(u16),
+
+    /// Any other `<!-- ... -->` line. Stored verbatim.
+    Comment(String),
+
+    /// `source<#>translation` line.
+    ///
+    /// `translation` holds everything after the first separator verbatim.
+    Entry { source: String, translation: String },
+
+    /// Line that can't be split to source and translation. Stored verbatim, so the file round-trips.
+    Raw(String),
+}
+
+/// Translation file, represented as a sequence of lines.
+///
+/// Parsing and serializing a file is lossless, so it can be used to edit files produced by the library without disturbing anything else.
+#[derive(Debug, Clone, Default)]
+pub struct TranslationFile {
+    pub lines: Vec<Line>,
+}
+
+impl TranslationFile {
+    #[must_use]
+    pub fn parse(content: &str) -> Self {
+        let lines = content
+            .lines()
+            .map(|line| {
+                if let Some(id) = line
+                    .strip_prefix(ID_COMMENT)
+                    .and_then(|id| id.strip_prefix(SEPARATOR))
+                    .and_then(|id| id.trim_end().parse::<u16>().ok())
+                {
+                    Line::Id(id)
+                } else if line.starts_with(COMMENT_PREFIX) {
+                    Line::Comment(line.to_string())
+                } else if let Some((source, translation)) =
+                    line.split_once(SEPARATOR)
+                {
+                    Line::Entry {
+                        source: source.to_string(),
+                        translation: translation.to_string(),
+                    }
+                } else {
+                    Line::Raw(line.to_string())
+                }
+            })
+            .collect();
+
+        Self { lines }
+    }
+
+    #[must_use]
+    pub fn serialize(&self) -> String {
+        let mut output = String::with_capacity(self.lines.len() * 64);
+
+        for line in &self.lines {
+            let _ = match line {
+                Line::Id(id) => writeln!(output, "{ID_COMMENT}{SEPARATOR}{id}"),
+                Line::Comment(line) | Line::Raw(line) => {
+                    writeln!(output, "{line}")
+                }
+                Line::Entry {
+                    source,
+                    translation,
+                } => writeln!(output, "{source}{SEPARATOR}{translation}"),
+            };
+        }
+
+        // Library doesn't terminate translation files with a new line.
+        output.pop();
+        output
+    }
+
+    /// Returns `(section id, source, translation)` of each entry in the file.
+    ///
+    /// Entries before the first `<!-- ID -->` line have no section.
+    pub fn entries(
+        &self,
+    ) -> impl Iterator<Item = (Option<u16>, &str, &str)> + '_ {
+        let mut id = None;
+
+        self.lines.iter().filter_map(move |line| match line {
+            Line::Id(new_id) => {
+                id = Some(*new_id);
+                None
+            }
+            Line::Entry {
+                source,
+                translation,
+            } => {
+                Some((id, source.as_str(), effective_translation(translation)))
+            }
+            _ => None,
+        })
+    }
+
+    /// Precedes entries with `<!-- LABEL: text -->` comments, where `prefix` is `<!-- LABEL: ` and text is returned by `f(section, source)`. Previous comments with `prefix` are removed, so the file can be annotated repeatedly. Returns the number of annotated entries.
+    pub fn annotate(
+        &mut self,
+        prefix: &str,
+        mut f: impl FnMut(Option<u16>, &str) -> Option<String>,
+    ) -> usize {
+        let mut id = None;
+        let mut annotated = 0;
+        let mut lines = Vec::with_capacity(self.lines.len());
+
+        for line in self.lines.drain(..) {
+            match &line {
+                Line::Comment(comment) if comment.starts_with(prefix) => {
+                    continue;
+                }
+                Line::Id(new_id) => id = Some(*new_id),
+                Line::Entry { source, .. } => {
+                    if let Some(text) = f(id, source) {
+                        lines
+                            .push(Line::Comment(format!("{prefix}{text} -->")));
+                        annotated += 1;
+                    }
+                }
+                _ => {}
+            }
+
+            lines.push(line);
+        }
+
+        self.lines = lines;
+        annotated
+    }
+}
+
+/// Returns the translation, that library would use from the raw part after the first separator.
+///
+/// If translation contains additional separators, library takes the last non-empty part.
+#[must_use]
+pub fn effective_translation(raw: &str) -> &str {
+    raw.rsplit(SEPARATOR)
+        .find(|part| !part.is_empty())
+        .unwrap_or_default()
+}
+
+/// Replaces the part of raw translation, that library would use, with `f(part)`. Other parts are kept as is.
+#[must_use]
+pub fn map_effective_translation(
+    raw: &str,
+    f: impl FnOnce(&str) -> String,
+) -> String {
+    let mut parts: Vec<&str> = raw.split(SEPARATOR).collect();
+
+    let Some(index) = parts.iter().rposition(|part| !part.is_empty()) else {
+        return raw.to_string();
+    };
+
+    let replaced = f(parts[index]);
+    parts[index] = &replaced;
+    parts.join(SEPARATOR)
+}
+
+/// Control codes and format arguments, that must survive translation: `\N[1]`, `\C[2]`, `\G`, `\{`, `%1` and so on.
+///
+/// `\#` isn't included, since it's the new line marker of translation files.
+static PLACEHOLDER_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\\[A-Za-z]+(?:\[[^\]]*\]|<[^>]*>)?|\\[{}.|!<>^$]|%\d+")
+        .unwrap()
+});
+
+/// Returns placeholders of `text`, sorted, so they can be compared regardless of their order.
+#[must_use]
+pub fn placeholders(text: &str) -> Vec<&str> {
+    let mut placeholders: Vec<&str> = PLACEHOLDER_RE
+        .find_iter(text)
+        .map(|placeholder| placeholder.as_str())
+        .collect();
+
+    placeholders.sort_unstable();
+    placeholders
+}
+
+/// Returns byte ranges of placeholders of `text`, in their order, so they can be protected from edits of the surrounding text.
+#[must_use]
+pub fn placeholder_ranges(text: &str) -> Vec<Range<usize>> {
+    PLACEHOLDER_RE
+        .find_iter(text)
+        .map(|placeholder| placeholder.range())
+        .collect()
+}
+
+/// Removes placeholders from `text`, leaving the text, that the game displays.
+#[must_use]
+pub fn strip_placeholders(text: &str) -> std::borrow::Cow<'_, str> {
+    PLACEHOLDER_RE.replace_all(text, "")
+}
+
+/// Returns names of all translation files in `translation_path`, sorted.
+pub fn translation_files(translation_path: &Path) -> Result<Vec<String>> {
+    let mut names: Vec<String> = read_dir(translation_path)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .filter_map(|path| path.file_name()?.to_str().map(String::from))
+        .collect();
+
+    names.sort();
+    Ok(names)
+}
+
+/// Converts text from game data to single-line form, that translation files use.
+#[must_use]
+pub fn normalize(string: &str) -> String {
+    string.replace("\r\n", "\n").replace('\n', NEW_LINE)
+}
+
+/// Converts text from translation files back to the form, that game data uses.
+#[must_use]
+pub fn denormalize(string: &str) -> String {
+    string.replace(NEW_LINE, "\n")
+}
+
+/// Lookup of translations by translation file, section ID and source.
+///
+/// If the entry isn't found in the section, falls back to the same source anywhere in the file, since the library removes duplicates in default duplicate mode.
+#[derive(Debug, Default)]
+pub struct TranslationIndex {
+    sections: HashMap<(String, u16, String), String>,
+    files: HashMap<(String, String), String>,
+}
+
+impl TranslationIndex {
+    /// Loads translation files with `names` from `translation_path`. Missing files are skipped.
+    pub fn load(translation_path: &Path, names: &[&str]) -> Result<Self> {
+        let mut index = Self::default();
+
+        for name in names {
+            let path = translation_path.join(name);
+
+            if !path.exists() {
+                continue;
+            }
+
+            let file = TranslationFile::parse(&read_to_string(&path)?);
+
+            for (id, source, translation) in file.entries() {
+                if translation.is_empty() {
+                    continue;
+                }
+
+                if let Some(id) = id {
+                    index.sections.insert(
+                        ((*name).to_string(), id, source.to_string()),
+                        translation.to_string(),
+                    );
+                }
+
+                index
+                    .files
+                    .entry(((*name).to_string(), source.to_string()))
+                    .or_insert_with(|| translation.to_string());
+            }
+        }
+
+        Ok(index)
+    }
+
+    #[must_use]
+    pub fn get(&self, file: &str, id: u16, source: &str) -> Option<&str> {
+        self.sections
+            .get(&(file.to_string(), id, source.to_string()))
+            .or_else(|| self.files.get(&(file.to_string(), source.to_string())))
+            .map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT: &str = "<!-- MAP NAME -->\nTitle<#>Заголовок\n<!-- ID --><#>3\nHello\\#world<#>Привет\\#мир\nBroken line\nOld<#><#>New\nEmpty<#>";
+
+    #[test]
+    fn round_trips() {
+        assert_eq!(TranslationFile::parse(CONTENT).serialize(), CONTENT);
+    }
+
+    #[test]
+    fn parses_entries_with_sections() {
+        let file = TranslationFile::parse(CONTENT);
+        let entries: Vec<_> = file.entries().collect();
+
+        assert_eq!(
+            entries,
+            [
+                (None, "Title", "Заголовок"),
+                (Some(3), "Hello\\#world", "Привет\\#мир"),
+                (Some(3), "Old", "New"),
+                (Some(3), "Empty", ""),
+            ]
+        );
+        assert!(matches!(file.lines[4], Line::Raw(_)));
+    }
+
+    #[test]
+    fn takes_last_non_empty_part_of_translation() {
+        assert_eq!(effective_translation("a<#>b<#>"), "b");
+        assert_eq!(effective_translation(""), "");
+    }
+
+    #[test]
+    fn normalizes_line_breaks() {
+        assert_eq!(normalize("a\r\nb\nc"), r"a\#b\#c");
+        assert_eq!(denormalize(r"a\#b"), "a\nb");
+    }
+
+    #[test]
+    fn maps_last_non_empty_part_of_translation() {
+        assert_eq!(
+            map_effective_translation("a<#>b<#>", str::to_uppercase),
+            "a<#>B<#>"
+        );
+        assert_eq!(map_effective_translation("<#>", |_| "x".into()), "<#>");
+    }
+
+    #[test]
+    fn finds_placeholders() {
+        assert_eq!(
+            placeholders(r"\C[2]Hi\G, %1\{\N[1]"),
+            [r"%1", r"\C[2]", r"\G", r"\N[1]", r"\{"]
+        );
+        assert!(placeholders(r"Line\#break").is_empty());
+    }
+
+    #[test]
+    fn strips_placeholders() {
+        assert_eq!(strip_placeholders(r"\C[2]Hi\G, %1\{\N[1]"), "Hi, ");
+    }
+
+    #[test]
+    fn annotates_repeatedly() {
+        let mut file = TranslationFile::parse(CONTENT);
+        let prefix = "<!-- NOTE: ";
+
+        file.annotate(prefix, |id, _| id.map(|id| id.to_string()));
+        let annotated = file.annotate(prefix, |id, _| id.map(|_| "x".into()));
+
+        assert_eq!(annotated, 3);
+        assert_eq!(
+            file.lines
+                .iter()
+                .filter(|line| matches!(line, Line::Comment(comment) if comment.starts_with(prefix)))
+                .count(),
+            3
+        );
+    }
+
+    #[test]
+    fn finds_placeholder_ranges_in_order() {
+        let text = r"\C[2]Hi\G, %1\{\N[1]";
+
+        assert_eq!(
+            placeholder_ranges(text)
+                .into_iter()
+                .map(|range| &text[range])
+                .collect::<Vec<_>>(),
+            [r"\C[2]", r"\G", r"%1", r"\{", r"\N[1]"]
+        );
+    }
+}
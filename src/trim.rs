@@ -0,0 +1,87 @@
+//! Retroactive application of trimming to existing translation files.
+//!
+//! `--trim` changes sources of entries, so translation files, that were read without it, don't match the game data read with it, and append drops their translations as unused. Translation files are read from scratch with the wanted setting instead, and translations are carried over from existing files by their sources with leading and trailing whitespace removed. When trimming is applied, translations are trimmed as well, and when it's undone, whitespace of the source is restored around the translation.
+
+use crate::{
+    dedup::{display_names, split_display_name},
+    translation::{
+        Line, TranslationFile, denormalize, effective_translation, normalize,
+    },
+};
+use rvpacker_lib::SEPARATOR;
+use std::collections::HashMap;
+
+/// Returns `source` without leading and trailing whitespace, in single-line form.
+fn key(source: &str) -> String {
+    normalize(denormalize(source).trim())
+}
+
+/// Surrounds `translation` with the same leading and trailing whitespace, that `source` has.
+fn fit(source: &str, translation: &str) -> String {
+    let source = denormalize(source);
+    let trimmed = source.trim_start();
+    let leading = &source[..source.len() - trimmed.len()];
+    let trailing = &trimmed[trimmed.trim_end().len()..];
+
+    normalize(&format!(
+        "{leading}{}{trailing}",
+        denormalize(translation).trim()
+    ))
+}
+
+/// Carries translations of `old` file over to `new` file, that was read with the wanted trimming. Translation of the same section takes precedence over translations of the same source in other sections. Returns the number of carried translations.
+pub fn reconcile(old: &TranslationFile, new: &mut TranslationFile) -> usize {
+    let mut by_section: HashMap<(Option<u16>, String), &str> = HashMap::new();
+    let mut by_source: HashMap<String, &str> = HashMap::new();
+
+    for (id, source, translation) in old.entries() {
+        if translation.is_empty() {
+            continue;
+        }
+
+        by_section.entry((id, key(source))).or_insert(translation);
+        by_source.entry(key(source)).or_insert(translation);
+    }
+
+    let display_names = display_names(old);
+    let mut carried = 0;
+    let mut section = None;
+
+    for line in &mut new.lines {
+        match line {
+            Line::Id(id) => section = Some(*id),
+            Line::Comment(comment) => {
+                if let Some((name, translation)) = split_display_name(comment)
+                    && translation.is_empty()
+                    && let Some(old_translation) =
+                        display_names.get(&(section, name))
+                {
+                    *comment = format!("{name}{SEPARATOR}{old_translation}");
+                    carried += 1;
+                }
+            }
+            Line::Entry {
+                source,
+                translation,
+            } => {
+                if !effective_translation(translation).is_empty() {
+                    continue;
+                }
+
+                let key = key(source);
+                let Some(found) = by_section
+                    .get(&(section, key.clone()))
+                    .or_else(|| by_source.get(&key))
+                else {
+                    continue;
+                };
+
+                *translation = fit(source, found);
+                carried += 1;
+            }
+            Line::Raw(_) => {}
+        }
+    }
+
+    carried
+}
@@ -0,0 +1,259 @@
+//! Migration of translations to a new version of the game.
+//!
+//! Existing translation files serve as the snapshot of the previous version. Translations are carried over to fresh translation files of the new version: by the same source in the same section, by the same source in a renumbered section, by the same source anywhere in the file, and by a similar source in the same section, in that order. Entries, that already have a translation, are left as is. Translations, that were carried over to similar sources, are marked with `<!-- FUZZY: ... -->` comments for review.
+
+use crate::{
+    export::FUZZY_COMMENT_PREFIX,
+    fuzzy::similarity,
+    remap,
+    translation::{Line, TranslationFile, effective_translation},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+    mem::take,
+};
+
+/// Sections, that are at least this similar, are considered renumbered.
+const SECTION_THRESHOLD: f64 = 0.5;
+
+/// Translation, that was carried over to an edited source.
+pub struct FuzzyMatch {
+    pub section: Option<u16>,
+    pub old_source: String,
+    pub new_source: String,
+    pub similarity: f64,
+}
+
+/// Translated entry of the previous version, that wasn't carried over.
+pub struct Lost {
+    pub section: Option<u16>,
+    pub source: String,
+    pub translation: String,
+}
+
+#[derive(Default)]
+pub struct FileReport {
+    pub exact: usize,
+    pub moved: usize,
+    pub fuzzy: Vec<FuzzyMatch>,
+    pub lost: Vec<Lost>,
+}
+
+/// Carries translations of `old` file over to `new` file.
+///
+/// Sources are matched fuzzily only if they're at least `threshold` similar.
+pub fn migrate(
+    old: &TranslationFile,
+    new: &mut TranslationFile,
+    threshold: f64,
+) -> FileReport {
+    let mut report = FileReport::default();
+
+    // New section ID to old one.
+    let renumbered: HashMap<u16, u16> =
+        remap::derive(old, new, SECTION_THRESHOLD)
+            .into_iter()
+            .map(|(old_id, new_id, _)| (new_id, old_id))
+            .collect();
+
+    let translated: Vec<(Option<u16>, &str, &str)> = old
+        .entries()
+        .filter(|(_, _, translation)| !translation.is_empty())
+        .collect();
+
+    let by_section: HashMap<(Option<u16>, &str), &str> = translated
+        .iter()
+        .map(|(id, source, translation)| ((*id, *source), *translation))
+        .collect();
+
+    let mut by_source: HashMap<&str, &str> = HashMap::new();
+
+    for (_, source, translation) in &translated {
+        by_source.entry(source).or_insert(translation);
+    }
+
+    // Old sources, that were matched fuzzily.
+    let mut used: HashSet<&str> = HashSet::new();
+    let mut section = None;
+    let mut lines = Vec::with_capacity(new.lines.len());
+
+    for mut line in take(&mut new.lines) {
+        let (source, translation) = match &mut line {
+            Line::Id(id) => {
+                section = Some(*id);
+                lines.push(line);
+                continue;
+            }
+            Line::Entry {
+                source,
+                translation,
+            } if effective_translation(translation).is_empty() => {
+                (source, translation)
+            }
+            _ => {
+                lines.push(line);
+                continue;
+            }
+        };
+
+        let old_section =
+            section.map(|id| renumbered.get(&id).copied().unwrap_or(id));
+
+        let found = if let Some(found) = by_section
+            .get(&(old_section, source.as_str()))
+            .or_else(|| by_section.get(&(section, source.as_str())))
+        {
+            report.exact += 1;
+            Some(*found)
+        } else if let Some(found) = by_source.get(source.as_str()) {
+            report.moved += 1;
+            Some(*found)
+        } else {
+            translated
+                .iter()
+                .filter(|(id, old_source, _)| {
+                    *id == old_section && !used.contains(old_source)
+                })
+                .map(|(_, old_source, old_translation)| {
+                    (
+                        similarity(old_source, source, threshold),
+                        *old_source,
+                        *old_translation,
+                    )
+                })
+                .filter(|(score, _, _)| *score >= threshold)
+                .max_by(|a, b| a.0.total_cmp(&b.0))
+                .map(|(score, old_source, old_translation)| {
+                    used.insert(old_source);
+                    report.fuzzy.push(FuzzyMatch {
+                        section,
+                        old_source: old_source.to_string(),
+                        new_source: source.clone(),
+                        similarity: score,
+                    });
+                    lines.push(Line::Comment(format!(
+                        "{FUZZY_COMMENT_PREFIX}{old_translation} -->"
+                    )));
+                    old_translation
+                })
+        };
+
+        if let Some(found) = found {
+            *translation = found.to_string();
+        }
+
+        lines.push(line);
+    }
+
+    new.lines = lines;
+
+    // Entries, whose source exists in the new version, are carried over at least by source.
+    let new_sources: HashSet<&str> =
+        new.entries().map(|(_, source, _)| source).collect();
+
+    for (id, source, translation) in translated {
+        if !new_sources.contains(source) && !used.contains(source) {
+            report.lost.push(Lost {
+                section: id,
+                source: source.to_string(),
+                translation: translation.to_string(),
+            });
+        }
+    }
+
+    report
+}
+
+/// Formats reports of all files to a human-readable text.
+#[must_use]
+pub fn format_report(reports: &[(String, FileReport)]) -> String {
+    let mut output = String::new();
+    let section = |id: Option<u16>| {
+        id.map_or_else(String::new, |id| format!(" (section {id})"))
+    };
+
+    output.push_str("Fuzzy matches. Review these translations, since their sources were edited:\n");
+
+    for (name, report) in reports {
+        for fuzzy in &report.fuzzy {
+            let _ = writeln!(
+                output,
+                "{name}{}: {:.0}%\n  - {}\n  + {}",
+                section(fuzzy.section),
+                fuzzy.similarity * 100.0,
+                fuzzy.old_source,
+                fuzzy.new_source
+            );
+        }
+    }
+
+    output.push_str("\nUnmatched translations. Their sources no longer exist in the game:\n");
+
+    for (name, report) in reports {
+        for lost in &report.lost {
+            let _ = writeln!(
+                output,
+                "{name}{}: {}<#>{}",
+                section(lost.section),
+                lost.source,
+                lost.translation
+            );
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = "<!-- ID --><#>1\nHello<#>Привет\nHow are you today?<#>Как ты сегодня?\nRemoved line<#>Удалено\n<!-- ID --><#>2\nBye<#>Пока";
+
+    #[test]
+    fn carries_translations_over() {
+        let old = TranslationFile::parse(OLD);
+        let mut new = TranslationFile::parse(
+            "<!-- ID --><#>1\nHello<#>\nHow are you today!<#>\nBye<#>\nSomething new<#>",
+        );
+
+        let report = migrate(&old, &mut new, 0.8);
+
+        assert_eq!((report.exact, report.moved), (1, 1));
+        assert_eq!(report.fuzzy.len(), 1);
+        assert_eq!(report.fuzzy[0].old_source, "How are you today?");
+        assert_eq!(report.lost.len(), 1);
+        assert_eq!(report.lost[0].source, "Removed line");
+        assert_eq!(
+            new.serialize(),
+            "<!-- ID --><#>1\nHello<#>Привет\n<!-- FUZZY: Как ты сегодня? -->\nHow are you today!<#>Как ты сегодня?\nBye<#>Пока\nSomething new<#>"
+        );
+    }
+
+    #[test]
+    fn leaves_translated_entries_as_is() {
+        let old = TranslationFile::parse(OLD);
+        let content = "<!-- ID --><#>1\nHello<#>Здравствуй\nHow are you today!<#>Как дела?";
+        let mut new = TranslationFile::parse(content);
+
+        let report = migrate(&old, &mut new, 0.8);
+
+        assert_eq!((report.exact, report.moved), (0, 0));
+        assert!(report.fuzzy.is_empty());
+        assert_eq!(report.lost.len(), 3);
+        assert_eq!(new.serialize(), content);
+    }
+
+    #[test]
+    fn skips_sources_below_threshold() {
+        let old = TranslationFile::parse(OLD);
+        let mut new =
+            TranslationFile::parse("<!-- ID --><#>1\nHow are you, friend?<#>");
+
+        let report = migrate(&old, &mut new, 0.9);
+
+        assert!(report.fuzzy.is_empty());
+        assert_eq!(new.serialize(), "<!-- ID --><#>1\nHow are you, friend?<#>");
+    }
+}
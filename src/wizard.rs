@@ -0,0 +1,197 @@
+//! Interactive mode for users, that aren't comfortable with terminals.
+//!
+//! It starts, when the tool is launched without arguments, e.g. by double-clicking it, or with only a game directory, e.g. when the game folder is dragged onto it. The wizard detects the game, asks what to do with it, and turns the answers into a regular command line, which it prints, so users can learn it.
+
+use crate::error::{ErrorKind, WithKind};
+use anyhow::{Result, anyhow};
+use std::{
+    ffi::OsString,
+    io::{Write, stdin, stdout},
+    path::{Path, PathBuf},
+};
+
+/// Command line, that the wizard built, and the hint to show after it succeeds.
+pub struct Plan {
+    pub args: Vec<OsString>,
+    pub next_step: String,
+}
+
+/// Returns whether the tool was launched without a command, with at most a game directory.
+#[must_use]
+pub fn is_requested(args: &[OsString]) -> bool {
+    match args {
+        [_] => true,
+        [_, path] => Path::new(path).is_dir(),
+        _ => false,
+    }
+}
+
+/// Prints `question` and returns the answer without surrounding whitespace.
+fn ask(question: &str) -> Result<String> {
+    print!("{question} ");
+    stdout().flush()?;
+
+    let mut answer = String::new();
+
+    if stdin().read_line(&mut answer)? == 0 {
+        return Err(anyhow!("No input is available.")).kind(ErrorKind::Aborted);
+    }
+
+    Ok(answer.trim().to_string())
+}
+
+/// Returns the directory with game data in `dir`, and the name of the game's engine.
+fn detect(dir: &Path) -> Option<(PathBuf, &'static str)> {
+    // Deployed MV games keep data in `www`.
+    let www = dir.join("www");
+    let dir = if www.join("data").exists() { &www } else { dir };
+
+    let engine = [
+        ("data/System.json", "MV/MZ"),
+        ("Data/System.rvdata2", "VX Ace"),
+        ("Game.rgss3a", "VX Ace"),
+        ("Data/System.rvdata", "VX"),
+        ("Game.rgss2a", "VX"),
+        ("Data/System.rxdata", "XP"),
+        ("Game.rgssad", "XP"),
+    ]
+    .into_iter()
+    .find_map(|(file, engine)| dir.join(file).exists().then_some(engine))?;
+
+    Some((dir.to_path_buf(), engine))
+}
+
+/// Asks the user to choose one of `options`, and returns its index.
+fn choose(options: &[&str]) -> Result<usize> {
+    for (i, option) in options.iter().enumerate() {
+        println!("  {}. {option}", i + 1);
+    }
+
+    loop {
+        let answer = ask("Choose a number:")?;
+
+        if let Ok(number) = answer.parse::<usize>()
+            && (1..=options.len()).contains(&number)
+        {
+            return Ok(number - 1);
+        }
+
+        println!("Enter a number from 1 to {}.", options.len());
+    }
+}
+
+/// Walks the user through the choices, and returns the command line to run, or `None`, if the user chose to exit.
+pub fn run(args: &[OsString]) -> Result<Option<Plan>> {
+    println!(
+        "rvpacker-txt-rs {} interactive mode. Run it from a terminal with `--help` to see all commands and options.\n",
+        env!("CARGO_PKG_VERSION")
+    );
+
+    let dir = if let [_, path] = args {
+        PathBuf::from(path)
+    } else {
+        let answer = ask(
+            "Game directory (drag the game folder here, or press Enter to use the current directory):",
+        )?;
+        PathBuf::from(answer.trim_matches(['"', '\'']))
+    };
+
+    let dir = if dir.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        dir
+    };
+
+    let Some((input_dir, engine)) = detect(&dir) else {
+        return Err(anyhow!(
+            "No RPG Maker game was found in {}. Choose the directory with the game's executable.",
+            dir.display()
+        ))
+        .kind(ErrorKind::EngineNotDetected);
+    };
+
+    let translation_path = input_dir.join("translation");
+    let translated = translation_path.exists();
+
+    println!("Found RPG Maker {engine} game in {}.", input_dir.display());
+
+    if translated {
+        println!("It already has translation files.");
+    }
+
+    println!("\nWhat do you want to do?");
+
+    let read_hint = || {
+        format!(
+            "Translation files are in {}. Each line is `source<#>translation`: write the translation after `<#>`, then launch the tool again and choose to write the translation.",
+            translation_path.display()
+        )
+    };
+
+    let mut command: Vec<&str> = Vec::new();
+
+    let options: &[&str] = if translated {
+        &[
+            "Write the translation to the game files",
+            "Update translation files with new text of the game, keeping existing translations",
+            "Exit",
+        ]
+    } else {
+        &["Read the game text to translation files", "Exit"]
+    };
+
+    let next_step = match (translated, choose(options)?) {
+        (true, 0) => {
+            command.push("write");
+
+            println!(
+                "\nDo you want a complete playable copy of the game, instead of only the changed files?"
+            );
+
+            if choose(&["Only the changed files", "Complete copy"])? == 1 {
+                command.push("--full-output");
+            } else {
+                command.extend(["--layout", "game"]);
+            }
+
+            format!(
+                "Translated files are in {}. Copy them over the game to play it translated.",
+                input_dir.join("output").display()
+            )
+        }
+        (true, 1) => {
+            command.extend(["read", "--mode", "append"]);
+            read_hint()
+        }
+        (false, 0) => {
+            command.push("read");
+            read_hint()
+        }
+        _ => return Ok(None),
+    };
+
+    let mut plan_args: Vec<OsString> =
+        vec![args[0].clone(), "--input-dir".into(), input_dir.into()];
+    plan_args.extend(command.iter().map(OsString::from));
+
+    println!(
+        "\nRunning: rvpacker-txt-rs {}\n",
+        plan_args[1..]
+            .iter()
+            .map(|arg| arg.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(" ")
+    );
+
+    Ok(Some(Plan {
+        args: plan_args,
+        next_step,
+    }))
+}
+
+/// Waits for Enter, so the window of a double-clicked tool doesn't close before the user reads the output.
+pub fn pause() {
+    print!("\nPress Enter to exit.");
+    let _ = stdout().flush();
+    let _ = stdin().read_line(&mut String::new());
+}
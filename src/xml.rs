@@ -0,0 +1,112 @@
+//! Escaping of text and reading of attributes in XML and HTML, that exports, translation memories, progress pages and providers write and read.
+
+use regex::Regex;
+use std::{collections::HashMap, sync::LazyLock};
+
+static ATTRIBUTE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"([\w:]+)="([^"]*)""#).unwrap());
+
+/// Returns `string` with characters, that are special in XML, escaped, and control characters, that XML doesn't allow, removed.
+pub fn escape(string: &str) -> String {
+    let mut escaped = String::with_capacity(string.len());
+
+    for char in string.chars() {
+        match char {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            // Control characters are not allowed in XML.
+            char if char.is_control() && !matches!(char, '\t' | '\n') => {}
+            char => escaped.push(char),
+        }
+    }
+
+    escaped
+}
+
+/// Returns `text` escaped like [`escape`], with line breaks replaced with `line_break` tags, e.g. `<br>`.
+pub fn escape_lines(text: &str, line_break: &str) -> String {
+    escape(text).replace('\n', line_break)
+}
+
+/// Returns `string` with predefined and numeric entities replaced with their characters. Unknown entities are kept.
+pub fn unescape(string: &str) -> String {
+    let mut unescaped = String::with_capacity(string.len());
+    let mut rest = string;
+
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+
+        let entity = &rest[1..end];
+        let char = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+
+        if let Some(char) = char {
+            unescaped.push(char);
+            rest = &rest[end + 1..];
+        } else {
+            unescaped.push('&');
+            rest = &rest[1..];
+        }
+    }
+
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// Returns attributes of `tag` by their names. Values aren't unescaped.
+pub fn attributes(tag: &str) -> HashMap<&str, &str> {
+    ATTRIBUTE_RE
+        .captures_iter(tag)
+        .filter_map(|captures| {
+            Some((captures.get(1)?.as_str(), captures.get(2)?.as_str()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_escaped_text() {
+        let text = "<a href=\"x\">Tom & Jerry</a>\n\tend";
+
+        assert_eq!(
+            escape(text),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&lt;/a&gt;\n\tend"
+        );
+        assert_eq!(unescape(&escape(text)), text);
+        assert_eq!(escape("a\u{1}b"), "ab");
+    }
+
+    #[test]
+    fn unescapes_numeric_and_unknown_entities() {
+        assert_eq!(unescape("&#65;&#x42;&apos;&nbsp;&"), "AB'&nbsp;&");
+    }
+
+    #[test]
+    fn reads_attributes() {
+        let attributes = attributes(r#"<tuv xml:lang="en-US" id="1">"#);
+
+        assert_eq!(attributes["xml:lang"], "en-US");
+        assert_eq!(attributes["id"], "1");
+    }
+}